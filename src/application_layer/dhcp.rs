@@ -0,0 +1,1016 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::{
+        ethernet::{append_mac, get_mac},
+        ipv4::Ipv4Packet,
+        udp::{UDPBuilder, UDPFrame},
+    },
+    ip_address::{Ipv4Address, Ipv4AddressAllocator, Ipv4Cidr},
+    mac_address::MacAddress,
+    rand::XorShift,
+};
+
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+
+pub const DEFAULT_LEASE_TIME: Duration = Duration::from_secs(60 * 60);
+/// How long the server holds an offered address for the client.
+pub const DEFAULT_OFFER_TIMEOUT: Duration = Duration::from_secs(60);
+pub const DEFAULT_RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(4);
+/// The amount of requests sent for an offer before starting over.
+pub const MAX_REQUESTS: usize = 4;
+
+const BOOT_REQUEST: u8 = 1;
+const BOOT_REPLY: u8 = 2;
+const HARDWARE_TYPE_ETHERNET: u8 = 1;
+const FLAG_BROADCAST: u16 = 0x8000;
+const MAGIC_COOKIE: u32 = 0x6382_5363;
+/// The fixed part of a message, up to and including the magic cookie.
+const DHCP_HEADER_LEN: usize = 240;
+
+// Options
+const OPTION_PAD: u8 = 0;
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_REQUESTED_IP: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_END: u8 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpMessageType {
+    Discover = 1,
+    Offer = 2,
+    Request = 3,
+    Decline = 4,
+    Ack = 5,
+    Nak = 6,
+    Release = 7,
+}
+
+impl DhcpMessageType {
+    const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Discover),
+            2 => Some(Self::Offer),
+            3 => Some(Self::Request),
+            4 => Some(Self::Decline),
+            5 => Some(Self::Ack),
+            6 => Some(Self::Nak),
+            7 => Some(Self::Release),
+            _ => None,
+        }
+    }
+
+    /// Whether clients send the message, rather than servers.
+    #[must_use]
+    pub const fn from_client(self) -> bool {
+        matches!(
+            self,
+            Self::Discover | Self::Request | Self::Decline | Self::Release
+        )
+    }
+}
+
+/// A DHCP message. Only the options used for assigning addresses are
+/// supported, others are skipped when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DhcpMessage {
+    pub message_type: DhcpMessageType,
+    pub transaction_id: u32,
+    pub client_mac: MacAddress,
+    /// The address of the client, only set when it already has one.
+    pub client_ip: Ipv4Address,
+    /// The address offered or assigned to the client.
+    pub your_ip: Ipv4Address,
+    pub requested_ip: Option<Ipv4Address>,
+    pub server_id: Option<Ipv4Address>,
+    pub lease_time: Option<Duration>,
+    pub subnet_mask: Option<Ipv4Address>,
+    pub router: Option<Ipv4Address>,
+}
+
+impl DhcpMessage {
+    #[must_use]
+    pub const fn new(
+        message_type: DhcpMessageType,
+        transaction_id: u32,
+        client_mac: MacAddress,
+    ) -> Self {
+        Self {
+            message_type,
+            transaction_id,
+            client_mac,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: Ipv4Address::UNSPECIFIED,
+            requested_ip: None,
+            server_id: None,
+            lease_time: None,
+            subnet_mask: None,
+            router: None,
+        }
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> BitString {
+        let mut output = BitString::with_capacity(DHCP_HEADER_LEN * 8);
+
+        let op = if self.message_type.from_client() {
+            BOOT_REQUEST
+        } else {
+            BOOT_REPLY
+        };
+        // Without an address the client can't receive unicast replies
+        let flags = if self.client_ip == Ipv4Address::UNSPECIFIED {
+            FLAG_BROADCAST
+        } else {
+            0
+        };
+
+        output.append_u8(op);
+        output.append_u8(HARDWARE_TYPE_ETHERNET);
+        output.append_u8(6); // Hardware address length
+        output.append_u8(0); // Hops
+        output.append_u32(self.transaction_id);
+        output.append_u16(0); // Seconds elapsed
+        output.append_u16(flags);
+        output.append_u32(self.client_ip.to_u32());
+        output.append_u32(self.your_ip.to_u32());
+        // Next server and relay agent, neither of which are used
+        output.append_u32(0);
+        output.append_u32(0);
+        append_mac(&mut output, self.client_mac);
+        // Rest of the hardware address, server name and boot file name
+        output.append_zeroes((10 + 64 + 128) * 8);
+        output.append_u32(MAGIC_COOKIE);
+
+        output.append_u8(OPTION_MESSAGE_TYPE);
+        output.append_u8(1);
+        output.append_u8(self.message_type as u8);
+
+        let addresses = [
+            (OPTION_SUBNET_MASK, self.subnet_mask),
+            (OPTION_ROUTER, self.router),
+            (OPTION_REQUESTED_IP, self.requested_ip),
+            (OPTION_SERVER_ID, self.server_id),
+        ];
+        for (kind, address) in addresses {
+            if let Some(address) = address {
+                output.append_u8(kind);
+                output.append_u8(4);
+                output.append_u32(address.to_u32());
+            }
+        }
+
+        if let Some(lease_time) = self.lease_time {
+            output.append_u8(OPTION_LEASE_TIME);
+            output.append_u8(4);
+            output.append_u32(u32::try_from(lease_time.as_secs()).unwrap_or(u32::MAX));
+        }
+
+        output.append_u8(OPTION_END);
+
+        output
+    }
+
+    /// Parses a message, ignoring anything after the end option.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= DHCP_HEADER_LEN * 8 && data.len().is_multiple_of(8),
+            "Message of {} bits is not a valid DHCP message",
+            data.len()
+        );
+        ensure!(
+            data.get_u8(8) == HARDWARE_TYPE_ETHERNET && data.get_u8(16) == 6,
+            "Only ethernet hardware addresses are supported"
+        );
+        ensure!(
+            data.get_u32(1888) == MAGIC_COOKIE,
+            "Message does not carry DHCP options"
+        );
+
+        let mut message_type = None;
+        let mut message = Self::new(
+            DhcpMessageType::Discover,
+            data.get_u32(32),
+            get_mac(data, 224),
+        );
+        message.client_ip = Ipv4Address::from_u32(data.get_u32(96));
+        message.your_ip = Ipv4Address::from_u32(data.get_u32(128));
+
+        let mut idx = DHCP_HEADER_LEN * 8;
+        while idx < data.len() {
+            let kind = data.get_u8(idx);
+            match kind {
+                OPTION_PAD => {
+                    idx += 8;
+                    continue;
+                }
+                OPTION_END => break,
+                _ => {}
+            }
+
+            ensure!(idx + 16 <= data.len(), "Option {kind} is cut off");
+            let len = usize::from(data.get_u8(idx + 8)) * 8;
+            let start = idx + 16;
+            ensure!(start + len <= data.len(), "Option {kind} is cut off");
+
+            let address = || -> anyhow::Result<Option<Ipv4Address>> {
+                ensure!(len == 32, "Option {kind} does not hold an address");
+                Ok(Some(Ipv4Address::from_u32(data.get_u32(start))))
+            };
+
+            match kind {
+                OPTION_MESSAGE_TYPE => {
+                    ensure!(len == 8, "Message type option has the wrong length");
+                    message_type = Some(data.get_u8(start));
+                }
+                OPTION_SUBNET_MASK => message.subnet_mask = address()?,
+                OPTION_ROUTER => message.router = address()?,
+                OPTION_REQUESTED_IP => message.requested_ip = address()?,
+                OPTION_SERVER_ID => message.server_id = address()?,
+                OPTION_LEASE_TIME => {
+                    ensure!(len == 32, "Lease time option has the wrong length");
+                    let secs = data.get_u32(start);
+                    message.lease_time = Some(Duration::from_secs(secs.into()));
+                }
+                _ => {}
+            }
+
+            idx = start + len;
+        }
+
+        let Some(message_type) = message_type else {
+            bail!("Message has no DHCP message type");
+        };
+        let Some(message_type) = DhcpMessageType::from_u8(message_type) else {
+            bail!("Unknown DHCP message type {message_type}");
+        };
+
+        let op = if message_type.from_client() {
+            BOOT_REQUEST
+        } else {
+            BOOT_REPLY
+        };
+        ensure!(
+            data.get_u8(0) == op,
+            "Message type {message_type:?} does not match the operation"
+        );
+        message.message_type = message_type;
+
+        Ok(message)
+    }
+
+    /// Wraps the message in a packet, using the client or server port
+    /// depending on who sends it.
+    #[must_use]
+    pub fn to_packet(&self, source: Ipv4Address, destination: Ipv4Address) -> Ipv4Packet {
+        let (source_port, target_port) = if self.message_type.from_client() {
+            (DHCP_CLIENT_PORT, DHCP_SERVER_PORT)
+        } else {
+            (DHCP_SERVER_PORT, DHCP_CLIENT_PORT)
+        };
+
+        UDPBuilder::new()
+            .set_source_port(source_port)
+            .set_target_port(target_port)
+            .build_ipv4(source, destination, self.encode())
+    }
+
+    pub fn decode_ipv4(packet: &Ipv4Packet) -> anyhow::Result<Self> {
+        let datagram = UDPFrame::decode_ipv4(packet)?;
+        ensure!(
+            matches!(datagram.target_port(), DHCP_SERVER_PORT | DHCP_CLIENT_PORT),
+            "Datagram is not sent to a DHCP port"
+        );
+
+        Self::decode(datagram.data())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lease {
+    pub address: Ipv4Address,
+    pub expires: Instant,
+}
+
+/// Hands out addresses from a pool. Offered addresses are held for a while
+/// for the client to request them, and leased addresses return to the pool
+/// once they expire or are released.
+#[derive(Debug)]
+pub struct DhcpServer {
+    address: Ipv4Address,
+    pool: Ipv4AddressAllocator,
+    router: Option<Ipv4Address>,
+    lease_time: Duration,
+    offer_timeout: Duration,
+    offers: HashMap<MacAddress, Lease>,
+    leases: HashMap<MacAddress, Lease>,
+}
+
+impl DhcpServer {
+    /// A server at `address` handing out the other hosts of `cidr`. Clients
+    /// are told to use the server as their router.
+    pub fn new(address: Ipv4Address, cidr: Ipv4Cidr) -> anyhow::Result<Self> {
+        let mut pool = Ipv4AddressAllocator::new(cidr);
+        pool.reserve(address)?;
+
+        Ok(Self {
+            address,
+            pool,
+            router: Some(address),
+            lease_time: DEFAULT_LEASE_TIME,
+            offer_timeout: DEFAULT_OFFER_TIMEOUT,
+            offers: HashMap::new(),
+            leases: HashMap::new(),
+        })
+    }
+
+    #[must_use]
+    pub fn set_lease_time(self, lease_time: Duration) -> Self {
+        Self { lease_time, ..self }
+    }
+
+    #[must_use]
+    pub fn set_offer_timeout(self, offer_timeout: Duration) -> Self {
+        Self {
+            offer_timeout,
+            ..self
+        }
+    }
+
+    /// Sets the default router handed to clients, if any.
+    #[must_use]
+    pub fn set_router(self, router: Option<Ipv4Address>) -> Self {
+        Self { router, ..self }
+    }
+
+    /// Keeps an address from being handed out, for example because it's
+    /// assigned by hand.
+    pub fn reserve(&mut self, address: Ipv4Address) -> anyhow::Result<()> {
+        self.pool.reserve(address)
+    }
+
+    #[must_use]
+    pub const fn address(&self) -> Ipv4Address {
+        self.address
+    }
+
+    #[must_use]
+    pub const fn cidr(&self) -> Ipv4Cidr {
+        self.pool.cidr()
+    }
+
+    #[must_use]
+    pub fn lease(&self, client_mac: MacAddress) -> Option<Lease> {
+        self.leases.get(&client_mac).copied()
+    }
+
+    #[must_use]
+    pub fn lease_count(&self) -> usize {
+        self.leases.len()
+    }
+
+    /// Handles a message from a client, returning the reply if there is one.
+    pub fn receive(&mut self, message: &DhcpMessage, now: Instant) -> Option<DhcpMessage> {
+        self.remove_expired(now);
+
+        let client_mac = message.client_mac;
+
+        match message.message_type {
+            DhcpMessageType::Discover => {
+                let address = match self.held_address(client_mac) {
+                    Some(address) => address,
+                    None => self.pool.gen_addr()?,
+                };
+                self.offers.insert(
+                    client_mac,
+                    Lease {
+                        address,
+                        expires: now + self.offer_timeout,
+                    },
+                );
+
+                Some(self.reply(DhcpMessageType::Offer, message, address))
+            }
+            DhcpMessageType::Request => {
+                // The client went with another server
+                if message.server_id.is_some_and(|id| id != self.address) {
+                    if let Some(offer) = self.offers.remove(&client_mac) {
+                        self.release_unless_held(client_mac, offer.address);
+                    }
+                    return None;
+                }
+
+                // Renewing clients send their address instead of requesting one
+                let requested = message.requested_ip.unwrap_or(message.client_ip);
+
+                if self.held_address(client_mac) == Some(requested) {
+                    self.offers.remove(&client_mac);
+                    self.leases.insert(
+                        client_mac,
+                        Lease {
+                            address: requested,
+                            expires: now + self.lease_time,
+                        },
+                    );
+
+                    return Some(self.reply(DhcpMessageType::Ack, message, requested));
+                }
+
+                // Only refuse addresses from our own network
+                let ours = message.server_id.is_some() || self.cidr().contains(requested);
+                ours.then(|| {
+                    let mut nak =
+                        DhcpMessage::new(DhcpMessageType::Nak, message.transaction_id, client_mac);
+                    nak.server_id = Some(self.address);
+                    nak
+                })
+            }
+            DhcpMessageType::Decline => {
+                // The address is in use by someone else, so it's kept out of
+                // the pool
+                self.offers.remove(&client_mac);
+                self.leases.remove(&client_mac);
+                None
+            }
+            DhcpMessageType::Release => {
+                if let Some(lease) = self.leases.remove(&client_mac) {
+                    self.release_unless_held(client_mac, lease.address);
+                }
+                None
+            }
+            DhcpMessageType::Offer | DhcpMessageType::Ack | DhcpMessageType::Nak => None,
+        }
+    }
+
+    /// Handles a packet from a client, returning the reply to send. Clients
+    /// without an address get their reply broadcast.
+    pub fn receive_ipv4(
+        &mut self,
+        packet: &Ipv4Packet,
+        now: Instant,
+    ) -> anyhow::Result<Option<Ipv4Packet>> {
+        let message = DhcpMessage::decode_ipv4(packet)?;
+
+        let destination = if message.client_ip == Ipv4Address::UNSPECIFIED {
+            Ipv4Address::BROADCAST
+        } else {
+            message.client_ip
+        };
+
+        Ok(self
+            .receive(&message, now)
+            .map(|reply| reply.to_packet(self.address, destination)))
+    }
+
+    /// Returns the addresses of expired offers and leases to the pool.
+    pub fn remove_expired(&mut self, now: Instant) {
+        let expired: Vec<(MacAddress, Ipv4Address)> = self
+            .offers
+            .iter()
+            .chain(&self.leases)
+            .filter(|(_, lease)| now >= lease.expires)
+            .map(|(mac, lease)| (*mac, lease.address))
+            .collect();
+
+        self.offers.retain(|_, offer| now < offer.expires);
+        self.leases.retain(|_, lease| now < lease.expires);
+
+        for (client_mac, address) in expired {
+            self.release_unless_held(client_mac, address);
+        }
+    }
+
+    /// The address offered or leased to the client.
+    fn held_address(&self, client_mac: MacAddress) -> Option<Ipv4Address> {
+        self.leases
+            .get(&client_mac)
+            .or_else(|| self.offers.get(&client_mac))
+            .map(|lease| lease.address)
+    }
+
+    /// Returns the address to the pool, unless the client still holds it
+    /// through another offer or lease.
+    fn release_unless_held(&mut self, client_mac: MacAddress, address: Ipv4Address) {
+        let held = [&self.offers, &self.leases].iter().any(|held| {
+            held.get(&client_mac)
+                .is_some_and(|lease| lease.address == address)
+        });
+
+        if !held {
+            self.pool.release(address);
+        }
+    }
+
+    fn reply(
+        &self,
+        message_type: DhcpMessageType,
+        request: &DhcpMessage,
+        address: Ipv4Address,
+    ) -> DhcpMessage {
+        let mut reply = DhcpMessage::new(message_type, request.transaction_id, request.client_mac);
+        reply.client_ip = request.client_ip;
+        reply.your_ip = address;
+        reply.server_id = Some(self.address);
+        reply.lease_time = Some(self.lease_time);
+        reply.subnet_mask = Some(self.cidr().netmask());
+        reply.router = self.router;
+
+        reply
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpState {
+    Init,
+    /// Waiting for offers.
+    Selecting,
+    /// Waiting for the chosen server to acknowledge the offer.
+    Requesting,
+    Bound,
+    /// Asking the server that handed out the lease to extend it.
+    Renewing,
+    /// Asking any server to extend the lease, as the original one didn't
+    /// answer.
+    Rebinding,
+}
+
+/// An address handed out by a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DhcpLease {
+    pub address: Ipv4Address,
+    pub subnet_mask: Option<Ipv4Address>,
+    pub router: Option<Ipv4Address>,
+    pub server: Ipv4Address,
+    pub obtained: Instant,
+    pub lease_time: Duration,
+}
+
+impl DhcpLease {
+    /// When to start renewing the lease with its server.
+    #[must_use]
+    pub fn renew_at(&self) -> Instant {
+        self.obtained + self.lease_time / 2
+    }
+
+    /// When to ask any server to extend the lease.
+    #[must_use]
+    pub fn rebind_at(&self) -> Instant {
+        self.obtained + self.lease_time * 7 / 8
+    }
+
+    #[must_use]
+    pub fn expires(&self) -> Instant {
+        self.obtained + self.lease_time
+    }
+}
+
+/// Gets an address from a server and keeps it by renewing the lease. Starts
+/// looking for a server on the first poll.
+#[derive(Debug)]
+pub struct DhcpClient {
+    mac: MacAddress,
+    state: DhcpState,
+    rand: XorShift,
+    transaction_id: u32,
+    // The server and address that were offered while requesting
+    offer: Option<(Ipv4Address, Ipv4Address)>,
+    lease: Option<DhcpLease>,
+    last_sent: Option<Instant>,
+    requests: usize,
+    retransmit_timeout: Duration,
+}
+
+impl DhcpClient {
+    #[must_use]
+    pub fn new(mac: MacAddress) -> Self {
+        let seed = mac
+            .octets()
+            .iter()
+            .fold(1, |seed, byte| seed << 8 | u128::from(*byte));
+
+        Self {
+            mac,
+            state: DhcpState::Init,
+            rand: XorShift::new(seed),
+            transaction_id: 0,
+            offer: None,
+            lease: None,
+            last_sent: None,
+            requests: 0,
+            retransmit_timeout: DEFAULT_RETRANSMIT_TIMEOUT,
+        }
+    }
+
+    /// Sets how long to wait for a reply before asking again.
+    #[must_use]
+    pub fn set_retransmit_timeout(self, retransmit_timeout: Duration) -> Self {
+        Self {
+            retransmit_timeout,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub const fn state(&self) -> DhcpState {
+        self.state
+    }
+
+    #[must_use]
+    pub const fn lease(&self) -> Option<&DhcpLease> {
+        self.lease.as_ref()
+    }
+
+    /// The leased address, while the lease is valid.
+    #[must_use]
+    pub fn address(&self) -> Option<Ipv4Address> {
+        self.lease.map(|lease| lease.address)
+    }
+
+    /// Sends the messages that are due: the first discover, retransmissions,
+    /// and renewals. Leases that weren't extended in time are dropped.
+    pub fn poll(&mut self, now: Instant) -> Option<Ipv4Packet> {
+        let timed_out = self
+            .last_sent
+            .is_none_or(|sent| now.duration_since(sent) >= self.retransmit_timeout);
+
+        match self.state {
+            DhcpState::Init => {
+                self.state = DhcpState::Selecting;
+                self.transaction_id = self.next_transaction_id();
+                Some(self.discover(now))
+            }
+            DhcpState::Selecting => timed_out.then(|| self.discover(now)),
+            DhcpState::Requesting if timed_out && self.requests >= MAX_REQUESTS => {
+                self.state = DhcpState::Init;
+                self.offer = None;
+                self.poll(now)
+            }
+            DhcpState::Requesting => timed_out.then(|| self.request(now)),
+            DhcpState::Bound | DhcpState::Renewing | DhcpState::Rebinding => {
+                let lease = self.lease.expect("Client has a lease once bound");
+
+                if now >= lease.expires() {
+                    self.lease = None;
+                    self.state = DhcpState::Init;
+                    return self.poll(now);
+                }
+
+                let due = if now >= lease.rebind_at() {
+                    DhcpState::Rebinding
+                } else if now >= lease.renew_at() {
+                    DhcpState::Renewing
+                } else {
+                    DhcpState::Bound
+                };
+
+                if due == self.state && (due == DhcpState::Bound || !timed_out) {
+                    return None;
+                }
+
+                if self.state == DhcpState::Bound {
+                    self.transaction_id = self.next_transaction_id();
+                }
+                self.state = due;
+                Some(self.request(now))
+            }
+        }
+    }
+
+    /// Handles a packet from a server, returning the message to send in
+    /// response.
+    pub fn receive(
+        &mut self,
+        packet: &Ipv4Packet,
+        now: Instant,
+    ) -> anyhow::Result<Option<Ipv4Packet>> {
+        let message = DhcpMessage::decode_ipv4(packet)?;
+
+        if message.message_type.from_client()
+            || message.client_mac != self.mac
+            || message.transaction_id != self.transaction_id
+        {
+            return Ok(None);
+        }
+
+        match (self.state, message.message_type) {
+            (DhcpState::Selecting, DhcpMessageType::Offer) => {
+                let server = message.server_id.unwrap_or(packet.source());
+                self.offer = Some((server, message.your_ip));
+                self.state = DhcpState::Requesting;
+                self.requests = 0;
+
+                Ok(Some(self.request(now)))
+            }
+            (
+                DhcpState::Requesting | DhcpState::Renewing | DhcpState::Rebinding,
+                DhcpMessageType::Ack,
+            ) => {
+                let Some(lease_time) = message.lease_time else {
+                    bail!("Acknowledgement has no lease time");
+                };
+
+                self.lease = Some(DhcpLease {
+                    address: message.your_ip,
+                    subnet_mask: message.subnet_mask,
+                    router: message.router,
+                    server: message.server_id.unwrap_or(packet.source()),
+                    obtained: now,
+                    lease_time,
+                });
+                self.offer = None;
+                self.state = DhcpState::Bound;
+
+                Ok(None)
+            }
+            (
+                DhcpState::Requesting | DhcpState::Renewing | DhcpState::Rebinding,
+                DhcpMessageType::Nak,
+            ) => {
+                self.lease = None;
+                self.offer = None;
+                self.state = DhcpState::Init;
+
+                Ok(self.poll(now))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Gives the lease back to the server. The client stays without an
+    /// address until it's polled again.
+    pub fn release(&mut self) -> Option<Ipv4Packet> {
+        let lease = self.lease.take()?;
+        self.state = DhcpState::Init;
+        self.last_sent = None;
+
+        let mut release = DhcpMessage::new(
+            DhcpMessageType::Release,
+            self.next_transaction_id(),
+            self.mac,
+        );
+        release.client_ip = lease.address;
+        release.server_id = Some(lease.server);
+
+        Some(release.to_packet(lease.address, lease.server))
+    }
+
+    fn discover(&mut self, now: Instant) -> Ipv4Packet {
+        self.last_sent = Some(now);
+
+        DhcpMessage::new(DhcpMessageType::Discover, self.transaction_id, self.mac)
+            .to_packet(Ipv4Address::UNSPECIFIED, Ipv4Address::BROADCAST)
+    }
+
+    fn request(&mut self, now: Instant) -> Ipv4Packet {
+        self.last_sent = Some(now);
+        self.requests += 1;
+
+        let mut request = DhcpMessage::new(DhcpMessageType::Request, self.transaction_id, self.mac);
+
+        match (self.state, self.lease) {
+            (DhcpState::Renewing, Some(lease)) => {
+                request.client_ip = lease.address;
+                request.to_packet(lease.address, lease.server)
+            }
+            (DhcpState::Rebinding, Some(lease)) => {
+                request.client_ip = lease.address;
+                request.to_packet(lease.address, Ipv4Address::BROADCAST)
+            }
+            _ => {
+                let (server, address) = self.offer.expect("Client requests an offer");
+                request.requested_ip = Some(address);
+                request.server_id = Some(server);
+                request.to_packet(Ipv4Address::UNSPECIFIED, Ipv4Address::BROADCAST)
+            }
+        }
+    }
+
+    fn next_transaction_id(&mut self) -> u32 {
+        #[allow(clippy::cast_possible_truncation)]
+        let transaction_id = self.rand.next_int() as u32;
+        transaction_id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::{
+        data_link_layer::frame::ipv4::Ipv4Packet,
+        ip_address::{Ipv4Address, Ipv4Cidr},
+        mac_address::MacAddressGenerator,
+    };
+
+    use super::{
+        DhcpClient, DhcpMessage, DhcpMessageType, DhcpServer, DhcpState, DEFAULT_LEASE_TIME,
+        DEFAULT_OFFER_TIMEOUT, DEFAULT_RETRANSMIT_TIMEOUT,
+    };
+
+    const SERVER: Ipv4Address = Ipv4Address::new([192, 168, 1, 1]);
+
+    fn server() -> DhcpServer {
+        let cidr = "192.168.1.0/24".parse().expect("CIDR is valid");
+        DhcpServer::new(SERVER, cidr).expect("Server is in the network")
+    }
+
+    /// Exchanges messages until the client has nothing more to send.
+    fn exchange(client: &mut DhcpClient, server: &mut DhcpServer, now: Instant) {
+        let mut outgoing = client.poll(now);
+
+        while let Some(packet) = outgoing.take() {
+            let reply = server
+                .receive_ipv4(&packet, now)
+                .expect("Client sends valid messages");
+            if let Some(reply) = reply {
+                outgoing = client
+                    .receive(&reply, now)
+                    .expect("Server sends valid messages");
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mac = MacAddressGenerator::new(1).gen_addr();
+        let mut message = DhcpMessage::new(DhcpMessageType::Ack, 0xDEAD_BEEF, mac);
+        message.your_ip = Ipv4Address::new([192, 168, 1, 20]);
+        message.server_id = Some(SERVER);
+        message.router = Some(SERVER);
+        message.subnet_mask = Some(Ipv4Address::new([255, 255, 255, 0]));
+        message.lease_time = Some(DEFAULT_LEASE_TIME);
+
+        let encoded = message.encode();
+        // Reply operation, ethernet and the magic cookie
+        assert_eq!(encoded.get_u32(0), 0x0201_0600);
+        assert_eq!(encoded.get_u32(1888), 0x6382_5363);
+        assert_eq!(
+            DhcpMessage::decode(&encoded).expect("Message is valid"),
+            message
+        );
+
+        let packet = message.to_packet(SERVER, Ipv4Address::BROADCAST);
+        assert_eq!(
+            DhcpMessage::decode_ipv4(&packet).expect("Packet carries a DHCP message"),
+            message
+        );
+
+        // Clients don't send acknowledgements
+        let mut spoofed = encoded;
+        spoofed.set_u8(0, 1);
+        assert!(DhcpMessage::decode(&spoofed).is_err());
+    }
+
+    #[test]
+    fn assigns_addresses() {
+        let now = Instant::now();
+        let mut server = server();
+        let mut mac_gen = MacAddressGenerator::new(2);
+
+        let mut clients: Vec<DhcpClient> = (0..3)
+            .map(|_| DhcpClient::new(mac_gen.gen_addr()))
+            .collect();
+
+        for client in &mut clients {
+            exchange(client, &mut server, now);
+
+            assert_eq!(client.state(), DhcpState::Bound);
+            let lease = client.lease().expect("Client is bound");
+            assert_eq!(lease.router, Some(SERVER));
+            assert_eq!(lease.server, SERVER);
+        }
+
+        let addresses: Vec<Ipv4Address> = clients
+            .iter()
+            .map(|client| client.address().expect("Client is bound"))
+            .collect();
+        assert_eq!(
+            addresses,
+            [2, 3, 4].map(|host| Ipv4Address::new([192, 168, 1, host]))
+        );
+        assert_eq!(server.lease_count(), 3);
+    }
+
+    #[test]
+    fn renews_and_rebinds() {
+        let start = Instant::now();
+        let mut server = server();
+        let mac = MacAddressGenerator::new(3).gen_addr();
+        let mut client = DhcpClient::new(mac);
+
+        exchange(&mut client, &mut server, start);
+        let address = client.address();
+
+        // Nothing is due until half the lease time
+        assert_eq!(client.poll(start + DEFAULT_LEASE_TIME / 4), None);
+
+        // Renewing goes straight to the server
+        let renew_at = start + DEFAULT_LEASE_TIME / 2;
+        let request = client.poll(renew_at).expect("Lease is renewed");
+        assert_eq!(client.state(), DhcpState::Renewing);
+        assert_eq!(request.destination(), SERVER);
+
+        let ack = server
+            .receive_ipv4(&request, renew_at)
+            .expect("Request is valid")
+            .expect("Lease is extended");
+        assert_eq!(ack.destination(), address.expect("Client is bound"));
+        client.receive(&ack, renew_at).expect("Ack is valid");
+        assert_eq!(client.state(), DhcpState::Bound);
+        assert_eq!(client.address(), address);
+
+        let lease = server.lease(mac).expect("Client holds a lease");
+        assert_eq!(lease.expires, renew_at + DEFAULT_LEASE_TIME);
+    }
+
+    #[test]
+    fn lease_expires_without_server() {
+        let start = Instant::now();
+        let mut server = server();
+        let mut client = DhcpClient::new(MacAddressGenerator::new(4).gen_addr());
+
+        exchange(&mut client, &mut server, start);
+
+        let rebind_at = start + DEFAULT_LEASE_TIME * 7 / 8;
+        let request = client.poll(rebind_at).expect("Lease is rebound");
+        assert_eq!(client.state(), DhcpState::Rebinding);
+        assert_eq!(request.destination(), Ipv4Address::BROADCAST);
+
+        // Retransmitted while nobody answers
+        assert_eq!(
+            client.poll(rebind_at + DEFAULT_RETRANSMIT_TIMEOUT / 2),
+            None
+        );
+        assert!(client
+            .poll(rebind_at + DEFAULT_RETRANSMIT_TIMEOUT)
+            .is_some());
+
+        // Once expired, the client starts over
+        let expired = start + DEFAULT_LEASE_TIME;
+        let discover = client.poll(expired).expect("Client looks for a server");
+        assert_eq!(client.address(), None);
+        assert_eq!(client.state(), DhcpState::Selecting);
+        assert_eq!(
+            DhcpMessage::decode_ipv4(&discover)
+                .expect("Discover is valid")
+                .message_type,
+            DhcpMessageType::Discover
+        );
+
+        // The server got the address back
+        server.remove_expired(expired);
+        assert_eq!(server.lease_count(), 0);
+    }
+
+    #[test]
+    fn pool_runs_dry() {
+        let now = Instant::now();
+        let cidr: Ipv4Cidr = "10.0.0.0/30".parse().expect("CIDR is valid");
+        let mut server = DhcpServer::new(Ipv4Address::new([10, 0, 0, 1]), cidr)
+            .expect("Server is in the network");
+        let mut mac_gen = MacAddressGenerator::new(5);
+        let host = Some(Ipv4Address::new([10, 0, 0, 2]));
+
+        // A /30 holds two hosts, one of which is the server
+        let mut first = DhcpClient::new(mac_gen.gen_addr());
+        exchange(&mut first, &mut server, now);
+        assert_eq!(first.address(), host);
+
+        let mut second = DhcpClient::new(mac_gen.gen_addr());
+        let discover = second.poll(now).expect("Client looks for a server");
+        assert_eq!(server.receive(&decode(&discover), now), None);
+
+        // Released addresses are handed out again
+        let release = first.release().expect("Client holds a lease");
+        assert_eq!(server.receive(&decode(&release), now), None);
+
+        let later = now + DEFAULT_RETRANSMIT_TIMEOUT;
+        exchange(&mut second, &mut server, later);
+        assert_eq!(second.address(), host);
+
+        // Offers that are never requested are taken back as well
+        let release = second.release().expect("Client holds a lease");
+        let _ = server.receive(&decode(&release), later);
+
+        let mut third = DhcpClient::new(mac_gen.gen_addr());
+        let discover = third.poll(later).expect("Client looks for a server");
+        assert!(server.receive(&decode(&discover), later).is_some());
+
+        exchange(&mut first, &mut server, later + DEFAULT_OFFER_TIMEOUT);
+        assert_eq!(first.address(), host);
+    }
+
+    fn decode(packet: &Ipv4Packet) -> DhcpMessage {
+        DhcpMessage::decode_ipv4(packet).expect("Packet carries a DHCP message")
+    }
+}
@@ -0,0 +1,1213 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::{
+        ipv4::Ipv4Packet,
+        udp::{UDPBuilder, UDPFrame},
+    },
+    ip_address::{Ipv4Address, Ipv6Address},
+};
+
+pub const DNS_PORT: u16 = 53;
+/// The port resolvers send queries from, unless set otherwise.
+pub const DEFAULT_RESOLVER_PORT: u16 = 49152;
+/// The TTL of records in a zone that doesn't set one.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// The amount of times a query is sent before giving up on it.
+pub const MAX_QUERY_ATTEMPTS: usize = 3;
+/// The longest chain of aliases followed for a name.
+pub const MAX_CNAME_CHAIN: usize = 8;
+
+const DNS_HEADER_LEN: usize = 12;
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 255;
+const CLASS_IN: u16 = 1;
+
+// Header flags
+const FLAG_RESPONSE: u16 = 1 << 15;
+const FLAG_AUTHORITATIVE: u16 = 1 << 10;
+const FLAG_TRUNCATED: u16 = 1 << 9;
+const FLAG_RECURSION_DESIRED: u16 = 1 << 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A = 1,
+    Cname = 5,
+    Aaaa = 28,
+}
+
+impl RecordType {
+    const fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(Self::A),
+            5 => Some(Self::Cname),
+            28 => Some(Self::Aaaa),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "A" => Ok(Self::A),
+            "CNAME" => Ok(Self::Cname),
+            "AAAA" => Ok(Self::Aaaa),
+            _ => bail!("Unsupported record type {s}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseCode {
+    NoError = 0,
+    FormatError = 1,
+    ServerFailure = 2,
+    /// The name does not exist.
+    NameError = 3,
+    NotImplemented = 4,
+    Refused = 5,
+}
+
+impl ResponseCode {
+    const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::NoError),
+            1 => Some(Self::FormatError),
+            2 => Some(Self::ServerFailure),
+            3 => Some(Self::NameError),
+            4 => Some(Self::NotImplemented),
+            5 => Some(Self::Refused),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Address),
+    Aaaa(Ipv6Address),
+    /// The name is an alias of this one.
+    Cname(String),
+}
+
+impl Display for RecordData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::A(address) => write!(f, "A {address}"),
+            Self::Aaaa(address) => write!(f, "AAAA {address}"),
+            Self::Cname(name) => write!(f, "CNAME {name}"),
+        }
+    }
+}
+
+/// A record of the internet class. Names are lowercase, without the
+/// trailing dot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRecord {
+    pub name: String,
+    pub ttl: Duration,
+    pub data: RecordData,
+}
+
+impl ResourceRecord {
+    #[must_use]
+    pub const fn record_type(&self) -> RecordType {
+        match self.data {
+            RecordData::A(_) => RecordType::A,
+            RecordData::Aaaa(_) => RecordType::Aaaa,
+            RecordData::Cname(_) => RecordType::Cname,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Question {
+    pub name: String,
+    pub record_type: RecordType,
+}
+
+impl Question {
+    /// A question for the name, which is checked and normalized.
+    pub fn new(name: &str, record_type: RecordType) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: parse_name(name)?,
+            record_type,
+        })
+    }
+}
+
+/// A DNS message. Only the question and answer sections are supported,
+/// others are left out when encoding and skipped when parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsMessage {
+    pub id: u16,
+    pub is_response: bool,
+    pub authoritative: bool,
+    pub recursion_desired: bool,
+    pub response_code: ResponseCode,
+    pub questions: Vec<Question>,
+    pub answers: Vec<ResourceRecord>,
+}
+
+impl DnsMessage {
+    #[must_use]
+    pub fn query(id: u16, question: Question) -> Self {
+        Self {
+            id,
+            is_response: false,
+            authoritative: false,
+            recursion_desired: true,
+            response_code: ResponseCode::NoError,
+            questions: vec![question],
+            answers: Vec::new(),
+        }
+    }
+
+    /// An empty response to the query, with the same questions.
+    #[must_use]
+    pub fn response(&self, response_code: ResponseCode) -> Self {
+        Self {
+            id: self.id,
+            is_response: true,
+            authoritative: false,
+            recursion_desired: self.recursion_desired,
+            response_code,
+            questions: self.questions.clone(),
+            answers: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> BitString {
+        let mut output = Vec::with_capacity(512);
+
+        let mut flags = self.response_code as u16;
+        for (set, flag) in [
+            (self.is_response, FLAG_RESPONSE),
+            (self.authoritative, FLAG_AUTHORITATIVE),
+            (self.recursion_desired, FLAG_RECURSION_DESIRED),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+
+        let count = |len: usize| u16::try_from(len).expect("Section has too many entries");
+
+        output.extend(self.id.to_be_bytes());
+        output.extend(flags.to_be_bytes());
+        output.extend(count(self.questions.len()).to_be_bytes());
+        output.extend(count(self.answers.len()).to_be_bytes());
+        // Authority and additional sections
+        output.extend([0; 4]);
+
+        for question in &self.questions {
+            append_name(&mut output, &question.name);
+            output.extend((question.record_type as u16).to_be_bytes());
+            output.extend(CLASS_IN.to_be_bytes());
+        }
+
+        for record in &self.answers {
+            append_name(&mut output, &record.name);
+            output.extend((record.record_type() as u16).to_be_bytes());
+            output.extend(CLASS_IN.to_be_bytes());
+            let ttl = u32::try_from(record.ttl.as_secs()).unwrap_or(u32::MAX);
+            output.extend(ttl.to_be_bytes());
+
+            let mut data = Vec::new();
+            match &record.data {
+                RecordData::A(address) => data.extend(address.octets()),
+                RecordData::Aaaa(address) => data.extend(address.octets()),
+                RecordData::Cname(name) => append_name(&mut data, name),
+            }
+            output.extend(count(data.len()).to_be_bytes());
+            output.extend(data);
+        }
+
+        output.into()
+    }
+
+    /// Parses a message. Compressed names are followed, and answers of
+    /// unsupported types or classes are skipped.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= DNS_HEADER_LEN * 8 && data.len().is_multiple_of(8),
+            "Message of {} bits is not a valid DNS message",
+            data.len()
+        );
+
+        let bytes = data.as_vec_exact_u8();
+        let get_u16 = |idx: usize| -> anyhow::Result<u16> {
+            let Some(&[a, b]) = bytes.get(idx..idx + 2) else {
+                bail!("Message is cut off");
+            };
+            Ok(u16::from_be_bytes([a, b]))
+        };
+
+        let flags = get_u16(2)?;
+        ensure!(
+            flags & FLAG_TRUNCATED == 0,
+            "Truncated messages are not supported"
+        );
+        ensure!(flags & 0x7800 == 0, "Only standard queries are supported");
+
+        #[allow(clippy::cast_possible_truncation)]
+        let response_code = (flags & 0xF) as u8;
+        let Some(response_code) = ResponseCode::from_u8(response_code) else {
+            bail!("Unknown response code {response_code}");
+        };
+
+        let mut message = Self {
+            id: get_u16(0)?,
+            is_response: flags & FLAG_RESPONSE != 0,
+            authoritative: flags & FLAG_AUTHORITATIVE != 0,
+            recursion_desired: flags & FLAG_RECURSION_DESIRED != 0,
+            response_code,
+            questions: Vec::new(),
+            answers: Vec::new(),
+        };
+
+        let mut idx = DNS_HEADER_LEN;
+
+        for _ in 0..get_u16(4)? {
+            let (name, next) = decode_name(&bytes, idx)?;
+            let kind = get_u16(next)?;
+            let Some(record_type) = RecordType::from_u16(kind) else {
+                bail!("Unsupported record type {kind}");
+            };
+            ensure!(
+                get_u16(next + 2)? == CLASS_IN,
+                "Only the internet class is supported"
+            );
+
+            message.questions.push(Question { name, record_type });
+            idx = next + 4;
+        }
+
+        for _ in 0..get_u16(6)? {
+            let (name, next) = decode_name(&bytes, idx)?;
+            let kind = get_u16(next)?;
+            let class = get_u16(next + 2)?;
+            let ttl = u32::from(get_u16(next + 4)?) << 16 | u32::from(get_u16(next + 6)?);
+            let len = usize::from(get_u16(next + 8)?);
+            let start = next + 10;
+            ensure!(start + len <= bytes.len(), "Record data is cut off");
+            idx = start + len;
+
+            let record_data = &bytes[start..start + len];
+            let data = match RecordType::from_u16(kind) {
+                _ if class != CLASS_IN => continue,
+                Some(RecordType::A) => {
+                    let Ok(octets) = <[u8; 4]>::try_from(record_data) else {
+                        bail!("A record does not hold an IPv4 address");
+                    };
+                    RecordData::A(Ipv4Address::new(octets))
+                }
+                Some(RecordType::Aaaa) => {
+                    let Ok(octets) = <[u8; 16]>::try_from(record_data) else {
+                        bail!("AAAA record does not hold an IPv6 address");
+                    };
+                    RecordData::Aaaa(Ipv6Address::new(octets))
+                }
+                Some(RecordType::Cname) => RecordData::Cname(decode_name(&bytes, start)?.0),
+                None => continue,
+            };
+
+            message.answers.push(ResourceRecord {
+                name,
+                ttl: Duration::from_secs(ttl.into()),
+                data,
+            });
+        }
+
+        Ok(message)
+    }
+
+    /// Wraps the message in a packet between the given addresses and ports.
+    #[must_use]
+    pub fn to_packet(
+        &self,
+        source: Ipv4Address,
+        source_port: u16,
+        destination: Ipv4Address,
+        target_port: u16,
+    ) -> Ipv4Packet {
+        UDPBuilder::new()
+            .set_source_port(source_port)
+            .set_target_port(target_port)
+            .build_ipv4(source, destination, self.encode())
+    }
+
+    pub fn decode_ipv4(packet: &Ipv4Packet) -> anyhow::Result<Self> {
+        Self::decode(UDPFrame::decode_ipv4(packet)?.data())
+    }
+}
+
+/// Checks a name and brings it in the form used throughout: lowercase,
+/// without the trailing dot.
+fn parse_name(name: &str) -> anyhow::Result<String> {
+    let name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+
+    ensure!(name.len() < MAX_NAME_LEN, "Name {name} is too long");
+    if !name.is_empty() {
+        for label in name.split('.') {
+            ensure!(
+                !label.is_empty() && label.len() <= MAX_LABEL_LEN,
+                "Name {name} has an invalid label"
+            );
+            ensure!(
+                label.bytes().all(|byte| byte.is_ascii_graphic()),
+                "Name {name} holds invalid characters"
+            );
+        }
+    }
+
+    Ok(name)
+}
+
+fn append_name(output: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let len = u8::try_from(label.len())
+            .ok()
+            .filter(|len| usize::from(*len) <= MAX_LABEL_LEN)
+            .expect("Label is too long");
+        output.push(len);
+        output.extend(label.bytes());
+    }
+    output.push(0);
+}
+
+/// Reads the name at the index, returning it with the index just past it.
+fn decode_name(bytes: &[u8], index: usize) -> anyhow::Result<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut idx = index;
+    let mut next = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *bytes.get(idx).context("Name is cut off")?;
+
+        match len {
+            0 => break,
+            // A pointer to the rest of the name elsewhere in the message
+            0xC0.. => {
+                let low = *bytes.get(idx + 1).context("Name is cut off")?;
+                jumps += 1;
+                ensure!(jumps <= MAX_NAME_LEN, "Name pointers form a loop");
+
+                next.get_or_insert(idx + 2);
+                idx = usize::from(len & 0x3F) << 8 | usize::from(low);
+            }
+            1..=0x3F => {
+                let len = usize::from(len);
+                let label = bytes
+                    .get(idx + 1..idx + 1 + len)
+                    .context("Name is cut off")?;
+                labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+                idx += 1 + len;
+            }
+            _ => bail!("Invalid label length {len}"),
+        }
+    }
+
+    let name = labels.join(".");
+    ensure!(name.len() < MAX_NAME_LEN, "Name is too long");
+
+    Ok((name, next.unwrap_or(idx + 1)))
+}
+
+/// The records of a domain, loaded from a zone description like
+///
+/// ```text
+/// $ORIGIN example.com.
+/// $TTL 300
+/// @     IN A     192.0.2.1
+/// www   60 CNAME @
+/// ipv6  AAAA     2001:db8::1
+/// ```
+///
+/// Names not ending in a dot are relative to the origin, and `@` is the
+/// origin itself. Comments start with `;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    origin: String,
+    records: Vec<ResourceRecord>,
+}
+
+impl Zone {
+    #[must_use]
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    #[must_use]
+    pub fn records(&self) -> &[ResourceRecord] {
+        &self.records
+    }
+
+    /// Whether the name is part of the zone.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.origin.is_empty()
+            || name == self.origin
+            || name
+                .strip_suffix(&self.origin)
+                .is_some_and(|head| head.ends_with('.'))
+    }
+
+    /// Answers the question from the records, following aliases within the
+    /// zone.
+    #[must_use]
+    pub fn resolve(&self, question: &Question) -> (ResponseCode, Vec<ResourceRecord>) {
+        if !self.contains(&question.name) {
+            return (ResponseCode::Refused, Vec::new());
+        }
+
+        let mut answers = Vec::new();
+        let mut name = question.name.clone();
+
+        for _ in 0..=MAX_CNAME_CHAIN {
+            let records: Vec<&ResourceRecord> = self
+                .records
+                .iter()
+                .filter(|record| record.name == name)
+                .collect();
+
+            if records.is_empty() {
+                // Only the name asked about has to exist
+                let code = if answers.is_empty() {
+                    ResponseCode::NameError
+                } else {
+                    ResponseCode::NoError
+                };
+                return (code, answers);
+            }
+
+            let alias = records.iter().find_map(|record| match &record.data {
+                RecordData::Cname(target) if question.record_type != RecordType::Cname => {
+                    Some((*record, target.clone()))
+                }
+                _ => None,
+            });
+
+            match alias {
+                Some((record, target)) => {
+                    answers.push(record.clone());
+                    if !self.contains(&target) {
+                        break;
+                    }
+                    name = target;
+                }
+                None => {
+                    answers.extend(
+                        records
+                            .into_iter()
+                            .filter(|record| record.record_type() == question.record_type)
+                            .cloned(),
+                    );
+                    break;
+                }
+            }
+        }
+
+        (ResponseCode::NoError, answers)
+    }
+}
+
+impl FromStr for Zone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut origin = None;
+        let mut ttl = DEFAULT_TTL;
+        let mut records = Vec::new();
+
+        for (number, line) in (1..).zip(s.lines()) {
+            let line = line.split(';').next().unwrap_or_default();
+            let mut fields = line.split_whitespace().peekable();
+
+            let Some(first) = fields.next() else {
+                continue;
+            };
+
+            let context = || format!("Invalid zone on line {number}");
+
+            match first {
+                "$ORIGIN" => {
+                    let name = fields
+                        .next()
+                        .context("Origin has no name")
+                        .with_context(context)?;
+                    origin = Some(parse_name(name).with_context(context)?);
+                    continue;
+                }
+                "$TTL" => {
+                    let secs = fields
+                        .next()
+                        .context("TTL has no value")
+                        .with_context(context)?;
+                    ttl = Duration::from_secs(secs.parse().with_context(context)?);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let origin = origin
+                .as_deref()
+                .context("Records come after $ORIGIN")
+                .with_context(context)?;
+            let absolute = |name: &str| -> anyhow::Result<String> {
+                match name {
+                    "@" => Ok(origin.to_owned()),
+                    name if name.ends_with('.') => parse_name(name),
+                    name => parse_name(&format!("{name}.{origin}")),
+                }
+            };
+
+            let name = absolute(first).with_context(context)?;
+
+            let mut record_ttl = ttl;
+            if let Some(secs) = fields.peek().and_then(|field| field.parse().ok()) {
+                record_ttl = Duration::from_secs(secs);
+                fields.next();
+            }
+            if fields
+                .peek()
+                .is_some_and(|field| field.eq_ignore_ascii_case("IN"))
+            {
+                fields.next();
+            }
+
+            let record_type: RecordType = fields
+                .next()
+                .context("Record has no type")
+                .and_then(str::parse)
+                .with_context(context)?;
+            let value = fields
+                .next()
+                .context("Record has no data")
+                .with_context(context)?;
+            ensure!(fields.next().is_none(), "{}: trailing fields", context());
+
+            let data = match record_type {
+                RecordType::A => RecordData::A(value.parse().with_context(context)?),
+                RecordType::Aaaa => RecordData::Aaaa(value.parse().with_context(context)?),
+                RecordType::Cname => RecordData::Cname(absolute(value).with_context(context)?),
+            };
+
+            records.push(ResourceRecord {
+                name,
+                ttl: record_ttl,
+                data,
+            });
+        }
+
+        Ok(Self {
+            origin: origin.context("Zone has no $ORIGIN")?,
+            records,
+        })
+    }
+}
+
+/// Answers queries for the names in its zones.
+#[derive(Debug, Clone, Default)]
+pub struct DnsServer {
+    zones: Vec<Zone>,
+}
+
+impl DnsServer {
+    #[must_use]
+    pub fn new(zone: Zone) -> Self {
+        Self { zones: vec![zone] }
+    }
+
+    pub fn add_zone(&mut self, zone: Zone) {
+        self.zones.push(zone);
+    }
+
+    #[must_use]
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    /// The response to a query. Responses are never answered.
+    #[must_use]
+    pub fn respond(&self, query: &DnsMessage) -> Option<DnsMessage> {
+        if query.is_response {
+            return None;
+        }
+
+        let [question] = query.questions.as_slice() else {
+            return Some(query.response(ResponseCode::FormatError));
+        };
+
+        // The most specific zone holding the name
+        let Some(zone) = self
+            .zones
+            .iter()
+            .filter(|zone| zone.contains(&question.name))
+            .max_by_key(|zone| zone.origin.len())
+        else {
+            return Some(query.response(ResponseCode::Refused));
+        };
+
+        let (response_code, answers) = zone.resolve(question);
+
+        let mut response = query.response(response_code);
+        response.authoritative = true;
+        response.answers = answers;
+        Some(response)
+    }
+
+    /// Handles a packet sent to the DNS port, returning the response to
+    /// send back.
+    pub fn receive_ipv4(&self, packet: &Ipv4Packet) -> anyhow::Result<Option<Ipv4Packet>> {
+        let datagram = UDPFrame::decode_ipv4(packet)?;
+        ensure!(
+            datagram.target_port() == DNS_PORT,
+            "Datagram is not sent to the DNS port"
+        );
+
+        let query = DnsMessage::decode(datagram.data())?;
+
+        Ok(self.respond(&query).map(|response| {
+            response.to_packet(
+                packet.destination(),
+                DNS_PORT,
+                packet.source(),
+                datagram.source_port(),
+            )
+        }))
+    }
+}
+
+/// The outcome of a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsAnswer {
+    pub question: Question,
+    pub response_code: ResponseCode,
+    pub records: Vec<ResourceRecord>,
+}
+
+impl DnsAnswer {
+    /// The IPv4 addresses the name resolved to, aliases followed.
+    #[must_use]
+    pub fn ipv4_addresses(&self) -> Vec<Ipv4Address> {
+        self.records
+            .iter()
+            .filter_map(|record| match record.data {
+                RecordData::A(address) => Some(address),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// What resolving a name led to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// The answer was cached, with the TTLs that are left.
+    Cached(Vec<ResourceRecord>),
+    /// The name is being looked up. Holds the query to send, unless one was
+    /// already sent.
+    Query(Option<Ipv4Packet>),
+}
+
+#[derive(Debug)]
+struct PendingQuery {
+    question: Question,
+    source: Ipv4Address,
+    last_sent: Instant,
+    attempts: usize,
+}
+
+/// Sends queries to a nameserver and caches the answers for as long as
+/// their TTLs allow. Only positive answers are cached.
+#[derive(Debug)]
+pub struct DnsResolver {
+    nameserver: Option<Ipv4Address>,
+    port: u16,
+    // Answers with the time they were received and when they expire
+    cache: HashMap<Question, (Vec<ResourceRecord>, Instant, Instant)>,
+    pending: HashMap<u16, PendingQuery>,
+    next_id: u16,
+    query_timeout: Duration,
+    cache_hits: usize,
+    cache_misses: usize,
+    failed_queries: usize,
+}
+
+impl DnsResolver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            nameserver: None,
+            port: DEFAULT_RESOLVER_PORT,
+            cache: HashMap::new(),
+            pending: HashMap::new(),
+            next_id: 0,
+            query_timeout: DEFAULT_QUERY_TIMEOUT,
+            cache_hits: 0,
+            cache_misses: 0,
+            failed_queries: 0,
+        }
+    }
+
+    /// Sets the server queries are sent to. Outstanding queries are resent
+    /// to it right away, returning the packets to send. Responses from the
+    /// previous server are ignored from then on.
+    pub fn set_nameserver(&mut self, nameserver: Ipv4Address, now: Instant) -> Vec<Ipv4Packet> {
+        if self.nameserver.replace(nameserver) == Some(nameserver) {
+            return Vec::new();
+        }
+
+        self.pending
+            .iter_mut()
+            .map(|(id, pending)| {
+                pending.last_sent = now;
+                pending.attempts = 1;
+
+                let query = DnsMessage::query(*id, pending.question.clone());
+                query.to_packet(pending.source, self.port, nameserver, DNS_PORT)
+            })
+            .collect()
+    }
+
+    /// Sets the port queries are sent from.
+    #[must_use]
+    pub fn set_port(self, port: u16) -> Self {
+        Self { port, ..self }
+    }
+
+    /// Sets how long to wait for a response before asking again.
+    #[must_use]
+    pub fn set_query_timeout(self, query_timeout: Duration) -> Self {
+        Self {
+            query_timeout,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub const fn nameserver(&self) -> Option<Ipv4Address> {
+        self.nameserver
+    }
+
+    #[must_use]
+    pub const fn cache_hits(&self) -> usize {
+        self.cache_hits
+    }
+
+    #[must_use]
+    pub const fn cache_misses(&self) -> usize {
+        self.cache_misses
+    }
+
+    /// The amount of queries that went unanswered after all attempts.
+    #[must_use]
+    pub const fn failed_queries(&self) -> usize {
+        self.failed_queries
+    }
+
+    /// The cached answer to the question, with the TTLs that are left.
+    pub fn lookup(&mut self, question: &Question, now: Instant) -> Option<Vec<ResourceRecord>> {
+        let cached = self
+            .cache
+            .get(question)
+            .filter(|(_, _, expires)| now < *expires);
+
+        let Some((records, received, _)) = cached else {
+            self.cache_misses += 1;
+            return None;
+        };
+        self.cache_hits += 1;
+
+        let age = now.duration_since(*received);
+        Some(
+            records
+                .iter()
+                .map(|record| ResourceRecord {
+                    ttl: record.ttl.saturating_sub(age),
+                    ..record.clone()
+                })
+                .collect(),
+        )
+    }
+
+    /// Asks the nameserver the question, from the given address. Nothing is
+    /// sent if the question is already being asked.
+    pub fn query(
+        &mut self,
+        question: Question,
+        source: Ipv4Address,
+        now: Instant,
+    ) -> anyhow::Result<Option<Ipv4Packet>> {
+        let nameserver = self.nameserver.context("Resolver has no nameserver")?;
+
+        if self
+            .pending
+            .values()
+            .any(|pending| pending.question == question)
+        {
+            return Ok(None);
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let packet = DnsMessage::query(id, question.clone())
+            .to_packet(source, self.port, nameserver, DNS_PORT);
+        self.pending.insert(
+            id,
+            PendingQuery {
+                question,
+                source,
+                last_sent: now,
+                attempts: 1,
+            },
+        );
+
+        Ok(Some(packet))
+    }
+
+    /// Answers the question from the cache, or asks the nameserver.
+    pub fn resolve(
+        &mut self,
+        question: Question,
+        source: Ipv4Address,
+        now: Instant,
+    ) -> anyhow::Result<Resolution> {
+        if let Some(records) = self.lookup(&question, now) {
+            return Ok(Resolution::Cached(records));
+        }
+
+        self.query(question, source, now).map(Resolution::Query)
+    }
+
+    /// Handles a response from the nameserver, returning the answer to the
+    /// query it belongs to. Responses to no outstanding query are ignored.
+    pub fn receive(
+        &mut self,
+        packet: &Ipv4Packet,
+        now: Instant,
+    ) -> anyhow::Result<Option<DnsAnswer>> {
+        let datagram = UDPFrame::decode_ipv4(packet)?;
+        if Some(packet.source()) != self.nameserver
+            || datagram.source_port() != DNS_PORT
+            || datagram.target_port() != self.port
+        {
+            return Ok(None);
+        }
+
+        let response = DnsMessage::decode(datagram.data())?;
+        ensure!(response.is_response, "Message is not a response");
+
+        let matches = self.pending.get(&response.id).is_some_and(|pending| {
+            response.questions.as_slice() == std::slice::from_ref(&pending.question)
+        });
+        if !matches {
+            return Ok(None);
+        }
+        let pending = self
+            .pending
+            .remove(&response.id)
+            .expect("Response matches a pending query");
+
+        let ttl = response.answers.iter().map(|record| record.ttl).min();
+        if let Some(ttl) = ttl.filter(|ttl| !ttl.is_zero()) {
+            self.cache.insert(
+                pending.question.clone(),
+                (response.answers.clone(), now, now + ttl),
+            );
+        }
+
+        Ok(Some(DnsAnswer {
+            question: pending.question,
+            response_code: response.response_code,
+            records: response.answers,
+        }))
+    }
+
+    /// Resends queries that timed out, giving up on those that were sent
+    /// too often. Expired answers are removed from the cache.
+    pub fn poll(&mut self, now: Instant) -> Vec<Ipv4Packet> {
+        self.cache.retain(|_, (_, _, expires)| now < *expires);
+
+        let query_timeout = self.query_timeout;
+        let mut failed_queries = 0;
+        self.pending.retain(|_, pending| {
+            let timed_out = now.duration_since(pending.last_sent) >= query_timeout;
+            if timed_out && pending.attempts >= MAX_QUERY_ATTEMPTS {
+                failed_queries += 1;
+                return false;
+            }
+            true
+        });
+        self.failed_queries += failed_queries;
+
+        let Some(nameserver) = self.nameserver else {
+            return Vec::new();
+        };
+
+        let mut retries = Vec::new();
+        for (id, pending) in &mut self.pending {
+            if now.duration_since(pending.last_sent) >= query_timeout {
+                pending.last_sent = now;
+                pending.attempts += 1;
+
+                let query = DnsMessage::query(*id, pending.question.clone());
+                retries.push(query.to_packet(pending.source, self.port, nameserver, DNS_PORT));
+            }
+        }
+
+        retries
+    }
+}
+
+impl Default for DnsResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::{bit_string::BitString, ip_address::Ipv4Address};
+
+    use super::{
+        DnsMessage, DnsResolver, DnsServer, Question, RecordData, RecordType, ResourceRecord,
+        ResponseCode, Zone, DEFAULT_QUERY_TIMEOUT, MAX_QUERY_ATTEMPTS,
+    };
+
+    const ZONE: &str = "
+        $ORIGIN example.com.
+        $TTL 300
+        @        IN A     192.0.2.1
+        www      60 CNAME @ ; Alias of the apex
+        mail        A     192.0.2.2
+        mail        AAAA  2001:db8::2
+        ftp         CNAME files.example.net.
+    ";
+
+    const CLIENT: Ipv4Address = Ipv4Address::new([10, 0, 0, 2]);
+    const SERVER: Ipv4Address = Ipv4Address::new([10, 0, 0, 53]);
+
+    fn zone() -> Zone {
+        ZONE.parse().expect("Zone is valid")
+    }
+
+    fn question(name: &str, record_type: RecordType) -> Question {
+        Question::new(name, record_type).expect("Name is valid")
+    }
+
+    #[test]
+    fn round_trip() {
+        let query = DnsMessage::query(0x1234, question("WWW.Example.com.", RecordType::A));
+        assert_eq!(query.questions[0].name, "www.example.com");
+
+        let (_, answers) = zone().resolve(&query.questions[0]);
+        let mut response = query.response(ResponseCode::NoError);
+        response.authoritative = true;
+        response.answers = answers;
+
+        for message in [query, response] {
+            let encoded = message.encode();
+            assert_eq!(
+                DnsMessage::decode(&encoded).expect("Message is valid"),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_compressed_names() {
+        #[rustfmt::skip]
+        let bytes: Vec<u8> = vec![
+            0x00, 0x07, 0x84, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+            // Question for a.example.com
+            0x01, b'a', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00,
+            0x00, 0x01, 0x00, 0x01,
+            // Answer pointing back at the name of the question
+            0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3C, 0x00, 0x04,
+            192, 0, 2, 7,
+        ];
+
+        let message = DnsMessage::decode(&BitString::from(bytes)).expect("Message is valid");
+
+        assert!(message.is_response && message.authoritative);
+        assert_eq!(
+            message.answers,
+            [ResourceRecord {
+                name: "a.example.com".to_owned(),
+                ttl: Duration::from_secs(60),
+                data: RecordData::A(Ipv4Address::new([192, 0, 2, 7])),
+            }]
+        );
+
+        // Pointers to themselves never end
+        let mut looping = message.encode().as_vec_exact_u8();
+        looping.truncate(12);
+        looping[5] = 1;
+        looping[7] = 0;
+        looping.extend([0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01]);
+        assert!(DnsMessage::decode(&BitString::from(looping)).is_err());
+    }
+
+    #[test]
+    fn zone_answers() {
+        let zone = zone();
+        assert_eq!(zone.records().len(), 5);
+
+        let (code, answers) = zone.resolve(&question("www.example.com", RecordType::A));
+        assert_eq!(code, ResponseCode::NoError);
+        let data: Vec<String> = answers
+            .iter()
+            .map(|record| record.data.to_string())
+            .collect();
+        assert_eq!(data, ["CNAME example.com", "A 192.0.2.1"]);
+        assert_eq!(answers[0].ttl, Duration::from_secs(60));
+        assert_eq!(answers[1].ttl, Duration::from_secs(300));
+
+        // Aliases outside the zone are left to the resolver
+        let (_, answers) = zone.resolve(&question("ftp.example.com", RecordType::A));
+        assert_eq!(answers.len(), 1);
+
+        let (code, answers) = zone.resolve(&question("mail.example.com", RecordType::Cname));
+        assert_eq!((code, answers.len()), (ResponseCode::NoError, 0));
+
+        let (code, _) = zone.resolve(&question("nope.example.com", RecordType::A));
+        assert_eq!(code, ResponseCode::NameError);
+
+        let server = DnsServer::new(zone);
+        let query = DnsMessage::query(1, question("example.org", RecordType::A));
+        let response = server.respond(&query).expect("Queries are answered");
+        assert_eq!(response.response_code, ResponseCode::Refused);
+        assert_eq!(server.respond(&response), None);
+
+        assert!("www A 192.0.2.1".parse::<Zone>().is_err());
+        assert!("$ORIGIN a.\nwww MX mail".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn resolver_caches() {
+        let start = Instant::now();
+        let server = DnsServer::new(zone());
+        let mut resolver = DnsResolver::new();
+        let _ = resolver.set_nameserver(SERVER, start);
+        let question = question("www.example.com", RecordType::A);
+
+        assert_eq!(resolver.lookup(&question, start), None);
+        let query = resolver
+            .query(question.clone(), CLIENT, start)
+            .expect("Resolver has a nameserver")
+            .expect("Question is new");
+        // Asking again while waiting doesn't send another query
+        assert_eq!(
+            resolver
+                .query(question.clone(), CLIENT, start)
+                .ok()
+                .flatten(),
+            None
+        );
+
+        let response = server
+            .receive_ipv4(&query)
+            .expect("Query is valid")
+            .expect("Query is answered");
+        let answer = resolver
+            .receive(&response, start)
+            .expect("Response is valid")
+            .expect("Response matches the query");
+        assert_eq!(answer.ipv4_addresses(), [Ipv4Address::new([192, 0, 2, 1])]);
+
+        // Cached for the lowest TTL of the answer, counting down
+        let later = start + Duration::from_secs(45);
+        let cached = resolver.lookup(&question, later).expect("Answer is cached");
+        assert_eq!(cached[0].ttl, Duration::from_secs(15));
+        assert_eq!(cached[1].ttl, Duration::from_secs(255));
+
+        assert_eq!(
+            resolver.lookup(&question, start + Duration::from_secs(60)),
+            None
+        );
+        assert_eq!((resolver.cache_hits(), resolver.cache_misses()), (1, 2));
+
+        // The same response again is not expected anymore
+        assert_eq!(resolver.receive(&response, later).ok().flatten(), None);
+    }
+
+    #[test]
+    fn resolver_retries() {
+        let start = Instant::now();
+        let mut resolver = DnsResolver::new();
+        let _ = resolver.set_nameserver(SERVER, start);
+
+        let query = resolver
+            .query(question("example.com", RecordType::A), CLIENT, start)
+            .expect("Resolver has a nameserver");
+        assert!(query.is_some());
+
+        let mut retries = 0;
+        for attempt in 1..=MAX_QUERY_ATTEMPTS {
+            let now = start + DEFAULT_QUERY_TIMEOUT * u32::try_from(attempt).expect("Few attempts");
+            retries += resolver.poll(now).len();
+        }
+
+        assert_eq!(retries, MAX_QUERY_ATTEMPTS - 1);
+        assert_eq!(resolver.failed_queries(), 1);
+    }
+
+    #[test]
+    fn resolver_switches_nameserver() {
+        let start = Instant::now();
+        let backup = Ipv4Address::new([10, 0, 1, 53]);
+        let server = DnsServer::new(zone());
+        let mut resolver = DnsResolver::new();
+        assert!(resolver.set_nameserver(SERVER, start).is_empty());
+
+        let question = question("mail.example.com", RecordType::A);
+        let query = resolver
+            .query(question.clone(), CLIENT, start)
+            .expect("Resolver has a nameserver")
+            .expect("Question is new");
+        assert!(resolver.set_nameserver(SERVER, start).is_empty());
+
+        // The outstanding query is asked again of the new server
+        let later = start + DEFAULT_QUERY_TIMEOUT / 2;
+        let resent = resolver.set_nameserver(backup, later);
+        assert_eq!(resent.len(), 1);
+        assert_eq!(resent[0].destination(), backup);
+        assert!(resolver.poll(later).is_empty());
+
+        // Only the new server is listened to
+        let stale = server
+            .receive_ipv4(&query)
+            .expect("Query is valid")
+            .expect("Query is answered");
+        assert_eq!(resolver.receive(&stale, later).ok().flatten(), None);
+
+        let response = server
+            .receive_ipv4(&resent[0])
+            .expect("Query is valid")
+            .expect("Query is answered");
+        let answer = resolver
+            .receive(&response, later)
+            .expect("Response is valid")
+            .expect("Response matches the query");
+        assert_eq!(answer.question, question);
+    }
+}
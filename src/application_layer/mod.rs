@@ -0,0 +1,2 @@
+pub mod dhcp;
+pub mod dns;
@@ -0,0 +1,86 @@
+use std::env;
+
+use anyhow::{bail, Context};
+use network_sim::{bit::Bit, bit_string::BitString, data_link_layer::crc_analysis::CrcAnalysis};
+
+const USAGE: &str = "Usage: crc_analysis <generator> <message length> [bit error rate...]
+
+    generator       The generator in binary, highest power first, e.g. 1011
+    message length  The amount of message bits the crc is appended to
+    bit error rate  Rates to estimate the undetected error probability for,
+                    defaults to 1e-2 1e-3 1e-4 1e-5 1e-6";
+
+const DEFAULT_BERS: [f64; 5] = [1e-2, 1e-3, 1e-4, 1e-5, 1e-6];
+
+fn parse_generator(arg: &str) -> anyhow::Result<BitString> {
+    arg.chars()
+        .map(|char| match char {
+            '0' => Ok(Bit::Off),
+            '1' => Ok(Bit::On),
+            _ => bail!("The generator may only contain 0 and 1, found '{char}'"),
+        })
+        .collect()
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() < 2 || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let generator = parse_generator(&args[0])?;
+    let message_len = args[1]
+        .parse::<usize>()
+        .context("The message length must be a positive integer")?;
+
+    let bers = if args.len() > 2 {
+        args[2..]
+            .iter()
+            .map(|arg| {
+                arg.parse::<f64>()
+                    .with_context(|| format!("'{arg}' is not a bit error rate"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        DEFAULT_BERS.to_vec()
+    };
+
+    let analysis = CrcAnalysis::new(&generator, message_len)?;
+
+    println!("Generator:            {generator}");
+    println!("Message length:       {message_len}");
+    println!("Codeword length:      {}", analysis.codeword_len());
+
+    match analysis.min_hamming_distance() {
+        Some(distance) => println!("Min hamming distance: {distance}"),
+        None => println!("Min hamming distance: every error is detected"),
+    }
+    println!(
+        "Burst detection:      every burst up to {} bits",
+        analysis.burst_detection_len()
+    );
+
+    println!();
+    println!("Undetectable error patterns by weight:");
+    for (weight, count) in analysis.weight_distribution().iter().enumerate().skip(1) {
+        if *count > 0. {
+            println!("    {weight:>6}: {count}");
+        }
+    }
+
+    println!();
+    println!("Undetected error probability:");
+    for ber in bers {
+        if !(0. ..=1.).contains(&ber) {
+            bail!("The bit error rate {ber} is not between 0 and 1");
+        }
+        println!(
+            "    BER {ber:e}: {:e}",
+            analysis.undetected_error_probability(ber)
+        );
+    }
+
+    Ok(())
+}
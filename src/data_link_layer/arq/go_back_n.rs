@@ -0,0 +1,289 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::tcp::{TCPFrame, TCPFrameBuilder, ACK, FIN},
+};
+
+use super::{
+    data_frame, distance, sequence_num, Arq, ArqReceiver, ArqSender, DEFAULT_RETRANSMIT_TIMEOUT,
+    NAK,
+};
+
+/// The sending half of Go-Back-N. Up to a window of frames is in flight, a
+/// single timer runs for the oldest of them. When it expires, every frame
+/// from the oldest onwards is sent again.
+#[derive(Debug, Clone)]
+pub struct GoBackNSender {
+    builder: TCPFrameBuilder,
+    data: Vec<BitString>,
+    window_size: usize,
+    sequence_space: u32,
+    timeout: Duration,
+
+    // The oldest unacknowledged frame
+    base: usize,
+    // The next frame to send
+    next: usize,
+    deadline: Option<Instant>,
+
+    transmissions: usize,
+    retransmissions: usize,
+}
+
+impl GoBackNSender {
+    /// Sends every data point in its own frame, built by the builder. The
+    /// sequence space is the smallest power of two larger than the window.
+    #[must_use]
+    pub fn new(builder: TCPFrameBuilder, data: Vec<BitString>, window_size: u16) -> Self {
+        assert!(window_size > 0, "The window holds at least one frame");
+
+        Self {
+            builder,
+            data,
+            window_size: usize::from(window_size),
+            sequence_space: Arq::GoBackN.sequence_space(window_size),
+            timeout: DEFAULT_RETRANSMIT_TIMEOUT,
+            base: 0,
+            next: 0,
+            deadline: None,
+            transmissions: 0,
+            retransmissions: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn set_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+impl ArqSender for GoBackNSender {
+    /// Once the timer expires the whole window is sent again, otherwise the
+    /// window is filled up with new frames.
+    fn poll(&mut self, now: Instant) -> Vec<TCPFrame> {
+        if self.deadline.is_some_and(|deadline| now >= deadline) {
+            self.retransmissions += self.next - self.base;
+            self.next = self.base;
+            self.deadline = None;
+        }
+
+        let end = usize::min(self.base + self.window_size, self.data.len());
+        let frames: Vec<TCPFrame> = (self.next..end)
+            .map(|index| data_frame(&self.builder, &self.data, index, self.sequence_space))
+            .collect();
+
+        if !frames.is_empty() {
+            self.next = end;
+            self.transmissions += frames.len();
+            self.deadline.get_or_insert(now + self.timeout);
+        }
+
+        frames
+    }
+
+    /// Handles a cumulative acknowledgement, which names the next frame the
+    /// receiver expects.
+    fn receive(&mut self, ack: &TCPFrame, now: Instant) {
+        if !ack.has_flags(ACK) || ack.has_flags(NAK) {
+            return;
+        }
+
+        let base_num = sequence_num(self.base, self.sequence_space);
+        let acknowledged = distance(base_num, ack.ack_num(), self.sequence_space) as usize;
+
+        if acknowledged == 0 || acknowledged > self.next - self.base {
+            return;
+        }
+
+        self.base += acknowledged;
+        self.deadline = (self.base < self.next).then(|| now + self.timeout);
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    fn is_finished(&self) -> bool {
+        self.base == self.data.len()
+    }
+
+    fn acknowledged(&self) -> usize {
+        self.base
+    }
+
+    fn transmissions(&self) -> usize {
+        self.transmissions
+    }
+
+    fn retransmissions(&self) -> usize {
+        self.retransmissions
+    }
+
+    fn sequence_space(&self) -> u32 {
+        self.sequence_space
+    }
+}
+
+/// The receiving half of Go-Back-N. Only the frame that is next in line is
+/// accepted, every frame is answered with a cumulative acknowledgement.
+#[derive(Debug, Clone)]
+pub struct GoBackNReceiver {
+    builder: TCPFrameBuilder,
+    sequence_space: u32,
+    expected: u32,
+    finished: bool,
+}
+
+impl GoBackNReceiver {
+    /// Acknowledgements are built by the builder. The window has to match
+    /// that of the sender, as it determines the sequence space.
+    #[must_use]
+    pub fn new(builder: TCPFrameBuilder, window_size: u16) -> Self {
+        assert!(window_size > 0, "The window holds at least one frame");
+
+        Self {
+            builder: builder.set_flags(ACK),
+            sequence_space: Arq::GoBackN.sequence_space(window_size),
+            expected: 0,
+            finished: false,
+        }
+    }
+}
+
+impl ArqReceiver for GoBackNReceiver {
+    /// Only a frame that is next in line delivers data, every frame is
+    /// answered with an acknowledgement.
+    fn receive(&mut self, frame: &TCPFrame) -> (Option<TCPFrame>, Vec<BitString>) {
+        let accepted = !self.finished && frame.sequence_num() == self.expected;
+
+        if accepted {
+            self.expected = (self.expected + 1) % self.sequence_space;
+            self.finished = frame.has_flags(FIN);
+        }
+
+        let ack = self
+            .builder
+            .clone()
+            .set_ack_num(self.expected)
+            .build(BitString::new());
+
+        (
+            Some(ack),
+            accepted.then(|| frame.data().clone()).into_iter().collect(),
+        )
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::data_link_layer::{
+        arq::fixtures::{data, pair, TIMEOUT},
+        frame::tcp::TCPFrame,
+    };
+
+    use super::Arq;
+
+    #[test]
+    fn fills_window() {
+        let (mut sender, mut receiver) = pair(Arq::GoBackN, 10);
+        let now = Instant::now();
+        assert_eq!(sender.sequence_space(), 4);
+
+        let frames = sender.poll(now);
+        assert_eq!(frames.len(), 3);
+        assert!(sender.poll(now).is_empty());
+
+        // Every acknowledgement moves the window along by one frame
+        let (ack, delivered) = receiver.receive(&frames[0]);
+        assert_eq!(delivered, data(1));
+        sender.receive(&ack.expect("Frames are acknowledged"), now);
+
+        let frames = sender.poll(now);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].sequence_num(), 3);
+    }
+
+    #[test]
+    fn goes_back_on_timeout() {
+        let (mut sender, mut receiver) = pair(Arq::GoBackN, 10);
+        let start = Instant::now();
+
+        let frames = sender.poll(start);
+
+        // The first frame is lost, so the others are out of order
+        for frame in &frames[1..] {
+            let (ack, delivered) = receiver.receive(frame);
+            let ack = ack.expect("Frames are acknowledged");
+            assert!(delivered.is_empty());
+            assert_eq!(ack.ack_num(), 0);
+            sender.receive(&ack, start);
+        }
+        assert!(sender.poll(start + TIMEOUT / 2).is_empty());
+
+        let resent = sender.poll(start + TIMEOUT);
+        assert_eq!(resent, frames);
+        assert_eq!(sender.retransmissions(), 3);
+    }
+
+    #[test]
+    fn transfers_with_wraparound() {
+        let (mut sender, mut receiver) = pair(Arq::GoBackN, 10);
+        let mut now = Instant::now();
+        let mut received = Vec::new();
+
+        // Every third frame and every fourth acknowledgement is lost
+        let mut sent = 0;
+        let mut acks = 0;
+        while !sender.is_finished() {
+            for frame in sender.poll(now) {
+                sent += 1;
+                if sent % 3 == 0 {
+                    continue;
+                }
+
+                let (ack, delivered) = receiver.receive(&frame);
+                received.extend(delivered);
+
+                acks += 1;
+                if acks % 4 != 0 {
+                    sender.receive(&ack.expect("Frames are acknowledged"), now);
+                }
+            }
+
+            now += TIMEOUT;
+        }
+
+        assert_eq!(received, data(10));
+        assert!(receiver.is_finished());
+        assert!(sender.retransmissions() > 0);
+    }
+
+    #[test]
+    fn ignores_stale_acks() {
+        let (mut sender, mut receiver) = pair(Arq::GoBackN, 2);
+        let now = Instant::now();
+
+        let frames: Vec<TCPFrame> = sender.poll(now);
+        let (Some(first_ack), _) = receiver.receive(&frames[0]) else {
+            panic!("Frames are acknowledged");
+        };
+        let (Some(last_ack), _) = receiver.receive(&frames[1]) else {
+            panic!("Frames are acknowledged");
+        };
+
+        sender.receive(&last_ack, now);
+        assert!(sender.is_finished());
+        assert_eq!(sender.deadline(), None);
+
+        // An older acknowledgement can't undo progress
+        sender.receive(&first_ack, now);
+        assert_eq!(sender.acknowledged(), 2);
+    }
+}
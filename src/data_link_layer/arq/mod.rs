@@ -0,0 +1,228 @@
+//! Automatic repeat request protocols, which make a link reliable by
+//! retransmitting frames until they are acknowledged.
+//!
+//! Data frames are TCP frames carrying a sequence number, the last frame of a
+//! transfer has the FIN flag set. Acknowledgements are TCP frames with the ACK
+//! flag and an acknowledgement number, negative acknowledgements also carry the
+//! RST flag. Sequence numbers count frames and wrap around in a sequence space
+//! just large enough for the window.
+//!
+//! The link layer numbers its transfers, so acknowledgements meant for the
+//! transfer before aren't mistaken for ones of the next. Data frames carry the
+//! number in their acknowledgement number, acknowledgements in their sequence
+//! number.
+
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::tcp::{TCPFrame, TCPFrameBuilder, ACK, FIN, RST},
+};
+
+use self::{
+    go_back_n::{GoBackNReceiver, GoBackNSender},
+    selective_repeat::{SelectiveRepeatReceiver, SelectiveRepeatSender},
+    stop_and_wait::{StopAndWaitReceiver, StopAndWaitSender},
+};
+
+pub mod go_back_n;
+pub mod selective_repeat;
+pub mod stop_and_wait;
+
+/// How long a sender waits for an acknowledgement before retransmitting.
+pub const DEFAULT_RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How many retransmissions in a row a sender makes without any progress
+/// before it gives up.
+pub const MAX_RETRANSMISSIONS: usize = 20;
+
+/// The flags of a negative acknowledgement, which asks for the frame named
+/// by its acknowledgement number to be sent again.
+pub const NAK: u8 = ACK | RST;
+
+/// The protocols a reliable link can run. Both ends of a link have to run the
+/// same one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arq {
+    /// A single frame in flight with an alternating bit as sequence number,
+    /// damaged frames are rejected with a negative acknowledgement. The
+    /// window size is ignored.
+    StopAndWait,
+    /// Cumulative acknowledgements and a single timer, every frame from a
+    /// lost one onwards is sent again.
+    #[default]
+    GoBackN,
+    /// Individual acknowledgements and a timer per frame, only lost frames
+    /// are sent again and the receiver buffers frames that arrive early.
+    SelectiveRepeat,
+}
+
+impl Arq {
+    /// The amount of sequence numbers a window of the given size needs.
+    #[must_use]
+    pub const fn sequence_space(self, window_size: u16) -> u32 {
+        let window_size = window_size as u32;
+
+        match self {
+            Self::StopAndWait => 2,
+            Self::GoBackN => (window_size + 1).next_power_of_two(),
+            // The windows of sender and receiver can't overlap
+            Self::SelectiveRepeat => (window_size * 2).next_power_of_two(),
+        }
+    }
+
+    /// A sender for the data, with every data point in its own frame.
+    #[must_use]
+    pub fn sender(
+        self,
+        builder: TCPFrameBuilder,
+        data: Vec<BitString>,
+        window_size: u16,
+        timeout: Duration,
+    ) -> Box<dyn ArqSender> {
+        match self {
+            Self::StopAndWait => {
+                Box::new(StopAndWaitSender::new(builder, data).set_timeout(timeout))
+            }
+            Self::GoBackN => {
+                Box::new(GoBackNSender::new(builder, data, window_size).set_timeout(timeout))
+            }
+            Self::SelectiveRepeat => Box::new(
+                SelectiveRepeatSender::new(builder, data, window_size).set_timeout(timeout),
+            ),
+        }
+    }
+
+    /// A receiver acknowledging frames with frames built by the builder.
+    #[must_use]
+    pub fn receiver(self, builder: TCPFrameBuilder, window_size: u16) -> Box<dyn ArqReceiver> {
+        match self {
+            Self::StopAndWait => Box::new(StopAndWaitReceiver::new(builder)),
+            Self::GoBackN => Box::new(GoBackNReceiver::new(builder, window_size)),
+            Self::SelectiveRepeat => Box::new(SelectiveRepeatReceiver::new(builder, window_size)),
+        }
+    }
+}
+
+/// The sending half of an ARQ protocol.
+pub trait ArqSender: Debug {
+    /// The frames to send now, new ones as well as retransmissions.
+    fn poll(&mut self, now: Instant) -> Vec<TCPFrame>;
+
+    /// Handles an acknowledgement. Acknowledgements for frames that aren't in
+    /// flight are ignored.
+    fn receive(&mut self, ack: &TCPFrame, now: Instant);
+
+    /// When the next frame is sent again, if any frame is in flight.
+    fn deadline(&self) -> Option<Instant>;
+
+    /// Whether every frame has been acknowledged.
+    fn is_finished(&self) -> bool;
+
+    /// The amount of frames up to the first unacknowledged one.
+    fn acknowledged(&self) -> usize;
+
+    /// The amount of frames sent, retransmissions included.
+    fn transmissions(&self) -> usize;
+
+    fn retransmissions(&self) -> usize;
+
+    fn sequence_space(&self) -> u32;
+}
+
+/// The receiving half of an ARQ protocol.
+pub trait ArqReceiver: Debug {
+    /// Handles a data frame, returning the acknowledgement to send back, if
+    /// any, and the data that can now be delivered in order.
+    fn receive(&mut self, frame: &TCPFrame) -> (Option<TCPFrame>, Vec<BitString>);
+
+    /// Handles a frame that arrived damaged, returning the negative
+    /// acknowledgement to send back if the protocol uses them.
+    fn damaged(&mut self) -> Option<TCPFrame> {
+        None
+    }
+
+    /// Whether the whole transfer has been delivered.
+    fn is_finished(&self) -> bool;
+}
+
+/// How far `to` lies ahead of `from` in a sequence space of `modulus`.
+pub(crate) const fn distance(from: u32, to: u32, modulus: u32) -> u32 {
+    (to + modulus - from) % modulus
+}
+
+/// The sequence number of the frame at `index` in a transfer.
+pub(crate) fn sequence_num(index: usize, modulus: u32) -> u32 {
+    u32::try_from(index % modulus as usize).expect("Sequence numbers are below the modulus")
+}
+
+/// The frame carrying the data point at `index` in a transfer, the last one
+/// marked with FIN.
+pub(crate) fn data_frame(
+    builder: &TCPFrameBuilder,
+    data: &[BitString],
+    index: usize,
+    modulus: u32,
+) -> TCPFrame {
+    let mut builder = builder
+        .clone()
+        .set_sequence_num(sequence_num(index, modulus));
+    if index + 1 == data.len() {
+        builder = builder.set_flags(FIN);
+    }
+
+    builder.build(data[index].clone())
+}
+
+#[cfg(test)]
+mod fixtures {
+    use std::time::Duration;
+
+    use crate::{bit_string::BitString, data_link_layer::frame::tcp::TCPFrameBuilder};
+
+    use super::{Arq, ArqReceiver, ArqSender};
+
+    pub const TIMEOUT: Duration = Duration::from_millis(100);
+
+    pub const WINDOW_SIZE: u16 = 3;
+
+    pub fn builder(source_port: u16, target_port: u16) -> TCPFrameBuilder {
+        TCPFrameBuilder::new()
+            .set_source_port(source_port)
+            .set_target_port(target_port)
+            .set_window_size(WINDOW_SIZE)
+    }
+
+    pub fn data(frames: u8) -> Vec<BitString> {
+        (0..frames)
+            .map(|frame| BitString::from(&[frame][..]))
+            .collect()
+    }
+
+    /// Both halves of the protocol, sending the given amount of frames.
+    pub fn pair(arq: Arq, frames: u8) -> (Box<dyn ArqSender>, Box<dyn ArqReceiver>) {
+        let sender = arq.sender(builder(30, 40), data(frames), WINDOW_SIZE, TIMEOUT);
+        let receiver = arq.receiver(builder(40, 30), WINDOW_SIZE);
+
+        (sender, receiver)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{distance, sequence_num};
+
+    #[test]
+    fn wraps_around() {
+        assert_eq!(sequence_num(7, 8), 7);
+        assert_eq!(sequence_num(8, 8), 0);
+        assert_eq!(sequence_num(21, 8), 5);
+
+        assert_eq!(distance(6, 1, 8), 3);
+        assert_eq!(distance(1, 6, 8), 5);
+        assert_eq!(distance(3, 3, 8), 0);
+    }
+}
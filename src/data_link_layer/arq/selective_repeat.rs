@@ -0,0 +1,345 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::tcp::{TCPFrame, TCPFrameBuilder, ACK, FIN},
+};
+
+use super::{
+    data_frame, distance, sequence_num, Arq, ArqReceiver, ArqSender, DEFAULT_RETRANSMIT_TIMEOUT,
+    NAK,
+};
+
+/// The sending half of Selective Repeat. Up to a window of frames is in
+/// flight, each with its own timer. Only frames whose timer expires are sent
+/// again.
+#[derive(Debug, Clone)]
+pub struct SelectiveRepeatSender {
+    builder: TCPFrameBuilder,
+    data: Vec<BitString>,
+    window_size: usize,
+    sequence_space: u32,
+    timeout: Duration,
+
+    // The oldest unacknowledged frame
+    base: usize,
+    // The next frame to send
+    next: usize,
+    // When each frame from base to next is sent again, none once acknowledged
+    timers: VecDeque<Option<Instant>>,
+
+    transmissions: usize,
+    retransmissions: usize,
+}
+
+impl SelectiveRepeatSender {
+    /// Sends every data point in its own frame, built by the builder. The
+    /// sequence space is the smallest power of two holding two windows.
+    #[must_use]
+    pub fn new(builder: TCPFrameBuilder, data: Vec<BitString>, window_size: u16) -> Self {
+        assert!(window_size > 0, "The window holds at least one frame");
+
+        Self {
+            builder,
+            data,
+            window_size: usize::from(window_size),
+            sequence_space: Arq::SelectiveRepeat.sequence_space(window_size),
+            timeout: DEFAULT_RETRANSMIT_TIMEOUT,
+            base: 0,
+            next: 0,
+            timers: VecDeque::new(),
+            transmissions: 0,
+            retransmissions: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn set_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+impl ArqSender for SelectiveRepeatSender {
+    /// Frames whose timer expired are sent again, then the window is filled
+    /// up with new frames.
+    fn poll(&mut self, now: Instant) -> Vec<TCPFrame> {
+        let mut frames = Vec::new();
+
+        for (offset, timer) in self.timers.iter_mut().enumerate() {
+            if timer.is_some_and(|deadline| now >= deadline) {
+                *timer = Some(now + self.timeout);
+                frames.push(offset);
+            }
+        }
+        self.retransmissions += frames.len();
+
+        let mut frames: Vec<TCPFrame> = frames
+            .into_iter()
+            .map(|offset| {
+                data_frame(
+                    &self.builder,
+                    &self.data,
+                    self.base + offset,
+                    self.sequence_space,
+                )
+            })
+            .collect();
+
+        let end = usize::min(self.base + self.window_size, self.data.len());
+        for index in self.next..end {
+            frames.push(data_frame(
+                &self.builder,
+                &self.data,
+                index,
+                self.sequence_space,
+            ));
+            self.timers.push_back(Some(now + self.timeout));
+        }
+        self.next = self.next.max(end);
+        self.transmissions += frames.len();
+
+        frames
+    }
+
+    /// Handles an individual acknowledgement, which names the frame it
+    /// acknowledges. The window moves along once its oldest frame is
+    /// acknowledged.
+    fn receive(&mut self, ack: &TCPFrame, _now: Instant) {
+        if !ack.has_flags(ACK) || ack.has_flags(NAK) {
+            return;
+        }
+
+        let base_num = sequence_num(self.base, self.sequence_space);
+        let offset = distance(base_num, ack.ack_num(), self.sequence_space) as usize;
+
+        if let Some(timer) = self.timers.get_mut(offset) {
+            *timer = None;
+        }
+
+        while self.timers.front() == Some(&None) {
+            self.timers.pop_front();
+            self.base += 1;
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.timers.iter().flatten().min().copied()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.base == self.data.len()
+    }
+
+    fn acknowledged(&self) -> usize {
+        self.base
+    }
+
+    fn transmissions(&self) -> usize {
+        self.transmissions
+    }
+
+    fn retransmissions(&self) -> usize {
+        self.retransmissions
+    }
+
+    fn sequence_space(&self) -> u32 {
+        self.sequence_space
+    }
+}
+
+/// The receiving half of Selective Repeat. Frames within the window are
+/// acknowledged individually and buffered until every frame in front of them
+/// has arrived.
+#[derive(Debug, Clone)]
+pub struct SelectiveRepeatReceiver {
+    builder: TCPFrameBuilder,
+    window_size: u32,
+    sequence_space: u32,
+    expected: u32,
+    // The data and FIN flag of the frames from expected onwards
+    buffer: VecDeque<Option<(BitString, bool)>>,
+    finished: bool,
+}
+
+impl SelectiveRepeatReceiver {
+    /// Acknowledgements are built by the builder. The window has to match
+    /// that of the sender, as it determines the sequence space.
+    #[must_use]
+    pub fn new(builder: TCPFrameBuilder, window_size: u16) -> Self {
+        assert!(window_size > 0, "The window holds at least one frame");
+
+        Self {
+            builder: builder.set_flags(ACK),
+            window_size: u32::from(window_size),
+            sequence_space: Arq::SelectiveRepeat.sequence_space(window_size),
+            expected: 0,
+            buffer: vec![None; usize::from(window_size)].into(),
+            finished: false,
+        }
+    }
+
+    fn ack(&self, sequence_num: u32) -> TCPFrame {
+        self.builder
+            .clone()
+            .set_ack_num(sequence_num)
+            .build(BitString::new())
+    }
+}
+
+impl ArqReceiver for SelectiveRepeatReceiver {
+    /// Frames within the window are buffered and acknowledged. Frames from
+    /// the window before are acknowledged again, as their acknowledgement
+    /// may have been lost. Any other frame is ignored.
+    fn receive(&mut self, frame: &TCPFrame) -> (Option<TCPFrame>, Vec<BitString>) {
+        let sequence_num = frame.sequence_num();
+        let ahead = distance(self.expected, sequence_num, self.sequence_space);
+        let behind = distance(sequence_num, self.expected, self.sequence_space);
+
+        if !self.finished && ahead < self.window_size {
+            self.buffer[ahead as usize]
+                .get_or_insert_with(|| (frame.data().clone(), frame.has_flags(FIN)));
+
+            let mut delivered = Vec::new();
+            while let Some((data, fin)) = self.buffer.front_mut().and_then(Option::take) {
+                self.buffer.pop_front();
+                self.buffer.push_back(None);
+                self.expected = (self.expected + 1) % self.sequence_space;
+                delivered.push(data);
+
+                if fin {
+                    self.finished = true;
+                    break;
+                }
+            }
+
+            (Some(self.ack(sequence_num)), delivered)
+        } else if (1..=self.window_size).contains(&behind) {
+            (Some(self.ack(sequence_num)), Vec::new())
+        } else {
+            (None, Vec::new())
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::{
+        bit_string::BitString,
+        data_link_layer::arq::fixtures::{builder, data, pair, TIMEOUT},
+    };
+
+    use super::Arq;
+
+    #[test]
+    fn buffers_out_of_order() {
+        let (mut sender, mut receiver) = pair(Arq::SelectiveRepeat, 10);
+        let now = Instant::now();
+        assert_eq!(sender.sequence_space(), 8);
+
+        let frames = sender.poll(now);
+        assert_eq!(frames.len(), 3);
+
+        // Later frames are acknowledged but held back until the first arrives
+        for frame in &frames[1..] {
+            let (ack, delivered) = receiver.receive(frame);
+            let ack = ack.expect("Frames in the window are acknowledged");
+            assert!(delivered.is_empty());
+            assert_eq!(ack.ack_num(), frame.sequence_num());
+            sender.receive(&ack, now);
+        }
+        assert_eq!(sender.acknowledged(), 0);
+        assert!(sender.poll(now).is_empty());
+
+        let (ack, delivered) = receiver.receive(&frames[0]);
+        assert_eq!(delivered, data(3));
+        sender.receive(&ack.expect("Frames are acknowledged"), now);
+        assert_eq!(sender.acknowledged(), 3);
+        assert_eq!(sender.poll(now).len(), 3);
+    }
+
+    #[test]
+    fn repeats_only_lost_frames() {
+        let (mut sender, mut receiver) = pair(Arq::SelectiveRepeat, 10);
+        let start = Instant::now();
+
+        let frames = sender.poll(start);
+
+        // The middle frame is lost
+        for frame in [&frames[0], &frames[2]] {
+            let (ack, _) = receiver.receive(frame);
+            sender.receive(&ack.expect("Frames are acknowledged"), start);
+        }
+        let refilled = sender.poll(start);
+        assert_eq!(refilled.len(), 1);
+        assert!(sender.poll(start + TIMEOUT / 2).is_empty());
+
+        let resent = sender.poll(start + TIMEOUT);
+        assert_eq!(resent, [frames[1].clone(), refilled[0].clone()]);
+        assert_eq!(sender.retransmissions(), 2);
+
+        let (_, delivered) = receiver.receive(&resent[0]);
+        assert_eq!(delivered, data(3)[1..]);
+    }
+
+    #[test]
+    fn transfers_with_wraparound() {
+        let (mut sender, mut receiver) = pair(Arq::SelectiveRepeat, 20);
+        let mut now = Instant::now();
+        let mut received = Vec::new();
+
+        // Every third frame and every fourth acknowledgement is lost
+        let mut sent = 0;
+        let mut acks = 0;
+        while !sender.is_finished() {
+            for frame in sender.poll(now) {
+                sent += 1;
+                if sent % 3 == 0 {
+                    continue;
+                }
+
+                let (ack, delivered) = receiver.receive(&frame);
+                received.extend(delivered);
+
+                acks += 1;
+                if acks % 4 != 0 {
+                    sender.receive(&ack.expect("Frames are acknowledged"), now);
+                }
+            }
+
+            now += TIMEOUT;
+        }
+
+        assert_eq!(received, data(20));
+        assert!(receiver.is_finished());
+        assert!(sender.retransmissions() > 0);
+    }
+
+    #[test]
+    fn ignores_frames_outside_windows() {
+        let (mut sender, mut receiver) = pair(Arq::SelectiveRepeat, 10);
+        let now = Instant::now();
+
+        let frames = sender.poll(now);
+        for frame in &frames {
+            receiver.receive(frame);
+        }
+
+        // A duplicate from the window before is acknowledged again
+        let (ack, delivered) = receiver.receive(&frames[0]);
+        assert_eq!(ack.map(|ack| ack.ack_num()), Some(0));
+        assert!(delivered.is_empty());
+
+        // A frame beyond the window is neither buffered nor acknowledged
+        let early = builder(30, 40).set_sequence_num(6).build(BitString::new());
+        assert_eq!(receiver.receive(&early), (None, Vec::new()));
+    }
+}
@@ -0,0 +1,263 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::tcp::{TCPFrame, TCPFrameBuilder, ACK, FIN},
+};
+
+use super::{
+    data_frame, sequence_num, Arq, ArqReceiver, ArqSender, DEFAULT_RETRANSMIT_TIMEOUT, NAK,
+};
+
+/// The sending half of stop-and-wait. A single frame is in flight, the next
+/// one is only sent once it is acknowledged. The frame is sent again when its
+/// timer expires or the receiver reports it damaged.
+#[derive(Debug, Clone)]
+pub struct StopAndWaitSender {
+    builder: TCPFrameBuilder,
+    data: Vec<BitString>,
+    timeout: Duration,
+
+    // The frame in flight
+    current: usize,
+    deadline: Option<Instant>,
+    // Whether the frame in flight was negatively acknowledged
+    rejected: bool,
+
+    transmissions: usize,
+    retransmissions: usize,
+}
+
+impl StopAndWaitSender {
+    /// Sends every data point in its own frame, built by the builder. The
+    /// sequence number is a single alternating bit.
+    #[must_use]
+    pub fn new(builder: TCPFrameBuilder, data: Vec<BitString>) -> Self {
+        Self {
+            builder,
+            data,
+            timeout: DEFAULT_RETRANSMIT_TIMEOUT,
+            current: 0,
+            deadline: None,
+            rejected: false,
+            transmissions: 0,
+            retransmissions: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn set_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+impl ArqSender for StopAndWaitSender {
+    /// Sends the next frame once the previous one is acknowledged, or the
+    /// same frame again once it timed out or was rejected.
+    fn poll(&mut self, now: Instant) -> Vec<TCPFrame> {
+        if self.is_finished() {
+            return Vec::new();
+        }
+
+        match self.deadline {
+            None => {}
+            Some(deadline) if self.rejected || now >= deadline => self.retransmissions += 1,
+            Some(_) => return Vec::new(),
+        }
+
+        self.rejected = false;
+        self.deadline = Some(now + self.timeout);
+        self.transmissions += 1;
+
+        vec![data_frame(
+            &self.builder,
+            &self.data,
+            self.current,
+            self.sequence_space(),
+        )]
+    }
+
+    /// Handles an acknowledgement naming the frame the receiver expects next,
+    /// or a negative acknowledgement naming the frame it expected but got
+    /// damaged.
+    fn receive(&mut self, ack: &TCPFrame, _now: Instant) {
+        if self.is_finished() || self.deadline.is_none() {
+            return;
+        }
+
+        let sequence_space = self.sequence_space();
+        if ack.has_flags(NAK) {
+            self.rejected = ack.ack_num() == sequence_num(self.current, sequence_space);
+        } else if ack.has_flags(ACK)
+            && ack.ack_num() == sequence_num(self.current + 1, sequence_space)
+        {
+            self.current += 1;
+            self.deadline = None;
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    fn is_finished(&self) -> bool {
+        self.current == self.data.len()
+    }
+
+    fn acknowledged(&self) -> usize {
+        self.current
+    }
+
+    fn transmissions(&self) -> usize {
+        self.transmissions
+    }
+
+    fn retransmissions(&self) -> usize {
+        self.retransmissions
+    }
+
+    fn sequence_space(&self) -> u32 {
+        Arq::StopAndWait.sequence_space(1)
+    }
+}
+
+/// The receiving half of stop-and-wait. Accepts the frame carrying the
+/// expected bit, acknowledges every intact frame and rejects damaged ones.
+#[derive(Debug, Clone)]
+pub struct StopAndWaitReceiver {
+    builder: TCPFrameBuilder,
+    expected: u32,
+    finished: bool,
+}
+
+impl StopAndWaitReceiver {
+    /// Acknowledgements are built by the builder.
+    #[must_use]
+    pub fn new(builder: TCPFrameBuilder) -> Self {
+        Self {
+            builder,
+            expected: 0,
+            finished: false,
+        }
+    }
+}
+
+impl ArqReceiver for StopAndWaitReceiver {
+    /// A duplicate is acknowledged again without delivering its data, as the
+    /// acknowledgement for it was lost.
+    fn receive(&mut self, frame: &TCPFrame) -> (Option<TCPFrame>, Vec<BitString>) {
+        let accepted = !self.finished && frame.sequence_num() == self.expected;
+
+        if accepted {
+            self.expected ^= 1;
+            self.finished = frame.has_flags(FIN);
+        }
+
+        let ack = self
+            .builder
+            .clone()
+            .set_flags(ACK)
+            .set_ack_num(self.expected)
+            .build(BitString::new());
+
+        (
+            Some(ack),
+            accepted.then(|| frame.data().clone()).into_iter().collect(),
+        )
+    }
+
+    /// Asks for the expected frame again.
+    fn damaged(&mut self) -> Option<TCPFrame> {
+        (!self.finished).then(|| {
+            self.builder
+                .clone()
+                .set_flags(NAK)
+                .set_ack_num(self.expected)
+                .build(BitString::new())
+        })
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::data_link_layer::arq::fixtures::{data, pair, TIMEOUT};
+
+    use super::{Arq, NAK};
+
+    #[test]
+    fn alternates_bit() {
+        let (mut sender, mut receiver) = pair(Arq::StopAndWait, 3);
+        let now = Instant::now();
+        assert_eq!(sender.sequence_space(), 2);
+
+        let mut sequence_nums = Vec::new();
+        while !sender.is_finished() {
+            let frames = sender.poll(now);
+            assert_eq!(frames.len(), 1);
+            assert!(sender.poll(now).is_empty());
+            sequence_nums.push(frames[0].sequence_num());
+
+            let (ack, _) = receiver.receive(&frames[0]);
+            sender.receive(&ack.expect("Frames are acknowledged"), now);
+        }
+
+        assert_eq!(sequence_nums, [0, 1, 0]);
+        assert!(receiver.is_finished());
+        assert_eq!(sender.retransmissions(), 0);
+    }
+
+    #[test]
+    fn resends_on_nak_and_timeout() {
+        let (mut sender, mut receiver) = pair(Arq::StopAndWait, 2);
+        let start = Instant::now();
+
+        // The first frame arrives damaged and is rejected right away
+        let frame = sender.poll(start).remove(0);
+        let nak = receiver.damaged().expect("The transfer isn't finished");
+        assert!(nak.has_flags(NAK));
+        sender.receive(&nak, start);
+        assert_eq!(sender.poll(start), std::slice::from_ref(&frame));
+
+        // Its acknowledgement is lost, so it is sent again once it times out
+        let (_, delivered) = receiver.receive(&frame);
+        assert_eq!(delivered, data(1));
+        assert!(sender.poll(start + TIMEOUT / 2).is_empty());
+        assert_eq!(sender.poll(start + TIMEOUT), std::slice::from_ref(&frame));
+        assert_eq!(sender.retransmissions(), 2);
+
+        // The duplicate is acknowledged but not delivered again
+        let (ack, delivered) = receiver.receive(&frame);
+        assert!(delivered.is_empty());
+        sender.receive(&ack.expect("Frames are acknowledged"), start);
+        assert_eq!(sender.acknowledged(), 1);
+    }
+
+    #[test]
+    fn ignores_stale_acks() {
+        let (mut sender, mut receiver) = pair(Arq::StopAndWait, 3);
+        let now = Instant::now();
+
+        let first = sender.poll(now).remove(0);
+        let (Some(ack), _) = receiver.receive(&first) else {
+            panic!("Frames are acknowledged");
+        };
+        sender.receive(&ack, now);
+
+        // Another acknowledgement for the first frame doesn't skip the second
+        let second = sender.poll(now).remove(0);
+        sender.receive(&ack, now);
+        assert_eq!(sender.acknowledged(), 1);
+
+        let (Some(ack), _) = receiver.receive(&second) else {
+            panic!("Frames are acknowledged");
+        };
+        sender.receive(&ack, now);
+        assert_eq!(sender.acknowledged(), 2);
+    }
+}
@@ -1,3 +1,5 @@
+use anyhow::ensure;
+
 use crate::{bit::Bit, bit_string::BitString};
 
 pub const FLAG_SEQUECE: u8 = 0b0111_1110u8;
@@ -7,6 +9,52 @@ pub fn prepare_bits(data: BitString) -> BitString {
     surround_flags(bs)
 }
 
+/// Undoes [`prepare_bits`], stripping the surrounding flags and removing the
+/// stuffed bits. Zeroes after the closing flag, as added by block codes when
+/// padding, are ignored.
+pub fn unprepare_bits(data: BitString) -> anyhow::Result<BitString> {
+    let bs = remove_flags(data)?;
+    Ok(unstuff_bits(bs))
+}
+
+/// Picks the frames out of a stream of bits, such as the bits arriving on a
+/// cable. Frames are found by their flags, as added by [`prepare_bits`].
+#[derive(Debug, Clone, Default)]
+pub struct Delimiter {
+    // The frame being received, starting with its opening flag
+    frame: Option<BitString>,
+    // The last bits of the stream, up to the length of a flag
+    recent: u8,
+    recent_len: usize,
+}
+
+impl Delimiter {
+    /// Takes the next bit of the stream. Returns a frame, flags included,
+    /// once its closing flag is complete.
+    pub fn push(&mut self, bit: Bit) -> Option<BitString> {
+        if let Some(frame) = &mut self.frame {
+            frame.append_bit(bit);
+        }
+
+        self.recent = self.recent << 1 | bit as u8;
+        self.recent_len += 1;
+        if self.recent_len < u8::BITS as usize || self.recent != FLAG_SEQUECE {
+            return None;
+        }
+        self.recent_len = 0;
+
+        match self.frame.take() {
+            // Two flags in a row close one frame and open the next, so a flag
+            // right after another one always opens a frame
+            Some(frame) if frame.len() > 2 * u8::BITS as usize => Some(frame),
+            _ => {
+                self.frame = Some(BitString::from(FLAG_SEQUECE));
+                None
+            }
+        }
+    }
+}
+
 fn unstuff_bits(mut data: BitString) -> BitString {
     let mut count = 0;
     let mut remove_places = Vec::new();
@@ -60,11 +108,46 @@ fn surround_flags(mut data: BitString) -> BitString {
     data
 }
 
+fn remove_flags(mut data: BitString) -> anyhow::Result<BitString> {
+    let flag_len = u8::BITS as usize;
+
+    ensure!(
+        data.len() >= 2 * flag_len,
+        "Frame of {} bits is too short to be surrounded by flags",
+        data.len()
+    );
+    ensure!(
+        data.get_u8(0) == FLAG_SEQUECE,
+        "Frame does not start with a flag"
+    );
+
+    // Stuffed data never contains the flag, so the last one closes the frame
+    let closing = (flag_len..=data.len() - flag_len)
+        .rev()
+        .find(|&idx| data.get_u8(idx) == FLAG_SEQUECE);
+    ensure!(closing.is_some(), "Frame does not end with a flag");
+    let closing = closing.expect("Already ensured");
+
+    ensure!(
+        data.iter()
+            .skip(closing + flag_len)
+            .all(|bit| *bit == Bit::Off),
+        "Frame has data after the closing flag"
+    );
+
+    data.remove_last_len(data.len() - closing);
+    data.remove_len(0, flag_len);
+
+    Ok(data)
+}
+
 #[cfg(test)]
 mod test {
     use crate::{bit_string::BitString, bitstring, data_link_layer::bit_stuffing::FLAG_SEQUECE};
 
-    use super::{stuff_bits, surround_flags, unstuff_bits};
+    use super::{
+        prepare_bits, stuff_bits, surround_flags, unprepare_bits, unstuff_bits, Delimiter,
+    };
 
     #[test]
     fn surround_flags_test() {
@@ -76,6 +159,32 @@ mod test {
         assert_eq!(bs.get_u8(bs.len() - 8), FLAG_SEQUECE);
     }
 
+    #[test]
+    fn unprepare_bits_test() {
+        let bs = bitstring![0, 1, 1, 1, 1, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0];
+
+        let prepared = prepare_bits(bs.clone());
+
+        assert_eq!(unprepare_bits(prepared).expect("Flags are present"), bs);
+    }
+
+    #[test]
+    fn unprepare_bits_padded() {
+        let bs = bitstring![1, 1, 1, 1, 1, 1, 0, 1, 1];
+
+        let mut prepared = prepare_bits(bs.clone());
+        prepared.append_zeroes(5);
+
+        assert_eq!(unprepare_bits(prepared).expect("Flags are present"), bs);
+    }
+
+    #[test]
+    fn unprepare_bits_missing_flags() {
+        let bs = bitstring![0, 1, 1, 1, 1, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0, 0];
+
+        assert!(unprepare_bits(bs).is_err());
+    }
+
     #[test]
     fn unstuff_bits_test() {
         let expected = bitstring!(0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0,);
@@ -96,6 +205,28 @@ mod test {
         assert_eq!(expected, bs);
     }
 
+    #[test]
+    fn delimits_stream() {
+        let frames = [bitstring![1, 1, 1, 1, 1, 1, 1, 0], bitstring![0, 1, 0]];
+
+        // Noise before the first frame is skipped
+        let mut stream = bitstring![1, 1, 0, 1];
+        for frame in &frames {
+            stream.append_bits(prepare_bits(frame.clone()));
+        }
+
+        let mut delimiter = Delimiter::default();
+        let delimited: Vec<BitString> = stream
+            .into_iter()
+            .filter_map(|bit| delimiter.push(bit))
+            .collect();
+
+        assert_eq!(delimited.len(), 2);
+        for (delimited, frame) in delimited.into_iter().zip(frames) {
+            assert_eq!(unprepare_bits(delimited).ok(), Some(frame));
+        }
+    }
+
     #[cfg(feature = "fuzz")]
     mod fuzz {
         use crate::data_link_layer::bit_stuffing::stuff_bits;
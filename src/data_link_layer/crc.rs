@@ -2,6 +2,16 @@ use anyhow::ensure;
 
 use crate::{bit::Bit, bit_string::BitString};
 
+use super::error_detection::ErrorDetection;
+
+/// The CRC-32 polynomial as used by ethernet, 0x04C11DB7 with the implicit
+/// leading one included.
+pub fn crc_32() -> BitString {
+    let mut generator = BitString::from(0x04C1_1DB7u32);
+    generator.prepend_bit(Bit::On);
+    generator
+}
+
 pub fn add(generator: &BitString, mut data: BitString) -> BitString {
     assert!(!generator.is_empty(), "Generator cannot be empty");
     assert!(!data.is_empty(), "Unable to add a crc to no data");
@@ -19,6 +29,10 @@ pub fn add(generator: &BitString, mut data: BitString) -> BitString {
 }
 
 pub fn check_and_remove(generator: &BitString, mut data: BitString) -> anyhow::Result<BitString> {
+    ensure!(
+        data.len() >= generator.len(),
+        "The message {data} is too short to contain a crc for generator {generator}"
+    );
     ensure!(
         binary_division(&data, generator)
             .into_iter()
@@ -33,6 +47,49 @@ pub fn check_and_remove(generator: &BitString, mut data: BitString) -> anyhow::R
     Ok(data)
 }
 
+/// A cyclic redundancy check with the given generator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crc {
+    generator: BitString,
+}
+
+impl Crc {
+    #[must_use]
+    pub fn new(generator: BitString) -> Self {
+        assert!(!generator.is_empty(), "Generator cannot be empty");
+        assert!(
+            generator[0] == Bit::On,
+            "Generator must start with a 1 or On bit"
+        );
+
+        Self { generator }
+    }
+
+    #[must_use]
+    pub fn crc_32() -> Self {
+        Self::new(crc_32())
+    }
+
+    #[must_use]
+    pub const fn get_generator(&self) -> &BitString {
+        &self.generator
+    }
+}
+
+impl ErrorDetection for Crc {
+    fn add(&self, data: BitString) -> BitString {
+        add(&self.generator, data)
+    }
+
+    fn check_and_remove(&self, data: BitString) -> anyhow::Result<BitString> {
+        check_and_remove(&self.generator, data)
+    }
+
+    fn overhead(&self, _data_len: usize) -> usize {
+        self.generator.len() - 1
+    }
+}
+
 fn binary_division(divident: &BitString, divisor: &BitString) -> BitString {
     if divident.len() < divisor.len() {
         let len_to_add = divisor.len() - divident.len() - 1;
@@ -72,7 +129,7 @@ fn binary_division(divident: &BitString, divisor: &BitString) -> BitString {
 #[cfg(test)]
 mod test {
     use crate::bit_string::{bitstring, BitString};
-    use crate::data_link_layer::crc::{add, binary_division, check_and_remove};
+    use crate::data_link_layer::crc::{add, binary_division, check_and_remove, crc_32};
 
     #[test]
     fn simple_check() {
@@ -154,6 +211,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn too_short_for_crc() {
+        let data = bitstring!(1, 0);
+        let gen = bitstring!(1, 0, 1);
+
+        assert!(check_and_remove(&gen, data).is_err());
+    }
+
+    #[test]
+    fn crc_32_round_trip() {
+        let data = BitString::from(*b"Hello world!");
+
+        let with_crc = add(&crc_32(), data.clone());
+        assert_eq!(with_crc.len(), data.len() + 32);
+
+        assert_eq!(
+            check_and_remove(&crc_32(), with_crc).expect("Crc should be valid"),
+            data
+        );
+    }
+
     #[test]
     fn broken_crc() {
         let broken_crc = bitstring!(1, 1, 0, 1);
@@ -0,0 +1,345 @@
+use anyhow::{bail, ensure};
+
+use crate::{bit::Bit, bit_string::BitString};
+
+/// The most work, in elementary operations, an analysis may take.
+const WORK_BUDGET: u128 = 1 << 34;
+
+/// The most counts the syndrome table may hold, which bounds its memory to
+/// 128 MiB.
+const TABLE_BUDGET: usize = 1 << 24;
+
+/// Analysis of how well a CRC generator protects messages of a given length.
+///
+/// An error pattern goes undetected exactly when it is itself a valid
+/// codeword, so everything follows from the weight distribution of the code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrcAnalysis {
+    generator: BitString,
+    message_len: usize,
+    // Index w holds the amount of undetectable error patterns of weight w
+    weight_distribution: Vec<f64>,
+}
+
+impl CrcAnalysis {
+    /// Computes the weight distribution of the code. This takes time
+    /// exponential in either the message length or the generator length, and
+    /// fails if both are too long to analyse.
+    pub fn new(generator: &BitString, message_len: usize) -> anyhow::Result<Self> {
+        ensure!(
+            generator.len() >= 2,
+            "The generator needs at least two bits"
+        );
+        ensure!(
+            generator[0] == Bit::On,
+            "Generator must start with a 1 or On bit"
+        );
+        ensure!(message_len > 0, "The message needs at least one bit");
+
+        let degree = generator.len() - 1;
+        let codeword_len = message_len + degree;
+
+        // Either enumerate every codeword, or count every error pattern per
+        // syndrome, whichever is cheaper
+        let enumerate_cost = 1u128
+            .checked_shl(u32::try_from(message_len).unwrap_or(u32::MAX))
+            .and_then(|codewords| codewords.checked_mul(codeword_len.div_ceil(64) as u128));
+        // The syndrome table has to fit in memory as well
+        let syndrome_cost = 1usize
+            .checked_shl(u32::try_from(degree).unwrap_or(u32::MAX))
+            .and_then(|syndromes| syndromes.checked_mul(codeword_len + 1))
+            .filter(|&entries| entries <= TABLE_BUDGET)
+            .map(|entries| entries as u128 * codeword_len as u128);
+
+        let weight_distribution = match (enumerate_cost, syndrome_cost) {
+            (Some(enumerate), syndrome)
+                if enumerate <= WORK_BUDGET && syndrome.is_none_or(|s| enumerate <= s) =>
+            {
+                Self::enumerate_codewords(generator, message_len)
+            }
+            (_, Some(syndrome)) if syndrome <= WORK_BUDGET => {
+                Self::count_by_syndrome(generator, message_len)
+            }
+            _ => bail!(
+                "A generator of {} bits with messages of {message_len} bits is too large to analyse",
+                generator.len()
+            ),
+        };
+
+        Ok(Self {
+            generator: generator.clone(),
+            message_len,
+            weight_distribution,
+        })
+    }
+
+    /// Walks all multiples of the generator in gray code order, so every step
+    /// adds a single shifted generator.
+    fn enumerate_codewords(generator: &BitString, message_len: usize) -> Vec<f64> {
+        let codeword_len = message_len + generator.len() - 1;
+        let words = codeword_len.div_ceil(64);
+
+        let shifted = (0..message_len)
+            .map(|shift| {
+                let mut bits = vec![0u64; words];
+                for (idx, bit) in generator.iter().enumerate() {
+                    if *bit == Bit::On {
+                        let position = shift + idx;
+                        bits[position / 64] |= 1 << (position % 64);
+                    }
+                }
+                bits
+            })
+            .collect::<Vec<_>>();
+
+        let mut distribution = vec![0.; codeword_len + 1];
+        let mut codeword = vec![0u64; words];
+        distribution[0] = 1.;
+
+        for step in 1..1u128 << message_len {
+            let flipped = step.trailing_zeros() as usize;
+            for (word, shifted) in codeword.iter_mut().zip(&shifted[flipped]) {
+                *word ^= shifted;
+            }
+
+            let weight = codeword
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum::<usize>();
+            distribution[weight] += 1.;
+        }
+
+        distribution
+    }
+
+    /// Counts the error patterns of every weight per syndrome, position by
+    /// position. The patterns with a zero syndrome are the codewords.
+    fn count_by_syndrome(generator: &BitString, message_len: usize) -> Vec<f64> {
+        let degree = generator.len() - 1;
+        let codeword_len = message_len + degree;
+
+        let poly = generator
+            .iter()
+            .fold(0u64, |acc, bit| (acc << 1) | *bit as u64);
+        let top = 1u64 << degree;
+
+        // The syndrome of an error at position i is x^i mod g(x)
+        let mut syndrome = 1u64;
+        let columns = (0..codeword_len)
+            .map(|_| {
+                let column = syndrome;
+                syndrome <<= 1;
+                if syndrome & top != 0 {
+                    syndrome ^= poly;
+                }
+                column
+            })
+            .collect::<Vec<_>>();
+
+        let syndromes = 1usize << degree;
+        // counts[w * syndromes + s] is the amount of patterns of weight w with
+        // syndrome s using the positions seen so far
+        let mut counts = vec![0.; (codeword_len + 1) * syndromes];
+        counts[0] = 1.;
+
+        for (position, &column) in columns.iter().enumerate() {
+            let column = usize::try_from(column).expect("The syndrome fits in the table");
+
+            for weight in (0..=position).rev() {
+                for syndrome in 0..syndromes {
+                    let count = counts[weight * syndromes + syndrome];
+                    if count != 0. {
+                        counts[(weight + 1) * syndromes + (syndrome ^ column)] += count;
+                    }
+                }
+            }
+        }
+
+        (0..=codeword_len)
+            .map(|weight| counts[weight * syndromes])
+            .collect()
+    }
+
+    #[must_use]
+    pub const fn generator(&self) -> &BitString {
+        &self.generator
+    }
+
+    #[must_use]
+    pub const fn message_len(&self) -> usize {
+        self.message_len
+    }
+
+    /// The length of the message with the crc appended.
+    #[must_use]
+    pub fn codeword_len(&self) -> usize {
+        self.message_len + self.generator.len() - 1
+    }
+
+    /// Index w holds the amount of error patterns of weight w that go
+    /// undetected, index 0 holds the error free pattern. Counts are exact up
+    /// to 2^53.
+    #[must_use]
+    pub fn weight_distribution(&self) -> &[f64] {
+        &self.weight_distribution
+    }
+
+    /// The lowest amount of bit errors that can go undetected, or [`None`] if
+    /// every error is detected.
+    #[must_use]
+    pub fn min_hamming_distance(&self) -> Option<usize> {
+        self.weight_distribution
+            .iter()
+            .skip(1)
+            .position(|&count| count > 0.)
+            .map(|idx| idx + 1)
+    }
+
+    /// The length up to which every burst error is detected. A generator
+    /// x^t * h(x) with h(0) = 1 detects every burst up to the degree of h.
+    #[must_use]
+    pub fn burst_detection_len(&self) -> usize {
+        let trailing_zeroes = self
+            .generator
+            .iter()
+            .rev()
+            .take_while(|bit| **bit == Bit::Off)
+            .count();
+
+        self.generator.len() - 1 - trailing_zeroes
+    }
+
+    /// The chance that a message goes through a channel flipping every bit
+    /// independently with chance `ber` and arrives corrupted, yet passes the
+    /// crc.
+    #[must_use]
+    pub fn undetected_error_probability(&self, ber: f64) -> f64 {
+        assert!(
+            (0. ..=1.).contains(&ber),
+            "The bit error rate must be a chance"
+        );
+
+        let len = i32::try_from(self.codeword_len()).expect("Analysed codewords are short");
+
+        self.weight_distribution
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, &count)| count > 0.)
+            .map(|(weight, count)| {
+                let weight = i32::try_from(weight).expect("Analysed codewords are short");
+                count * ber.powi(weight) * (1. - ber).powi(len - weight)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{bit::Bit, bit_string::BitString, bitstring, data_link_layer::crc::crc_32};
+
+    use super::CrcAnalysis;
+
+    #[test]
+    fn hamming_7_4_as_crc() {
+        // x^3 + x + 1 generates the cyclic Hamming(7, 4) code
+        let analysis = CrcAnalysis::new(&bitstring!(1, 0, 1, 1), 4).expect("Small code");
+
+        assert_eq!(
+            analysis.weight_distribution(),
+            &[1., 0., 0., 7., 7., 0., 0., 1.]
+        );
+        assert_eq!(analysis.min_hamming_distance(), Some(3));
+        assert_eq!(analysis.burst_detection_len(), 3);
+    }
+
+    #[test]
+    fn both_methods_agree() {
+        let generator = bitstring!(1, 1, 0, 1, 0, 1);
+
+        for message_len in 1..12 {
+            assert_eq!(
+                CrcAnalysis::enumerate_codewords(&generator, message_len),
+                CrcAnalysis::count_by_syndrome(&generator, message_len),
+                "Failed at message length {message_len}"
+            );
+        }
+    }
+
+    #[test]
+    fn parity_generator() {
+        // x + 1 detects every odd amount of errors
+        let analysis = CrcAnalysis::new(&bitstring!(1, 1), 7).expect("Small code");
+
+        assert_eq!(analysis.min_hamming_distance(), Some(2));
+        assert_eq!(
+            analysis.weight_distribution(),
+            &[1., 0., 28., 0., 70., 0., 28., 0., 1.]
+        );
+    }
+
+    #[test]
+    fn undetected_error_probability() {
+        let analysis = CrcAnalysis::new(&bitstring!(1, 1), 1).expect("Small code");
+
+        // The only undetected error flips both bits
+        assert!((analysis.undetected_error_probability(0.1) - 0.01).abs() < 1e-12);
+        assert!(analysis.undetected_error_probability(0.) == 0.);
+
+        // At a bit error rate of 1/2 every pattern is equally likely, so
+        // 2^-r of the corrupted messages pass
+        let analysis = CrcAnalysis::new(&bitstring!(1, 0, 1, 1), 10).expect("Small code");
+        let expected = (analysis.weight_distribution().iter().sum::<f64>() - 1.) / 2f64.powi(13);
+        assert!((analysis.undetected_error_probability(0.5) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn burst_detection_with_factor_x() {
+        // x * (x^2 + x + 1)
+        let analysis = CrcAnalysis::new(&bitstring!(1, 1, 1, 0), 8).expect("Small code");
+
+        assert_eq!(analysis.burst_detection_len(), 2);
+    }
+
+    #[test]
+    fn long_messages_use_syndromes() {
+        let mut generator = BitString::from(0b0010_0001u8);
+        generator.prepend_bit(Bit::On);
+
+        let analysis = CrcAnalysis::new(&generator, 100).expect("Small generator");
+
+        assert_eq!(analysis.codeword_len(), 108);
+        assert!(analysis.min_hamming_distance().is_some());
+        assert_eq!(analysis.weight_distribution()[0], 1.);
+    }
+
+    #[test]
+    fn messages_near_the_shift_limit() {
+        let mut generator = BitString::from(0b0010_0001u8);
+        generator.prepend_bit(Bit::On);
+
+        // Enumerating every codeword would overflow its cost estimate
+        for message_len in 120..=128 {
+            let analysis = CrcAnalysis::new(&generator, message_len).expect("Small generator");
+            assert_eq!(analysis.codeword_len(), message_len + 8);
+        }
+
+        assert!(CrcAnalysis::new(&crc_32(), 127).is_err());
+    }
+
+    #[test]
+    fn too_large() {
+        assert!(CrcAnalysis::new(&crc_32(), 12_000).is_err());
+        assert!(CrcAnalysis::new(&bitstring!(0, 1), 8).is_err());
+
+        // Cheap enough to count by syndrome, but the table doesn't fit in
+        // memory
+        for degree in [20, 40] {
+            let mut generator = vec![Bit::Off; degree + 1];
+            generator[0] = Bit::On;
+            generator[degree] = Bit::On;
+
+            assert!(CrcAnalysis::new(&BitString::from(&generator[..]), 100).is_err());
+        }
+    }
+}
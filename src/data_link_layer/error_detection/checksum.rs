@@ -0,0 +1,285 @@
+use anyhow::ensure;
+
+use crate::bit_string::BitString;
+
+use super::{pad_to_multiple, ErrorDetection};
+
+/// The ones' complement of the ones' complement sum of `words`, as described in
+/// RFC 1071.
+#[must_use]
+pub fn internet_checksum(words: &[u16]) -> u16 {
+    let mut sum: u32 = 0;
+
+    for &word in words {
+        sum += u32::from(word);
+
+        // Fold the carry back in before it can overflow
+        if sum > 0xFFFF {
+            sum = (sum >> 16) + (sum & 0xFFFF);
+        }
+    }
+
+    !(u16::try_from(sum).expect("The carry was folded back in"))
+}
+
+#[must_use]
+pub fn fletcher_16(bytes: &[u8]) -> u16 {
+    let mut sum1: u16 = 0;
+    let mut sum2: u16 = 0;
+
+    for &byte in bytes {
+        sum1 = (sum1 + u16::from(byte)) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+
+    (sum2 << 8) | sum1
+}
+
+#[must_use]
+pub fn fletcher_32(words: &[u16]) -> u32 {
+    let mut sum1: u32 = 0;
+    let mut sum2: u32 = 0;
+
+    for &word in words {
+        sum1 = (sum1 + u32::from(word)) % 65535;
+        sum2 = (sum2 + sum1) % 65535;
+    }
+
+    (sum2 << 16) | sum1
+}
+
+const ADLER_MOD: u32 = 65521;
+
+#[must_use]
+pub fn adler_32(bytes: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % ADLER_MOD;
+        b = (b + a) % ADLER_MOD;
+    }
+
+    (b << 16) | a
+}
+
+/// Splits the trailing `len` bits off `data` and returns both, failing if
+/// `data` is too short to contain them.
+fn split_check_value(mut data: BitString, len: usize) -> anyhow::Result<(BitString, BitString)> {
+    ensure!(
+        data.len() >= len,
+        "The message {data} is too short to contain a {len} bit check value"
+    );
+
+    let check_value = BitString::from(data.remove_last_len(len));
+
+    Ok((data, check_value))
+}
+
+/// The RFC 1071 internet checksum, computed over the data padded with zeroes to
+/// a multiple of 16 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InternetChecksum;
+
+impl ErrorDetection for InternetChecksum {
+    fn add(&self, mut data: BitString) -> BitString {
+        let words = pad_to_multiple(&data, 16).as_vec_exact_u16();
+        data.append_u16(internet_checksum(&words));
+        data
+    }
+
+    fn check_and_remove(&self, data: BitString) -> anyhow::Result<BitString> {
+        let (data, check_value) = split_check_value(data, 16)?;
+
+        let words = pad_to_multiple(&data, 16).as_vec_exact_u16();
+        ensure!(
+            internet_checksum(&words) == check_value.get_u16(0),
+            "The message {data} does not match its internet checksum"
+        );
+
+        Ok(data)
+    }
+
+    fn overhead(&self, _data_len: usize) -> usize {
+        16
+    }
+}
+
+/// Fletcher-16, computed over the data padded with zeroes to a multiple of 8
+/// bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Fletcher16;
+
+impl ErrorDetection for Fletcher16 {
+    fn add(&self, mut data: BitString) -> BitString {
+        let bytes = pad_to_multiple(&data, 8).as_vec_exact_u8();
+        data.append_u16(fletcher_16(&bytes));
+        data
+    }
+
+    fn check_and_remove(&self, data: BitString) -> anyhow::Result<BitString> {
+        let (data, check_value) = split_check_value(data, 16)?;
+
+        let bytes = pad_to_multiple(&data, 8).as_vec_exact_u8();
+        ensure!(
+            fletcher_16(&bytes) == check_value.get_u16(0),
+            "The message {data} does not match its fletcher-16 checksum"
+        );
+
+        Ok(data)
+    }
+
+    fn overhead(&self, _data_len: usize) -> usize {
+        16
+    }
+}
+
+/// Fletcher-32, computed over the data padded with zeroes to a multiple of 16
+/// bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Fletcher32;
+
+impl ErrorDetection for Fletcher32 {
+    fn add(&self, mut data: BitString) -> BitString {
+        let words = pad_to_multiple(&data, 16).as_vec_exact_u16();
+        data.append_u32(fletcher_32(&words));
+        data
+    }
+
+    fn check_and_remove(&self, data: BitString) -> anyhow::Result<BitString> {
+        let (data, check_value) = split_check_value(data, 32)?;
+
+        let words = pad_to_multiple(&data, 16).as_vec_exact_u16();
+        ensure!(
+            fletcher_32(&words) == check_value.get_u32(0),
+            "The message {data} does not match its fletcher-32 checksum"
+        );
+
+        Ok(data)
+    }
+
+    fn overhead(&self, _data_len: usize) -> usize {
+        32
+    }
+}
+
+/// Adler-32, computed over the data padded with zeroes to a multiple of 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Adler32;
+
+impl ErrorDetection for Adler32 {
+    fn add(&self, mut data: BitString) -> BitString {
+        let bytes = pad_to_multiple(&data, 8).as_vec_exact_u8();
+        data.append_u32(adler_32(&bytes));
+        data
+    }
+
+    fn check_and_remove(&self, data: BitString) -> anyhow::Result<BitString> {
+        let (data, check_value) = split_check_value(data, 32)?;
+
+        let bytes = pad_to_multiple(&data, 8).as_vec_exact_u8();
+        ensure!(
+            adler_32(&bytes) == check_value.get_u32(0),
+            "The message {data} does not match its adler-32 checksum"
+        );
+
+        Ok(data)
+    }
+
+    fn overhead(&self, _data_len: usize) -> usize {
+        32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        bitstring,
+        data_link_layer::error_detection::{ErrorDetection, InternetChecksum},
+    };
+
+    use super::{
+        adler_32, fletcher_16, fletcher_32, internet_checksum, Adler32, Fletcher16, Fletcher32,
+    };
+
+    #[test]
+    fn internet_checksum_rfc_1071() {
+        // The example from RFC 1071 section 3
+        let words = [0x0001, 0xF203, 0xF4F5, 0xF6F7];
+
+        assert_eq!(internet_checksum(&words), !0xDDF2);
+    }
+
+    #[test]
+    fn internet_checksum_empty() {
+        assert_eq!(internet_checksum(&[]), 0xFFFF);
+    }
+
+    #[test]
+    fn fletcher_16_known_value() {
+        assert_eq!(fletcher_16(b"abcde"), 0xC8F0);
+        assert_eq!(fletcher_16(b"abcdef"), 0x2057);
+    }
+
+    #[test]
+    fn fletcher_32_known_value() {
+        // "abcde" padded with a zero byte, as little endian 16 bit words
+        assert_eq!(fletcher_32(&[0x6261, 0x6463, 0x0065]), 0xF04F_C729);
+    }
+
+    #[test]
+    fn adler_32_known_value() {
+        assert_eq!(adler_32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    fn round_trip(code: &dyn ErrorDetection, data: &BitString) {
+        let with_check = code.add(data.clone());
+        assert_eq!(with_check.len(), data.len() + code.overhead(data.len()));
+
+        let received = code
+            .check_and_remove(with_check)
+            .expect("Unmodified data should pass");
+        assert_eq!(&received, data);
+    }
+
+    #[test]
+    fn round_trips() {
+        let odd_len = bitstring!(1, 0, 1, 1, 0, 1, 1, 1, 0, 0, 1);
+        let bytes = BitString::from(*b"Hello world!");
+
+        for code in [
+            &InternetChecksum as &dyn ErrorDetection,
+            &Fletcher16,
+            &Fletcher32,
+            &Adler32,
+        ] {
+            round_trip(code, &odd_len);
+            round_trip(code, &bytes);
+        }
+    }
+
+    #[test]
+    fn detects_bit_flip() {
+        let bytes = BitString::from(*b"Hello world!");
+
+        for code in [
+            &InternetChecksum as &dyn ErrorDetection,
+            &Fletcher16,
+            &Fletcher32,
+            &Adler32,
+        ] {
+            let mut with_check = code.add(bytes.clone());
+            with_check.flip_bit(3);
+
+            assert!(code.check_and_remove(with_check).is_err());
+        }
+    }
+
+    #[test]
+    fn too_short() {
+        assert!(InternetChecksum
+            .check_and_remove(bitstring!(1, 0, 1))
+            .is_err());
+    }
+}
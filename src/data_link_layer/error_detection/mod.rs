@@ -0,0 +1,30 @@
+pub mod checksum;
+pub mod parity;
+
+use crate::bit_string::BitString;
+
+pub use super::crc::Crc;
+pub use checksum::{Adler32, Fletcher16, Fletcher32, InternetChecksum};
+pub use parity::{Parity, TwoDimensionalParity};
+
+/// A code that appends redundancy to data, so a receiver can detect whether the
+/// data was corrupted on the way.
+pub trait ErrorDetection: Send {
+    /// Appends the redundancy for `data` to the end of `data`.
+    fn add(&self, data: BitString) -> BitString;
+
+    /// Verifies the redundancy at the end of `data` and strips it, returning an
+    /// error if corruption was detected.
+    fn check_and_remove(&self, data: BitString) -> anyhow::Result<BitString>;
+
+    /// The amount of bits [`ErrorDetection::add`] appends to `data_len` bits of
+    /// data.
+    fn overhead(&self, data_len: usize) -> usize;
+}
+
+/// Returns a copy of `data` padded with zeroes to a multiple of `bits`.
+pub(crate) fn pad_to_multiple(data: &BitString, bits: usize) -> BitString {
+    let mut padded = data.clone();
+    padded.append_zeroes((bits - data.len() % bits) % bits);
+    padded
+}
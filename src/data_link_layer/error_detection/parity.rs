@@ -0,0 +1,203 @@
+use anyhow::ensure;
+
+use crate::{bit::Bit, bit_string::BitString};
+
+use super::ErrorDetection;
+
+fn parity_of<'a>(bits: impl IntoIterator<Item = &'a Bit>) -> Bit {
+    bits.into_iter().fold(Bit::Off, |acc, bit| acc ^ *bit)
+}
+
+/// A single parity bit appended to the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    /// The total amount of ones, including the parity bit, is even
+    Even,
+    /// The total amount of ones, including the parity bit, is odd
+    Odd,
+}
+
+impl Parity {
+    fn parity_bit(self, data: &BitString) -> Bit {
+        match self {
+            Self::Even => parity_of(data),
+            Self::Odd => !parity_of(data),
+        }
+    }
+}
+
+impl ErrorDetection for Parity {
+    fn add(&self, mut data: BitString) -> BitString {
+        let bit = self.parity_bit(&data);
+        data.append_bit(bit);
+        data
+    }
+
+    fn check_and_remove(&self, mut data: BitString) -> anyhow::Result<BitString> {
+        let parity = data.remove_last();
+        ensure!(parity.is_some(), "Cannot check the parity of no data");
+
+        ensure!(
+            parity == Some(self.parity_bit(&data)),
+            "The message {data} has incorrect parity"
+        );
+
+        Ok(data)
+    }
+
+    fn overhead(&self, _data_len: usize) -> usize {
+        1
+    }
+}
+
+/// Even parity over a grid of the data. The data is laid out in rows of
+/// `row_len` bits, the last row padded with zeroes. A parity bit is appended
+/// for every row, followed by a row of column parities and the parity over all
+/// row parities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TwoDimensionalParity {
+    row_len: usize,
+}
+
+impl TwoDimensionalParity {
+    #[must_use]
+    pub fn new(row_len: usize) -> Self {
+        assert!(row_len > 0, "Rows must contain at least one bit");
+        Self { row_len }
+    }
+
+    #[must_use]
+    pub const fn row_len(&self) -> usize {
+        self.row_len
+    }
+
+    fn parities(&self, data: &BitString) -> BitString {
+        let rows = data.as_bit_slice().chunks(self.row_len);
+
+        let mut row_parities = BitString::new();
+        let mut column_parities = BitString::with_zeroes(self.row_len);
+
+        for row in rows {
+            row_parities.append_bit(parity_of(row));
+
+            for (idx, bit) in row.iter().enumerate() {
+                column_parities[idx] ^= *bit;
+            }
+        }
+
+        let corner = parity_of(&row_parities);
+
+        let mut parities = row_parities;
+        parities.append_bits(column_parities);
+        parities.append_bit(corner);
+        parities
+    }
+}
+
+impl ErrorDetection for TwoDimensionalParity {
+    fn add(&self, mut data: BitString) -> BitString {
+        let parities = self.parities(&data);
+        data.append_bits(parities);
+        data
+    }
+
+    fn check_and_remove(&self, mut data: BitString) -> anyhow::Result<BitString> {
+        // The length of the data is the only n for which
+        // n + ceil(n / row_len) + row_len + 1 equals the received length
+        let grid_len = data.len().checked_sub(self.row_len + 1);
+        ensure!(grid_len.is_some(), "The message {data} is too short");
+        let grid_len = grid_len.expect("Already ensured");
+
+        let rows = grid_len.div_ceil(self.row_len + 1);
+        let data_len = grid_len - rows;
+        ensure!(
+            data_len + self.overhead(data_len) == data.len(),
+            "The message {data} has an invalid length for rows of {} bits",
+            self.row_len
+        );
+
+        let parities = BitString::from(data.remove_last_len(self.overhead(data_len)));
+
+        ensure!(
+            parities == self.parities(&data),
+            "The message {data} has incorrect parity"
+        );
+
+        Ok(data)
+    }
+
+    fn overhead(&self, data_len: usize) -> usize {
+        data_len.div_ceil(self.row_len) + self.row_len + 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString, bitstring, data_link_layer::error_detection::ErrorDetection,
+    };
+
+    use super::{Parity, TwoDimensionalParity};
+
+    #[test]
+    fn even_parity() {
+        let data = bitstring!(1, 0, 1, 1);
+
+        assert_eq!(Parity::Even.add(data.clone()), bitstring!(1, 0, 1, 1, 1));
+        assert_eq!(Parity::Odd.add(data), bitstring!(1, 0, 1, 1, 0));
+    }
+
+    #[test]
+    fn parity_detects_odd_flips() {
+        let mut data = Parity::Even.add(bitstring!(1, 0, 1, 1));
+        data.flip_bit(1);
+
+        assert!(Parity::Even.check_and_remove(data.clone()).is_err());
+
+        // Two flips go unnoticed
+        data.flip_bit(2);
+        assert!(Parity::Even.check_and_remove(data).is_ok());
+    }
+
+    #[test]
+    fn two_dimensional_layout() {
+        // 1 0 1 | 0
+        // 1 1 0 | 0
+        // 1 0 0 | 1
+        // ------+--
+        // 1 1 1 | 1
+        let data = bitstring!(1, 0, 1, 1, 1, 0, 1);
+        let code = TwoDimensionalParity::new(3);
+
+        let expected = bitstring!(1, 0, 1, 1, 1, 0, 1, 0, 0, 1, 1, 1, 1, 1);
+
+        assert_eq!(code.add(data), expected);
+    }
+
+    #[test]
+    fn two_dimensional_round_trip() {
+        let code = TwoDimensionalParity::new(8);
+
+        for len in 1..40 {
+            let data = BitString::with_ones(len);
+            let with_parity = code.add(data.clone());
+
+            assert_eq!(
+                code.check_and_remove(with_parity)
+                    .expect("Unmodified data should pass"),
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn two_dimensional_detects_even_flips_in_row() {
+        let code = TwoDimensionalParity::new(8);
+        let mut data = code.add(BitString::from(*b"Hello"));
+
+        data.flip_bit(1);
+        data.flip_bit(2);
+
+        assert!(code.check_and_remove(data).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use anyhow::{bail, ensure};
+
+use crate::{bit::Bit, bit_string::BitString};
+
+use super::{Decoded, ForwardErrorCorrection};
+
+/// A rate 1/n convolutional code, optionally punctured to a higher rate. The
+/// encoder is terminated with `constraint_len - 1` zero bits, so the decoder
+/// knows the final state.
+///
+/// Generators are given with the most significant bit applying to the newest
+/// input bit, so the NASA K = 7 code is `[0o171, 0o133]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Convolutional {
+    constraint_len: u32,
+    generators: Vec<u32>,
+    // One row per generator, one column per input bit in the puncturing period
+    puncturing: Vec<Vec<bool>>,
+}
+
+/// The costs of a received symbol being a 0 or a 1.
+type SymbolCost = (f64, f64);
+
+impl Convolutional {
+    #[must_use]
+    pub fn new(constraint_len: u32, generators: &[u32]) -> Self {
+        assert!(
+            (2..=16).contains(&constraint_len),
+            "The constraint length must be between 2 and 16"
+        );
+        assert!(
+            generators.len() >= 2,
+            "At least two generators are needed for a rate 1/n code"
+        );
+        assert!(
+            generators
+                .iter()
+                .all(|&generator| generator > 0 && generator < 1 << constraint_len),
+            "Generators must be non zero and fit in the constraint length"
+        );
+
+        let puncturing = vec![vec![true]; generators.len()];
+
+        Self {
+            constraint_len,
+            generators: generators.to_vec(),
+            puncturing,
+        }
+    }
+
+    /// The rate 1/2, K = 7 code used by NASA and 802.11.
+    #[must_use]
+    pub fn nasa_k7() -> Self {
+        Self::new(7, &[0o171, 0o133])
+    }
+
+    /// Punctures the output. `pattern` has one row per generator and one
+    /// column per input bit, output bits with a false entry are not sent. For
+    /// example `[[true, true], [true, false]]` makes a rate 1/2 code rate 2/3.
+    #[must_use]
+    pub fn set_puncturing(self, pattern: Vec<Vec<bool>>) -> Self {
+        assert_eq!(
+            pattern.len(),
+            self.generators.len(),
+            "The pattern needs a row per generator"
+        );
+
+        let period = pattern[0].len();
+        assert!(period > 0, "The pattern needs at least one column");
+        assert!(
+            pattern.iter().all(|row| row.len() == period),
+            "Every row of the pattern must have the same length"
+        );
+        assert!(
+            (0..period).all(|column| pattern.iter().any(|row| row[column])),
+            "Every column of the pattern must send at least one bit"
+        );
+
+        Self {
+            puncturing: pattern,
+            ..self
+        }
+    }
+
+    /// The rate 2/3 puncturing pattern for rate 1/2 codes.
+    #[must_use]
+    pub fn puncture_2_3() -> Vec<Vec<bool>> {
+        vec![vec![true, true], vec![true, false]]
+    }
+
+    /// The rate 3/4 puncturing pattern for rate 1/2 codes.
+    #[must_use]
+    pub fn puncture_3_4() -> Vec<Vec<bool>> {
+        vec![vec![true, true, false], vec![true, false, true]]
+    }
+
+    #[must_use]
+    pub const fn constraint_len(&self) -> u32 {
+        self.constraint_len
+    }
+
+    const fn state_count(&self) -> usize {
+        1 << (self.constraint_len - 1)
+    }
+
+    const fn tail_len(&self) -> usize {
+        self.constraint_len as usize - 1
+    }
+
+    fn period(&self) -> usize {
+        self.puncturing[0].len()
+    }
+
+    /// Whether the output of `generator` at input step `step` is sent.
+    fn is_sent(&self, generator: usize, step: usize) -> bool {
+        self.puncturing[generator][step % self.period()]
+    }
+
+    /// The amount of sent bits for `steps` input bits, including the tail.
+    fn sent_len(&self, steps: usize) -> usize {
+        let per_period = self
+            .puncturing
+            .iter()
+            .flatten()
+            .filter(|&&sent| sent)
+            .count();
+        let partial = (0..steps % self.period())
+            .map(|column| self.puncturing.iter().filter(|row| row[column]).count())
+            .sum::<usize>();
+
+        steps / self.period() * per_period + partial
+    }
+
+    /// The output bits, one per generator, when `input` enters the encoder in
+    /// `state`.
+    fn output(&self, state: usize, input: Bit) -> impl Iterator<Item = Bit> + '_ {
+        let register = ((input as u32) << (self.constraint_len - 1)) | state as u32;
+
+        self.generators
+            .iter()
+            .map(move |generator| Bit::from((register & generator).count_ones() % 2 == 1))
+    }
+
+    const fn next_state(&self, state: usize, input: Bit) -> usize {
+        ((input as usize) << (self.constraint_len - 1) | state) >> 1
+    }
+
+    /// Decodes soft symbols, where +1.0 is a confident 0 bit and -1.0 is a
+    /// confident 1 bit. A symbol of 0.0 carries no information.
+    pub fn decode_soft(&self, symbols: &[f64]) -> anyhow::Result<Decoded> {
+        let costs = symbols
+            .iter()
+            .map(|symbol| ((symbol - 1.).powi(2), (symbol + 1.).powi(2)))
+            .collect::<Vec<_>>();
+
+        let received = symbols
+            .iter()
+            .map(|&symbol| Bit::from(symbol < 0.))
+            .collect::<BitString>();
+
+        self.viterbi(&costs, &received)
+    }
+
+    /// Finds the most likely input given the cost of every sent symbol.
+    fn viterbi(&self, costs: &[SymbolCost], received: &BitString) -> anyhow::Result<Decoded> {
+        let Some(steps) = (0..=costs.len()).find(|&steps| self.sent_len(steps) == costs.len())
+        else {
+            bail!(
+                "Received {} bits, which is not a valid length for this code",
+                costs.len()
+            );
+        };
+        ensure!(
+            steps >= self.tail_len(),
+            "Received {} bits, which is shorter than the tail",
+            costs.len()
+        );
+
+        let state_count = self.state_count();
+        let mut metrics = vec![f64::INFINITY; state_count];
+        metrics[0] = 0.;
+
+        // The previous state and input bit of every state at every step
+        let mut survivors: Vec<Vec<(usize, Bit)>> = Vec::with_capacity(steps);
+        let mut costs = costs.iter();
+
+        for step in 0..steps {
+            // Punctured symbols cost the same for either value
+            let step_costs = (0..self.generators.len())
+                .map(|generator| {
+                    if self.is_sent(generator, step) {
+                        *costs.next().expect("The length was checked")
+                    } else {
+                        (0., 0.)
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let mut next_metrics = vec![f64::INFINITY; state_count];
+            let mut step_survivors = vec![(0, Bit::Off); state_count];
+
+            for (state, &metric) in metrics.iter().enumerate() {
+                if metric.is_infinite() {
+                    continue;
+                }
+
+                for input in [Bit::Off, Bit::On] {
+                    let branch = self
+                        .output(state, input)
+                        .zip(&step_costs)
+                        .map(|(bit, cost)| match bit {
+                            Bit::Off => cost.0,
+                            Bit::On => cost.1,
+                        })
+                        .sum::<f64>();
+
+                    let next = self.next_state(state, input);
+                    if metric + branch < next_metrics[next] {
+                        next_metrics[next] = metric + branch;
+                        step_survivors[next] = (state, input);
+                    }
+                }
+            }
+
+            metrics = next_metrics;
+            survivors.push(step_survivors);
+        }
+
+        // The tail drives the encoder back to the zero state
+        let mut state = 0;
+        let mut input = Vec::with_capacity(steps);
+        for step_survivors in survivors.iter().rev() {
+            let (previous, bit) = step_survivors[state];
+            input.push(bit);
+            state = previous;
+        }
+        input.reverse();
+        input.truncate(steps - self.tail_len());
+
+        let data = BitString::from(input);
+        let corrected = self
+            .encode(data.clone())
+            .iter()
+            .zip(received)
+            .filter(|(expected, actual)| expected != actual)
+            .count();
+
+        Ok(Decoded {
+            data,
+            corrected,
+            detected: 0,
+        })
+    }
+}
+
+impl ForwardErrorCorrection for Convolutional {
+    fn encode(&self, mut data: BitString) -> BitString {
+        let mut output = BitString::with_capacity(self.encoded_len(data.len()));
+        data.append_zeroes(self.tail_len());
+
+        let mut state = 0;
+        for (step, &bit) in data.iter().enumerate() {
+            for (generator, output_bit) in self.output(state, bit).enumerate() {
+                if self.is_sent(generator, step) {
+                    output.append_bit(output_bit);
+                }
+            }
+            state = self.next_state(state, bit);
+        }
+
+        output
+    }
+
+    /// Hard decision Viterbi decoding.
+    fn decode(&self, data: BitString) -> anyhow::Result<Decoded> {
+        let costs = data
+            .iter()
+            .map(|bit| match bit {
+                Bit::Off => (0., 1.),
+                Bit::On => (1., 0.),
+            })
+            .collect::<Vec<_>>();
+
+        self.viterbi(&costs, &data)
+    }
+
+    fn encoded_len(&self, data_len: usize) -> usize {
+        self.sent_len(data_len + self.tail_len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit::Bit, bit_string::BitString, bitstring, corruption_type::Corruption,
+        data_link_layer::fec::ForwardErrorCorrection, rand::XorShift,
+    };
+
+    use super::Convolutional;
+
+    const DATA: &[u8] = b"Hello world!";
+
+    #[test]
+    fn known_encoding() {
+        // K = 3 with generators 7 and 5, the textbook example
+        let code = Convolutional::new(3, &[0b111, 0b101]);
+
+        let encoded = code.encode(bitstring!(1, 0, 1, 1));
+
+        assert_eq!(encoded, bitstring!(1, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1, 1));
+    }
+
+    #[test]
+    fn lengths() {
+        let code = Convolutional::nasa_k7();
+        assert_eq!(code.encoded_len(10), 32);
+        assert_eq!(code.encode(BitString::with_ones(10)).len(), 32);
+
+        let code = code.set_puncturing(Convolutional::puncture_3_4());
+        assert_eq!(code.encoded_len(12), 24);
+        assert_eq!(code.encode(BitString::with_ones(12)).len(), 24);
+    }
+
+    #[test]
+    fn clean_round_trip() {
+        let data = BitString::from(DATA);
+
+        for code in [
+            Convolutional::new(3, &[0b111, 0b101]),
+            Convolutional::nasa_k7(),
+            Convolutional::nasa_k7().set_puncturing(Convolutional::puncture_2_3()),
+            Convolutional::nasa_k7().set_puncturing(Convolutional::puncture_3_4()),
+        ] {
+            let decoded = code
+                .decode(code.encode(data.clone()))
+                .expect("Valid length");
+
+            assert_eq!(decoded.data, data);
+            assert_eq!(decoded.corrected, 0);
+        }
+    }
+
+    #[test]
+    fn corrects_multi_bit_flip() {
+        let data = BitString::from(DATA);
+        let code = Convolutional::nasa_k7();
+        let mut rand = XorShift::new(7);
+
+        for _ in 0..20 {
+            // Roughly one flip in every 50 bits
+            let corruption = Corruption::MultiBitFlipOdd(rand.copy_reset(), 2);
+            let corrupted = corruption.corrupt(code.encode(data.clone()));
+
+            let decoded = code.decode(corrupted).expect("Valid length");
+
+            assert_eq!(decoded.data, data);
+            assert!(decoded.corrected > 0);
+        }
+    }
+
+    #[test]
+    fn punctured_corrects_one_bit_flip() {
+        let data = BitString::from(DATA);
+        let code = Convolutional::nasa_k7().set_puncturing(Convolutional::puncture_2_3());
+        let mut corruption = Corruption::OneBitFlip(XorShift::new(3));
+
+        for _ in 0..20 {
+            let corrupted = corruption.corrupt_borrow(code.encode(data.clone()));
+
+            let decoded = code.decode(corrupted).expect("Valid length");
+
+            assert_eq!(decoded.data, data);
+            assert_eq!(decoded.corrected, 1);
+        }
+    }
+
+    #[test]
+    fn soft_decision() {
+        let data = BitString::from(DATA);
+        let code = Convolutional::nasa_k7();
+
+        let mut symbols = code
+            .encode(data.clone())
+            .iter()
+            .map(|bit| match bit {
+                Bit::Off => 1.,
+                Bit::On => -1.,
+            })
+            .collect::<Vec<f64>>();
+
+        // Weak wrong symbols are outvoted by the confident ones around them
+        for idx in (0..symbols.len()).step_by(5) {
+            symbols[idx] *= -0.2;
+        }
+
+        let decoded = code.decode_soft(&symbols).expect("Valid length");
+
+        assert_eq!(decoded.data, data);
+        assert!(decoded.corrected > 0);
+    }
+
+    #[test]
+    fn invalid_length() {
+        let code = Convolutional::nasa_k7();
+
+        assert!(code.decode(bitstring!(1, 0, 1)).is_err());
+        assert!(code.decode(bitstring!(1, 0, 1, 1)).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Arithmetic over GF(2^8) with the primitive polynomial
+//! x^8 + x^4 + x^3 + x^2 + 1.
+
+const PRIMITIVE_POLY: u16 = 0x11D;
+
+const fn build_tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= PRIMITIVE_POLY;
+        }
+        i += 1;
+    }
+
+    // Duplicate the table so products of logs never have to be reduced
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+
+    (exp, log)
+}
+
+const TABLES: ([u8; 512], [u8; 256]) = build_tables();
+const EXP: [u8; 512] = TABLES.0;
+const LOG: [u8; 256] = TABLES.1;
+
+/// The primitive element alpha to the power `power`.
+pub const fn alpha_pow(power: usize) -> u8 {
+    EXP[power % 255]
+}
+
+pub const fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+}
+
+pub fn div(a: u8, b: u8) -> u8 {
+    assert!(b != 0, "Division by zero in GF(2^8)");
+    if a == 0 {
+        return 0;
+    }
+    EXP[LOG[a as usize] as usize + 255 - LOG[b as usize] as usize]
+}
+
+pub fn inv(a: u8) -> u8 {
+    div(1, a)
+}
+
+/// Evaluates a polynomial with its coefficients in ascending order of power.
+pub fn poly_eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &coef| mul(acc, x) ^ coef)
+}
+
+/// Multiplies two polynomials with their coefficients in ascending order of
+/// power.
+pub fn poly_mul(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut res = vec![0; left.len() + right.len() - 1];
+
+    for (i, &l) in left.iter().enumerate() {
+        for (j, &r) in right.iter().enumerate() {
+            res[i + j] ^= mul(l, r);
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::{alpha_pow, div, inv, mul, poly_eval, poly_mul};
+
+    #[test]
+    fn multiplication() {
+        assert_eq!(mul(0, 0x53), 0);
+        assert_eq!(mul(1, 0x53), 0x53);
+        assert_eq!(mul(2, 0x80), 0x1D);
+        assert_eq!(mul(alpha_pow(254), alpha_pow(1)), 1);
+    }
+
+    #[test]
+    fn inverses() {
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1, "Failed for {a}");
+            assert_eq!(div(mul(a, 0x37), 0x37), a, "Failed for {a}");
+        }
+    }
+
+    #[test]
+    fn polynomials() {
+        // (1 + x)(1 + x) = 1 + x^2 in characteristic 2
+        assert_eq!(poly_mul(&[1, 1], &[1, 1]), vec![1, 0, 1]);
+        assert_eq!(poly_eval(&[1, 0, 1], 1), 0);
+        assert_eq!(poly_eval(&[3, 0, 1], 0), 3);
+    }
+}
@@ -0,0 +1,335 @@
+use anyhow::ensure;
+
+use crate::{bit::Bit, bit_string::BitString};
+
+use super::{Decoded, ForwardErrorCorrection};
+
+/// A Hamming(2^r - 1, 2^r - r - 1) code, optionally extended with an overall
+/// parity bit to single error correction, double error detection (SECDED).
+///
+/// Parity bits are placed on the power of two positions of every block, the
+/// overall parity bit of the extended code is placed at the end of the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hamming {
+    parity_bits: u32,
+    extended: bool,
+}
+
+impl Hamming {
+    /// A Hamming code with `parity_bits` parity bits per block.
+    #[must_use]
+    pub fn new(parity_bits: u32) -> Self {
+        assert!(
+            (2..usize::BITS).contains(&parity_bits),
+            "A Hamming code needs between 2 and {} parity bits",
+            usize::BITS - 1
+        );
+
+        Self {
+            parity_bits,
+            extended: false,
+        }
+    }
+
+    /// The extended SECDED variant of [`Hamming::new`].
+    #[must_use]
+    pub fn secded(parity_bits: u32) -> Self {
+        Self {
+            extended: true,
+            ..Self::new(parity_bits)
+        }
+    }
+
+    #[must_use]
+    pub fn hamming_7_4() -> Self {
+        Self::new(3)
+    }
+
+    /// The amount of bits in a block without the overall parity bit.
+    const fn hamming_len(&self) -> usize {
+        (1 << self.parity_bits) - 1
+    }
+
+    /// The amount of bits in a block.
+    #[must_use]
+    pub const fn block_len(&self) -> usize {
+        self.hamming_len() + self.extended as usize
+    }
+
+    /// The amount of data bits in a block.
+    #[must_use]
+    pub const fn data_len(&self) -> usize {
+        self.hamming_len() - self.parity_bits as usize
+    }
+
+    #[must_use]
+    pub const fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    fn encode_block(&self, data: &[Bit]) -> BitString {
+        // Index 0 is unused, so positions match the 1 indexed syndrome
+        let mut block = vec![Bit::Off; self.hamming_len() + 1];
+
+        let mut data_bits = data.iter();
+        for (position, bit) in block.iter_mut().enumerate().skip(1) {
+            if !position.is_power_of_two() {
+                *bit = *data_bits.next().unwrap_or(&Bit::Off);
+            }
+        }
+
+        let syndrome = Self::syndrome(&block);
+        for parity in 0..self.parity_bits {
+            if syndrome & (1 << parity) != 0 {
+                block[1 << parity] = Bit::On;
+            }
+        }
+
+        let mut output = BitString::from(&block[1..]);
+        if self.extended {
+            let overall = block.iter().fold(Bit::Off, |acc, bit| acc ^ *bit);
+            output.append_bit(overall);
+        }
+
+        output
+    }
+
+    /// The xor of all positions holding an on bit, which is zero for a valid
+    /// block.
+    fn syndrome(block: &[Bit]) -> usize {
+        block
+            .iter()
+            .enumerate()
+            .filter(|(_, bit)| **bit == Bit::On)
+            .fold(0, |acc, (position, _)| acc ^ position)
+    }
+
+    /// Decodes a single block, returning the data bits and the amount of
+    /// corrected and detected errors.
+    fn decode_block(&self, received: &[Bit]) -> (BitString, usize, usize) {
+        let mut block = Vec::with_capacity(self.hamming_len() + 1);
+        block.push(Bit::Off);
+        block.extend_from_slice(&received[..self.hamming_len()]);
+
+        let syndrome = Self::syndrome(&block);
+
+        let (corrected, detected) = if self.extended {
+            let overall = received.iter().fold(Bit::Off, |acc, bit| acc ^ *bit);
+
+            match (syndrome, overall) {
+                (0, Bit::Off) => (0, 0),
+                // The overall parity bit itself was flipped
+                (0, Bit::On) => (1, 0),
+                (_, Bit::On) => {
+                    block[syndrome].flip();
+                    (1, 0)
+                }
+                // An even amount of errors, which can't be corrected
+                (_, Bit::Off) => (0, 1),
+            }
+        } else if syndrome == 0 {
+            (0, 0)
+        } else {
+            block[syndrome].flip();
+            (1, 0)
+        };
+
+        let data = block
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(position, _)| !position.is_power_of_two())
+            .map(|(_, bit)| *bit)
+            .collect::<BitString>();
+
+        (data, corrected, detected)
+    }
+}
+
+impl ForwardErrorCorrection for Hamming {
+    fn encode(&self, data: BitString) -> BitString {
+        let mut output = BitString::with_capacity(self.encoded_len(data.len()));
+
+        for chunk in data.as_bit_slice().chunks(self.data_len()) {
+            output.append_bits(self.encode_block(chunk));
+        }
+
+        output
+    }
+
+    fn decode(&self, data: BitString) -> anyhow::Result<Decoded> {
+        ensure!(
+            data.len().is_multiple_of(self.block_len()),
+            "Received {} bits, which is not a multiple of the block length {}",
+            data.len(),
+            self.block_len()
+        );
+
+        let mut decoded = Decoded {
+            data: BitString::with_capacity(data.len() / self.block_len() * self.data_len()),
+            corrected: 0,
+            detected: 0,
+        };
+
+        for block in data.as_bit_slice().chunks(self.block_len()) {
+            let (bits, corrected, detected) = self.decode_block(block);
+
+            decoded.data.append_bits(bits);
+            decoded.corrected += corrected;
+            decoded.detected += detected;
+        }
+
+        Ok(decoded)
+    }
+
+    fn encoded_len(&self, data_len: usize) -> usize {
+        data_len.div_ceil(self.data_len()) * self.block_len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        bitstring,
+        corruption_type::Corruption,
+        data_link_layer::fec::{Decoded, ForwardErrorCorrection},
+        rand::XorShift,
+    };
+
+    use super::Hamming;
+
+    const DATA: &[u8] = b"Hello world!";
+
+    fn bits_flipped(left: &BitString, right: &BitString) -> usize {
+        left.iter().zip(right).filter(|(l, r)| l != r).count()
+    }
+
+    #[test]
+    fn lengths() {
+        let code = Hamming::hamming_7_4();
+        assert_eq!(code.block_len(), 7);
+        assert_eq!(code.data_len(), 4);
+
+        let code = Hamming::secded(3);
+        assert_eq!(code.block_len(), 8);
+        assert_eq!(code.data_len(), 4);
+
+        let code = Hamming::new(5);
+        assert_eq!(code.block_len(), 31);
+        assert_eq!(code.data_len(), 26);
+        assert_eq!(code.encoded_len(27), 62);
+    }
+
+    #[test]
+    fn hamming_7_4_codeword() {
+        let code = Hamming::hamming_7_4();
+
+        let encoded = code.encode(bitstring!(1, 0, 1, 1));
+
+        assert_eq!(encoded, bitstring!(0, 1, 1, 0, 0, 1, 1));
+    }
+
+    #[test]
+    fn secded_codeword() {
+        let code = Hamming::secded(3);
+
+        let encoded = code.encode(bitstring!(1, 0, 1, 1));
+
+        assert_eq!(encoded, bitstring!(0, 1, 1, 0, 0, 1, 1, 0));
+    }
+
+    #[test]
+    fn clean_round_trip() {
+        let data = BitString::from(DATA);
+
+        for code in [Hamming::hamming_7_4(), Hamming::secded(4), Hamming::new(6)] {
+            let decoded = code
+                .decode(code.encode(data.clone()))
+                .expect("Valid length");
+
+            assert_eq!(decoded.corrected, 0);
+            assert_eq!(decoded.detected, 0);
+            assert_eq!(decoded.data.copy_len(0, data.len()), data);
+        }
+    }
+
+    #[test]
+    fn corrects_every_single_flip() {
+        let code = Hamming::secded(3);
+        let data = bitstring!(1, 1, 0, 1);
+        let encoded = code.encode(data.clone());
+
+        for idx in 0..encoded.len() {
+            let mut corrupted = encoded.clone();
+            corrupted.flip_bit(idx);
+
+            let decoded = code.decode(corrupted).expect("Valid length");
+            assert_eq!(
+                decoded,
+                Decoded {
+                    data: data.clone(),
+                    corrected: 1,
+                    detected: 0
+                },
+                "Failed at index {idx}"
+            );
+        }
+    }
+
+    #[test]
+    fn corrects_one_bit_flip() {
+        let data = BitString::from(DATA);
+
+        for code in [Hamming::hamming_7_4(), Hamming::secded(3), Hamming::new(5)] {
+            let mut corruption = Corruption::OneBitFlip(XorShift::new(69));
+
+            for _ in 0..20 {
+                let corrupted = corruption.corrupt_borrow(code.encode(data.clone()));
+
+                let decoded = code.decode(corrupted).expect("Valid length");
+
+                assert_eq!(decoded.corrected, 1);
+                assert_eq!(decoded.detected, 0);
+                assert_eq!(decoded.data.copy_len(0, data.len()), data);
+            }
+        }
+    }
+
+    #[test]
+    fn secded_detects_multi_bit_flip_even() {
+        let code = Hamming::secded(3);
+        let data = bitstring!(1, 0, 0, 1);
+        let encoded = code.encode(data.clone());
+
+        let mut rand = XorShift::new(420);
+        let mut double_flips = 0;
+
+        for _ in 0..100 {
+            let corruption = Corruption::MultiBitFlipEven(rand.copy_reset(), 30);
+            let corrupted = corruption.corrupt(encoded.clone());
+            let flips = bits_flipped(&encoded, &corrupted);
+
+            let decoded = code.decode(corrupted).expect("Valid length");
+
+            match flips {
+                0 => assert_eq!(decoded.data, data),
+                2 => {
+                    double_flips += 1;
+                    assert_eq!(decoded.corrected, 0);
+                    assert_eq!(decoded.detected, 1);
+                }
+                _ => {}
+            }
+        }
+
+        assert!(double_flips > 0);
+    }
+
+    #[test]
+    fn invalid_length() {
+        let code = Hamming::hamming_7_4();
+
+        assert!(code.decode(bitstring!(1, 0, 1)).is_err());
+    }
+}
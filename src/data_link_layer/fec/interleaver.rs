@@ -0,0 +1,321 @@
+use anyhow::ensure;
+
+use crate::{bit::Bit, bit_string::BitString};
+
+use super::{Decoded, ForwardErrorCorrection};
+
+/// Reorders bits so errors that are adjacent on the cable end up spread over
+/// the data.
+pub trait Interleaver: Send {
+    fn interleave(&self, data: BitString) -> BitString;
+
+    /// Undoes [`Interleaver::interleave`]. Fails if `data` cannot be the
+    /// output of [`Interleaver::interleave`].
+    fn deinterleave(&self, data: BitString) -> anyhow::Result<BitString>;
+
+    /// The amount of bits [`Interleaver::interleave`] produces for `data_len`
+    /// bits.
+    fn interleaved_len(&self, data_len: usize) -> usize;
+}
+
+/// Writes the data into a matrix row by row and reads it out column by
+/// column. Data that doesn't fill the last matrix is interleaved in a matrix
+/// with fewer rows, so the length never changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInterleaver {
+    rows: usize,
+    columns: usize,
+}
+
+impl BlockInterleaver {
+    #[must_use]
+    pub fn new(rows: usize, columns: usize) -> Self {
+        assert!(rows > 0 && columns > 0, "The matrix cannot be empty");
+        Self { rows, columns }
+    }
+
+    #[must_use]
+    pub const fn rows(&self) -> usize {
+        self.rows
+    }
+
+    #[must_use]
+    pub const fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// The order in which the bits of a matrix holding `len` bits are read.
+    fn read_order(&self, len: usize) -> impl Iterator<Item = usize> {
+        let columns = self.columns;
+        let rows = len.div_ceil(columns);
+
+        (0..columns)
+            .flat_map(move |column| (0..rows).map(move |row| row * columns + column))
+            .filter(move |&idx| idx < len)
+    }
+}
+
+impl Interleaver for BlockInterleaver {
+    fn interleave(&self, data: BitString) -> BitString {
+        let mut output = BitString::with_capacity(data.len());
+
+        for matrix in data.as_bit_slice().chunks(self.rows * self.columns) {
+            for idx in self.read_order(matrix.len()) {
+                output.append_bit(matrix[idx]);
+            }
+        }
+
+        output
+    }
+
+    fn deinterleave(&self, data: BitString) -> anyhow::Result<BitString> {
+        let mut output = BitString::with_capacity(data.len());
+
+        for matrix in data.as_bit_slice().chunks(self.rows * self.columns) {
+            let mut bits = vec![Bit::Off; matrix.len()];
+
+            for (&bit, idx) in matrix.iter().zip(self.read_order(matrix.len())) {
+                bits[idx] = bit;
+            }
+
+            output.append_bits(bits);
+        }
+
+        Ok(output)
+    }
+
+    fn interleaved_len(&self, data_len: usize) -> usize {
+        data_len
+    }
+}
+
+/// A convolutional interleaver with `branches` delay lines, where branch `i`
+/// delays its bits by `i * depth` uses of that branch. Zeroes are appended to
+/// flush the delay lines, which adds `branches * (branches - 1) * depth` bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvolutionalInterleaver {
+    branches: usize,
+    depth: usize,
+}
+
+impl ConvolutionalInterleaver {
+    #[must_use]
+    pub fn new(branches: usize, depth: usize) -> Self {
+        assert!(branches > 0, "The interleaver needs at least one branch");
+        Self { branches, depth }
+    }
+
+    #[must_use]
+    pub const fn branches(&self) -> usize {
+        self.branches
+    }
+
+    #[must_use]
+    pub const fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The total delay a bit experiences through interleaving and
+    /// deinterleaving.
+    const fn flush_len(&self) -> usize {
+        self.branches * (self.branches - 1) * self.depth
+    }
+
+    /// Runs `data` through delay lines where branch `i` has a delay of
+    /// `delay(i)` uses, starting out filled with zeroes.
+    fn delay_lines(&self, data: &BitString, delay: impl Fn(usize) -> usize) -> BitString {
+        let mut lines = (0..self.branches)
+            .map(|branch| vec![Bit::Off; delay(branch)])
+            .collect::<Vec<_>>();
+        let mut heads = vec![0; self.branches];
+
+        data.iter()
+            .enumerate()
+            .map(|(idx, &bit)| {
+                let branch = idx % self.branches;
+                let line = &mut lines[branch];
+
+                if line.is_empty() {
+                    return bit;
+                }
+
+                // The line is used as a ring buffer
+                let head = heads[branch];
+                let out = std::mem::replace(&mut line[head], bit);
+                heads[branch] = (head + 1) % line.len();
+                out
+            })
+            .collect()
+    }
+}
+
+impl Interleaver for ConvolutionalInterleaver {
+    fn interleave(&self, mut data: BitString) -> BitString {
+        data.append_zeroes(self.flush_len());
+        self.delay_lines(&data, |branch| branch * self.depth)
+    }
+
+    fn deinterleave(&self, data: BitString) -> anyhow::Result<BitString> {
+        ensure!(
+            data.len() >= self.flush_len(),
+            "Received {} bits, which is shorter than the {} bits of flushing",
+            data.len(),
+            self.flush_len()
+        );
+
+        let mut output =
+            self.delay_lines(&data, |branch| (self.branches - 1 - branch) * self.depth);
+        output.remove_len(0, self.flush_len());
+
+        Ok(output)
+    }
+
+    fn interleaved_len(&self, data_len: usize) -> usize {
+        data_len + self.flush_len()
+    }
+}
+
+/// Interleaves the output of a forward error correction code, so bursts of
+/// errors are spread over many codewords.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interleaved<C, I> {
+    fec: C,
+    interleaver: I,
+}
+
+impl<C, I> Interleaved<C, I>
+where
+    C: ForwardErrorCorrection,
+    I: Interleaver,
+{
+    #[must_use]
+    pub const fn new(fec: C, interleaver: I) -> Self {
+        Self { fec, interleaver }
+    }
+}
+
+impl<C, I> ForwardErrorCorrection for Interleaved<C, I>
+where
+    C: ForwardErrorCorrection,
+    I: Interleaver,
+{
+    fn encode(&self, data: BitString) -> BitString {
+        self.interleaver.interleave(self.fec.encode(data))
+    }
+
+    fn decode(&self, data: BitString) -> anyhow::Result<Decoded> {
+        self.fec.decode(self.interleaver.deinterleave(data)?)
+    }
+
+    fn encoded_len(&self, data_len: usize) -> usize {
+        self.interleaver
+            .interleaved_len(self.fec.encoded_len(data_len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        bitstring,
+        corruption_type::Corruption,
+        data_link_layer::fec::{ForwardErrorCorrection, Hamming},
+        rand::XorShift,
+    };
+
+    use super::{BlockInterleaver, ConvolutionalInterleaver, Interleaved, Interleaver};
+
+    // 16 bytes is 32 Hamming(7, 4) codewords, exactly two 16 by 7 matrices
+    const DATA: &[u8] = b"Burst errors :o!";
+    const CYCLES: usize = 100;
+
+    #[test]
+    fn block_interleave() {
+        let interleaver = BlockInterleaver::new(2, 3);
+
+        // 0 1 1
+        // 0 0 1
+        let interleaved = interleaver.interleave(bitstring!(0, 1, 1, 0, 0, 1));
+
+        assert_eq!(interleaved, bitstring!(0, 0, 1, 0, 1, 1));
+    }
+
+    #[test]
+    fn block_partial_matrix() {
+        let interleaver = BlockInterleaver::new(2, 3);
+
+        // 1 1 0 | 1 0 1
+        // 0 0 1 | 1
+        let data = bitstring!(1, 1, 0, 0, 0, 1, 1, 0, 1, 1);
+        let interleaved = interleaver.interleave(data.clone());
+
+        assert_eq!(interleaved, bitstring!(1, 0, 1, 0, 0, 1, 1, 1, 0, 1));
+        assert_eq!(
+            interleaver.deinterleave(interleaved).expect("Valid data"),
+            data
+        );
+    }
+
+    #[test]
+    fn convolutional_round_trip() {
+        let data = BitString::from(DATA);
+
+        for (branches, depth) in [(1, 0), (2, 1), (4, 3), (16, 1), (5, 2)] {
+            let interleaver = ConvolutionalInterleaver::new(branches, depth);
+
+            let interleaved = interleaver.interleave(data.clone());
+            assert_eq!(interleaved.len(), interleaver.interleaved_len(data.len()));
+
+            assert_eq!(
+                interleaver.deinterleave(interleaved).expect("Valid data"),
+                data,
+                "Failed for {branches} branches of depth {depth}"
+            );
+        }
+    }
+
+    #[test]
+    fn convolutional_spreads_adjacent_bits() {
+        let interleaver = ConvolutionalInterleaver::new(3, 1);
+
+        let interleaved = interleaver.interleave(bitstring!(1, 1, 1, 1, 1, 1));
+
+        // Branch 0 passes through, branch 1 and 2 are delayed by 3 and 6 bits
+        assert_eq!(interleaved, bitstring!(1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1));
+    }
+
+    /// Counts how many of `CYCLES` burst corrupted transmissions are fully
+    /// repaired by `fec`.
+    fn repaired_bursts(fec: &dyn ForwardErrorCorrection) -> usize {
+        let data = BitString::from(DATA);
+        let mut corruption = Corruption::BurstFlip(XorShift::new(31));
+
+        (0..CYCLES)
+            .filter(|_| {
+                let corrupted = corruption.corrupt_borrow(fec.encode(data.clone()));
+                let decoded = fec.decode(corrupted).expect("Valid length");
+
+                decoded.data == data
+            })
+            .count()
+    }
+
+    #[test]
+    fn interleaving_repairs_burst_flip() {
+        let hamming = Hamming::hamming_7_4();
+
+        let plain = repaired_bursts(&hamming);
+        let block = repaired_bursts(&Interleaved::new(hamming, BlockInterleaver::new(16, 7)));
+        let convolutional = repaired_bursts(&Interleaved::new(
+            hamming,
+            ConvolutionalInterleaver::new(16, 1),
+        ));
+
+        assert!(
+            plain < CYCLES / 10,
+            "Repaired {plain} bursts without interleaving"
+        );
+        assert_eq!(block, CYCLES);
+        assert_eq!(convolutional, CYCLES);
+    }
+}
@@ -0,0 +1,38 @@
+pub mod convolutional;
+mod galois_field;
+pub mod hamming;
+pub mod interleaver;
+pub mod reed_solomon;
+
+use crate::bit_string::BitString;
+
+pub use convolutional::Convolutional;
+pub use hamming::Hamming;
+pub use interleaver::{BlockInterleaver, ConvolutionalInterleaver, Interleaved, Interleaver};
+pub use reed_solomon::ReedSolomon;
+
+/// The result of decoding received data with a forward error correction code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub data: BitString,
+    /// The amount of errors that were found and repaired
+    pub corrected: usize,
+    /// The amount of errors that were found but could not be repaired
+    pub detected: usize,
+}
+
+/// A code that adds redundancy to data, so a receiver can repair some amount
+/// of corruption without a retransmission.
+pub trait ForwardErrorCorrection: Send {
+    /// Encodes `data`. Block codes pad the data with zeroes to a whole amount of
+    /// blocks, this padding is part of the decoded data.
+    fn encode(&self, data: BitString) -> BitString;
+
+    /// Decodes `data`, repairing what can be repaired. Fails if `data` cannot
+    /// be the output of [`ForwardErrorCorrection::encode`].
+    fn decode(&self, data: BitString) -> anyhow::Result<Decoded>;
+
+    /// The amount of bits [`ForwardErrorCorrection::encode`] produces for
+    /// `data_len` bits of data.
+    fn encoded_len(&self, data_len: usize) -> usize;
+}
@@ -0,0 +1,465 @@
+use anyhow::ensure;
+
+use crate::{bit_string::BitString, data_link_layer::error_detection::pad_to_multiple};
+
+use super::{
+    galois_field::{alpha_pow, div, inv, mul, poly_eval, poly_mul},
+    Decoded, ForwardErrorCorrection,
+};
+
+/// The maximum length of a codeword over GF(2^8).
+pub const MAX_CODEWORD_LEN: usize = 255;
+
+/// A systematic Reed-Solomon (n, k) code over GF(2^8). Every block of `k` data
+/// bytes is followed by `n - k` parity bytes, which can correct up to
+/// (n - k) / 2 byte errors, or up to n - k erasures, per block. Codes with
+/// `n < 255` are shortened codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReedSolomon {
+    n: usize,
+    k: usize,
+    // Generator polynomial, coefficients in ascending order of power
+    generator: Vec<u8>,
+}
+
+/// The outcome of decoding a single block.
+struct BlockDecoded {
+    corrected: usize,
+    failed: bool,
+}
+
+impl ReedSolomon {
+    #[must_use]
+    pub fn new(n: usize, k: usize) -> Self {
+        assert!(
+            n <= MAX_CODEWORD_LEN,
+            "A codeword can be at most {MAX_CODEWORD_LEN} bytes"
+        );
+        assert!(k > 0 && k < n, "Expected 0 < k < n, got n {n} and k {k}");
+
+        // g(x) = (x - a^0)(x - a^1)...(x - a^(n - k - 1))
+        let generator = (0..n - k).fold(vec![1], |generator, power| {
+            poly_mul(&generator, &[alpha_pow(power), 1])
+        });
+
+        Self { n, k, generator }
+    }
+
+    #[must_use]
+    pub const fn n(&self) -> usize {
+        self.n
+    }
+
+    #[must_use]
+    pub const fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The amount of parity bytes per block.
+    #[must_use]
+    pub const fn parity_len(&self) -> usize {
+        self.n - self.k
+    }
+
+    /// Encodes `data`, padding the last block with zero bytes.
+    #[must_use]
+    pub fn encode_bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len().div_ceil(self.k) * self.n);
+
+        for chunk in data.chunks(self.k) {
+            let mut block = chunk.to_vec();
+            block.resize(self.k, 0);
+
+            let parity = self.parity(&block);
+
+            output.append(&mut block);
+            output.extend(parity);
+        }
+
+        output
+    }
+
+    /// Decodes `data`, treating the bytes at the indices in `erasures` as
+    /// erased. Uncorrectable blocks are returned as received.
+    pub fn decode_bytes(&self, data: &[u8], erasures: &[usize]) -> anyhow::Result<Decoded> {
+        ensure!(
+            data.len().is_multiple_of(self.n),
+            "Received {} bytes, which is not a multiple of the block length {}",
+            data.len(),
+            self.n
+        );
+        ensure!(
+            erasures.iter().all(|&idx| idx < data.len()),
+            "Erasures must lie within the received data"
+        );
+
+        let mut decoded = Decoded {
+            data: BitString::with_capacity(data.len() / self.n * self.k * 8),
+            corrected: 0,
+            detected: 0,
+        };
+
+        for (block_idx, block) in data.chunks(self.n).enumerate() {
+            let start = block_idx * self.n;
+            let block_erasures = erasures
+                .iter()
+                .filter(|&&idx| (start..start + self.n).contains(&idx))
+                .map(|idx| idx - start)
+                .collect::<Vec<_>>();
+
+            let mut block = block.to_vec();
+            let outcome = self.decode_block(&mut block, &block_erasures);
+
+            decoded.corrected += outcome.corrected;
+            decoded.detected += usize::from(outcome.failed);
+            decoded.data.append_bits(BitString::from(&block[..self.k]));
+        }
+
+        Ok(decoded)
+    }
+
+    /// Decodes bits as produced by [`ForwardErrorCorrection::encode`], treating
+    /// the bytes at the indices in `erasures` as erased.
+    pub fn decode_with_erasures(
+        &self,
+        data: &BitString,
+        erasures: &[usize],
+    ) -> anyhow::Result<Decoded> {
+        let bytes = data.try_as_vec_exact_u8()?;
+        self.decode_bytes(&bytes, erasures)
+    }
+
+    /// The remainder of `data * x^(n - k)` divided by the generator.
+    fn parity(&self, data: &[u8]) -> Vec<u8> {
+        let parity_len = self.parity_len();
+        // Polynomial long division, highest order coefficient first
+        let mut remainder = vec![0u8; parity_len];
+
+        for &byte in data {
+            let factor = byte ^ remainder[0];
+            remainder.rotate_left(1);
+            remainder[parity_len - 1] = 0;
+
+            if factor != 0 {
+                for (idx, rem) in remainder.iter_mut().enumerate() {
+                    *rem ^= mul(self.generator[parity_len - 1 - idx], factor);
+                }
+            }
+        }
+
+        remainder
+    }
+
+    /// The codeword evaluated at a^0 through a^(n - k - 1).
+    fn syndromes(&self, block: &[u8]) -> Vec<u8> {
+        (0..self.parity_len())
+            .map(|power| {
+                let x = alpha_pow(power);
+                block.iter().fold(0, |acc, &byte| mul(acc, x) ^ byte)
+            })
+            .collect()
+    }
+
+    /// The locator a^(n - 1 - position) of the byte at `position`.
+    const fn locator(&self, position: usize) -> u8 {
+        alpha_pow(self.n - 1 - position)
+    }
+
+    fn decode_block(&self, block: &mut [u8], erasures: &[usize]) -> BlockDecoded {
+        let failed = BlockDecoded {
+            corrected: 0,
+            failed: true,
+        };
+
+        let syndromes = self.syndromes(block);
+        if syndromes.iter().all(|&s| s == 0) {
+            return BlockDecoded {
+                corrected: 0,
+                failed: false,
+            };
+        }
+
+        let parity_len = self.parity_len();
+        if erasures.len() > parity_len {
+            return failed;
+        }
+
+        let Some(locator) = self.errata_locator(&syndromes, erasures) else {
+            return failed;
+        };
+
+        // Chien search
+        let degree = locator.len() - 1;
+        let positions = (0..self.n)
+            .filter(|&position| poly_eval(&locator, inv(self.locator(position))) == 0)
+            .collect::<Vec<_>>();
+        if positions.len() != degree {
+            return failed;
+        }
+
+        // Forney, the error evaluator is S(x) * L(x) mod x^(n - k)
+        let mut evaluator = poly_mul(&syndromes, &locator);
+        evaluator.truncate(parity_len);
+
+        let derivative = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(power, &coef)| if power % 2 == 1 { coef } else { 0 })
+            .collect::<Vec<_>>();
+
+        let received = block.to_vec();
+        let mut corrected = 0;
+        for position in positions {
+            let x = self.locator(position);
+            let x_inv = inv(x);
+
+            let denominator = poly_eval(&derivative, x_inv);
+            if denominator == 0 {
+                block.copy_from_slice(&received);
+                return failed;
+            }
+
+            let magnitude = mul(x, div(poly_eval(&evaluator, x_inv), denominator));
+            if magnitude != 0 {
+                block[position] ^= magnitude;
+                corrected += 1;
+            }
+        }
+
+        if self.syndromes(block).iter().any(|&s| s != 0) {
+            block.copy_from_slice(&received);
+            return failed;
+        }
+
+        BlockDecoded {
+            corrected,
+            failed: false,
+        }
+    }
+
+    /// Finds the errata locator using Berlekamp-Massey, initialized with the
+    /// erasure locator. Returns [`None`] if there are more errors than the code
+    /// can correct.
+    fn errata_locator(&self, syndromes: &[u8], erasures: &[usize]) -> Option<Vec<u8>> {
+        let erasure_count = erasures.len();
+
+        // Product of (1 + X_i x) over all erasures
+        let erasure_locator = erasures.iter().fold(vec![1], |locator, &position| {
+            poly_mul(&locator, &[1, self.locator(position)])
+        });
+
+        let mut locator = erasure_locator.clone();
+        let mut previous = erasure_locator;
+        let mut len = erasure_count;
+
+        for step in erasure_count + 1..=self.parity_len() {
+            let discrepancy = locator
+                .iter()
+                .enumerate()
+                .take(step)
+                .fold(0, |acc, (idx, &coef)| {
+                    acc ^ mul(coef, syndromes[step - 1 - idx])
+                });
+
+            let mut shifted = vec![0];
+            shifted.extend(&previous);
+
+            if discrepancy == 0 {
+                previous = shifted;
+                continue;
+            }
+
+            let mut next = locator.clone();
+            next.resize(next.len().max(shifted.len()), 0);
+            for (idx, &coef) in shifted.iter().enumerate() {
+                next[idx] ^= mul(coef, discrepancy);
+            }
+
+            if 2 * len < step + erasure_count {
+                previous = locator.iter().map(|&coef| div(coef, discrepancy)).collect();
+                len = step + erasure_count - len;
+            } else {
+                previous = shifted;
+            }
+            locator = next;
+        }
+
+        while locator.len() > 1 && locator.last() == Some(&0) {
+            locator.pop();
+        }
+
+        let errors = (locator.len() - 1).checked_sub(erasure_count)?;
+        (2 * errors + erasure_count <= self.parity_len()).then_some(locator)
+    }
+}
+
+impl ForwardErrorCorrection for ReedSolomon {
+    fn encode(&self, data: BitString) -> BitString {
+        let bytes = pad_to_multiple(&data, 8).as_vec_exact_u8();
+        BitString::from(self.encode_bytes(&bytes))
+    }
+
+    fn decode(&self, data: BitString) -> anyhow::Result<Decoded> {
+        self.decode_with_erasures(&data, &[])
+    }
+
+    fn encoded_len(&self, data_len: usize) -> usize {
+        data_len.div_ceil(8).div_ceil(self.k) * self.n * 8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString, corruption_type::Corruption,
+        data_link_layer::fec::ForwardErrorCorrection, rand::XorShift,
+    };
+
+    use super::ReedSolomon;
+
+    const DATA: &[u8] = b"Hello world! This is a reed solomon test.";
+
+    #[test]
+    fn systematic_encoding() {
+        let code = ReedSolomon::new(255, 223);
+        let encoded = code.encode_bytes(DATA);
+
+        assert_eq!(encoded.len(), 255);
+        assert_eq!(&encoded[..DATA.len()], DATA);
+        assert!(code.syndromes(&encoded).iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn known_parity() {
+        // A (7, 3) code with generator (x - 1)(x - a)(x - a^2)(x - a^3)
+        let code = ReedSolomon::new(7, 3);
+
+        let encoded = code.encode_bytes(&[1, 2, 3]);
+
+        assert!(code.syndromes(&encoded).iter().all(|&s| s == 0));
+        assert_eq!(code.generator.len(), 5);
+        assert_eq!(code.generator[4], 1);
+    }
+
+    #[test]
+    fn corrects_byte_errors() {
+        let code = ReedSolomon::new(255, 223);
+        let mut rand = XorShift::new(1337);
+
+        for _ in 0..20 {
+            let mut encoded = code.encode_bytes(DATA);
+
+            for _ in 0..16 {
+                let idx = (rand.next_int() % 255) as usize;
+                encoded[idx] ^= (rand.next_int() % 255 + 1) as u8;
+            }
+
+            let decoded = code.decode_bytes(&encoded, &[]).expect("Valid length");
+
+            assert_eq!(decoded.detected, 0);
+            assert!(decoded.corrected <= 16);
+            assert_eq!(
+                decoded.data.copy_len(0, DATA.len() * 8),
+                BitString::from(DATA)
+            );
+        }
+    }
+
+    #[test]
+    fn detects_too_many_errors() {
+        let code = ReedSolomon::new(20, 16);
+        let mut encoded = code.encode_bytes(&DATA[..16]);
+
+        encoded[0] ^= 0x01;
+        encoded[5] ^= 0x20;
+        encoded[9] ^= 0xFF;
+
+        let decoded = code.decode_bytes(&encoded, &[]).expect("Valid length");
+
+        assert_eq!(decoded.detected, 1);
+        assert_eq!(decoded.corrected, 0);
+    }
+
+    #[test]
+    fn corrects_erasures() {
+        let code = ReedSolomon::new(40, 32);
+        let mut encoded = code.encode_bytes(&DATA[..32]);
+
+        let erasures = [0, 3, 7, 12, 20, 31, 35, 39];
+        for &idx in &erasures {
+            encoded[idx] = 0;
+        }
+
+        // Without knowing where, 8 errors are too many for 8 parity bytes
+        let decoded = code.decode_bytes(&encoded, &[]).expect("Valid length");
+        assert_eq!(decoded.detected, 1);
+
+        let decoded = code
+            .decode_bytes(&encoded, &erasures)
+            .expect("Valid length");
+        assert_eq!(decoded.detected, 0);
+        assert_eq!(decoded.data, BitString::from(&DATA[..32]));
+    }
+
+    #[test]
+    fn corrects_errors_and_erasures() {
+        let code = ReedSolomon::new(40, 32);
+        let mut encoded = code.encode_bytes(&DATA[..32]);
+
+        // 4 erasures and 2 errors use exactly the 8 parity bytes
+        let erasures = [1, 2, 30, 33];
+        for &idx in &erasures {
+            encoded[idx] ^= 0x55;
+        }
+        encoded[10] ^= 0x01;
+        encoded[38] ^= 0x80;
+
+        let decoded = code
+            .decode_bytes(&encoded, &erasures)
+            .expect("Valid length");
+        assert_eq!(decoded.detected, 0);
+        assert_eq!(decoded.corrected, 6);
+        assert_eq!(decoded.data, BitString::from(&DATA[..32]));
+    }
+
+    #[test]
+    fn corrects_burst_flip() {
+        // A burst of at most 16 bits touches at most 3 bytes
+        let code = ReedSolomon::new(24, 16);
+        let data = BitString::from(DATA);
+        let mut corruption = Corruption::BurstFlip(XorShift::new(42));
+
+        for _ in 0..100 {
+            let encoded = code.encode(data.clone());
+            let corrupted = corruption.corrupt_borrow(encoded);
+
+            let decoded = code.decode(corrupted).expect("Valid length");
+
+            assert_eq!(decoded.detected, 0);
+            assert!(decoded.corrected > 0);
+            assert_eq!(decoded.data.copy_len(0, data.len()), data);
+        }
+    }
+
+    #[test]
+    fn encoded_len() {
+        let code = ReedSolomon::new(24, 16);
+
+        assert_eq!(code.encoded_len(1), 24 * 8);
+        assert_eq!(code.encoded_len(16 * 8), 24 * 8);
+        assert_eq!(code.encoded_len(16 * 8 + 1), 48 * 8);
+        assert_eq!(
+            code.encode(BitString::from(DATA)).len(),
+            code.encoded_len(DATA.len() * 8)
+        );
+    }
+
+    #[test]
+    fn invalid_length() {
+        let code = ReedSolomon::new(24, 16);
+
+        assert!(code.decode(BitString::from([0u8; 23])).is_err());
+        assert!(code.decode_bytes(&[0; 24], &[24]).is_err());
+    }
+}
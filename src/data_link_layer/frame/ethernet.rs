@@ -0,0 +1,531 @@
+use anyhow::ensure;
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::crc::{self, crc_32},
+    mac_address::MacAddress,
+};
+
+use super::{ipv4::Ipv4Packet, ipv6::Ipv6Packet, vlan::VlanTag, Carries, Encapsulate, Frame};
+
+// EtherTypes
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+pub const ETHERTYPE_IPV6: u16 = 0x86DD;
+
+const PREAMBLE_BYTE: u8 = 0b0101_0101u8;
+const PREAMBLE_LEN: usize = 7;
+const START_FRAME_DELIMITER: u8 = 0b1101_0101u8;
+
+const HEADER_LEN: usize = 14;
+const FCS_LEN: usize = 4;
+pub const MIN_PAYLOAD_LEN: usize = 46;
+pub const MAX_PAYLOAD_LEN: usize = 1500;
+pub const MAX_VLAN_TAGS: usize = 2;
+const VLAN_TAG_LEN: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct EthernetFrameBuilder {
+    destination: Option<MacAddress>,
+    source: Option<MacAddress>,
+    ether_type: Option<u16>,
+    vlan_tags: Vec<VlanTag>,
+}
+
+impl EthernetFrameBuilder {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            destination: None,
+            source: None,
+            ether_type: None,
+            vlan_tags: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn set_destination(self, destination: MacAddress) -> Self {
+        Self {
+            destination: Some(destination),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_source(self, source: MacAddress) -> Self {
+        Self {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_ether_type(self, ether_type: u16) -> Self {
+        Self {
+            ether_type: Some(ether_type),
+            ..self
+        }
+    }
+
+    /// Sets the 802.1Q tags, outermost first. Two tags make a QinQ frame.
+    #[must_use]
+    pub fn set_vlan_tags(self, vlan_tags: Vec<VlanTag>) -> Self {
+        assert!(
+            vlan_tags.len() <= MAX_VLAN_TAGS,
+            "Cannot tag a frame more than {MAX_VLAN_TAGS} times"
+        );
+        Self { vlan_tags, ..self }
+    }
+
+    /// Adds an 802.1Q tag inside the tags already set.
+    #[must_use]
+    pub fn add_vlan_tag(mut self, vlan_tag: VlanTag) -> Self {
+        assert!(
+            self.vlan_tags.len() < MAX_VLAN_TAGS,
+            "Cannot tag a frame more than {MAX_VLAN_TAGS} times"
+        );
+        self.vlan_tags.push(vlan_tag);
+        self
+    }
+
+    pub fn build_all(&self, data_points: &[BitString]) -> Vec<EthernetFrame> {
+        data_points
+            .iter()
+            .map(|data| self.build(data.clone()))
+            .collect()
+    }
+
+    /// Builds a single frame. The payload is padded with zeroes to whole bytes
+    /// and to the minimal payload length of 46 bytes, minus 4 bytes for every
+    /// VLAN tag.
+    pub fn build(&self, mut payload: BitString) -> EthernetFrame {
+        let destination = self
+            .destination
+            .expect("Cannot construct an EthernetFrame without destination");
+        let source = self
+            .source
+            .expect("Cannot construct an EthernetFrame without source");
+        let ether_type = self
+            .ether_type
+            .expect("Cannot construct an EthernetFrame without EtherType");
+
+        assert!(
+            payload.len() <= MAX_PAYLOAD_LEN * 8,
+            "Payload cannot be larger than {MAX_PAYLOAD_LEN} bytes"
+        );
+
+        payload.append_zeroes((8 - payload.len() % 8) % 8);
+        let min_payload_len = MIN_PAYLOAD_LEN - self.vlan_tags.len() * VLAN_TAG_LEN;
+        payload.append_zeroes((min_payload_len * 8).saturating_sub(payload.len()));
+
+        let mut checked = BitString::with_capacity(HEADER_LEN * 8 + payload.len());
+        append_mac(&mut checked, destination);
+        append_mac(&mut checked, source);
+        for vlan_tag in &self.vlan_tags {
+            vlan_tag.encode(&mut checked);
+        }
+        checked.append_u16(ether_type);
+        checked.append_bits(payload.clone());
+
+        // The frame check sequence covers everything but the preamble
+        let checked = crc::add(&crc_32(), checked);
+        let fcs = checked.get_u32(checked.len() - FCS_LEN * 8);
+
+        let mut output_bitstring = preamble();
+        output_bitstring.append_bits(checked);
+
+        EthernetFrame {
+            destination,
+            source,
+            vlan_tags: self.vlan_tags.clone(),
+            ether_type,
+            payload,
+            fcs,
+            output_bitstring,
+        }
+    }
+}
+
+impl Default for EthernetFrameBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An Ethernet II frame, including preamble and start frame delimiter. The
+/// frame check sequence is a plain CRC-32, without the bit reflection and
+/// inversion real hardware applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthernetFrame {
+    // Header
+    destination: MacAddress,
+    source: MacAddress,
+    vlan_tags: Vec<VlanTag>,
+    ether_type: u16,
+
+    // Data
+    payload: BitString,
+
+    // Trailer
+    fcs: u32,
+
+    // Full bit_string, since it already had to be calculated for the fcs
+    output_bitstring: BitString,
+}
+
+impl EthernetFrame {
+    /// Parses a frame as serialized by [`EthernetFrameBuilder`]. Padding of
+    /// the payload cannot be told apart from data, so it is kept.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        let preamble_len = (PREAMBLE_LEN + 1) * 8;
+
+        ensure!(
+            data.len() >= preamble_len + (HEADER_LEN + MIN_PAYLOAD_LEN + FCS_LEN) * 8,
+            "Frame of {} bits is too short to be an ethernet frame",
+            data.len()
+        );
+        ensure!(
+            data.len().is_multiple_of(8),
+            "Frame of {} bits is not a whole amount of bytes",
+            data.len()
+        );
+        ensure!(
+            data.copy_len(0, preamble_len) == preamble(),
+            "Frame does not start with a preamble"
+        );
+
+        let checked = data.copy_len(preamble_len, data.len() - preamble_len);
+        let fcs = checked.get_u32(checked.len() - FCS_LEN * 8);
+        let checked = crc::check_and_remove(&crc_32(), checked)?;
+
+        // Tags sit where the EtherType would otherwise be
+        let mut index = 96;
+        let mut vlan_tags = Vec::new();
+        while vlan_tags.len() < MAX_VLAN_TAGS && VlanTag::is_tpid(checked.get_u16(index)) {
+            vlan_tags.push(VlanTag::decode(&checked, index));
+            index += VLAN_TAG_LEN * 8;
+        }
+
+        let payload_index = index + 16;
+
+        Ok(Self {
+            destination: get_mac(&checked, 0),
+            source: get_mac(&checked, 48),
+            vlan_tags,
+            ether_type: checked.get_u16(index),
+            payload: checked.copy_len(payload_index, checked.len() - payload_index),
+            fcs,
+            output_bitstring: data.clone(),
+        })
+    }
+
+    /// A builder with the header of this frame, for resending the payload with
+    /// a changed header.
+    #[must_use]
+    pub fn builder(&self) -> EthernetFrameBuilder {
+        EthernetFrameBuilder::new()
+            .set_destination(self.destination)
+            .set_source(self.source)
+            .set_ether_type(self.ether_type)
+            .set_vlan_tags(self.vlan_tags.clone())
+    }
+
+    #[must_use]
+    pub const fn destination(&self) -> MacAddress {
+        self.destination
+    }
+
+    #[must_use]
+    pub const fn source(&self) -> MacAddress {
+        self.source
+    }
+
+    /// The 802.1Q tags, outermost first.
+    #[must_use]
+    pub fn vlan_tags(&self) -> &[VlanTag] {
+        &self.vlan_tags
+    }
+
+    #[must_use]
+    pub const fn ether_type(&self) -> u16 {
+        self.ether_type
+    }
+
+    #[must_use]
+    pub const fn payload(&self) -> &BitString {
+        &self.payload
+    }
+
+    #[must_use]
+    pub const fn fcs(&self) -> u32 {
+        self.fcs
+    }
+}
+
+impl Frame for EthernetFrame {
+    type Builder = EthernetFrameBuilder;
+
+    fn setup_frames(data: BitString, builder: EthernetFrameBuilder) -> Vec<Self> {
+        let chunks = data.as_bit_slice().chunks(MAX_PAYLOAD_LEN * 8);
+
+        let bundled_data = chunks.map(BitString::from).collect::<Vec<_>>();
+
+        builder.build_all(&bundled_data)
+    }
+
+    fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode(data)
+    }
+
+    fn as_bit_string(&self) -> &BitString {
+        &self.output_bitstring
+    }
+
+    /// The preamble and start frame delimiter count towards the header.
+    fn header_len(&self) -> usize {
+        PREAMBLE_LEN + 1 + HEADER_LEN + self.vlan_tags.len() * VLAN_TAG_LEN
+    }
+
+    fn trailer_len(&self) -> usize {
+        FCS_LEN
+    }
+
+    fn payload(&self) -> &BitString {
+        &self.payload
+    }
+}
+
+impl Carries<Ipv4Packet> for EthernetFrame {
+    fn carries(&self) -> bool {
+        self.ether_type == ETHERTYPE_IPV4
+    }
+
+    fn decode_payload(&self) -> anyhow::Result<Ipv4Packet> {
+        ensure!(
+            Carries::<Ipv4Packet>::carries(self),
+            "Frame does not carry an IPv4 packet"
+        );
+
+        Ipv4Packet::decode(&self.payload)
+    }
+}
+
+impl Carries<Ipv6Packet> for EthernetFrame {
+    fn carries(&self) -> bool {
+        self.ether_type == ETHERTYPE_IPV6
+    }
+
+    fn decode_payload(&self) -> anyhow::Result<Ipv6Packet> {
+        ensure!(
+            Carries::<Ipv6Packet>::carries(self),
+            "Frame does not carry an IPv6 packet"
+        );
+
+        Ipv6Packet::decode(&self.payload)
+    }
+}
+
+impl Encapsulate<Ipv4Packet> for EthernetFrameBuilder {
+    type Frame = EthernetFrame;
+
+    fn encapsulate(&self, payload: &Ipv4Packet) -> EthernetFrame {
+        self.clone()
+            .set_ether_type(ETHERTYPE_IPV4)
+            .build(payload.as_bit_string().clone())
+    }
+}
+
+impl Encapsulate<Ipv6Packet> for EthernetFrameBuilder {
+    type Frame = EthernetFrame;
+
+    fn encapsulate(&self, payload: &Ipv6Packet) -> EthernetFrame {
+        self.clone()
+            .set_ether_type(ETHERTYPE_IPV6)
+            .build(payload.as_bit_string().clone())
+    }
+}
+
+fn preamble() -> BitString {
+    let mut preamble = BitString::from([PREAMBLE_BYTE; PREAMBLE_LEN]);
+    preamble.append_u8(START_FRAME_DELIMITER);
+    preamble
+}
+
+pub(crate) fn append_mac(data: &mut BitString, mac: MacAddress) {
+    for byte in mac.octets() {
+        data.append_u8(byte);
+    }
+}
+
+pub(crate) fn get_mac(data: &BitString, index: usize) -> MacAddress {
+    let mut addr = [0; 6];
+    for (idx, byte) in addr.iter_mut().enumerate() {
+        *byte = data.get_u8(index + idx * 8);
+    }
+    MacAddress::new(addr)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{bit_string::BitString, bitstring, mac_address::MacAddress};
+
+    use crate::data_link_layer::frame::vlan::VlanTag;
+
+    use super::{
+        EthernetFrame, EthernetFrameBuilder, Frame, ETHERTYPE_IPV4, MAX_PAYLOAD_LEN,
+        MIN_PAYLOAD_LEN,
+    };
+
+    const DESTINATION: MacAddress = MacAddress::new([0x02, 0x00, 0x5E, 0x10, 0x00, 0x01]);
+    const SOURCE: MacAddress = MacAddress::new([0x02, 0x00, 0x5E, 0x10, 0x00, 0x02]);
+    const PAYLOAD: &[u8] = b"Hello world! This payload is long enough to not be padded";
+
+    fn builder() -> EthernetFrameBuilder {
+        EthernetFrameBuilder::new()
+            .set_destination(DESTINATION)
+            .set_source(SOURCE)
+            .set_ether_type(ETHERTYPE_IPV4)
+    }
+
+    #[test]
+    fn basic_header() {
+        let frame = builder().build(BitString::from(PAYLOAD));
+        let frame_bs = frame.as_bit_string();
+
+        assert_eq!(frame_bs.get_u64(0), 0x5555_5555_5555_55D5);
+        assert_eq!(frame_bs.get_u32(64), 0x0200_5E10, "Failed at destination");
+        assert_eq!(frame_bs.get_u16(96), 0x0001, "Failed at destination");
+        assert_eq!(frame_bs.get_u32(112), 0x0200_5E10, "Failed at source");
+        assert_eq!(frame_bs.get_u16(144), 0x0002, "Failed at source");
+        assert_eq!(
+            frame_bs.get_u16(160),
+            ETHERTYPE_IPV4,
+            "Failed at ether_type"
+        );
+        assert_eq!(
+            frame_bs.copy_len(176, PAYLOAD.len() * 8),
+            BitString::from(PAYLOAD)
+        );
+        assert_eq!(frame_bs.get_u32(frame_bs.len() - 32), frame.fcs());
+        assert_eq!(frame_bs.len(), (8 + 14 + PAYLOAD.len() + 4) * 8);
+    }
+
+    #[test]
+    fn pads_short_payload() {
+        let frame = builder().build(bitstring!(1, 1, 1));
+
+        let mut expected = bitstring!(1, 1, 1);
+        expected.append_zeroes(MIN_PAYLOAD_LEN * 8 - 3);
+
+        assert_eq!(frame.payload(), &expected);
+        assert_eq!(frame.as_bit_string().len(), (8 + 14 + 46 + 4) * 8);
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        for payload in [
+            BitString::from(PAYLOAD),
+            BitString::from(b"short".as_slice()),
+        ] {
+            let frame = builder().build(payload);
+
+            let decoded = EthernetFrame::decode(frame.as_bit_string()).expect("Frame is valid");
+
+            assert_eq!(decoded, frame);
+            assert_eq!(decoded.destination(), DESTINATION);
+            assert_eq!(decoded.source(), SOURCE);
+            assert_eq!(decoded.ether_type(), ETHERTYPE_IPV4);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_corruption() {
+        let frame = builder().build(BitString::from(PAYLOAD));
+        let frame_bs = frame.as_bit_string();
+
+        for idx in 0..frame_bs.len() {
+            let mut corrupted = frame_bs.clone();
+            corrupted.flip_bit(idx);
+
+            assert!(
+                EthernetFrame::decode(&corrupted).is_err(),
+                "Failed at {idx}"
+            );
+        }
+    }
+
+    #[test]
+    fn decode_rejects_short() {
+        let frame = builder().build(BitString::new());
+        let frame_bs = frame.as_bit_string();
+
+        let truncated = frame_bs.copy_len(0, frame_bs.len() - 8);
+        assert!(EthernetFrame::decode(&truncated).is_err());
+    }
+
+    #[test]
+    fn splits_into_frames() {
+        let data = BitString::with_ones(MAX_PAYLOAD_LEN * 8 + 8);
+
+        let frames = EthernetFrame::setup_frames(data, builder());
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].payload().len(), MAX_PAYLOAD_LEN * 8);
+        assert_eq!(frames[1].payload().len(), MIN_PAYLOAD_LEN * 8);
+    }
+
+    #[test]
+    #[allow(clippy::should_panic_without_expect)]
+    #[should_panic]
+    fn too_large_payload() {
+        builder().build(BitString::with_zeroes(MAX_PAYLOAD_LEN * 8 + 1));
+    }
+
+    #[test]
+    fn vlan_tagged() {
+        let tag = VlanTag::new(10).set_pcp(3);
+        let frame = builder().add_vlan_tag(tag).build(BitString::new());
+        let frame_bs = frame.as_bit_string();
+
+        assert_eq!(frame_bs.get_u32(160), 0x8100_600A, "Failed at vlan tag");
+        assert_eq!(
+            frame_bs.get_u16(192),
+            ETHERTYPE_IPV4,
+            "Failed at ether_type"
+        );
+        // Tagging doesn't change the minimal frame size
+        assert_eq!(frame_bs.len(), (8 + 64) * 8);
+
+        let decoded = EthernetFrame::decode(frame_bs).expect("Frame is valid");
+        assert_eq!(decoded.vlan_tags(), [tag]);
+        assert_eq!(decoded, frame);
+    }
+
+    #[test]
+    fn qinq_tagged() {
+        let tags = vec![VlanTag::service(100), VlanTag::new(10)];
+        let frame = builder()
+            .set_vlan_tags(tags.clone())
+            .build(BitString::from(PAYLOAD));
+
+        let decoded = EthernetFrame::decode(frame.as_bit_string()).expect("Frame is valid");
+
+        assert_eq!(decoded.vlan_tags(), tags);
+        assert_eq!(decoded.ether_type(), ETHERTYPE_IPV4);
+        assert_eq!(decoded.payload(), &BitString::from(PAYLOAD));
+    }
+
+    #[test]
+    fn rebuild_untagged() {
+        let frame = builder()
+            .add_vlan_tag(VlanTag::new(10))
+            .build(BitString::from(PAYLOAD));
+
+        let untagged = frame
+            .builder()
+            .set_vlan_tags(Vec::new())
+            .build(frame.payload().clone());
+
+        assert_eq!(untagged, builder().build(BitString::from(PAYLOAD)));
+    }
+}
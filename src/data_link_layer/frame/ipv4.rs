@@ -0,0 +1,770 @@
+use anyhow::ensure;
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::error_detection::checksum::internet_checksum,
+    ip_address::{Ipv4Address, Ipv6Address},
+};
+
+use super::{tcp::TCPFrame, udp::UDPFrame, Carries, Encapsulate, Frame};
+
+// Protocols
+pub const PROTOCOL_ICMP: u8 = 1;
+pub const PROTOCOL_TCP: u8 = 6;
+pub const PROTOCOL_UDP: u8 = 17;
+
+pub const DEFAULT_TTL: u8 = 64;
+pub const MIN_IPV4_HEADER_LEN: usize = 20;
+pub const MAX_IPV4_OPTIONS_LEN: usize = 40;
+/// The smallest MTU every IPv4 link has to support.
+pub const MIN_IPV4_MTU: u16 = 68;
+const MAX_FRAGMENT_OFFSET: u16 = 0x1FFF;
+
+#[derive(Debug, Clone)]
+pub struct Ipv4PacketBuilder {
+    source: Option<Ipv4Address>,
+    destination: Option<Ipv4Address>,
+    protocol: Option<u8>,
+    dscp: u8,
+    ecn: u8,
+    identification: u16,
+    dont_fragment: bool,
+    more_fragments: bool,
+    fragment_offset: u16,
+    ttl: u8,
+    options: Vec<u8>,
+}
+
+impl Ipv4PacketBuilder {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            source: None,
+            destination: None,
+            protocol: None,
+            dscp: 0,
+            ecn: 0,
+            identification: 0,
+            dont_fragment: false,
+            more_fragments: false,
+            fragment_offset: 0,
+            ttl: DEFAULT_TTL,
+            options: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn set_source(self, source: Ipv4Address) -> Self {
+        Self {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_destination(self, destination: Ipv4Address) -> Self {
+        Self {
+            destination: Some(destination),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_protocol(self, protocol: u8) -> Self {
+        Self {
+            protocol: Some(protocol),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_dscp(self, dscp: u8) -> Self {
+        assert!(dscp <= 0b0011_1111u8);
+        Self { dscp, ..self }
+    }
+
+    #[must_use]
+    pub fn set_ecn(self, ecn: u8) -> Self {
+        assert!(ecn <= 0b0000_0011u8);
+        Self { ecn, ..self }
+    }
+
+    #[must_use]
+    pub fn set_identification(self, identification: u16) -> Self {
+        Self {
+            identification,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_dont_fragment(self, dont_fragment: bool) -> Self {
+        Self {
+            dont_fragment,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_more_fragments(self, more_fragments: bool) -> Self {
+        Self {
+            more_fragments,
+            ..self
+        }
+    }
+
+    /// Sets the offset of the payload in the original packet, in units of 8
+    /// bytes.
+    #[must_use]
+    pub fn set_fragment_offset(self, fragment_offset: u16) -> Self {
+        assert!(fragment_offset <= MAX_FRAGMENT_OFFSET);
+        Self {
+            fragment_offset,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_ttl(self, ttl: u8) -> Self {
+        Self { ttl, ..self }
+    }
+
+    /// Sets the raw option bytes, which are padded with zeroes to a multiple
+    /// of 4 bytes.
+    #[must_use]
+    pub fn set_options(self, options: Vec<u8>) -> Self {
+        assert!(
+            options.len() <= MAX_IPV4_OPTIONS_LEN,
+            "Options cannot take up more than {MAX_IPV4_OPTIONS_LEN} bytes"
+        );
+        Self { options, ..self }
+    }
+
+    /// The addresses upper layer checksums of the payload are computed over.
+    fn pseudo_header(&self) -> PseudoHeader {
+        PseudoHeader::Ipv4 {
+            source: self
+                .source
+                .expect("Cannot construct an Ipv4Packet without source"),
+            destination: self
+                .destination
+                .expect("Cannot construct an Ipv4Packet without destination"),
+        }
+    }
+
+    /// Builds a single packet. The payload is padded with zeroes to whole
+    /// bytes, as the total length counts bytes.
+    pub fn build(&self, mut payload: BitString) -> Ipv4Packet {
+        let source = self
+            .source
+            .expect("Cannot construct an Ipv4Packet without source");
+        let destination = self
+            .destination
+            .expect("Cannot construct an Ipv4Packet without destination");
+        let protocol = self
+            .protocol
+            .expect("Cannot construct an Ipv4Packet without protocol");
+
+        let mut options = self.options.clone();
+        options.resize(options.len().next_multiple_of(4), 0);
+
+        payload.append_zeroes((8 - payload.len() % 8) % 8);
+
+        let header_len = MIN_IPV4_HEADER_LEN + options.len();
+        let ihl = u8::try_from(header_len / 4).expect("Options are limited in size");
+        let total_length = u16::try_from(header_len + payload.len() / 8)
+            .expect("Packet is larger than the total length allows");
+
+        let mut packet = Ipv4Packet {
+            ihl,
+            dscp: self.dscp,
+            ecn: self.ecn,
+            total_length,
+            identification: self.identification,
+            dont_fragment: self.dont_fragment,
+            more_fragments: self.more_fragments,
+            fragment_offset: self.fragment_offset,
+            ttl: self.ttl,
+            protocol,
+            header_checksum: 0,
+            source,
+            destination,
+            options,
+            payload,
+            output_bitstring: BitString::new(),
+        };
+        packet.serialize();
+
+        packet
+    }
+}
+
+impl Default for Ipv4PacketBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4Packet {
+    // Header
+    ihl: u8,
+    dscp: u8,
+    ecn: u8,
+    total_length: u16,
+    identification: u16,
+    dont_fragment: bool,
+    more_fragments: bool,
+    fragment_offset: u16,
+    ttl: u8,
+    protocol: u8,
+    header_checksum: u16,
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    options: Vec<u8>,
+
+    // Data
+    payload: BitString,
+
+    // Full bit_string, since it already had to be calculated for the checksum
+    output_bitstring: BitString,
+}
+
+impl Ipv4Packet {
+    /// Serializes the packet, filling in the header checksum.
+    fn serialize(&mut self) {
+        let mut output = BitString::with_capacity(usize::from(self.total_length) * 8);
+
+        output.append_u8(4 << 4 | self.ihl);
+        output.append_u8(self.dscp << 2 | self.ecn);
+        output.append_u16(self.total_length);
+        output.append_u16(self.identification);
+        output.append_u16(
+            u16::from(self.dont_fragment) << 14
+                | u16::from(self.more_fragments) << 13
+                | self.fragment_offset,
+        );
+        output.append_u8(self.ttl);
+        output.append_u8(self.protocol);
+        // Checksum defaults to zero
+        output.append_u16(0);
+        output.append_u32(self.source.to_u32());
+        output.append_u32(self.destination.to_u32());
+        for byte in &self.options {
+            output.append_u8(*byte);
+        }
+
+        // -- Find checksum --
+        self.header_checksum = internet_checksum(&output.as_vec_exact_u16());
+        output.set_u16(80, self.header_checksum);
+
+        output.append_bits(self.payload.clone());
+
+        self.output_bitstring = output;
+    }
+
+    /// Parses a packet, verifying the header checksum. Anything after the
+    /// total length, such as ethernet padding, is ignored.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= MIN_IPV4_HEADER_LEN * 8,
+            "Packet of {} bits is too short to hold an IPv4 header",
+            data.len()
+        );
+
+        let version = data.get_u8(0) >> 4;
+        let ihl = data.get_u8(0) & 0b1111;
+        let header_len = usize::from(ihl) * 32;
+        let total_length = data.get_u16(16);
+
+        ensure!(version == 4, "Packet has version {version} instead of 4");
+        ensure!(
+            ihl >= 5,
+            "Header length of {ihl} words is too small to hold an IPv4 header"
+        );
+        ensure!(
+            header_len <= usize::from(total_length) * 8
+                && usize::from(total_length) * 8 <= data.len(),
+            "Total length of {total_length} bytes does not match the packet"
+        );
+
+        let header = data.copy_len(0, header_len);
+        ensure!(
+            internet_checksum(&header.as_vec_exact_u16()) == 0,
+            "Packet has an invalid header checksum"
+        );
+
+        let flags = data.get_u16(48);
+
+        Ok(Self {
+            ihl,
+            dscp: data.get_u8(8) >> 2,
+            ecn: data.get_u8(8) & 0b11,
+            total_length,
+            identification: data.get_u16(32),
+            dont_fragment: flags & (0b1 << 14) != 0,
+            more_fragments: flags & (0b1 << 13) != 0,
+            fragment_offset: flags & MAX_FRAGMENT_OFFSET,
+            ttl: data.get_u8(64),
+            protocol: data.get_u8(72),
+            header_checksum: data.get_u16(80),
+            source: Ipv4Address::from_u32(data.get_u32(96)),
+            destination: Ipv4Address::from_u32(data.get_u32(128)),
+            options: header
+                .copy_len(
+                    MIN_IPV4_HEADER_LEN * 8,
+                    header_len - MIN_IPV4_HEADER_LEN * 8,
+                )
+                .as_vec_exact_u8(),
+            payload: data.copy_len(header_len, usize::from(total_length) * 8 - header_len),
+            output_bitstring: data.copy_len(0, usize::from(total_length) * 8),
+        })
+    }
+
+    /// A builder with the header fields of this packet, apart from the length
+    /// and checksum.
+    #[must_use]
+    pub fn builder(&self) -> Ipv4PacketBuilder {
+        Ipv4PacketBuilder::new()
+            .set_source(self.source)
+            .set_destination(self.destination)
+            .set_protocol(self.protocol)
+            .set_dscp(self.dscp)
+            .set_ecn(self.ecn)
+            .set_identification(self.identification)
+            .set_dont_fragment(self.dont_fragment)
+            .set_more_fragments(self.more_fragments)
+            .set_fragment_offset(self.fragment_offset)
+            .set_ttl(self.ttl)
+            .set_options(self.options.clone())
+    }
+
+    /// Splits the packet into fragments of at most `mtu` bytes. Every fragment
+    /// carries the options of the original packet. Returns [`None`] if the
+    /// packet is too large and may not be fragmented.
+    #[must_use]
+    pub fn fragment(&self, mtu: u16) -> Option<Vec<Self>> {
+        assert!(mtu >= MIN_IPV4_MTU, "MTU is below the IPv4 minimum");
+
+        if self.total_length <= mtu {
+            return Some(vec![self.clone()]);
+        }
+        if self.dont_fragment {
+            return None;
+        }
+
+        let header_len = usize::from(self.ihl) * 4;
+        // Fragment offsets count 8 byte blocks
+        let max_data_len = (usize::from(mtu) - header_len) / 8 * 8;
+        let payload_len = self.payload.len() / 8;
+
+        let builder = self.builder();
+        let fragments = (0..payload_len)
+            .step_by(max_data_len)
+            .map(|start| {
+                let len = max_data_len.min(payload_len - start);
+                let is_last = start + len == payload_len;
+                let offset = u16::try_from(start / 8).expect("Offset fits in the header");
+
+                builder
+                    .clone()
+                    .set_fragment_offset(self.fragment_offset + offset)
+                    .set_more_fragments(self.more_fragments || !is_last)
+                    .build(self.payload.copy_len(start * 8, len * 8))
+            })
+            .collect();
+
+        Some(fragments)
+    }
+
+    /// The addresses upper layer checksums are computed over.
+    #[must_use]
+    pub const fn pseudo_header(&self) -> PseudoHeader {
+        PseudoHeader::Ipv4 {
+            source: self.source,
+            destination: self.destination,
+        }
+    }
+
+    /// The packet as forwarded by a router, with the TTL decremented. Returns
+    /// [`None`] if the TTL runs out, in which case the packet is dropped.
+    #[must_use]
+    pub fn forwarded(&self) -> Option<Self> {
+        let ttl = self.ttl.checked_sub(1).filter(|ttl| *ttl > 0)?;
+
+        let mut packet = Self {
+            ttl,
+            ..self.clone()
+        };
+        packet.serialize();
+
+        Some(packet)
+    }
+
+    #[must_use]
+    pub const fn as_bit_string(&self) -> &BitString {
+        &self.output_bitstring
+    }
+
+    /// The length of the header in 32 bit words.
+    #[must_use]
+    pub const fn ihl(&self) -> u8 {
+        self.ihl
+    }
+
+    #[must_use]
+    pub const fn dscp(&self) -> u8 {
+        self.dscp
+    }
+
+    #[must_use]
+    pub const fn ecn(&self) -> u8 {
+        self.ecn
+    }
+
+    /// The length of the packet in bytes, header included.
+    #[must_use]
+    pub const fn total_length(&self) -> u16 {
+        self.total_length
+    }
+
+    #[must_use]
+    pub const fn identification(&self) -> u16 {
+        self.identification
+    }
+
+    #[must_use]
+    pub const fn dont_fragment(&self) -> bool {
+        self.dont_fragment
+    }
+
+    #[must_use]
+    pub const fn more_fragments(&self) -> bool {
+        self.more_fragments
+    }
+
+    /// The offset of the payload in the original packet, in units of 8 bytes.
+    #[must_use]
+    pub const fn fragment_offset(&self) -> u16 {
+        self.fragment_offset
+    }
+
+    #[must_use]
+    pub const fn ttl(&self) -> u8 {
+        self.ttl
+    }
+
+    #[must_use]
+    pub const fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    #[must_use]
+    pub const fn header_checksum(&self) -> u16 {
+        self.header_checksum
+    }
+
+    #[must_use]
+    pub const fn source(&self) -> Ipv4Address {
+        self.source
+    }
+
+    #[must_use]
+    pub const fn destination(&self) -> Ipv4Address {
+        self.destination
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &[u8] {
+        &self.options
+    }
+
+    #[must_use]
+    pub const fn payload(&self) -> &BitString {
+        &self.payload
+    }
+}
+
+/// The source and destination address of the IP packet carrying a segment,
+/// which TCP, UDP and ICMPv6 include in their checksum.
+impl Frame for Ipv4Packet {
+    type Builder = Ipv4PacketBuilder;
+
+    fn setup_frames(data: BitString, builder: Ipv4PacketBuilder) -> Vec<Self> {
+        let header_len = builder.build(BitString::new()).header_len();
+        let chunks = data
+            .as_bit_slice()
+            .chunks((usize::from(u16::MAX) - header_len) * 8);
+
+        chunks
+            .map(|chunk| builder.build(BitString::from(chunk)))
+            .collect()
+    }
+
+    fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode(data)
+    }
+
+    fn as_bit_string(&self) -> &BitString {
+        &self.output_bitstring
+    }
+
+    fn header_len(&self) -> usize {
+        usize::from(self.ihl) * 4
+    }
+
+    fn payload(&self) -> &BitString {
+        &self.payload
+    }
+}
+
+impl Carries<TCPFrame> for Ipv4Packet {
+    fn carries(&self) -> bool {
+        self.protocol == PROTOCOL_TCP
+    }
+
+    fn decode_payload(&self) -> anyhow::Result<TCPFrame> {
+        TCPFrame::decode_ipv4(self)
+    }
+}
+
+impl Carries<UDPFrame> for Ipv4Packet {
+    fn carries(&self) -> bool {
+        self.protocol == PROTOCOL_UDP
+    }
+
+    fn decode_payload(&self) -> anyhow::Result<UDPFrame> {
+        UDPFrame::decode_ipv4(self)
+    }
+}
+
+/// The checksum of the segment is recomputed over the addresses of the packet.
+impl Encapsulate<TCPFrame> for Ipv4PacketBuilder {
+    type Frame = Ipv4Packet;
+
+    fn encapsulate(&self, payload: &TCPFrame) -> Ipv4Packet {
+        let segment = payload.with_pseudo_header(self.pseudo_header());
+
+        self.clone()
+            .set_protocol(PROTOCOL_TCP)
+            .build(segment.as_bit_string().clone())
+    }
+}
+
+/// The checksum of the datagram is recomputed over the addresses of the
+/// packet.
+impl Encapsulate<UDPFrame> for Ipv4PacketBuilder {
+    type Frame = Ipv4Packet;
+
+    fn encapsulate(&self, payload: &UDPFrame) -> Ipv4Packet {
+        let datagram = payload.with_pseudo_header(self.pseudo_header());
+
+        self.clone()
+            .set_protocol(PROTOCOL_UDP)
+            .build(datagram.as_bit_string().clone())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PseudoHeader {
+    Ipv4 {
+        source: Ipv4Address,
+        destination: Ipv4Address,
+    },
+    Ipv6 {
+        source: Ipv6Address,
+        destination: Ipv6Address,
+    },
+}
+
+/// The ones' complement checksum used by TCP, UDP and ICMPv6. The segment is
+/// padded with zeroes to a multiple of 16 bits for the calculation only, and
+/// the pseudo header is prepended if there is one.
+pub(crate) fn transport_checksum(
+    segment: &BitString,
+    protocol: u8,
+    pseudo_header: Option<PseudoHeader>,
+) -> u16 {
+    let mut checked = BitString::new();
+    let length = segment.len().div_ceil(8);
+
+    match pseudo_header {
+        Some(PseudoHeader::Ipv4 {
+            source,
+            destination,
+        }) => {
+            checked.append_u32(source.to_u32());
+            checked.append_u32(destination.to_u32());
+            checked.append_u8(0);
+            checked.append_u8(protocol);
+            checked.append_u16(u16::try_from(length).expect("Segment should fit in an IP packet"));
+        }
+        Some(PseudoHeader::Ipv6 {
+            source,
+            destination,
+        }) => {
+            for byte in source.octets().into_iter().chain(destination.octets()) {
+                checked.append_u8(byte);
+            }
+            checked.append_u32(u32::try_from(length).expect("Segment should fit in an IP packet"));
+            checked.append_zeroes(24);
+            checked.append_u8(protocol);
+        }
+        None => {}
+    }
+
+    checked.append_bits(segment.clone());
+    checked.append_zeroes((16 - checked.len() % 16) % 16);
+
+    internet_checksum(&checked.as_vec_exact_u16())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{bit_string::BitString, bitstring, ip_address::Ipv4Address};
+
+    use super::{Ipv4Packet, Ipv4PacketBuilder, DEFAULT_TTL, PROTOCOL_UDP};
+
+    const PAYLOAD_LEN: usize = 1000;
+
+    const SOURCE: Ipv4Address = Ipv4Address::new([192, 168, 0, 1]);
+    const DESTINATION: Ipv4Address = Ipv4Address::new([192, 168, 0, 199]);
+
+    fn builder() -> Ipv4PacketBuilder {
+        Ipv4PacketBuilder::new()
+            .set_source(SOURCE)
+            .set_destination(DESTINATION)
+            .set_protocol(PROTOCOL_UDP)
+    }
+
+    #[test]
+    fn known_header_checksum() {
+        // Example header from the IPv4 header checksum article on Wikipedia
+        let packet = builder()
+            .set_dont_fragment(true)
+            .set_identification(0x0000)
+            .build(BitString::with_zeroes(0x0073 * 8 - 160));
+
+        let bs = packet.as_bit_string();
+        assert_eq!(bs.get_u32(0), 0x4500_0073);
+        assert_eq!(bs.get_u32(32), 0x0000_4000);
+        assert_eq!(bs.get_u32(64), 0x4011_B861);
+        assert_eq!(packet.header_checksum(), 0xB861);
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let packet = builder()
+            .set_dscp(46)
+            .set_ecn(1)
+            .set_identification(1234)
+            .set_more_fragments(true)
+            .set_fragment_offset(100)
+            .set_ttl(3)
+            .set_options(vec![1, 1, 1])
+            .build(BitString::from(b"Hello world!".as_slice()));
+
+        let mut bs = packet.as_bit_string().clone();
+        // Link layer padding is ignored
+        bs.append_zeroes(64);
+
+        let decoded = Ipv4Packet::decode(&bs).expect("Packet is valid");
+
+        assert_eq!(decoded, packet);
+        assert_eq!(decoded.ihl(), 6);
+        assert_eq!(decoded.options(), [1, 1, 1, 0]);
+        assert_eq!(decoded.total_length(), 24 + 12);
+        assert_eq!(decoded.fragment_offset(), 100);
+        assert!(decoded.more_fragments() && !decoded.dont_fragment());
+    }
+
+    #[test]
+    fn pads_payload() {
+        let packet = builder().build(bitstring!(1));
+
+        assert_eq!(packet.total_length(), 21);
+        assert_eq!(packet.payload(), &bitstring!(1, 0, 0, 0, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn decode_rejects_corrupted_header() {
+        let packet = builder().build(BitString::from(b"payload".as_slice()));
+        let bs = packet.as_bit_string();
+
+        for idx in 0..160 {
+            let mut corrupted = bs.clone();
+            corrupted.flip_bit(idx);
+
+            assert!(Ipv4Packet::decode(&corrupted).is_err(), "Failed at {idx}");
+        }
+
+        let truncated = bs.copy_len(0, bs.len() - 8);
+        assert!(Ipv4Packet::decode(&truncated).is_err());
+    }
+
+    #[test]
+    fn forwarding_decrements_ttl() {
+        let packet = builder().set_ttl(2).build(BitString::new());
+        assert_eq!(builder().build(BitString::new()).ttl(), DEFAULT_TTL);
+
+        let forwarded = packet.forwarded().expect("TTL is left");
+        assert_eq!(forwarded.ttl(), 1);
+        Ipv4Packet::decode(forwarded.as_bit_string()).expect("Checksum was updated");
+
+        assert_eq!(forwarded.forwarded(), None);
+    }
+
+    #[test]
+    fn fragments_to_mtu() {
+        let payload = BitString::from(vec![0xABu8; PAYLOAD_LEN].as_slice());
+        let packet = builder().set_identification(7).build(payload.clone());
+
+        let fragments = packet.fragment(400).expect("Packet may be fragmented");
+
+        // 380 bytes fit, rounded down to 376
+        let lens: Vec<u16> = fragments.iter().map(Ipv4Packet::total_length).collect();
+        assert_eq!(lens, [396, 396, 268]);
+
+        let offsets: Vec<u16> = fragments.iter().map(Ipv4Packet::fragment_offset).collect();
+        assert_eq!(offsets, [0, 47, 94]);
+
+        assert!(fragments[0].more_fragments() && fragments[1].more_fragments());
+        assert!(!fragments[2].more_fragments());
+        assert!(fragments
+            .iter()
+            .all(|fragment| fragment.identification() == 7));
+
+        let mut joined = BitString::new();
+        for fragment in &fragments {
+            joined.append_bits(fragment.payload().clone());
+        }
+        assert_eq!(joined, payload);
+    }
+
+    #[test]
+    fn fragments_fragments() {
+        let packet = builder().build(BitString::with_zeroes(PAYLOAD_LEN * 8));
+
+        let fragments = packet.fragment(600).expect("Packet may be fragmented");
+        let refragmented = fragments[0]
+            .fragment(200)
+            .expect("Fragment may be fragmented");
+
+        let offsets: Vec<u16> = refragmented
+            .iter()
+            .map(Ipv4Packet::fragment_offset)
+            .collect();
+        assert_eq!(offsets, [0, 22, 44, 66]);
+        assert!(refragmented.iter().all(Ipv4Packet::more_fragments));
+    }
+
+    #[test]
+    fn dont_fragment() {
+        let packet = builder()
+            .set_dont_fragment(true)
+            .build(BitString::with_zeroes(PAYLOAD_LEN * 8));
+
+        assert_eq!(packet.fragment(1500), Some(vec![packet.clone()]));
+        assert_eq!(packet.fragment(576), None);
+    }
+}
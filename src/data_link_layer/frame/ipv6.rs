@@ -0,0 +1,790 @@
+use anyhow::{bail, ensure};
+
+use crate::{bit_string::BitString, ip_address::Ipv6Address};
+
+use super::{
+    ipv4::{PseudoHeader, PROTOCOL_TCP, PROTOCOL_UDP},
+    tcp::TCPFrame,
+    udp::UDPFrame,
+    Carries, Encapsulate, Frame,
+};
+
+// Next header values of extension headers
+pub const NEXT_HEADER_HOP_BY_HOP: u8 = 0;
+pub const NEXT_HEADER_ROUTING: u8 = 43;
+pub const NEXT_HEADER_FRAGMENT: u8 = 44;
+pub const NEXT_HEADER_DESTINATION_OPTIONS: u8 = 60;
+// Upper layer next header values, TCP and UDP share their IPv4 protocol number
+pub const NEXT_HEADER_ICMPV6: u8 = 58;
+pub const NEXT_HEADER_NONE: u8 = 59;
+
+pub const IPV6_HEADER_LEN: usize = 40;
+pub const DEFAULT_HOP_LIMIT: u8 = 64;
+pub const MAX_FLOW_LABEL: u32 = 0xF_FFFF;
+
+// Padding options
+const OPTION_PAD1: u8 = 0;
+const OPTION_PADN: u8 = 1;
+
+/// A type-length-value option of the hop-by-hop or destination options
+/// header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Option {
+    pub kind: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionHeader {
+    /// Options every node on the path has to look at. Only allowed directly
+    /// after the fixed header.
+    HopByHop(Vec<Ipv6Option>),
+    /// The type specific data has to fill the header up to a multiple of 8
+    /// bytes, so its length is 4 more than a multiple of 8.
+    Routing {
+        routing_type: u8,
+        segments_left: u8,
+        data: Vec<u8>,
+    },
+    /// The offset is in units of 8 bytes, as with IPv4.
+    Fragment {
+        fragment_offset: u16,
+        more_fragments: bool,
+        identification: u32,
+    },
+    DestinationOptions(Vec<Ipv6Option>),
+}
+
+impl ExtensionHeader {
+    #[must_use]
+    pub const fn next_header_value(&self) -> u8 {
+        match self {
+            Self::HopByHop(_) => NEXT_HEADER_HOP_BY_HOP,
+            Self::Routing { .. } => NEXT_HEADER_ROUTING,
+            Self::Fragment { .. } => NEXT_HEADER_FRAGMENT,
+            Self::DestinationOptions(_) => NEXT_HEADER_DESTINATION_OPTIONS,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_extension_header(next_header: u8) -> bool {
+        matches!(
+            next_header,
+            NEXT_HEADER_HOP_BY_HOP
+                | NEXT_HEADER_ROUTING
+                | NEXT_HEADER_FRAGMENT
+                | NEXT_HEADER_DESTINATION_OPTIONS
+        )
+    }
+
+    /// Appends the header, pointing at the given next header. Options are
+    /// padded to a multiple of 8 bytes.
+    pub fn encode(&self, next_header: u8, output: &mut BitString) {
+        let mut body = Vec::new();
+
+        match self {
+            Self::HopByHop(options) | Self::DestinationOptions(options) => {
+                for option in options {
+                    body.push(option.kind);
+                    body.push(u8::try_from(option.data.len()).expect("Option data fits"));
+                    body.extend(&option.data);
+                }
+
+                // The first two bytes of the header precede the options
+                match (8 - (body.len() + 2) % 8) % 8 {
+                    0 => {}
+                    1 => body.push(OPTION_PAD1),
+                    padding => {
+                        body.push(OPTION_PADN);
+                        body.push(u8::try_from(padding - 2).expect("Padding is short"));
+                        body.resize(body.len() + padding - 2, 0);
+                    }
+                }
+            }
+            Self::Routing {
+                routing_type,
+                segments_left,
+                data,
+            } => {
+                body.push(*routing_type);
+                body.push(*segments_left);
+                body.extend(data);
+            }
+            Self::Fragment {
+                fragment_offset,
+                more_fragments,
+                identification,
+            } => {
+                body.extend((fragment_offset << 3 | u16::from(*more_fragments)).to_be_bytes());
+                body.extend(identification.to_be_bytes());
+            }
+        }
+
+        let len = body.len() + 2;
+        assert!(
+            len.is_multiple_of(8),
+            "Extension header has to be a multiple of 8 bytes"
+        );
+
+        output.append_u8(next_header);
+        match self {
+            // The fragment header has a reserved byte instead of a length
+            Self::Fragment { .. } => output.append_u8(0),
+            _ => output.append_u8(u8::try_from(len / 8 - 1).expect("Header fits")),
+        }
+        for byte in body {
+            output.append_u8(byte);
+        }
+    }
+
+    /// Reads the header of the given type starting at the bit index, returning
+    /// it with the next header value and its length in bits.
+    pub fn decode(kind: u8, data: &BitString, index: usize) -> anyhow::Result<(Self, u8, usize)> {
+        ensure!(
+            data.len() >= index + 64,
+            "Packet is too short to hold an extension header"
+        );
+
+        let next_header = data.get_u8(index);
+        let len = match kind {
+            NEXT_HEADER_FRAGMENT => 8,
+            _ => (usize::from(data.get_u8(index + 8)) + 1) * 8,
+        };
+
+        ensure!(
+            data.len() >= index + len * 8,
+            "Packet is too short to hold an extension header of {len} bytes"
+        );
+
+        let body = data.copy_len(index + 16, len * 8 - 16).as_vec_exact_u8();
+
+        let header = match kind {
+            NEXT_HEADER_HOP_BY_HOP => Self::HopByHop(decode_options(&body)?),
+            NEXT_HEADER_DESTINATION_OPTIONS => Self::DestinationOptions(decode_options(&body)?),
+            NEXT_HEADER_ROUTING => Self::Routing {
+                routing_type: body[0],
+                segments_left: body[1],
+                data: body[2..].to_vec(),
+            },
+            NEXT_HEADER_FRAGMENT => {
+                let offset_flags = u16::from_be_bytes([body[0], body[1]]);
+                Self::Fragment {
+                    fragment_offset: offset_flags >> 3,
+                    more_fragments: offset_flags & 0b1 != 0,
+                    identification: u32::from_be_bytes([body[2], body[3], body[4], body[5]]),
+                }
+            }
+            kind => bail!("{kind} is not an extension header"),
+        };
+
+        Ok((header, next_header, len * 8))
+    }
+}
+
+/// Parses the options of a header, leaving out the padding.
+fn decode_options(mut body: &[u8]) -> anyhow::Result<Vec<Ipv6Option>> {
+    let mut options = Vec::new();
+
+    while let [kind, rest @ ..] = body {
+        if *kind == OPTION_PAD1 {
+            body = rest;
+            continue;
+        }
+
+        let Some((len, rest)) = rest.split_first() else {
+            bail!("Option {kind} is missing its length");
+        };
+        let len = usize::from(*len);
+        ensure!(
+            rest.len() >= len,
+            "Option {kind} does not fit in the header"
+        );
+
+        if *kind != OPTION_PADN {
+            options.push(Ipv6Option {
+                kind: *kind,
+                data: rest[..len].to_vec(),
+            });
+        }
+        body = &rest[len..];
+    }
+
+    Ok(options)
+}
+
+#[derive(Debug, Clone)]
+pub struct Ipv6PacketBuilder {
+    source: Option<Ipv6Address>,
+    destination: Option<Ipv6Address>,
+    next_header: Option<u8>,
+    traffic_class: u8,
+    flow_label: u32,
+    hop_limit: u8,
+    extension_headers: Vec<ExtensionHeader>,
+}
+
+impl Ipv6PacketBuilder {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            source: None,
+            destination: None,
+            next_header: None,
+            traffic_class: 0,
+            flow_label: 0,
+            hop_limit: DEFAULT_HOP_LIMIT,
+            extension_headers: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn set_source(self, source: Ipv6Address) -> Self {
+        Self {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_destination(self, destination: Ipv6Address) -> Self {
+        Self {
+            destination: Some(destination),
+            ..self
+        }
+    }
+
+    /// Sets the protocol of the payload, which follows the extension headers.
+    #[must_use]
+    pub fn set_next_header(self, next_header: u8) -> Self {
+        Self {
+            next_header: Some(next_header),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_traffic_class(self, traffic_class: u8) -> Self {
+        Self {
+            traffic_class,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn set_flow_label(self, flow_label: u32) -> Self {
+        assert!(flow_label <= MAX_FLOW_LABEL);
+        Self { flow_label, ..self }
+    }
+
+    #[must_use]
+    pub fn set_hop_limit(self, hop_limit: u8) -> Self {
+        Self { hop_limit, ..self }
+    }
+
+    /// Sets the extension headers, in the order they appear in the packet.
+    #[must_use]
+    pub fn set_extension_headers(self, extension_headers: Vec<ExtensionHeader>) -> Self {
+        let hop_by_hop_later = extension_headers
+            .iter()
+            .skip(1)
+            .any(|header| matches!(header, ExtensionHeader::HopByHop(_)));
+        assert!(
+            !hop_by_hop_later,
+            "The hop-by-hop header has to come directly after the fixed header"
+        );
+
+        Self {
+            extension_headers,
+            ..self
+        }
+    }
+
+    /// The addresses upper layer checksums of the payload are computed over.
+    fn pseudo_header(&self) -> PseudoHeader {
+        PseudoHeader::Ipv6 {
+            source: self
+                .source
+                .expect("Cannot construct an Ipv6Packet without source"),
+            destination: self
+                .destination
+                .expect("Cannot construct an Ipv6Packet without destination"),
+        }
+    }
+
+    /// Builds a single packet. The payload is padded with zeroes to whole
+    /// bytes, as the payload length counts bytes.
+    pub fn build(&self, mut payload: BitString) -> Ipv6Packet {
+        let source = self
+            .source
+            .expect("Cannot construct an Ipv6Packet without source");
+        let destination = self
+            .destination
+            .expect("Cannot construct an Ipv6Packet without destination");
+        let next_header = self
+            .next_header
+            .expect("Cannot construct an Ipv6Packet without next header");
+
+        payload.append_zeroes((8 - payload.len() % 8) % 8);
+
+        let mut packet = Ipv6Packet {
+            traffic_class: self.traffic_class,
+            flow_label: self.flow_label,
+            payload_length: 0,
+            next_header,
+            hop_limit: self.hop_limit,
+            source,
+            destination,
+            extension_headers: self.extension_headers.clone(),
+            payload,
+            output_bitstring: BitString::new(),
+        };
+        packet.serialize();
+
+        packet
+    }
+}
+
+impl Default for Ipv6PacketBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Packet {
+    // Header
+    traffic_class: u8,
+    flow_label: u32,
+    payload_length: u16,
+    /// The protocol of the payload, after all extension headers.
+    next_header: u8,
+    hop_limit: u8,
+    source: Ipv6Address,
+    destination: Ipv6Address,
+    extension_headers: Vec<ExtensionHeader>,
+
+    // Data
+    payload: BitString,
+
+    // Full bit_string, serialized once the header is complete
+    output_bitstring: BitString,
+}
+
+impl Ipv6Packet {
+    /// Serializes the packet, chaining the extension headers together and
+    /// filling in the payload length.
+    fn serialize(&mut self) {
+        let mut extensions = BitString::new();
+
+        let next_values: Vec<u8> = self
+            .extension_headers
+            .iter()
+            .skip(1)
+            .map(ExtensionHeader::next_header_value)
+            .chain([self.next_header])
+            .collect();
+        for (header, next_header) in self.extension_headers.iter().zip(next_values) {
+            header.encode(next_header, &mut extensions);
+        }
+
+        let first_next_header = self
+            .extension_headers
+            .first()
+            .map_or(self.next_header, ExtensionHeader::next_header_value);
+
+        self.payload_length = u16::try_from((extensions.len() + self.payload.len()) / 8)
+            .expect("Packet is larger than the payload length allows");
+
+        let mut output = BitString::with_capacity(IPV6_HEADER_LEN * 8 + extensions.len());
+
+        output.append_u32(6 << 28 | u32::from(self.traffic_class) << 20 | self.flow_label);
+        output.append_u16(self.payload_length);
+        output.append_u8(first_next_header);
+        output.append_u8(self.hop_limit);
+        for byte in self
+            .source
+            .octets()
+            .into_iter()
+            .chain(self.destination.octets())
+        {
+            output.append_u8(byte);
+        }
+        output.append_bits(extensions);
+        output.append_bits(self.payload.clone());
+
+        self.output_bitstring = output;
+    }
+
+    /// Parses a packet, following the chain of extension headers up to the
+    /// payload. Anything after the payload length, such as ethernet padding,
+    /// is ignored.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= IPV6_HEADER_LEN * 8,
+            "Packet of {} bits is too short to hold an IPv6 header",
+            data.len()
+        );
+
+        let first_word = data.get_u32(0);
+        let version = first_word >> 28;
+        let payload_length = data.get_u16(32);
+        let total_len = IPV6_HEADER_LEN * 8 + usize::from(payload_length) * 8;
+
+        ensure!(version == 6, "Packet has version {version} instead of 6");
+        ensure!(
+            total_len <= data.len(),
+            "Payload length of {payload_length} bytes does not match the packet"
+        );
+
+        let data = data.copy_len(0, total_len);
+        let address = |index: usize| {
+            let octets = data.copy_len(index, 128).as_vec_exact_u8();
+            Ipv6Address::new(octets.try_into().expect("Address is 16 bytes"))
+        };
+
+        let mut next_header = data.get_u8(48);
+        let mut index = IPV6_HEADER_LEN * 8;
+        let mut extension_headers = Vec::new();
+
+        while ExtensionHeader::is_extension_header(next_header) {
+            ensure!(
+                next_header != NEXT_HEADER_HOP_BY_HOP || extension_headers.is_empty(),
+                "Hop-by-hop header does not directly follow the fixed header"
+            );
+
+            let (header, next, len) = ExtensionHeader::decode(next_header, &data, index)?;
+            extension_headers.push(header);
+            next_header = next;
+            index += len;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(Self {
+            traffic_class: (first_word >> 20) as u8,
+            flow_label: first_word & MAX_FLOW_LABEL,
+            payload_length,
+            next_header,
+            hop_limit: data.get_u8(56),
+            source: address(64),
+            destination: address(192),
+            extension_headers,
+            payload: data.copy_len(index, total_len - index),
+            output_bitstring: data,
+        })
+    }
+
+    /// A builder with the header fields and extension headers of this packet.
+    #[must_use]
+    pub fn builder(&self) -> Ipv6PacketBuilder {
+        Ipv6PacketBuilder::new()
+            .set_source(self.source)
+            .set_destination(self.destination)
+            .set_next_header(self.next_header)
+            .set_traffic_class(self.traffic_class)
+            .set_flow_label(self.flow_label)
+            .set_hop_limit(self.hop_limit)
+            .set_extension_headers(self.extension_headers.clone())
+    }
+
+    /// The packet as forwarded by a router, with the hop limit decremented.
+    /// Returns [`None`] if the hop limit runs out, in which case the packet is
+    /// dropped.
+    #[must_use]
+    pub fn forwarded(&self) -> Option<Self> {
+        let hop_limit = self.hop_limit.checked_sub(1).filter(|limit| *limit > 0)?;
+
+        let mut packet = Self {
+            hop_limit,
+            ..self.clone()
+        };
+        packet.serialize();
+
+        Some(packet)
+    }
+
+    /// The addresses upper layer checksums are computed over.
+    #[must_use]
+    pub const fn pseudo_header(&self) -> PseudoHeader {
+        PseudoHeader::Ipv6 {
+            source: self.source,
+            destination: self.destination,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_bit_string(&self) -> &BitString {
+        &self.output_bitstring
+    }
+
+    #[must_use]
+    pub const fn traffic_class(&self) -> u8 {
+        self.traffic_class
+    }
+
+    #[must_use]
+    pub const fn flow_label(&self) -> u32 {
+        self.flow_label
+    }
+
+    /// The length in bytes of everything after the fixed header, extension
+    /// headers included.
+    #[must_use]
+    pub const fn payload_length(&self) -> u16 {
+        self.payload_length
+    }
+
+    /// The protocol of the payload, after all extension headers.
+    #[must_use]
+    pub const fn next_header(&self) -> u8 {
+        self.next_header
+    }
+
+    #[must_use]
+    pub const fn hop_limit(&self) -> u8 {
+        self.hop_limit
+    }
+
+    #[must_use]
+    pub const fn source(&self) -> Ipv6Address {
+        self.source
+    }
+
+    #[must_use]
+    pub const fn destination(&self) -> Ipv6Address {
+        self.destination
+    }
+
+    #[must_use]
+    pub fn extension_headers(&self) -> &[ExtensionHeader] {
+        &self.extension_headers
+    }
+
+    #[must_use]
+    pub const fn payload(&self) -> &BitString {
+        &self.payload
+    }
+}
+
+impl Frame for Ipv6Packet {
+    type Builder = Ipv6PacketBuilder;
+
+    fn setup_frames(data: BitString, builder: Ipv6PacketBuilder) -> Vec<Self> {
+        // The payload length covers the extension headers, but not the fixed
+        // header
+        let extensions_len = builder.build(BitString::new()).header_len() - IPV6_HEADER_LEN;
+        let chunks = data
+            .as_bit_slice()
+            .chunks((usize::from(u16::MAX) - extensions_len) * 8);
+
+        chunks
+            .map(|chunk| builder.build(BitString::from(chunk)))
+            .collect()
+    }
+
+    fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode(data)
+    }
+
+    fn as_bit_string(&self) -> &BitString {
+        &self.output_bitstring
+    }
+
+    /// The fixed header and all extension headers.
+    fn header_len(&self) -> usize {
+        (self.output_bitstring.len() - self.payload.len()) / 8
+    }
+
+    fn payload(&self) -> &BitString {
+        &self.payload
+    }
+}
+
+impl Carries<TCPFrame> for Ipv6Packet {
+    fn carries(&self) -> bool {
+        self.next_header == PROTOCOL_TCP
+    }
+
+    fn decode_payload(&self) -> anyhow::Result<TCPFrame> {
+        TCPFrame::decode_ipv6(self)
+    }
+}
+
+impl Carries<UDPFrame> for Ipv6Packet {
+    fn carries(&self) -> bool {
+        self.next_header == PROTOCOL_UDP
+    }
+
+    fn decode_payload(&self) -> anyhow::Result<UDPFrame> {
+        UDPFrame::decode_ipv6(self)
+    }
+}
+
+/// The checksum of the segment is recomputed over the addresses of the packet.
+impl Encapsulate<TCPFrame> for Ipv6PacketBuilder {
+    type Frame = Ipv6Packet;
+
+    fn encapsulate(&self, payload: &TCPFrame) -> Ipv6Packet {
+        let segment = payload.with_pseudo_header(self.pseudo_header());
+
+        self.clone()
+            .set_next_header(PROTOCOL_TCP)
+            .build(segment.as_bit_string().clone())
+    }
+}
+
+/// The checksum of the datagram is recomputed over the addresses of the
+/// packet.
+impl Encapsulate<UDPFrame> for Ipv6PacketBuilder {
+    type Frame = Ipv6Packet;
+
+    fn encapsulate(&self, payload: &UDPFrame) -> Ipv6Packet {
+        let datagram = payload.with_pseudo_header(self.pseudo_header());
+
+        self.clone()
+            .set_next_header(PROTOCOL_UDP)
+            .build(datagram.as_bit_string().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString, data_link_layer::frame::ipv4::PROTOCOL_UDP, ip_address::Ipv6Address,
+    };
+
+    use super::{
+        ExtensionHeader, Ipv6Option, Ipv6Packet, Ipv6PacketBuilder,
+        NEXT_HEADER_DESTINATION_OPTIONS, NEXT_HEADER_FRAGMENT, NEXT_HEADER_HOP_BY_HOP,
+        NEXT_HEADER_ROUTING,
+    };
+
+    const SOURCE: Ipv6Address = Ipv6Address::from_u128(0x2001_0DB8 << 96 | 1);
+    const DESTINATION: Ipv6Address = Ipv6Address::from_u128(0x2001_0DB8 << 96 | 2);
+    const PAYLOAD: &[u8] = b"Hello world!";
+
+    fn builder() -> Ipv6PacketBuilder {
+        Ipv6PacketBuilder::new()
+            .set_source(SOURCE)
+            .set_destination(DESTINATION)
+            .set_next_header(PROTOCOL_UDP)
+    }
+
+    #[test]
+    fn fixed_header() {
+        let packet = builder()
+            .set_traffic_class(0xB8)
+            .set_flow_label(0x1_2345)
+            .set_hop_limit(3)
+            .build(BitString::from(PAYLOAD));
+
+        let bs = packet.as_bit_string();
+        assert_eq!(bs.get_u32(0), 0x6B81_2345);
+        assert_eq!(bs.get_u32(32), 0x000C_1103);
+        assert_eq!(bs.len(), (40 + PAYLOAD.len()) * 8);
+
+        let decoded = Ipv6Packet::decode(bs).expect("Packet is valid");
+        assert_eq!(decoded, packet);
+        assert_eq!(decoded.payload(), &BitString::from(PAYLOAD));
+    }
+
+    #[test]
+    fn extension_header_chain() {
+        let headers = vec![
+            ExtensionHeader::HopByHop(vec![Ipv6Option {
+                kind: 5,
+                data: vec![0, 0],
+            }]),
+            ExtensionHeader::Routing {
+                routing_type: 2,
+                segments_left: 1,
+                data: [[0; 4].as_slice(), &DESTINATION.octets()].concat(),
+            },
+            ExtensionHeader::Fragment {
+                fragment_offset: 100,
+                more_fragments: true,
+                identification: 0xDEAD_BEEF,
+            },
+            ExtensionHeader::DestinationOptions(Vec::new()),
+        ];
+        let packet = builder()
+            .set_extension_headers(headers.clone())
+            .build(BitString::from(PAYLOAD));
+
+        let bs = packet.as_bit_string();
+        // Every header points at the next one
+        assert_eq!(bs.get_u8(48), NEXT_HEADER_HOP_BY_HOP);
+        assert_eq!(bs.get_u8(320), NEXT_HEADER_ROUTING);
+        assert_eq!(bs.get_u8(384), NEXT_HEADER_FRAGMENT);
+        assert_eq!(bs.get_u8(576), NEXT_HEADER_DESTINATION_OPTIONS);
+        assert_eq!(bs.get_u8(640), PROTOCOL_UDP);
+        assert_eq!(packet.payload_length(), 8 + 24 + 8 + 8 + 12);
+
+        let mut padded = bs.clone();
+        padded.append_zeroes(64);
+
+        let decoded = Ipv6Packet::decode(&padded).expect("Packet is valid");
+        assert_eq!(decoded.extension_headers(), headers);
+        assert_eq!(decoded.next_header(), PROTOCOL_UDP);
+        assert_eq!(decoded, packet);
+    }
+
+    #[test]
+    fn pads_options() {
+        for len in 0..10 {
+            let options = vec![Ipv6Option {
+                kind: 0x1E,
+                data: vec![0xAB; len],
+            }];
+            let packet = builder()
+                .set_extension_headers(vec![ExtensionHeader::DestinationOptions(options.clone())])
+                .build(BitString::new());
+
+            assert!(usize::from(packet.payload_length()).is_multiple_of(8));
+
+            let decoded = Ipv6Packet::decode(packet.as_bit_string()).expect("Packet is valid");
+            assert_eq!(
+                decoded.extension_headers(),
+                [ExtensionHeader::DestinationOptions(options)]
+            );
+        }
+    }
+
+    #[test]
+    fn decode_rejects_invalid() {
+        let packet = builder().build(BitString::from(PAYLOAD));
+        let bs = packet.as_bit_string();
+
+        let mut wrong_version = bs.clone();
+        wrong_version.flip_bit(1);
+        assert!(Ipv6Packet::decode(&wrong_version).is_err());
+
+        let truncated = bs.copy_len(0, bs.len() - 8);
+        assert!(Ipv6Packet::decode(&truncated).is_err());
+
+        // A hop-by-hop header after another extension header
+        let mut late_hop_by_hop = builder()
+            .set_extension_headers(vec![
+                ExtensionHeader::DestinationOptions(Vec::new()),
+                ExtensionHeader::DestinationOptions(Vec::new()),
+            ])
+            .build(BitString::new())
+            .as_bit_string()
+            .clone();
+        late_hop_by_hop.set_u8(320, NEXT_HEADER_HOP_BY_HOP);
+        assert!(Ipv6Packet::decode(&late_hop_by_hop).is_err());
+    }
+
+    #[test]
+    fn forwarding_decrements_hop_limit() {
+        let packet = builder().set_hop_limit(2).build(BitString::new());
+
+        let forwarded = packet.forwarded().expect("Hop limit is left");
+        assert_eq!(forwarded.hop_limit(), 1);
+        assert_eq!(
+            Ipv6Packet::decode(forwarded.as_bit_string()).expect("Packet is valid"),
+            forwarded
+        );
+
+        assert_eq!(forwarded.forwarded(), None);
+    }
+}
@@ -1,12 +1,187 @@
 use crate::bit_string::BitString;
 
+pub mod ethernet;
+pub mod ipv4;
+pub mod ipv6;
 pub mod tcp;
+pub mod tcp_option;
 pub mod udp;
+pub mod vlan;
 
-pub trait Frame<T> {
-    fn setup_frames(data: BitString, builder: T) -> Vec<Self>
+/// A frame, packet or segment of some layer, which is a header followed by a
+/// payload and possibly a trailer.
+pub trait Frame {
+    type Builder;
+
+    /// Splits the data over as many frames as it takes, all built with the
+    /// given builder.
+    fn setup_frames(data: BitString, builder: Self::Builder) -> Vec<Self>
+    where
+        Self: Sized;
+
+    /// Parses a frame from its serialized form, as returned by
+    /// [`Frame::encode`].
+    fn decode(data: &BitString) -> anyhow::Result<Self>
     where
         Self: Sized;
 
     fn as_bit_string(&self) -> &BitString;
+
+    fn encode(&self) -> BitString {
+        self.as_bit_string().clone()
+    }
+
+    /// The length in bytes of everything in front of the payload.
+    fn header_len(&self) -> usize;
+
+    /// The length in bytes of everything behind the payload.
+    fn trailer_len(&self) -> usize {
+        0
+    }
+
+    fn payload(&self) -> &BitString;
+}
+
+/// A frame that can carry frames of type `P` as payload, such as an ethernet
+/// frame carrying an IPv4 packet.
+pub trait Carries<P: Frame>: Frame {
+    /// Whether the header marks the payload as a `P`.
+    fn carries(&self) -> bool;
+
+    /// Parses the payload as a `P`.
+    fn decode_payload(&self) -> anyhow::Result<P>;
+}
+
+/// A builder of frames that carry frames of type `P`, filling in the header
+/// fields that name the payload.
+pub trait Encapsulate<P: Frame> {
+    type Frame: Carries<P>;
+
+    fn encapsulate(&self, payload: &P) -> Self::Frame;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        ip_address::{Ipv4Address, Ipv6Address},
+        mac_address::MacAddressGenerator,
+    };
+
+    use super::{
+        ethernet::{EthernetFrame, EthernetFrameBuilder, ETHERTYPE_IPV4, ETHERTYPE_IPV6},
+        ipv4::{Ipv4Packet, Ipv4PacketBuilder, PROTOCOL_TCP, PROTOCOL_UDP},
+        ipv6::{Ipv6Packet, Ipv6PacketBuilder},
+        tcp::{TCPFrame, TCPFrameBuilder},
+        udp::{UDPBuilder, UDPFrame},
+        Carries, Encapsulate, Frame,
+    };
+
+    const DATA: &[u8] = b"Hello world!";
+
+    /// Wraps a payload in two layers of headers.
+    fn construct<O, M, I>(outer: &O, middle: &M, inner: &I) -> O::Frame
+    where
+        O: Encapsulate<M::Frame>,
+        M: Encapsulate<I>,
+        I: Frame,
+    {
+        outer.encapsulate(&middle.encapsulate(inner))
+    }
+
+    /// Takes apart a frame carrying two layers of frames.
+    fn dissect<O, M, I>(data: &BitString) -> anyhow::Result<(O, M, I)>
+    where
+        O: Carries<M>,
+        M: Carries<I>,
+        I: Frame,
+    {
+        let outer = O::decode(data)?;
+        let middle = outer.decode_payload()?;
+        let inner = middle.decode_payload()?;
+
+        Ok((outer, middle, inner))
+    }
+
+    fn ethernet() -> EthernetFrameBuilder {
+        let mut mac_gen = MacAddressGenerator::new(46);
+
+        EthernetFrameBuilder::new()
+            .set_destination(mac_gen.gen_addr())
+            .set_source(mac_gen.gen_addr())
+    }
+
+    fn tcp() -> TCPFrame {
+        TCPFrameBuilder::new()
+            .set_source_port(49152)
+            .set_target_port(80)
+            .set_window_size(1024)
+            .build(BitString::from(DATA))
+    }
+
+    #[test]
+    fn ethernet_ipv4_tcp() -> anyhow::Result<()> {
+        let ipv4 = Ipv4PacketBuilder::new()
+            .set_source(Ipv4Address::new([10, 0, 0, 1]))
+            .set_destination(Ipv4Address::new([10, 0, 0, 2]));
+
+        let frame = construct(&ethernet(), &ipv4, &tcp());
+        assert_eq!(frame.ether_type(), ETHERTYPE_IPV4);
+
+        let (ethernet, packet, segment): (EthernetFrame, Ipv4Packet, TCPFrame) =
+            dissect(&frame.encode())?;
+        assert_eq!(ethernet, frame);
+        assert_eq!(packet.protocol(), PROTOCOL_TCP);
+        assert_eq!(segment.data(), &BitString::from(DATA));
+
+        // The header lengths locate every payload in the serialized frame
+        let bits = frame.encode();
+        let packet_bits = bits.copy_len(
+            ethernet.header_len() * 8,
+            bits.len() - (ethernet.header_len() + ethernet.trailer_len()) * 8,
+        );
+        assert_eq!(
+            packet_bits.copy_len(0, packet.encode().len()),
+            packet.encode()
+        );
+        let segment_bits = packet_bits.copy_len(
+            packet.header_len() * 8,
+            packet.encode().len() - packet.header_len() * 8,
+        );
+        assert_eq!(segment_bits, segment.encode());
+        assert_eq!(
+            segment_bits.copy_len(segment.header_len() * 8, DATA.len() * 8),
+            BitString::from(DATA)
+        );
+
+        // Other payload types are refused
+        assert!(!Carries::<Ipv6Packet>::carries(&ethernet));
+        assert!(!Carries::<UDPFrame>::carries(&packet));
+        assert!(Carries::<UDPFrame>::decode_payload(&packet).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ethernet_ipv6_udp() -> anyhow::Result<()> {
+        let ipv6 = Ipv6PacketBuilder::new()
+            .set_source(Ipv6Address::LOOPBACK)
+            .set_destination(Ipv6Address::LOOPBACK);
+        let datagram = UDPBuilder::new()
+            .set_source_port(49152)
+            .set_target_port(53)
+            .build(BitString::from(DATA));
+
+        let frame = construct(&ethernet(), &ipv6, &datagram);
+        assert_eq!(frame.ether_type(), ETHERTYPE_IPV6);
+
+        let (_, packet, datagram): (EthernetFrame, Ipv6Packet, UDPFrame) =
+            dissect(&frame.encode())?;
+        assert_eq!(packet.next_header(), PROTOCOL_UDP);
+        assert_eq!(datagram.data(), &BitString::from(DATA));
+        // The checksum covers the addresses of the packet
+        assert_eq!(UDPFrame::decode_ipv6(&packet)?, datagram);
+
+        Ok(())
+    }
 }
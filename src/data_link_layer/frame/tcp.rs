@@ -1,7 +1,15 @@
-use crate::{bit::Bit, bit_string::BitString};
+use anyhow::ensure;
 
-use super::Frame;
+use crate::bit_string::BitString;
 
+use super::{
+    ipv4::{transport_checksum, Ipv4Packet, PseudoHeader, PROTOCOL_TCP},
+    ipv6::Ipv6Packet,
+    tcp_option::{TCPOption, MAX_OPTIONS_LEN},
+    Frame,
+};
+
+pub const MIN_TCP_HEADER_LEN: usize = 20;
 const MAX_TCP_HEADER_LEN: usize = 60;
 const MAX_TCP_DATA_LEN: usize = u16::MAX as usize - MAX_TCP_HEADER_LEN;
 
@@ -30,11 +38,13 @@ pub struct TCPFrameBuilder {
     target_port: Option<u16>,
     sequence_num: u32,
     ack_num: u32,
-    data_offset: u8,
     flag_byte: u8,
     window_size: Option<u16>,
     urgent_pointer: u16,
-    options: [u32; 10],
+    options: Vec<TCPOption>,
+
+    // Addresses of the carrying IP packet
+    pseudo_header: Option<PseudoHeader>,
 }
 
 impl TCPFrameBuilder {
@@ -44,11 +54,11 @@ impl TCPFrameBuilder {
             target_port: None,
             sequence_num: 0,
             ack_num: 0,
-            data_offset: 5,
             flag_byte: 0,
             window_size: None,
             urgent_pointer: 0,
-            options: [0; 10],
+            options: Vec::new(),
+            pseudo_header: None,
         }
     }
 
@@ -76,7 +86,7 @@ impl TCPFrameBuilder {
         res_vec
     }
 
-    fn build(&self, data: BitString) -> TCPFrame {
+    pub fn build(&self, data: BitString) -> TCPFrame {
         let source_port = self
             .source_port
             .expect("Cannot construct a TCPFrame without source port");
@@ -89,10 +99,13 @@ impl TCPFrameBuilder {
 
         let sequence_num = self.sequence_num;
         let ack_num = self.ack_num;
-        let data_offset = self.data_offset;
         let flag_byte = self.flag_byte;
         let urgent_pointer = self.urgent_pointer;
-        let options = self.options;
+        let options = self.options.clone();
+
+        let encoded_options = TCPOption::encode_all(&options);
+        let data_offset = u8::try_from(MIN_TCP_HEADER_LEN / 4 + encoded_options.len() / 32)
+            .expect("Options are limited in size");
 
         let mut output_bitstring = BitString::with_capacity(data_offset as usize * 32 + data.len());
 
@@ -108,38 +121,15 @@ impl TCPFrameBuilder {
         output_bitstring.append_u16(0);
         output_bitstring.append_u16(urgent_pointer);
 
-        if let Some(words) = data_offset.checked_sub(5) {
-            for i in 0..words {
-                output_bitstring.append_u32(options[i as usize]);
-            }
-        }
+        output_bitstring.append_bits(encoded_options);
 
         output_bitstring.append_bits(data.clone());
 
-        // pad with zeros
-        for _ in 0..(output_bitstring.len() % 16) {
-            output_bitstring.append_bit(Bit::Off);
-        }
-
-        assert!(
-            output_bitstring.len() % 16 == 0,
-            "The full bitstring wasn't padded correctly"
-        );
-
         // -- Find checksum --
-        let vec = output_bitstring.as_vec_exact_u16();
-        let mut sum: u32 = vec.iter().map(|&x| u32::from(x)).sum();
-
-        while sum > 0xFFFF {
-            sum = (sum >> 16) + (sum & 0xFFFF);
-        }
-
-        let checksum: u16 = !(u16::try_from(sum).expect("Error in calculating checksum"));
+        let checksum = transport_checksum(&output_bitstring, PROTOCOL_TCP, self.pseudo_header);
 
         output_bitstring.set_u16(128, checksum);
 
-        assert!(output_bitstring.len() % 16 == 0);
-
         TCPFrame {
             source_port,
             target_port,
@@ -156,40 +146,32 @@ impl TCPFrameBuilder {
         }
     }
 
-    pub const fn set_source_port(self, source_port: u16) -> Self {
+    pub fn set_source_port(self, source_port: u16) -> Self {
         Self {
             source_port: Some(source_port),
             ..self
         }
     }
 
-    pub const fn set_target_port(self, target_port: u16) -> Self {
+    pub fn set_target_port(self, target_port: u16) -> Self {
         Self {
             target_port: Some(target_port),
             ..self
         }
     }
 
-    // pub fn set_sequence_num(self, sequence_num: u32) -> Self {
-    //     Self {
-    //         sequence_num: Some(sequence_num),
-    //         ..self
-    //     }
-    // }
-
-    pub const fn set_ack_num(self, ack_num: u32) -> Self {
-        Self { ack_num, ..self }
-    }
-
-    pub fn set_data_offset(self, data_offset: u8) -> Self {
-        assert!(data_offset <= 0b0000_1111u8);
+    pub fn set_sequence_num(self, sequence_num: u32) -> Self {
         Self {
-            data_offset,
+            sequence_num,
             ..self
         }
     }
 
-    pub const fn set_flags(self, flag: u8) -> Self {
+    pub fn set_ack_num(self, ack_num: u32) -> Self {
+        Self { ack_num, ..self }
+    }
+
+    pub fn set_flags(self, flag: u8) -> Self {
         let mut flag_byte = self.flag_byte;
 
         flag_byte |= flag;
@@ -197,21 +179,35 @@ impl TCPFrameBuilder {
         Self { flag_byte, ..self }
     }
 
-    pub const fn set_window_size(self, window_size: u16) -> Self {
+    pub fn set_window_size(self, window_size: u16) -> Self {
         Self {
             window_size: Some(window_size),
             ..self
         }
     }
 
-    pub const fn set_urgent_pointer(self, urgent_pointer: u16) -> Self {
+    pub fn set_urgent_pointer(self, urgent_pointer: u16) -> Self {
         Self {
             urgent_pointer,
             ..self
         }
     }
 
-    pub const fn set_options(self, options: [u32; 10]) -> Self {
+    /// Includes the addresses of the carrying IP packet in the checksum.
+    pub fn set_pseudo_header(self, pseudo_header: PseudoHeader) -> Self {
+        Self {
+            pseudo_header: Some(pseudo_header),
+            ..self
+        }
+    }
+
+    /// Sets the options sent in the header. The data offset is derived from
+    /// the length of the options.
+    pub fn set_options(self, options: Vec<TCPOption>) -> Self {
+        assert!(
+            TCPOption::padded_words(&options) * 4 <= MAX_OPTIONS_LEN,
+            "Options cannot take up more than {MAX_OPTIONS_LEN} bytes"
+        );
         Self { options, ..self }
     }
 }
@@ -222,8 +218,7 @@ impl Default for TCPFrameBuilder {
     }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TCPFrame {
     // Header
     source_port: u16,
@@ -235,7 +230,7 @@ pub struct TCPFrame {
     window_size: u16,
     checksum: u16,
     urgent_pointer: u16,
-    options: [u32; 10],
+    options: Vec<TCPOption>,
 
     // Data
     data: BitString,
@@ -244,7 +239,162 @@ pub struct TCPFrame {
     output_bitstring: BitString,
 }
 
-impl Frame<TCPFrameBuilder> for TCPFrame {
+impl TCPFrame {
+    /// Parses a frame as serialized by [`TCPFrameBuilder`] without a pseudo
+    /// header. Everything after the header is taken to be data.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode_with(data, None)
+    }
+
+    /// Parses the frame carried by the packet, verifying the checksum with the
+    /// addresses of the packet.
+    pub fn decode_ipv4(packet: &Ipv4Packet) -> anyhow::Result<Self> {
+        ensure!(
+            packet.protocol() == PROTOCOL_TCP,
+            "Packet does not carry a TCP frame"
+        );
+
+        Self::decode_with(packet.payload(), Some(packet.pseudo_header()))
+    }
+
+    /// Parses the frame carried by the packet, verifying the checksum with the
+    /// addresses of the packet.
+    pub fn decode_ipv6(packet: &Ipv6Packet) -> anyhow::Result<Self> {
+        ensure!(
+            packet.next_header() == PROTOCOL_TCP,
+            "Packet does not carry a TCP frame"
+        );
+
+        Self::decode_with(packet.payload(), Some(packet.pseudo_header()))
+    }
+
+    fn decode_with(data: &BitString, pseudo_header: Option<PseudoHeader>) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= MIN_TCP_HEADER_LEN * 8,
+            "Frame of {} bits is too short to hold a TCP header",
+            data.len()
+        );
+
+        let data_offset = data.get_u8(96) >> 4;
+        let header_len = usize::from(data_offset) * 32;
+
+        ensure!(
+            data_offset >= 5,
+            "Data offset of {data_offset} words is too small to hold a TCP header"
+        );
+        ensure!(
+            data.len() >= header_len,
+            "Frame of {} bits is too short for a data offset of {data_offset} words",
+            data.len()
+        );
+        ensure!(
+            transport_checksum(data, PROTOCOL_TCP, pseudo_header) == 0,
+            "Frame has an invalid checksum"
+        );
+
+        let options = TCPOption::decode_all(
+            &data.copy_len(MIN_TCP_HEADER_LEN * 8, header_len - MIN_TCP_HEADER_LEN * 8),
+        )?;
+
+        Ok(Self {
+            source_port: data.get_u16(0),
+            target_port: data.get_u16(16),
+            sequence_num: data.get_u32(32),
+            ack_num: data.get_u32(64),
+            data_offset,
+            flag_byte: data.get_u8(104),
+            window_size: data.get_u16(112),
+            checksum: data.get_u16(128),
+            urgent_pointer: data.get_u16(144),
+            options,
+            data: data.copy_len(header_len, data.len() - header_len),
+            output_bitstring: data.clone(),
+        })
+    }
+
+    /// The frame with its checksum computed over the addresses of the
+    /// carrying IP packet.
+    #[must_use]
+    pub fn with_pseudo_header(&self, pseudo_header: PseudoHeader) -> Self {
+        let mut output_bitstring = self.output_bitstring.clone();
+        output_bitstring.set_u16(128, 0);
+
+        let checksum = transport_checksum(&output_bitstring, PROTOCOL_TCP, Some(pseudo_header));
+        output_bitstring.set_u16(128, checksum);
+
+        Self {
+            checksum,
+            output_bitstring,
+            ..self.clone()
+        }
+    }
+
+    #[must_use]
+    pub const fn source_port(&self) -> u16 {
+        self.source_port
+    }
+
+    #[must_use]
+    pub const fn target_port(&self) -> u16 {
+        self.target_port
+    }
+
+    #[must_use]
+    pub const fn sequence_num(&self) -> u32 {
+        self.sequence_num
+    }
+
+    #[must_use]
+    pub const fn ack_num(&self) -> u32 {
+        self.ack_num
+    }
+
+    /// The length of the header in 32 bit words.
+    #[must_use]
+    pub const fn data_offset(&self) -> u8 {
+        self.data_offset
+    }
+
+    #[must_use]
+    pub const fn flags(&self) -> u8 {
+        self.flag_byte
+    }
+
+    /// Whether all of the given flags are set.
+    #[must_use]
+    pub const fn has_flags(&self, flags: u8) -> bool {
+        self.flag_byte & flags == flags
+    }
+
+    #[must_use]
+    pub const fn window_size(&self) -> u16 {
+        self.window_size
+    }
+
+    #[must_use]
+    pub const fn checksum(&self) -> u16 {
+        self.checksum
+    }
+
+    #[must_use]
+    pub const fn urgent_pointer(&self) -> u16 {
+        self.urgent_pointer
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &[TCPOption] {
+        &self.options
+    }
+
+    #[must_use]
+    pub const fn data(&self) -> &BitString {
+        &self.data
+    }
+}
+
+impl Frame for TCPFrame {
+    type Builder = TCPFrameBuilder;
+
     fn setup_frames(data: BitString, builder: TCPFrameBuilder) -> Vec<Self> {
         let chunks = data.as_bit_slice().chunks(MAX_TCP_DATA_LEN);
 
@@ -258,16 +408,36 @@ impl Frame<TCPFrameBuilder> for TCPFrame {
         builder.build_all(&bundled_data)
     }
 
+    fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode(data)
+    }
+
     fn as_bit_string(&self) -> &BitString {
         &self.output_bitstring
     }
+
+    fn header_len(&self) -> usize {
+        usize::from(self.data_offset) * 4
+    }
+
+    fn payload(&self) -> &BitString {
+        &self.data
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::bit_string::BitString;
+    use crate::{bit_string::BitString, bitstring, data_link_layer::frame::Frame};
+
+    use crate::{
+        data_link_layer::frame::{
+            ipv4::{Ipv4Packet, Ipv4PacketBuilder, PseudoHeader, PROTOCOL_TCP},
+            tcp_option::{SackBlock, TCPOption},
+        },
+        ip_address::Ipv4Address,
+    };
 
-    use super::{TCPFrame, TCPFrameBuilder};
+    use super::{TCPFrame, TCPFrameBuilder, ACK, FIN, SYN};
 
     // Given
     const SOURCE_PORT: u16 = 0b1111_1111_1111_1111u16;
@@ -277,20 +447,55 @@ mod test {
     const FLAG: u8 = 0b0101_0101u8;
     const WINDOW_SIZE: u16 = 0b0011_1100_0011_1100u16;
     const URGENT_POINTER: u16 = 0b1100_0011_1100_0011u16;
-    const OPTIONS: [u32; 10] = [1, 0, 1, 0, 1, 0, 1, 0, 1, 0];
+    // Options as laid out in the header, see `options`
+    const OPTION_WORDS: [u32; 10] = [
+        0x0204_05B4, // MSS
+        0x0103_0307, // NOP, window scale
+        0x0402_080A, // SACK permitted, timestamps
+        0x0000_0001,
+        0x0000_0002,
+        0x0512_1000, // SACK
+        0x2000_3000,
+        0x4000_5000,
+        0x6000_7000,
+        0x8000_0000, // End of options
+    ];
 
     // Assumed
     const SEQUENCE_NUM1: u32 = 0b0000_0000_0000_0000_0000_0000_0000_0000u32;
     const SEQUENCE_NUM2: u32 = 0b0000_0000_0000_0000_0000_0000_0000_0001u32;
 
     // Hand calculated
-    const CHECKSUM1: u16 = 0b0010_1101_1100_0011;
-    const CHECKSUM2: u16 = 0b0010_1101_1100_0010;
+    const CHECKSUM1: u16 = 0b1101_0000_1110_0010;
+    const CHECKSUM2: u16 = 0b1101_0000_1110_0001;
 
     // Datapoints
     const DATA: [u128; 2] = [0b1011_0010_1011_1010_0100_1010_0101_1011_0110_1001_0010_1001_0110_1011_1010_1010_1001_0101_0010_1010_1101_1101_0101_0100_1010_1001_0101_0111_0101_0100_1010_1010_u128,
                              0b1001_1010_1001_0111_0100_1001_0101_0010_1010_1011_0101_0010_1010_0101_1111_0101_0101_0010_1010_1001_0101_0010_1011_0010_1010_1101_0101_1001_0011_0011_0000_1101_u128];
 
+    fn options() -> Vec<TCPOption> {
+        vec![
+            TCPOption::MaximumSegmentSize(1460),
+            TCPOption::NoOperation,
+            TCPOption::WindowScale(7),
+            TCPOption::SackPermitted,
+            TCPOption::Timestamps {
+                value: 1,
+                echo_reply: 2,
+            },
+            TCPOption::Sack(vec![
+                SackBlock {
+                    left_edge: 0x1000_2000,
+                    right_edge: 0x3000_4000,
+                },
+                SackBlock {
+                    left_edge: 0x5000_6000,
+                    right_edge: 0x7000_8000,
+                },
+            ]),
+        ]
+    }
+
     fn headers() -> Vec<TCPFrame> {
         let data_points = [BitString::new(), BitString::new()];
 
@@ -298,11 +503,10 @@ mod test {
             .set_source_port(SOURCE_PORT)
             .set_target_port(TARGET_PORT)
             .set_ack_num(ACK_NUM)
-            .set_data_offset(DATA_OFFSET)
             .set_flags(FLAG)
             .set_window_size(WINDOW_SIZE)
             .set_urgent_pointer(URGENT_POINTER)
-            .set_options(OPTIONS);
+            .set_options(options());
 
         builder.build_all(&data_points)
     }
@@ -312,11 +516,10 @@ mod test {
             .set_source_port(SOURCE_PORT)
             .set_target_port(TARGET_PORT)
             .set_ack_num(ACK_NUM)
-            .set_data_offset(DATA_OFFSET)
             .set_flags(FLAG)
             .set_window_size(WINDOW_SIZE)
             .set_urgent_pointer(URGENT_POINTER)
-            .set_options(OPTIONS);
+            .set_options(options());
 
         builder.build_all(data_points)
     }
@@ -341,16 +544,7 @@ mod test {
             first.urgent_pointer, URGENT_POINTER,
             "Failed at urgent_pointer"
         );
-        assert_eq!(first.options[0], OPTIONS[0], "Failed at options[0]");
-        assert_eq!(first.options[1], OPTIONS[1], "Failed at options[1]");
-        assert_eq!(first.options[2], OPTIONS[2], "Failed at options[2]");
-        assert_eq!(first.options[3], OPTIONS[3], "Failed at options[3]");
-        assert_eq!(first.options[4], OPTIONS[4], "Failed at options[4]");
-        assert_eq!(first.options[5], OPTIONS[5], "Failed at options[5]");
-        assert_eq!(first.options[6], OPTIONS[6], "Failed at options[6]");
-        assert_eq!(first.options[7], OPTIONS[7], "Failed at options[7]");
-        assert_eq!(first.options[8], OPTIONS[8], "Failed at options[8]");
-        assert_eq!(first.options[9], OPTIONS[9], "Failed at options[9]");
+        assert_eq!(first.options, options(), "Failed at options");
 
         let second = &headers[1];
         assert_eq!(second.source_port, SOURCE_PORT, "Failed at source_port");
@@ -368,16 +562,7 @@ mod test {
             second.urgent_pointer, URGENT_POINTER,
             "Failed at urgent_pointer"
         );
-        assert_eq!(second.options[0], OPTIONS[0], "Failed at options[0]");
-        assert_eq!(second.options[1], OPTIONS[1], "Failed at options[1]");
-        assert_eq!(second.options[2], OPTIONS[2], "Failed at options[2]");
-        assert_eq!(second.options[3], OPTIONS[3], "Failed at options[3]");
-        assert_eq!(second.options[4], OPTIONS[4], "Failed at options[4]");
-        assert_eq!(second.options[5], OPTIONS[5], "Failed at options[5]");
-        assert_eq!(second.options[6], OPTIONS[6], "Failed at options[6]");
-        assert_eq!(second.options[7], OPTIONS[7], "Failed at options[7]");
-        assert_eq!(second.options[8], OPTIONS[8], "Failed at options[8]");
-        assert_eq!(second.options[9], OPTIONS[9], "Failed at options[9]");
+        assert_eq!(second.options, options(), "Failed at options");
     }
 
     #[allow(clippy::cognitive_complexity)]
@@ -419,16 +604,56 @@ mod test {
             URGENT_POINTER,
             "Failed at urgent_pointer"
         );
-        assert_eq!(header1_bs.get_u32(160), OPTIONS[0], "Failed at options[0]");
-        assert_eq!(header1_bs.get_u32(192), OPTIONS[1], "Failed at options[1]");
-        assert_eq!(header1_bs.get_u32(224), OPTIONS[2], "Failed at options[2]");
-        assert_eq!(header1_bs.get_u32(256), OPTIONS[3], "Failed at options[3]");
-        assert_eq!(header1_bs.get_u32(288), OPTIONS[4], "Failed at options[4]");
-        assert_eq!(header1_bs.get_u32(320), OPTIONS[5], "Failed at options[5]");
-        assert_eq!(header1_bs.get_u32(352), OPTIONS[6], "Failed at options[6]");
-        assert_eq!(header1_bs.get_u32(384), OPTIONS[7], "Failed at options[7]");
-        assert_eq!(header1_bs.get_u32(416), OPTIONS[8], "Failed at options[8]");
-        assert_eq!(header1_bs.get_u32(448), OPTIONS[9], "Failed at options[9]");
+        assert_eq!(
+            header1_bs.get_u32(160),
+            OPTION_WORDS[0],
+            "Failed at options[0]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(192),
+            OPTION_WORDS[1],
+            "Failed at options[1]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(224),
+            OPTION_WORDS[2],
+            "Failed at options[2]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(256),
+            OPTION_WORDS[3],
+            "Failed at options[3]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(288),
+            OPTION_WORDS[4],
+            "Failed at options[4]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(320),
+            OPTION_WORDS[5],
+            "Failed at options[5]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(352),
+            OPTION_WORDS[6],
+            "Failed at options[6]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(384),
+            OPTION_WORDS[7],
+            "Failed at options[7]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(416),
+            OPTION_WORDS[8],
+            "Failed at options[8]"
+        );
+        assert_eq!(
+            header1_bs.get_u32(448),
+            OPTION_WORDS[9],
+            "Failed at options[9]"
+        );
 
         let header2_bs = &headers[1].output_bitstring;
         let second = &headers[1];
@@ -461,23 +686,78 @@ mod test {
             URGENT_POINTER,
             "Failed at urgent_pointer"
         );
-        assert_eq!(header2_bs.get_u32(160), OPTIONS[0], "Failed at options[0]");
-        assert_eq!(header2_bs.get_u32(192), OPTIONS[1], "Failed at options[1]");
-        assert_eq!(header2_bs.get_u32(224), OPTIONS[2], "Failed at options[2]");
-        assert_eq!(header2_bs.get_u32(256), OPTIONS[3], "Failed at options[3]");
-        assert_eq!(header2_bs.get_u32(288), OPTIONS[4], "Failed at options[4]");
-        assert_eq!(header2_bs.get_u32(320), OPTIONS[5], "Failed at options[5]");
-        assert_eq!(header2_bs.get_u32(352), OPTIONS[6], "Failed at options[6]");
-        assert_eq!(header2_bs.get_u32(384), OPTIONS[7], "Failed at options[7]");
-        assert_eq!(header2_bs.get_u32(416), OPTIONS[8], "Failed at options[8]");
-        assert_eq!(header2_bs.get_u32(448), OPTIONS[9], "Failed at options[9]");
+        assert_eq!(
+            header2_bs.get_u32(160),
+            OPTION_WORDS[0],
+            "Failed at options[0]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(192),
+            OPTION_WORDS[1],
+            "Failed at options[1]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(224),
+            OPTION_WORDS[2],
+            "Failed at options[2]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(256),
+            OPTION_WORDS[3],
+            "Failed at options[3]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(288),
+            OPTION_WORDS[4],
+            "Failed at options[4]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(320),
+            OPTION_WORDS[5],
+            "Failed at options[5]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(352),
+            OPTION_WORDS[6],
+            "Failed at options[6]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(384),
+            OPTION_WORDS[7],
+            "Failed at options[7]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(416),
+            OPTION_WORDS[8],
+            "Failed at options[8]"
+        );
+        assert_eq!(
+            header2_bs.get_u32(448),
+            OPTION_WORDS[9],
+            "Failed at options[9]"
+        );
     }
 
     #[test]
     #[allow(clippy::should_panic_without_expect)]
     #[should_panic]
-    fn too_large_data_offset() {
-        TCPFrameBuilder::new().set_data_offset(0b0001_0000u8); // 16
+    fn too_many_options() {
+        TCPFrameBuilder::new().set_options(vec![TCPOption::NoOperation; 41]);
+    }
+
+    #[test]
+    fn data_offset_from_options() {
+        let builder = TCPFrameBuilder::new()
+            .set_source_port(SOURCE_PORT)
+            .set_target_port(TARGET_PORT)
+            .set_window_size(WINDOW_SIZE)
+            .set_options(vec![TCPOption::WindowScale(3)]);
+
+        let frame = &builder.build_all(&[BitString::new()])[0];
+
+        assert_eq!(frame.data_offset, 6);
+        assert_eq!(frame.output_bitstring.len(), 24 * 8);
+        assert_eq!(frame.output_bitstring.get_u32(160), 0x0303_0300);
     }
 
     #[test]
@@ -486,14 +766,12 @@ mod test {
         let source_port = 0b0000_0000_0000_0000u16;
         let target_port = 0b0000_0000_0000_0000u16;
         let ack_num = 0b0000_0000_0000_0000_0000_0000_0000_0000u32;
-        let data_offset = 0b0000_0000u8;
         let flag = 0b0000_0000u8;
         let window_size = 0b0000_0000_0000_0000u16;
         let urgent_pointer = 0b0000_0000_0000_0000u16;
-        let options = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 
-        // Hand calculated
-        let checksum = 0b1111_1111_1111_1111;
+        // Hand calculated, only the data offset of 5 words is set
+        let checksum = 0b1010_1111_1111_1111;
 
         // Empty datapoints
         let data_points = [BitString::new()];
@@ -502,11 +780,9 @@ mod test {
             .set_source_port(source_port)
             .set_target_port(target_port)
             .set_ack_num(ack_num)
-            .set_data_offset(data_offset)
             .set_flags(flag)
             .set_window_size(window_size)
-            .set_urgent_pointer(urgent_pointer)
-            .set_options(options);
+            .set_urgent_pointer(urgent_pointer);
 
         let headers = builder.build_all(&data_points);
         let header_bs = headers[0].output_bitstring.clone();
@@ -563,53 +839,150 @@ mod test {
         );
         assert_eq!(
             frame_bs.get_u32(160),
-            frame.options[0],
+            OPTION_WORDS[0],
             "Failed at options[0]"
         );
         assert_eq!(
             frame_bs.get_u32(192),
-            frame.options[1],
+            OPTION_WORDS[1],
             "Failed at options[1]"
         );
         assert_eq!(
             frame_bs.get_u32(224),
-            frame.options[2],
+            OPTION_WORDS[2],
             "Failed at options[2]"
         );
         assert_eq!(
             frame_bs.get_u32(256),
-            frame.options[3],
+            OPTION_WORDS[3],
             "Failed at options[3]"
         );
         assert_eq!(
             frame_bs.get_u32(288),
-            frame.options[4],
+            OPTION_WORDS[4],
             "Failed at options[4]"
         );
         assert_eq!(
             frame_bs.get_u32(320),
-            frame.options[5],
+            OPTION_WORDS[5],
             "Failed at options[5]"
         );
         assert_eq!(
             frame_bs.get_u32(352),
-            frame.options[6],
+            OPTION_WORDS[6],
             "Failed at options[6]"
         );
         assert_eq!(
             frame_bs.get_u32(384),
-            frame.options[7],
+            OPTION_WORDS[7],
             "Failed at options[7]"
         );
         assert_eq!(
             frame_bs.get_u32(416),
-            frame.options[8],
+            OPTION_WORDS[8],
             "Failed at options[8]"
         );
         assert_eq!(
             frame_bs.get_u32(448),
-            frame.options[9],
+            OPTION_WORDS[9],
             "Failed at options[9]"
         );
     }
+
+    #[test]
+    fn decode_round_trip() {
+        let frames = headers_with_data(&[BitString::from(DATA), bitstring!(1, 0, 1)]);
+
+        for frame in frames {
+            let decoded = TCPFrame::decode(frame.as_bit_string()).expect("Frame is valid");
+
+            assert_eq!(decoded, frame);
+            assert_eq!(decoded.urgent_pointer(), URGENT_POINTER);
+            assert_eq!(decoded.options(), options());
+        }
+    }
+
+    #[test]
+    fn decode_without_options() {
+        let frame = TCPFrameBuilder::new()
+            .set_source_port(SOURCE_PORT)
+            .set_target_port(TARGET_PORT)
+            .set_window_size(WINDOW_SIZE)
+            .set_flags(SYN | ACK)
+            .build(BitString::from(b"odd".as_slice()));
+
+        let decoded = TCPFrame::decode(frame.as_bit_string()).expect("Frame is valid");
+
+        assert_eq!(decoded.data_offset(), 5);
+        assert!(decoded.options().is_empty());
+        assert_eq!(decoded.data(), &BitString::from(b"odd".as_slice()));
+        assert!(decoded.has_flags(SYN | ACK));
+        assert!(!decoded.has_flags(FIN));
+    }
+
+    #[test]
+    fn decode_rejects_corruption() {
+        let frames = headers_with_data(&[BitString::from(DATA)]);
+        let frame_bs = frames[0].as_bit_string();
+
+        for idx in 0..frame_bs.len() {
+            let mut corrupted = frame_bs.clone();
+            corrupted.flip_bit(idx);
+
+            assert!(TCPFrame::decode(&corrupted).is_err(), "Failed at {idx}");
+        }
+    }
+
+    #[test]
+    fn decode_rejects_bad_data_offset() {
+        let frames = headers();
+        let frame_bs = frames[0].as_bit_string();
+
+        let mut too_small = frame_bs.clone();
+        too_small.set_u8(96, 4 << 4);
+        assert!(TCPFrame::decode(&too_small).is_err());
+
+        let truncated = frame_bs.copy_len(0, frame_bs.len() - 32);
+        assert!(TCPFrame::decode(&truncated).is_err());
+
+        assert!(TCPFrame::decode(&BitString::with_zeroes(100)).is_err());
+    }
+
+    #[test]
+    fn inside_ipv4() {
+        let source = Ipv4Address::new([10, 0, 0, 1]);
+        let destination = Ipv4Address::new([10, 0, 0, 2]);
+        let pseudo_header = PseudoHeader::Ipv4 {
+            source,
+            destination,
+        };
+
+        let frame = TCPFrameBuilder::new()
+            .set_source_port(SOURCE_PORT)
+            .set_target_port(TARGET_PORT)
+            .set_window_size(WINDOW_SIZE)
+            .set_pseudo_header(pseudo_header)
+            .build(BitString::from(DATA));
+
+        let packet = Ipv4PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_protocol(PROTOCOL_TCP)
+            .build(frame.as_bit_string().clone());
+
+        let packet = Ipv4Packet::decode(packet.as_bit_string()).expect("Packet is valid");
+        assert_eq!(
+            TCPFrame::decode_ipv4(&packet).expect("Frame is valid"),
+            frame
+        );
+
+        // The checksum covers the addresses
+        assert!(TCPFrame::decode(frame.as_bit_string()).is_err());
+        let misdelivered = Ipv4PacketBuilder::new()
+            .set_source(source)
+            .set_destination(Ipv4Address::new([10, 0, 0, 3]))
+            .set_protocol(PROTOCOL_TCP)
+            .build(frame.as_bit_string().clone());
+        assert!(TCPFrame::decode_ipv4(&misdelivered).is_err());
+    }
 }
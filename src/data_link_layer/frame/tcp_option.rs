@@ -0,0 +1,321 @@
+use anyhow::{bail, ensure};
+
+use crate::bit_string::BitString;
+
+/// The maximum amount of option bytes that fit in a TCP header.
+pub const MAX_OPTIONS_LEN: usize = 40;
+
+const END_OF_OPTIONS: u8 = 0;
+const NO_OPERATION: u8 = 1;
+const MAXIMUM_SEGMENT_SIZE: u8 = 2;
+const WINDOW_SCALE: u8 = 3;
+const SACK_PERMITTED: u8 = 4;
+const SACK: u8 = 5;
+const TIMESTAMPS: u8 = 8;
+
+const MAX_SACK_BLOCKS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SackBlock {
+    pub left_edge: u32,
+    pub right_edge: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TCPOption {
+    EndOfOptions,
+    NoOperation,
+    MaximumSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    Sack(Vec<SackBlock>),
+    Timestamps {
+        value: u32,
+        echo_reply: u32,
+    },
+    /// An option this implementation doesn't know, kept as its raw data
+    /// without the kind and length bytes.
+    Unknown {
+        kind: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl TCPOption {
+    /// The amount of bytes the option takes up in the header.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Self::EndOfOptions | Self::NoOperation => 1,
+            Self::MaximumSegmentSize(_) => 4,
+            Self::WindowScale(_) => 3,
+            Self::SackPermitted => 2,
+            Self::Sack(blocks) => 2 + blocks.len() * 8,
+            Self::Timestamps { .. } => 10,
+            Self::Unknown { data, .. } => 2 + data.len(),
+        }
+    }
+
+    pub fn encode(&self, output: &mut BitString) {
+        #[allow(clippy::cast_possible_truncation)]
+        let len = self.encoded_len() as u8;
+
+        match self {
+            Self::EndOfOptions => output.append_u8(END_OF_OPTIONS),
+            Self::NoOperation => output.append_u8(NO_OPERATION),
+            Self::MaximumSegmentSize(mss) => {
+                output.append_u8(MAXIMUM_SEGMENT_SIZE);
+                output.append_u8(len);
+                output.append_u16(*mss);
+            }
+            Self::WindowScale(shift) => {
+                output.append_u8(WINDOW_SCALE);
+                output.append_u8(len);
+                output.append_u8(*shift);
+            }
+            Self::SackPermitted => {
+                output.append_u8(SACK_PERMITTED);
+                output.append_u8(len);
+            }
+            Self::Sack(blocks) => {
+                output.append_u8(SACK);
+                output.append_u8(len);
+                for block in blocks {
+                    output.append_u32(block.left_edge);
+                    output.append_u32(block.right_edge);
+                }
+            }
+            Self::Timestamps { value, echo_reply } => {
+                output.append_u8(TIMESTAMPS);
+                output.append_u8(len);
+                output.append_u32(*value);
+                output.append_u32(*echo_reply);
+            }
+            Self::Unknown { kind, data } => {
+                output.append_u8(*kind);
+                output.append_u8(len);
+                for byte in data {
+                    output.append_u8(*byte);
+                }
+            }
+        }
+    }
+
+    /// Encodes all options, padding them to a multiple of 32 bits with
+    /// zeroes, which reads as an end of options followed by padding.
+    #[must_use]
+    pub fn encode_all(options: &[Self]) -> BitString {
+        let mut output = BitString::new();
+
+        for option in options {
+            option.encode(&mut output);
+        }
+
+        output.append_zeroes((32 - output.len() % 32) % 32);
+
+        output
+    }
+
+    /// The amount of 32 bit words the options take up once padded.
+    #[must_use]
+    pub fn padded_words(options: &[Self]) -> usize {
+        options
+            .iter()
+            .map(Self::encoded_len)
+            .sum::<usize>()
+            .div_ceil(4)
+    }
+
+    /// Parses the options part of a TCP header. Parsing stops at the first end
+    /// of options, which is not included in the result, as everything after it
+    /// is padding.
+    pub fn decode_all(data: &BitString) -> anyhow::Result<Vec<Self>> {
+        ensure!(
+            data.len().is_multiple_of(8),
+            "Options of {} bits are not a whole amount of bytes",
+            data.len()
+        );
+
+        let bytes = data.as_vec_exact_u8();
+        let mut options = Vec::new();
+        let mut idx = 0;
+
+        while idx < bytes.len() {
+            let kind = bytes[idx];
+
+            match kind {
+                END_OF_OPTIONS => break,
+                NO_OPERATION => {
+                    options.push(Self::NoOperation);
+                    idx += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            ensure!(idx + 1 < bytes.len(), "Option {kind} is missing its length");
+            let len = usize::from(bytes[idx + 1]);
+            ensure!(
+                len >= 2 && idx + len <= bytes.len(),
+                "Option {kind} has an invalid length of {len}"
+            );
+
+            let option_data = &bytes[idx + 2..idx + len];
+            options.push(Self::decode(kind, option_data)?);
+
+            idx += len;
+        }
+
+        Ok(options)
+    }
+
+    fn decode(kind: u8, data: &[u8]) -> anyhow::Result<Self> {
+        let expect_len = |len: usize| {
+            ensure!(
+                data.len() == len,
+                "Option {kind} should have {len} bytes of data, found {}",
+                data.len()
+            );
+            Ok(())
+        };
+
+        let word = |idx: usize| {
+            u32::from_be_bytes([data[idx], data[idx + 1], data[idx + 2], data[idx + 3]])
+        };
+
+        let option = match kind {
+            MAXIMUM_SEGMENT_SIZE => {
+                expect_len(2)?;
+                Self::MaximumSegmentSize(u16::from_be_bytes([data[0], data[1]]))
+            }
+            WINDOW_SCALE => {
+                expect_len(1)?;
+                Self::WindowScale(data[0])
+            }
+            SACK_PERMITTED => {
+                expect_len(0)?;
+                Self::SackPermitted
+            }
+            SACK => {
+                let blocks = data.len() / 8;
+                ensure!(
+                    data.len().is_multiple_of(8) && (1..=MAX_SACK_BLOCKS).contains(&blocks),
+                    "SACK option has an invalid length of {} bytes",
+                    data.len() + 2
+                );
+
+                Self::Sack(
+                    (0..blocks)
+                        .map(|block| SackBlock {
+                            left_edge: word(block * 8),
+                            right_edge: word(block * 8 + 4),
+                        })
+                        .collect(),
+                )
+            }
+            TIMESTAMPS => {
+                expect_len(8)?;
+                Self::Timestamps {
+                    value: word(0),
+                    echo_reply: word(4),
+                }
+            }
+            END_OF_OPTIONS | NO_OPERATION => bail!("Option {kind} does not have a length"),
+            _ => Self::Unknown {
+                kind,
+                data: data.to_vec(),
+            },
+        };
+
+        Ok(option)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bit_string::BitString;
+
+    use super::{SackBlock, TCPOption};
+
+    fn all_options() -> Vec<TCPOption> {
+        vec![
+            TCPOption::MaximumSegmentSize(1460),
+            TCPOption::NoOperation,
+            TCPOption::WindowScale(7),
+            TCPOption::SackPermitted,
+            TCPOption::Timestamps {
+                value: 0xDEAD_BEEF,
+                echo_reply: 42,
+            },
+            TCPOption::Sack(vec![SackBlock {
+                left_edge: 1000,
+                right_edge: 2000,
+            }]),
+            TCPOption::Unknown {
+                kind: 30,
+                data: vec![1, 2, 3],
+            },
+        ]
+    }
+
+    #[test]
+    fn encode_known_bytes() {
+        let options = [
+            TCPOption::MaximumSegmentSize(1460),
+            TCPOption::NoOperation,
+            TCPOption::WindowScale(7),
+        ];
+
+        let encoded = TCPOption::encode_all(&options);
+
+        assert_eq!(encoded.as_vec_exact_u8(), [2, 4, 0x05, 0xB4, 1, 3, 3, 7]);
+        assert_eq!(TCPOption::padded_words(&options), 2);
+    }
+
+    #[test]
+    fn pads_to_words() {
+        let options = [TCPOption::SackPermitted, TCPOption::WindowScale(2)];
+
+        let encoded = TCPOption::encode_all(&options);
+
+        assert_eq!(encoded.as_vec_exact_u8(), [4, 2, 3, 3, 2, 0, 0, 0]);
+        assert_eq!(TCPOption::padded_words(&options), 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        let options = all_options();
+
+        let encoded = TCPOption::encode_all(&options);
+        assert_eq!(encoded.len(), TCPOption::padded_words(&options) * 32);
+
+        assert_eq!(
+            TCPOption::decode_all(&encoded).expect("Options are valid"),
+            options
+        );
+    }
+
+    #[test]
+    fn decode_stops_at_end_of_options() {
+        let bs = BitString::from([1u8, 0, 2, 4, 0, 0, 0, 0]);
+
+        assert_eq!(
+            TCPOption::decode_all(&bs).expect("Options are valid"),
+            [TCPOption::NoOperation]
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        // Length runs past the end
+        assert!(TCPOption::decode_all(&BitString::from([2u8, 8, 0, 0])).is_err());
+        // Length too small
+        assert!(TCPOption::decode_all(&BitString::from([30u8, 1, 0, 0])).is_err());
+        // Wrong length for a known option
+        assert!(TCPOption::decode_all(&BitString::from([2u8, 3, 0, 0])).is_err());
+        // Missing length
+        assert!(TCPOption::decode_all(&BitString::from([1u8, 1, 1, 2])).is_err());
+        // SACK without blocks
+        assert!(TCPOption::decode_all(&BitString::from([5u8, 2, 0, 0])).is_err());
+    }
+}
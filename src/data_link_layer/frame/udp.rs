@@ -1,28 +1,459 @@
-use crate::bit_string::BitString;
+use anyhow::ensure;
 
-use super::Frame;
+use crate::{bit_string::BitString, ip_address::Ipv4Address};
 
-#[allow(dead_code)]
-pub struct UDPBuilder {}
+use super::{
+    ipv4::{transport_checksum, Ipv4Packet, Ipv4PacketBuilder, PseudoHeader, PROTOCOL_UDP},
+    ipv6::Ipv6Packet,
+    Frame,
+};
 
-#[derive(Debug)]
-pub struct UDPFrame {}
+pub const UDP_HEADER_LEN: usize = 8;
+const MAX_UDP_DATA_LEN: usize = u16::MAX as usize - UDP_HEADER_LEN;
+
+#[derive(Debug, Clone)]
+pub struct UDPBuilder {
+    source_port: Option<u16>,
+    target_port: Option<u16>,
+
+    // Addresses of the carrying IP packet
+    pseudo_header: Option<PseudoHeader>,
+}
+
+impl UDPBuilder {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            source_port: None,
+            target_port: None,
+            pseudo_header: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn set_source_port(self, source_port: u16) -> Self {
+        Self {
+            source_port: Some(source_port),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub const fn set_target_port(self, target_port: u16) -> Self {
+        Self {
+            target_port: Some(target_port),
+            ..self
+        }
+    }
+
+    /// Includes the addresses of the carrying IP packet in the checksum.
+    #[must_use]
+    pub const fn set_pseudo_header(self, pseudo_header: PseudoHeader) -> Self {
+        Self {
+            pseudo_header: Some(pseudo_header),
+            ..self
+        }
+    }
+
+    /// Builds a datagram and wraps it in an IPv4 packet between the given
+    /// addresses.
+    pub fn build_ipv4(
+        &self,
+        source: Ipv4Address,
+        destination: Ipv4Address,
+        data: BitString,
+    ) -> Ipv4Packet {
+        let datagram = self
+            .clone()
+            .set_pseudo_header(PseudoHeader::Ipv4 {
+                source,
+                destination,
+            })
+            .build(data);
+
+        Ipv4PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_protocol(PROTOCOL_UDP)
+            .build(datagram.output_bitstring)
+    }
+
+    pub fn build_all(&self, data_points: &[BitString]) -> Vec<UDPFrame> {
+        data_points
+            .iter()
+            .map(|data| self.build(data.clone()))
+            .collect()
+    }
+
+    /// Builds a single datagram. Data that isn't a whole amount of bytes is
+    /// padded with zeroes, as the length field counts bytes.
+    pub fn build(&self, mut data: BitString) -> UDPFrame {
+        let source_port = self
+            .source_port
+            .expect("Cannot construct a UDPFrame without source port");
+        let target_port = self
+            .target_port
+            .expect("Cannot construct a UDPFrame without target port");
+
+        data.append_zeroes((8 - data.len() % 8) % 8);
+
+        let length = u16::try_from(UDP_HEADER_LEN + data.len() / 8)
+            .expect("Datagram is larger than the length field allows");
+
+        let mut output_bitstring = BitString::with_capacity(UDP_HEADER_LEN * 8 + data.len());
+
+        output_bitstring.append_u16(source_port);
+        output_bitstring.append_u16(target_port);
+        output_bitstring.append_u16(length);
+        // Checksum defaults to zero
+        output_bitstring.append_u16(0);
+        output_bitstring.append_bits(data.clone());
+
+        // -- Find checksum --
+        // A checksum of zero means no checksum was computed, so it's sent as
+        // all ones instead
+        let checksum = match transport_checksum(&output_bitstring, PROTOCOL_UDP, self.pseudo_header)
+        {
+            0 => 0xFFFF,
+            checksum => checksum,
+        };
+
+        output_bitstring.set_u16(48, checksum);
+
+        UDPFrame {
+            source_port,
+            target_port,
+            length,
+            checksum,
+            data,
+            output_bitstring,
+        }
+    }
+}
+
+impl Default for UDPBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UDPFrame {
+    // Header
+    source_port: u16,
+    target_port: u16,
+    length: u16,
+    checksum: u16,
+
+    // Data
+    data: BitString,
+
+    // Full bit_string, since it already had to be calculated for the checksum
+    output_bitstring: BitString,
+}
 
-#[allow(dead_code)]
 impl UDPFrame {
-    pub fn new(_data: &BitString) -> Self {
-        unimplemented!("Implement UDP mfer");
+    /// Parses a datagram as serialized by [`UDPBuilder`] without a pseudo
+    /// header. Anything after the length, such as link layer padding, is
+    /// ignored.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode_with(data, None)
+    }
+
+    /// Parses the datagram carried by the packet, verifying the checksum with
+    /// the addresses of the packet.
+    pub fn decode_ipv4(packet: &Ipv4Packet) -> anyhow::Result<Self> {
+        ensure!(
+            packet.protocol() == PROTOCOL_UDP,
+            "Packet does not carry a UDP datagram"
+        );
+
+        Self::decode_with(packet.payload(), Some(packet.pseudo_header()))
+    }
+
+    /// Parses the datagram carried by the packet, verifying the checksum with the
+    /// addresses of the packet.
+    pub fn decode_ipv6(packet: &Ipv6Packet) -> anyhow::Result<Self> {
+        ensure!(
+            packet.next_header() == PROTOCOL_UDP,
+            "Packet does not carry a UDP datagram"
+        );
+
+        Self::decode_with(packet.payload(), Some(packet.pseudo_header()))
+    }
+
+    fn decode_with(data: &BitString, pseudo_header: Option<PseudoHeader>) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= UDP_HEADER_LEN * 8,
+            "Datagram of {} bits is too short to hold a UDP header",
+            data.len()
+        );
+
+        let length = data.get_u16(32);
+        let len = usize::from(length) * 8;
+
+        ensure!(
+            UDP_HEADER_LEN * 8 <= len && len <= data.len(),
+            "Length of {length} bytes does not match the datagram"
+        );
+
+        let datagram = data.copy_len(0, len);
+        let checksum = datagram.get_u16(48);
+
+        // A checksum of zero means none was computed, which IPv6 doesn't allow
+        let optional = !matches!(pseudo_header, Some(PseudoHeader::Ipv6 { .. }));
+        ensure!(
+            (checksum == 0 && optional)
+                || transport_checksum(&datagram, PROTOCOL_UDP, pseudo_header) == 0,
+            "Datagram has an invalid checksum"
+        );
+
+        Ok(Self {
+            source_port: datagram.get_u16(0),
+            target_port: datagram.get_u16(16),
+            length,
+            checksum,
+            data: datagram.copy_len(UDP_HEADER_LEN * 8, len - UDP_HEADER_LEN * 8),
+            output_bitstring: datagram,
+        })
+    }
+
+    /// The datagram with its checksum computed over the addresses of the
+    /// carrying IP packet.
+    #[must_use]
+    pub fn with_pseudo_header(&self, pseudo_header: PseudoHeader) -> Self {
+        let mut output_bitstring = self.output_bitstring.clone();
+        output_bitstring.set_u16(48, 0);
+
+        let checksum =
+            match transport_checksum(&output_bitstring, PROTOCOL_UDP, Some(pseudo_header)) {
+                0 => 0xFFFF,
+                checksum => checksum,
+            };
+        output_bitstring.set_u16(48, checksum);
+
+        Self {
+            checksum,
+            output_bitstring,
+            ..self.clone()
+        }
+    }
+
+    #[must_use]
+    pub const fn source_port(&self) -> u16 {
+        self.source_port
+    }
+
+    #[must_use]
+    pub const fn target_port(&self) -> u16 {
+        self.target_port
+    }
+
+    /// The length of the datagram in bytes, header included.
+    #[must_use]
+    pub const fn length(&self) -> u16 {
+        self.length
+    }
+
+    #[must_use]
+    pub const fn checksum(&self) -> u16 {
+        self.checksum
+    }
+
+    #[must_use]
+    pub const fn data(&self) -> &BitString {
+        &self.data
     }
 }
 
-// TODO: Do this implementation
-#[allow(dead_code)]
-impl Frame<Self> for UDPFrame {
-    fn setup_frames(_data: BitString, _builder: Self) -> Vec<Self> {
-        unimplemented!("Implement UDP mfer");
+impl Frame for UDPFrame {
+    type Builder = UDPBuilder;
+
+    fn setup_frames(data: BitString, builder: UDPBuilder) -> Vec<Self> {
+        let chunks = data.as_bit_slice().chunks(MAX_UDP_DATA_LEN * 8);
+
+        let bundled_data = chunks.map(BitString::from).collect::<Vec<_>>();
+
+        builder.build_all(&bundled_data)
+    }
+
+    fn decode(data: &BitString) -> anyhow::Result<Self> {
+        Self::decode(data)
     }
 
     fn as_bit_string(&self) -> &BitString {
-        unimplemented!("Implement UDP mfer");
+        &self.output_bitstring
+    }
+
+    fn header_len(&self) -> usize {
+        UDP_HEADER_LEN
+    }
+
+    fn payload(&self) -> &BitString {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        bitstring,
+        data_link_layer::{
+            error_detection::checksum::internet_checksum,
+            frame::{
+                ipv4::{Ipv4PacketBuilder, PseudoHeader, PROTOCOL_UDP},
+                ipv6::Ipv6PacketBuilder,
+            },
+        },
+        ip_address::{Ipv4Address, Ipv6Address},
+    };
+
+    use super::{Frame, UDPBuilder, UDPFrame, MAX_UDP_DATA_LEN};
+
+    const SOURCE_PORT: u16 = 0b1111_0000_1111_0000u16;
+    const TARGET_PORT: u16 = 0b0000_0000_0011_0101u16;
+    const DATA: &[u8] = b"Hello world!";
+
+    fn builder() -> UDPBuilder {
+        UDPBuilder::new()
+            .set_source_port(SOURCE_PORT)
+            .set_target_port(TARGET_PORT)
+    }
+
+    #[test]
+    fn basic_header() {
+        let frame = builder().build(BitString::from(DATA));
+        let frame_bs = frame.as_bit_string();
+
+        assert_eq!(frame_bs.get_u16(0), SOURCE_PORT, "Failed at source_port");
+        assert_eq!(frame_bs.get_u16(16), TARGET_PORT, "Failed at target_port");
+        assert_eq!(frame_bs.get_u16(32), 8 + 12, "Failed at length");
+        assert_eq!(frame_bs.get_u16(48), frame.checksum(), "Failed at checksum");
+        assert_eq!(frame_bs.copy_len(64, 96), BitString::from(DATA));
+
+        assert_eq!(frame.source_port(), SOURCE_PORT);
+        assert_eq!(frame.target_port(), TARGET_PORT);
+        assert_eq!(frame.length(), 20);
+    }
+
+    #[test]
+    fn checksum_verifies() {
+        let frame = builder().build(BitString::from(DATA));
+
+        // Summing a datagram including its checksum gives all ones
+        let words = frame.as_bit_string().as_vec_exact_u16();
+        assert_eq!(internet_checksum(&words), 0);
+    }
+
+    #[test]
+    fn pads_to_bytes() {
+        let frame = builder().build(bitstring!(1, 0, 1));
+
+        assert_eq!(frame.length(), 9);
+        assert_eq!(frame.data(), &bitstring!(1, 0, 1, 0, 0, 0, 0, 0));
+        assert_eq!(frame.as_bit_string().len(), 72);
+    }
+
+    #[test]
+    fn splits_into_datagrams() {
+        let data = BitString::with_ones(MAX_UDP_DATA_LEN * 8 + 16);
+
+        let frames = UDPFrame::setup_frames(data, builder());
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].length(), u16::MAX);
+        assert_eq!(frames[1].length(), 8 + 2);
+        assert!(frames
+            .iter()
+            .all(|frame| frame.target_port() == TARGET_PORT));
+    }
+
+    #[test]
+    #[allow(clippy::should_panic_without_expect)]
+    #[should_panic]
+    fn missing_port() {
+        UDPBuilder::new()
+            .set_source_port(SOURCE_PORT)
+            .build(BitString::new());
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let frame = builder().build(BitString::from(DATA));
+
+        let mut bs = frame.as_bit_string().clone();
+        bs.append_zeroes(16);
+
+        assert_eq!(UDPFrame::decode(&bs).expect("Datagram is valid"), frame);
+
+        let mut corrupted = frame.as_bit_string().clone();
+        corrupted.flip_bit(70);
+        assert!(UDPFrame::decode(&corrupted).is_err());
+    }
+
+    #[test]
+    fn inside_ipv4() {
+        let source = Ipv4Address::new([10, 0, 0, 1]);
+        let destination = Ipv4Address::new([10, 0, 0, 2]);
+
+        let frame = builder()
+            .set_pseudo_header(PseudoHeader::Ipv4 {
+                source,
+                destination,
+            })
+            .build(BitString::from(DATA));
+
+        let packet = Ipv4PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_protocol(PROTOCOL_UDP)
+            .build(frame.as_bit_string().clone());
+
+        assert_eq!(
+            UDPFrame::decode_ipv4(&packet).expect("Datagram is valid"),
+            frame
+        );
+        assert_eq!(
+            builder().build_ipv4(source, destination, BitString::from(DATA)),
+            packet
+        );
+
+        let spoofed = Ipv4PacketBuilder::new()
+            .set_source(Ipv4Address::new([10, 0, 0, 3]))
+            .set_destination(destination)
+            .set_protocol(PROTOCOL_UDP)
+            .build(frame.as_bit_string().clone());
+        assert!(UDPFrame::decode_ipv4(&spoofed).is_err());
+    }
+
+    #[test]
+    fn inside_ipv6() {
+        let source = Ipv6Address::from_u128(0x2001_0DB8 << 96 | 1);
+        let destination = Ipv6Address::from_u128(0x2001_0DB8 << 96 | 2);
+
+        let frame = builder()
+            .set_pseudo_header(PseudoHeader::Ipv6 {
+                source,
+                destination,
+            })
+            .build(BitString::from(DATA));
+
+        let packet_builder = Ipv6PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_next_header(PROTOCOL_UDP);
+
+        let packet = packet_builder.build(frame.as_bit_string().clone());
+        assert_eq!(
+            UDPFrame::decode_ipv6(&packet).expect("Datagram is valid"),
+            frame
+        );
+
+        // The checksum is mandatory over IPv6
+        let mut unchecked = frame.as_bit_string().clone();
+        unchecked.set_u16(48, 0);
+        assert!(UDPFrame::decode(&unchecked).is_ok());
+        assert!(UDPFrame::decode_ipv6(&packet_builder.build(unchecked)).is_err());
     }
 }
@@ -0,0 +1,132 @@
+use crate::bit_string::BitString;
+
+// Tag protocol identifiers
+pub const TPID_CUSTOMER: u16 = 0x8100;
+pub const TPID_SERVICE: u16 = 0x88A8;
+
+pub const MAX_VID: u16 = 0x0FFF;
+
+/// An 802.1Q tag as inserted between the source address and the EtherType.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VlanTag {
+    tpid: u16,
+    pcp: u8,
+    dei: bool,
+    vid: u16,
+}
+
+impl VlanTag {
+    /// A customer tag for the given VLAN.
+    #[must_use]
+    pub const fn new(vid: u16) -> Self {
+        assert!(vid <= MAX_VID, "VLAN id does not fit in 12 bits");
+        Self {
+            tpid: TPID_CUSTOMER,
+            pcp: 0,
+            dei: false,
+            vid,
+        }
+    }
+
+    /// A service tag for the given VLAN, used as the outer tag with QinQ.
+    #[must_use]
+    pub const fn service(vid: u16) -> Self {
+        Self::new(vid).set_tpid(TPID_SERVICE)
+    }
+
+    #[must_use]
+    pub const fn set_tpid(self, tpid: u16) -> Self {
+        Self { tpid, ..self }
+    }
+
+    #[must_use]
+    pub const fn set_pcp(self, pcp: u8) -> Self {
+        assert!(pcp <= 0b111, "Priority code point does not fit in 3 bits");
+        Self { pcp, ..self }
+    }
+
+    #[must_use]
+    pub const fn set_dei(self, dei: bool) -> Self {
+        Self { dei, ..self }
+    }
+
+    #[must_use]
+    pub const fn tpid(&self) -> u16 {
+        self.tpid
+    }
+
+    #[must_use]
+    pub const fn pcp(&self) -> u8 {
+        self.pcp
+    }
+
+    #[must_use]
+    pub const fn dei(&self) -> bool {
+        self.dei
+    }
+
+    #[must_use]
+    pub const fn vid(&self) -> u16 {
+        self.vid
+    }
+
+    #[must_use]
+    pub const fn is_tpid(tpid: u16) -> bool {
+        tpid == TPID_CUSTOMER || tpid == TPID_SERVICE
+    }
+
+    pub fn encode(&self, output: &mut BitString) {
+        let tci = u16::from(self.pcp) << 13 | u16::from(self.dei) << 12 | self.vid;
+
+        output.append_u16(self.tpid);
+        output.append_u16(tci);
+    }
+
+    /// Reads the 32 bit tag starting at the given index.
+    #[must_use]
+    pub fn decode(data: &BitString, index: usize) -> Self {
+        let tci = data.get_u16(index + 16);
+
+        #[allow(clippy::cast_possible_truncation)]
+        Self {
+            tpid: data.get_u16(index),
+            pcp: (tci >> 13) as u8,
+            dei: tci & (0b1 << 12) != 0,
+            vid: tci & MAX_VID,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bit_string::BitString;
+
+    use super::{VlanTag, TPID_SERVICE};
+
+    #[test]
+    fn encode_tag() {
+        let tag = VlanTag::new(0x123).set_pcp(5).set_dei(true);
+
+        let mut bs = BitString::new();
+        tag.encode(&mut bs);
+
+        assert_eq!(bs.get_u32(0), 0x8100_B123);
+        assert_eq!(VlanTag::decode(&bs, 0), tag);
+    }
+
+    #[test]
+    fn service_tag() {
+        let tag = VlanTag::service(42);
+
+        assert_eq!(tag.tpid(), TPID_SERVICE);
+        assert_eq!(tag.vid(), 42);
+        assert!(VlanTag::is_tpid(tag.tpid()));
+    }
+
+    #[test]
+    #[allow(clippy::should_panic_without_expect)]
+    #[should_panic]
+    fn vid_too_large() {
+        let _ = VlanTag::new(0x1000);
+    }
+}
@@ -1,46 +1,285 @@
+pub mod arq;
 pub(crate) mod bit_stuffing;
 pub(crate) mod crc;
-pub(crate) mod frame;
+pub mod crc_analysis;
+pub mod error_detection;
+pub mod fec;
+pub mod frame;
 
 use std::{
+    fmt::{self, Debug},
     marker::PhantomData,
-    sync::{Arc, Mutex},
+    mem,
+    sync::{mpsc::Receiver, Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-use crate::{bit_string::BitString, mac_address::MacAddress, physical_layer::cable::Cable};
+use anyhow::{bail, ensure};
+
+use crate::{
+    bit::Bit,
+    bit_string::BitString,
+    mac_address::MacAddress,
+    physical_layer::cable::{Cable, CableContext},
+};
 
 use self::{
-    bit_stuffing::prepare_bits,
+    arq::{Arq, ArqReceiver, ArqSender, DEFAULT_RETRANSMIT_TIMEOUT, MAX_RETRANSMISSIONS},
+    bit_stuffing::{prepare_bits, unprepare_bits, Delimiter},
+    error_detection::{Crc, ErrorDetection},
+    fec::{ForwardErrorCorrection, Interleaver},
     frame::{
-        tcp::{TCPFrame, TCPFrameBuilder},
+        tcp::{TCPFrame, TCPFrameBuilder, ACK, MIN_TCP_HEADER_LEN},
+        udp::{UDPBuilder, UDPFrame},
         Frame,
     },
 };
 
-pub struct DataLinkLayer<B, F: Frame<B>> {
+pub struct DataLinkLayer<F: Frame> {
+    // The code used for the frame check sequence
+    error_detection: Box<dyn ErrorDetection>,
+    // The code applied to framed bits right before they go on the cable
+    fec: Option<Box<dyn ForwardErrorCorrection>>,
+    // Spreads the framed and coded bits over the transmission
+    interleaver: Option<Box<dyn Interleaver>>,
+    dropped_frames: usize,
+    corrected_errors: usize,
+
+    // Splits the bits arriving from the cable into frames
+    delimiter: Delimiter,
+    // Reliable transfers, in either direction
+    arq: Arq,
+    retransmit_timeout: Duration,
+    sender: Option<Box<dyn ArqSender>>,
+    // The number of the transfer being sent
+    transfer: u32,
+    // The transfer being received, along with its number
+    receiver: Option<(u32, Box<dyn ArqReceiver>)>,
+    received: BitString,
+    // Timer expiries since the last acknowledgement that made progress
+    timeouts: usize,
+    // Whether a transfer was given up on without the caller being told yet
+    gave_up: bool,
+
     frame_type: PhantomData<F>,
-    builder_type: PhantomData<B>,
 }
 
-impl<B, F: Frame<B>> Default for DataLinkLayer<B, F> {
+impl<F: Frame> Default for DataLinkLayer<F> {
     fn default() -> Self {
         Self {
+            error_detection: Box::new(Crc::crc_32()),
+            fec: None,
+            interleaver: None,
+            dropped_frames: 0,
+            corrected_errors: 0,
+            delimiter: Delimiter::default(),
+            arq: Arq::default(),
+            retransmit_timeout: DEFAULT_RETRANSMIT_TIMEOUT,
+            sender: None,
+            transfer: 0,
+            receiver: None,
+            received: BitString::new(),
+            timeouts: 0,
+            gave_up: false,
             frame_type: PhantomData::<F>,
-            builder_type: PhantomData::<B>,
         }
     }
 }
 
-impl DataLinkLayer<TCPFrameBuilder, TCPFrame> {
+impl<F: Frame> Debug for DataLinkLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataLinkLayer")
+            .field("dropped_frames", &self.dropped_frames)
+            .field("corrected_errors", &self.corrected_errors)
+            .field("arq", &self.arq)
+            .field("retransmit_timeout", &self.retransmit_timeout)
+            .field("sender", &self.sender)
+            .field("receiver", &self.receiver)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: Frame> DataLinkLayer<F> {
+    /// Sets the code used to compute the frame check sequence, CRC-32 by
+    /// default.
     #[must_use]
-    pub const fn new() -> Self {
+    pub fn set_error_detection<E>(self, error_detection: E) -> Self
+    where
+        E: ErrorDetection + 'static,
+    {
         Self {
-            frame_type: PhantomData::<TCPFrame>,
-            builder_type: PhantomData::<TCPFrameBuilder>,
+            error_detection: Box::new(error_detection),
+            ..self
+        }
+    }
+
+    /// Sets the forward error correction applied to framed bits before they
+    /// are put on the cable. No code is applied by default.
+    #[must_use]
+    pub fn set_fec<C>(self, fec: C) -> Self
+    where
+        C: ForwardErrorCorrection + 'static,
+    {
+        Self {
+            fec: Some(Box::new(fec)),
+            ..self
+        }
+    }
+
+    /// Sets the interleaver applied to the framed bits, after the forward
+    /// error correction if there is any. Nothing is interleaved by default.
+    #[must_use]
+    pub fn set_interleaver<I>(self, interleaver: I) -> Self
+    where
+        I: Interleaver + 'static,
+    {
+        Self {
+            interleaver: Some(Box::new(interleaver)),
+            ..self
+        }
+    }
+
+    /// The amount of received frames that failed the frame check sequence and
+    /// were dropped.
+    #[must_use]
+    pub const fn dropped_frames(&self) -> usize {
+        self.dropped_frames
+    }
+
+    /// The amount of bit errors repaired by the forward error correction.
+    #[must_use]
+    pub const fn corrected_errors(&self) -> usize {
+        self.corrected_errors
+    }
+
+    /// Appends the frame check sequence and prepares the bits to be put on a
+    /// cable.
+    fn frame_bits(&self, data: BitString) -> BitString {
+        let data = self.error_detection.add(data);
+        let data = prepare_bits(data);
+
+        let data = match &self.fec {
+            Some(fec) => fec.encode(data),
+            None => data,
+        };
+
+        match &self.interleaver {
+            Some(interleaver) => interleaver.interleave(data),
+            None => data,
         }
     }
 
+    /// Whether the bits on the cable no longer show the flags of the frame.
+    const fn hides_flags(&self) -> bool {
+        self.fec.is_some() || self.interleaver.is_some()
+    }
+
+    /// Frames the bits to be sent in a stream of frames, which the receiver
+    /// splits up by their flags. Coded frames are surrounded by another set of
+    /// flags, as coding and interleaving hide the flags of the frame.
+    fn stream_bits(&self, data: BitString) -> BitString {
+        let data = self.frame_bits(data);
+
+        if self.hides_flags() {
+            prepare_bits(data)
+        } else {
+            data
+        }
+    }
+
+    /// Undoes the framing of [`Self::stream_bits`], see
+    /// [`Self::deframe_bits`].
+    fn unstream_bits(&mut self, data: BitString) -> Option<BitString> {
+        let data = if self.hides_flags() {
+            unprepare_bits(data).ok()
+        } else {
+            Some(data)
+        };
+
+        match data {
+            Some(data) => self.deframe_bits(data),
+            None => {
+                self.dropped_frames += 1;
+                None
+            }
+        }
+    }
+
+    /// Undoes the framing of [`Self::frame_bits`], verifying and stripping the
+    /// frame check sequence. Frames that fail verification are counted and
+    /// dropped, in which case [`None`] is returned.
+    pub fn deframe_bits(&mut self, data: BitString) -> Option<BitString> {
+        let data = match &self.interleaver {
+            Some(interleaver) => interleaver.deinterleave(data),
+            None => Ok(data),
+        };
+
+        let data = data.and_then(|data| match &self.fec {
+            Some(fec) => fec.decode(data).map(|decoded| {
+                self.corrected_errors += decoded.corrected;
+                decoded.data
+            }),
+            None => Ok(data),
+        });
+
+        let frame = data
+            .and_then(unprepare_bits)
+            .and_then(|data| self.error_detection.check_and_remove(data));
+
+        match frame {
+            Ok(frame) => Some(frame),
+            Err(_) => {
+                self.dropped_frames += 1;
+                None
+            }
+        }
+    }
+}
+
+impl DataLinkLayer<TCPFrame> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the protocol used for reliable transfers, Go-Back-N by default.
+    /// Both ends of the cable have to use the same one.
+    #[must_use]
+    pub fn set_arq(self, arq: Arq) -> Self {
+        Self { arq, ..self }
+    }
+
+    /// Sets how long to wait for an acknowledgement before sending frames
+    /// again.
+    #[must_use]
+    pub fn set_retransmit_timeout(self, retransmit_timeout: Duration) -> Self {
+        Self {
+            retransmit_timeout,
+            ..self
+        }
+    }
+
+    /// The transfer being sent, if any was started.
+    #[must_use]
+    pub fn sender(&self) -> Option<&dyn ArqSender> {
+        self.sender.as_deref()
+    }
+
+    /// Whether a transfer is still waiting to be acknowledged.
+    #[must_use]
+    pub fn is_sending(&self) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| !sender.is_finished())
+    }
+
+    /// Starts sending the data reliably with the configured protocol, putting
+    /// the first window of frames on the cable. The window size is the amount
+    /// of unacknowledged frames in flight, the sequence space grows with it as
+    /// the protocol requires. Every frame carries as much data as the MTU of
+    /// the cable allows. The rest of the transfer is driven by [`Self::poll`].
     pub fn send_bits(
+        &mut self,
         window_size: u16,
         source_mac: MacAddress,
         source_port: u16,
@@ -48,37 +287,242 @@ impl DataLinkLayer<TCPFrameBuilder, TCPFrame> {
         cable: &Arc<Mutex<Cable>>,
         data: BitString,
     ) -> anyhow::Result<()> {
+        ensure!(!self.is_sending(), "A transfer is still being sent");
+        ensure!(window_size > 0, "The window holds at least one frame");
+
+        self.transfer = self.transfer.wrapping_add(1);
         let tcp_builder = TCPFrameBuilder::new()
             .set_source_port(source_port)
             .set_target_port(target_port)
+            .set_ack_num(self.transfer)
             .set_window_size(window_size);
 
-        let data: Vec<TCPFrame> = TCPFrame::setup_frames(data, tcp_builder);
+        let mtu = cable.lock().expect("The cable should never panic").mtu();
+        let frame_data_len = (usize::from(mtu) - MIN_TCP_HEADER_LEN) * 8;
 
-        Self::sliding_window(
+        let mut data_points: Vec<BitString> = data
+            .as_bit_slice()
+            .chunks(frame_data_len)
+            .map(BitString::from)
+            .collect();
+        // Even an empty transfer has to be acknowledged
+        if data_points.is_empty() {
+            data_points.push(BitString::new());
+        }
+
+        self.sender = Some(self.arq.sender(
+            tcp_builder,
+            data_points,
             window_size,
+            self.retransmit_timeout,
+        ));
+        self.timeouts = 0;
+
+        self.sliding_window(source_mac, cable)
+    }
+
+    /// Handles the bits that arrived on the receiver since the last call, see
+    /// [`Self::receive`]. Only works if the cable is the only one delivering
+    /// to the receiver.
+    pub fn poll(
+        &mut self,
+        source_mac: MacAddress,
+        cable: &Arc<Mutex<Cable>>,
+        receiver: &Receiver<CableContext>,
+    ) -> anyhow::Result<Option<BitString>> {
+        self.receive(
+            receiver.try_iter().map(|context| context.bit),
             source_mac,
-            source_port,
-            target_port,
             cable,
-            &data,
         )
     }
 
+    /// Handles bits that arrived over the cable. Data frames are acknowledged
+    /// over the cable, acknowledgements move the transfer being sent along,
+    /// after which any frames that are due are sent. Returns the data of a
+    /// received transfer once all of it is in.
+    ///
+    /// Fails once the transfer being sent is given up on, after which the
+    /// link can send again. A received transfer is returned first, the
+    /// failure is then reported by the next call.
+    pub fn receive<I>(
+        &mut self,
+        bits: I,
+        source_mac: MacAddress,
+        cable: &Arc<Mutex<Cable>>,
+    ) -> anyhow::Result<Option<BitString>>
+    where
+        I: IntoIterator<Item = Bit>,
+    {
+        let mut transfer = None;
+
+        for bit in bits {
+            let Some(bits) = self.delimiter.push(bit) else {
+                continue;
+            };
+            let Some(frame) = self
+                .unstream_bits(bits)
+                .and_then(|bits| TCPFrame::decode(&bits).ok())
+            else {
+                self.reject_frame(source_mac, cable)?;
+                continue;
+            };
+
+            if frame.has_flags(ACK) {
+                let current = frame.sequence_num() == self.transfer;
+                if let Some(sender) = self.sender.as_mut().filter(|_| current) {
+                    let acknowledged = sender.acknowledged();
+                    sender.receive(&frame, Instant::now());
+
+                    if sender.acknowledged() > acknowledged {
+                        self.timeouts = 0;
+                    }
+                }
+            } else if let Some(data) = self.receive_frame(&frame, source_mac, cable)? {
+                transfer = Some(data);
+            }
+        }
+
+        self.sliding_window(source_mac, cable)?;
+
+        if transfer.is_none() && mem::take(&mut self.gave_up) {
+            bail!("Gave up after {MAX_RETRANSMISSIONS} retransmissions without progress");
+        }
+
+        Ok(transfer)
+    }
+
+    /// Sends the frames of the transfer that are due, dropping the transfer
+    /// once the timer expired too often without any progress.
     fn sliding_window(
-        window_size: u16,
+        &mut self,
+        source_mac: MacAddress,
+        cable: &Arc<Mutex<Cable>>,
+    ) -> anyhow::Result<()> {
+        let Some(sender) = &mut self.sender else {
+            return Ok(());
+        };
+
+        let retransmissions = sender.retransmissions();
+        let frames = sender.poll(Instant::now());
+
+        if sender.retransmissions() > retransmissions {
+            self.timeouts += 1;
+        }
+        if self.timeouts > MAX_RETRANSMISSIONS {
+            self.sender = None;
+            self.timeouts = 0;
+            self.gave_up = true;
+            return Ok(());
+        }
+
+        for frame in &frames {
+            self.send_frame(frame, source_mac, cable)?;
+        }
+
+        Ok(())
+    }
+
+    /// Acknowledges a data frame, returning the data of the transfer once its
+    /// last frame is in.
+    fn receive_frame(
+        &mut self,
+        frame: &TCPFrame,
+        source_mac: MacAddress,
+        cable: &Arc<Mutex<Cable>>,
+    ) -> anyhow::Result<Option<BitString>> {
+        // A frame of another transfer starts receiving that one, as the cable
+        // keeps frames in order and the sender only moves on once the
+        // transfer before is acknowledged
+        let transfer = frame.ack_num();
+        if self
+            .receiver
+            .as_ref()
+            .is_none_or(|(receiving, _)| *receiving != transfer)
+        {
+            // Acknowledgements go back the way the frame came, the window of
+            // the sender determines the sequence space
+            let ack_builder = TCPFrameBuilder::new()
+                .set_source_port(frame.target_port())
+                .set_target_port(frame.source_port())
+                .set_sequence_num(transfer)
+                .set_window_size(frame.window_size());
+
+            self.receiver = Some((
+                transfer,
+                self.arq.receiver(ack_builder, frame.window_size()),
+            ));
+            self.received = BitString::new();
+        }
+        let (_, receiver) = self.receiver.as_mut().expect("A receiver was just started");
+
+        let (ack, data) = receiver.receive(frame);
+        let finished = !data.is_empty() && receiver.is_finished();
+
+        if let Some(ack) = ack {
+            self.send_frame(&ack, source_mac, cable)?;
+        }
+
+        for data in data {
+            self.received.append_bits(data);
+        }
+
+        Ok(finished.then(|| mem::take(&mut self.received)))
+    }
+
+    /// Answers a damaged frame with a negative acknowledgement, if the
+    /// protocol of the transfer being received uses them.
+    fn reject_frame(
+        &mut self,
+        source_mac: MacAddress,
+        cable: &Arc<Mutex<Cable>>,
+    ) -> anyhow::Result<()> {
+        match self
+            .receiver
+            .as_mut()
+            .and_then(|(_, receiver)| receiver.damaged())
+        {
+            Some(nak) => self.send_frame(&nak, source_mac, cable),
+            None => Ok(()),
+        }
+    }
+
+    fn send_frame(
+        &self,
+        frame: &TCPFrame,
+        source_mac: MacAddress,
+        cable: &Arc<Mutex<Cable>>,
+    ) -> anyhow::Result<()> {
+        let data = self.stream_bits(frame.as_bit_string().clone());
+
+        cable
+            .lock()
+            .expect("The cable should never panic")
+            .send_bits(source_mac, frame.source_port(), frame.target_port(), data)
+    }
+}
+
+impl DataLinkLayer<UDPFrame> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the data as datagrams without waiting for any acknowledgement.
+    pub fn send_bits(
+        &self,
         source_mac: MacAddress,
         source_port: u16,
         target_port: u16,
         cable: &Arc<Mutex<Cable>>,
-        data: &[TCPFrame],
+        data: BitString,
     ) -> anyhow::Result<()> {
-        let windows = data.windows(window_size.into());
+        let udp_builder = UDPBuilder::new()
+            .set_source_port(source_port)
+            .set_target_port(target_port);
 
-        // TODO: Fix this implementation
-        for window in windows {
-            let data = window[0].as_bit_string().clone();
-            let data = prepare_bits(data);
+        for frame in UDPFrame::setup_frames(data, udp_builder) {
+            let data = self.frame_bits(frame.as_bit_string().clone());
             cable
                 .lock()
                 .expect("The cable should never panic")
@@ -88,3 +532,131 @@ impl DataLinkLayer<TCPFrameBuilder, TCPFrame> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{bit_string::BitString, bitstring, corruption_type::Corruption, rand::XorShift};
+
+    use super::{
+        error_detection::{Adler32, Crc, InternetChecksum, TwoDimensionalParity},
+        fec::{BlockInterleaver, Convolutional, Hamming, ReedSolomon},
+        frame::tcp::TCPFrame,
+        DataLinkLayer,
+    };
+
+    const DATA: &[u8] = b"Hello world!";
+
+    #[test]
+    fn deframe_clean() {
+        let mut dll = DataLinkLayer::<TCPFrame>::new();
+        let data = BitString::from(DATA);
+
+        let framed = dll.frame_bits(data.clone());
+
+        assert_eq!(dll.deframe_bits(framed), Some(data));
+        assert_eq!(dll.dropped_frames(), 0);
+    }
+
+    #[test]
+    fn deframe_custom_generator() {
+        let mut dll =
+            DataLinkLayer::<TCPFrame>::new().set_error_detection(Crc::new(bitstring!(1, 0, 1)));
+        let data = BitString::from(DATA);
+
+        let framed = dll.frame_bits(data.clone());
+
+        assert_eq!(framed.len(), data.len() + 2 + 16);
+        assert_eq!(dll.deframe_bits(framed), Some(data));
+    }
+
+    #[test]
+    fn deframe_other_codes() {
+        let data = BitString::from(DATA);
+
+        let mut dlls = [
+            DataLinkLayer::<TCPFrame>::new().set_error_detection(InternetChecksum),
+            DataLinkLayer::<TCPFrame>::new().set_error_detection(Adler32),
+            DataLinkLayer::<TCPFrame>::new().set_error_detection(TwoDimensionalParity::new(8)),
+        ];
+
+        for dll in &mut dlls {
+            let framed = dll.frame_bits(data.clone());
+            assert_eq!(dll.deframe_bits(framed), Some(data.clone()));
+        }
+    }
+
+    #[test]
+    fn deframe_with_fec() {
+        let data = BitString::from(DATA);
+
+        let mut dlls = [
+            DataLinkLayer::<TCPFrame>::new().set_fec(Hamming::secded(4)),
+            DataLinkLayer::<TCPFrame>::new().set_fec(ReedSolomon::new(32, 24)),
+            DataLinkLayer::<TCPFrame>::new().set_fec(Convolutional::nasa_k7()),
+        ];
+
+        for dll in &mut dlls {
+            let mut framed = dll.frame_bits(data.clone());
+            framed.flip_bit(20);
+
+            assert_eq!(dll.deframe_bits(framed), Some(data.clone()));
+            assert_eq!(dll.corrected_errors(), 1);
+            assert_eq!(dll.dropped_frames(), 0);
+        }
+    }
+
+    #[test]
+    fn deframe_with_interleaver() {
+        let mut dll =
+            DataLinkLayer::<TCPFrame>::new().set_interleaver(BlockInterleaver::new(8, 16));
+        let data = BitString::from(DATA);
+
+        // Framing output is interleaved even without a code
+        let framed = dll.frame_bits(data.clone());
+        let plain = DataLinkLayer::<TCPFrame>::new().frame_bits(data.clone());
+        assert_eq!(framed.len(), plain.len());
+        assert_ne!(framed, plain);
+
+        assert_eq!(dll.deframe_bits(framed), Some(data));
+        assert_eq!(dll.dropped_frames(), 0);
+    }
+
+    #[test]
+    fn deframe_drops_corrupted() {
+        let mut dll = DataLinkLayer::<TCPFrame>::new();
+        let data = BitString::from(DATA);
+        let framed = dll.frame_bits(data.clone());
+
+        let mut dropped = 0;
+        for idx in 0..framed.len() {
+            let mut corrupted = framed.clone();
+            corrupted.flip_bit(idx);
+
+            // Flipping a stuffed bit doesn't change the data, so that frame may
+            // be delivered. Corrupted data must never be delivered.
+            match dll.deframe_bits(corrupted) {
+                Some(received) => assert_eq!(received, data, "Failed at index {idx}"),
+                None => dropped += 1,
+            }
+        }
+
+        assert!(dropped > 0);
+        assert_eq!(dll.dropped_frames(), dropped);
+    }
+
+    #[test]
+    fn deframe_drops_burst() {
+        let mut dll = DataLinkLayer::<TCPFrame>::new();
+        let mut corruption = Corruption::BurstFlip(XorShift::new(42));
+        let data = BitString::from(DATA);
+
+        for _ in 0..10 {
+            let framed = dll.frame_bits(data.clone());
+            let corrupted = corruption.corrupt_borrow(framed);
+
+            assert_ne!(dll.deframe_bits(corrupted), Some(data.clone()));
+        }
+
+        assert_eq!(dll.dropped_frames(), 10);
+    }
+}
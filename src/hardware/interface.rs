@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use anyhow::Context;
+
+use crate::{
+    bit::Bit,
+    bit_string::BitString,
+    data_link_layer::{
+        frame::{
+            ethernet::EthernetFrame,
+            ipv4::{Ipv4Packet, PROTOCOL_ICMP, PROTOCOL_TCP, PROTOCOL_UDP},
+            tcp::TCPFrame,
+        },
+        DataLinkLayer,
+    },
+    network_layer::{
+        arp::Arp,
+        fragmentation::{fragment_for, Egress, Reassembler},
+        icmp::{IcmpMessage, CODE_PROTOCOL_UNREACHABLE, CODE_REASSEMBLY_TIME_EXCEEDED},
+        ndp::Ndp,
+    },
+    physical_layer::cable::{Cable, CableContext},
+    utils::{
+        ip_address::{Ipv4Address, Ipv6Address},
+        mac_address::MacAddress,
+    },
+};
+
+/// The attachment of a node to the network: the channel the cables deliver
+/// bits on, a link layer for every cable, and the addresses and packet state
+/// of the network layer on top.
+#[derive(Debug)]
+pub struct Interface {
+    mac: MacAddress,
+    receiver: Receiver<CableContext>,
+    transmitter: Arc<Sender<CableContext>>,
+    // The cable to every neighbor and the link layer running over it, by the
+    // address of the neighbor
+    links: HashMap<MacAddress, (Arc<Mutex<Cable>>, DataLinkLayer<TCPFrame>)>,
+    arp: Arp,
+    ndp: Ndp,
+    reassembler: Reassembler,
+}
+
+impl Interface {
+    #[must_use]
+    pub fn new(mac: MacAddress) -> Self {
+        let (tx, rx) = channel::<CableContext>();
+
+        Self {
+            mac,
+            receiver: rx,
+            transmitter: tx.into(),
+            links: HashMap::new(),
+            arp: Arp::new(mac),
+            ndp: Ndp::new(mac),
+            reassembler: Reassembler::default(),
+        }
+    }
+
+    #[must_use]
+    pub const fn mac(&self) -> &MacAddress {
+        &self.mac
+    }
+
+    pub(crate) fn transmitter(&self) -> Arc<Sender<CableContext>> {
+        self.transmitter.clone()
+    }
+
+    /// Runs the link layer over the cable for reliable transfers with the
+    /// node at its other end, replacing any link to that node.
+    pub fn add_link(
+        &mut self,
+        cable: Arc<Mutex<Cable>>,
+        data_link_layer: DataLinkLayer<TCPFrame>,
+    ) -> anyhow::Result<()> {
+        let peer = cable
+            .lock()
+            .expect("The cable should never panic")
+            .peer(&self.mac)
+            .context("Cable does not connect this interface")?;
+
+        self.links.insert(peer, (cable, data_link_layer));
+        Ok(())
+    }
+
+    /// The link layer running over the cable to the given node.
+    #[must_use]
+    pub fn data_link_layer(&self, peer: &MacAddress) -> Option<&DataLinkLayer<TCPFrame>> {
+        self.links
+            .get(peer)
+            .map(|(_, data_link_layer)| data_link_layer)
+    }
+
+    /// Starts sending the data reliably to the given node, see
+    /// [`DataLinkLayer::send_bits`]. The transfer is driven by
+    /// [`Self::receive_bits`].
+    pub fn send_bits(
+        &mut self,
+        window_size: u16,
+        source_port: u16,
+        target_port: u16,
+        peer: &MacAddress,
+        data: BitString,
+    ) -> anyhow::Result<()> {
+        let (cable, data_link_layer) = self.links.get_mut(peer).context("No link to the node")?;
+
+        data_link_layer.send_bits(window_size, self.mac, source_port, target_port, cable, data)
+    }
+
+    /// Reads the bits that arrived, handing them to the link of the node that
+    /// sent them. Bits from nodes without a link are dropped. Data frames are
+    /// checked, put back in order and acknowledged over the cable they came
+    /// from, while the transfers being sent move along. Returns the data of
+    /// every transfer that is now complete, along with the node that sent it,
+    /// and the failure of every link that failed. A failing link doesn't keep
+    /// the others from being read.
+    pub fn receive_bits(&mut self) -> Vec<(MacAddress, anyhow::Result<BitString>)> {
+        let mut arrived: HashMap<MacAddress, Vec<Bit>> = HashMap::new();
+        for context in self.receiver.try_iter() {
+            arrived
+                .entry(context.source_mac)
+                .or_default()
+                .push(context.bit);
+        }
+
+        // Every link is polled, as its timers run even without any bits
+        self.links
+            .iter_mut()
+            .filter_map(|(peer, (cable, data_link_layer))| {
+                let bits = arrived.remove(peer).unwrap_or_default();
+
+                data_link_layer
+                    .receive(bits, self.mac, cable)
+                    .transpose()
+                    .map(|result| (*peer, result))
+            })
+            .collect()
+    }
+
+    /// Assigns an address to the interface, returning a gratuitous ARP frame
+    /// announcing it.
+    pub fn set_ip_address(&mut self, ip: Ipv4Address) -> EthernetFrame {
+        self.arp.set_ip_address(ip)
+    }
+
+    #[must_use]
+    pub const fn arp(&self) -> &Arp {
+        &self.arp
+    }
+
+    pub fn arp_mut(&mut self) -> &mut Arp {
+        &mut self.arp
+    }
+
+    /// Adds an IPv6 address next to the link-local one, returning an
+    /// unsolicited neighbor advertisement announcing it.
+    pub fn add_ipv6_address(&mut self, ip: Ipv6Address) -> EthernetFrame {
+        self.ndp.add_address(ip)
+    }
+
+    #[must_use]
+    pub const fn ndp(&self) -> &Ndp {
+        &self.ndp
+    }
+
+    pub fn ndp_mut(&mut self) -> &mut Ndp {
+        &mut self.ndp
+    }
+
+    /// Prepares a packet for the given cable, fragmenting it to the MTU.
+    #[must_use]
+    pub fn send_ipv4(&self, packet: &Ipv4Packet, cable: &Cable) -> Egress {
+        fragment_for(packet, cable, self.local_ipv4())
+    }
+
+    /// Handles a received packet, returning it once all its fragments arrived.
+    pub fn receive_ipv4(&mut self, packet: &Ipv4Packet, now: Instant) -> Option<Ipv4Packet> {
+        self.reassembler.receive(packet, now)
+    }
+
+    #[must_use]
+    pub const fn reassembler(&self) -> &Reassembler {
+        &self.reassembler
+    }
+
+    pub fn reassembler_mut(&mut self) -> &mut Reassembler {
+        &mut self.reassembler
+    }
+
+    /// The ICMP response to a packet addressed to this interface: a reply to
+    /// echo requests, or an error for protocols it doesn't speak.
+    #[must_use]
+    pub fn respond_ipv4(&self, packet: &Ipv4Packet) -> Option<Ipv4Packet> {
+        respond_locally(packet, self.local_ipv4())
+    }
+
+    /// Drops packets whose fragments didn't all arrive in time, returning
+    /// the errors to send to their sources.
+    pub fn poll_reassembly(&mut self, now: Instant) -> Vec<Ipv4Packet> {
+        let local = self.local_ipv4();
+
+        self.reassembler
+            .remove_expired(now)
+            .iter()
+            .filter(|first| IcmpMessage::may_report(first))
+            .map(|first| {
+                IcmpMessage::time_exceeded(first, CODE_REASSEMBLY_TIME_EXCEEDED)
+                    .to_packet(local, first.source())
+            })
+            .collect()
+    }
+
+    /// The address ICMP errors are sent from.
+    #[must_use]
+    pub fn local_ipv4(&self) -> Ipv4Address {
+        self.arp.ip_address().unwrap_or(Ipv4Address::UNSPECIFIED)
+    }
+}
+
+/// Handles a packet delivered to a node at `local`.
+fn respond_locally(packet: &Ipv4Packet, local: Ipv4Address) -> Option<Ipv4Packet> {
+    match packet.protocol() {
+        PROTOCOL_ICMP => {
+            let reply = IcmpMessage::decode_ipv4(packet).ok()?.echo_reply()?;
+            Some(reply.to_packet(local, packet.source()))
+        }
+        PROTOCOL_TCP | PROTOCOL_UDP => None,
+        _ => {
+            let error = IcmpMessage::destination_unreachable(packet, CODE_PROTOCOL_UNREACHABLE);
+            IcmpMessage::may_report(packet).then(|| error.to_packet(local, packet.source()))
+        }
+    }
+}
@@ -1,17 +1,32 @@
 use std::{
     fmt::Debug,
-    sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc,
-    },
+    sync::{mpsc::Sender, Arc},
+    time::Instant,
 };
 
+pub mod interface;
+pub mod switch;
+
+use anyhow::Context;
 use easy_threadpool::ThreadPool;
+use interface::Interface;
 
 use crate::{
+    application_layer::{
+        dhcp::{DhcpClient, DhcpServer},
+        dns::{DnsResolver, DnsServer, Question, RecordType, Resolution},
+    },
     bit_string::BitString,
+    data_link_layer::frame::ipv4::Ipv4Packet,
+    network_layer::{
+        fragmentation::{fragment_for, Egress},
+        icmp::{IcmpMessage, CODE_NETWORK_UNREACHABLE, CODE_TTL_EXCEEDED},
+    },
     physical_layer::cable::{Cable, CableContext},
-    utils::mac_address::{MacAddress, MacAddressGenerator},
+    utils::{
+        ip_address::Ipv4Address,
+        mac_address::{MacAddress, MacAddressGenerator},
+    },
 };
 
 pub trait Node: Debug {
@@ -48,12 +63,11 @@ impl Eq for dyn Node {}
 
 #[derive(Debug)]
 pub struct Router {
-    mac: MacAddress,
+    interface: Interface,
     connections: Vec<Arc<Cable>>,
-    receiver: Receiver<CableContext>,
-    transmitter: Arc<Sender<CableContext>>,
     is_edge_router: bool,
     runtime: ThreadPool,
+    dhcp_server: Option<DhcpServer>,
 }
 
 impl Node for Router {
@@ -69,11 +83,11 @@ impl Node for Router {
     }
 
     fn get_mac(&self) -> &MacAddress {
-        &self.mac
+        self.interface.mac()
     }
 
     fn get_transmitter(&self) -> Arc<Sender<CableContext>> {
-        self.transmitter.clone()
+        self.interface.transmitter()
     }
 }
 
@@ -83,18 +97,12 @@ impl Router {
         mac_address_gen: &mut MacAddressGenerator,
         threadpool: ThreadPool,
     ) -> Self {
-        let mac = mac_address_gen.gen_addr();
-
-        let (tx, rx) = channel::<CableContext>();
-        let transmitter = tx.into();
-
         Self {
-            mac,
-            transmitter,
-            receiver: rx,
+            interface: Interface::new(mac_address_gen.gen_addr()),
             connections: Vec::new(),
             is_edge_router,
             runtime: threadpool,
+            dhcp_server: None,
         }
     }
 
@@ -102,19 +110,77 @@ impl Router {
     pub const fn is_edge_router(&self) -> bool {
         self.is_edge_router
     }
+
+    #[must_use]
+    pub const fn interface(&self) -> &Interface {
+        &self.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut Interface {
+        &mut self.interface
+    }
+
+    /// Runs a DHCP server on the router, handing out addresses to the
+    /// network it's connected to.
+    pub fn set_dhcp_server(&mut self, server: DhcpServer) {
+        self.dhcp_server = Some(server);
+    }
+
+    #[must_use]
+    pub const fn dhcp_server(&self) -> Option<&DhcpServer> {
+        self.dhcp_server.as_ref()
+    }
+
+    pub fn dhcp_server_mut(&mut self) -> Option<&mut DhcpServer> {
+        self.dhcp_server.as_mut()
+    }
+
+    /// Handles a DHCP packet from a client, returning the reply to send.
+    /// Without a server the packet is ignored.
+    pub fn receive_dhcp(
+        &mut self,
+        packet: &Ipv4Packet,
+        now: Instant,
+    ) -> anyhow::Result<Option<Ipv4Packet>> {
+        match &mut self.dhcp_server {
+            Some(server) => server.receive_ipv4(packet, now),
+            None => Ok(None),
+        }
+    }
+
+    /// Forwards a packet over the cable to its next hop, or drops it with an
+    /// error if there is no route or its TTL runs out.
+    #[must_use]
+    pub fn forward_ipv4(&self, packet: &Ipv4Packet, next_hop: Option<&Cable>) -> Egress {
+        let local = self.interface.local_ipv4();
+
+        let Some(cable) = next_hop else {
+            let error = IcmpMessage::destination_unreachable(packet, CODE_NETWORK_UNREACHABLE);
+            return Egress::drop_with(packet, &error, local);
+        };
+
+        match packet.forwarded() {
+            Some(forwarded) => fragment_for(&forwarded, cable, local),
+            None => {
+                let error = IcmpMessage::time_exceeded(packet, CODE_TTL_EXCEEDED);
+                Egress::drop_with(packet, &error, local)
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct User {
-    mac: MacAddress,
+    interface: Interface,
     connections: Vec<Arc<Cable>>,
-    receiver: Receiver<CableContext>,
-    transmitter: Arc<Sender<CableContext>>,
+    dhcp: DhcpClient,
+    resolver: DnsResolver,
+    dns_server: Option<DnsServer>,
 }
 
 impl PartialEq for User {
     fn eq(&self, other: &Self) -> bool {
-        self.mac == other.mac && self.connections == other.connections
+        self.interface.mac() == other.interface.mac() && self.connections == other.connections
     }
 }
 
@@ -122,14 +188,114 @@ impl User {
     pub fn new(mac_address_gen: &mut MacAddressGenerator) -> Self {
         let mac = mac_address_gen.gen_addr();
 
-        let (tx, rx) = channel::<CableContext>();
-        let transmitter = tx.into();
-
         Self {
-            mac,
+            interface: Interface::new(mac),
             connections: Vec::new(),
-            transmitter,
-            receiver: rx,
+            dhcp: DhcpClient::new(mac),
+            resolver: DnsResolver::new(),
+            dns_server: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn interface(&self) -> &Interface {
+        &self.interface
+    }
+
+    pub fn interface_mut(&mut self) -> &mut Interface {
+        &mut self.interface
+    }
+
+    #[must_use]
+    pub const fn dhcp(&self) -> &DhcpClient {
+        &self.dhcp
+    }
+
+    pub fn dhcp_mut(&mut self) -> &mut DhcpClient {
+        &mut self.dhcp
+    }
+
+    /// Sends the DHCP messages that are due, starting with looking for a
+    /// server on the first call.
+    pub fn poll_dhcp(&mut self, now: Instant) -> Option<Ipv4Packet> {
+        let previous = self.dhcp.address();
+        let packet = self.dhcp.poll(now);
+        self.apply_dhcp_lease(previous);
+        packet
+    }
+
+    /// Handles a DHCP packet from a server, returning the message to send in
+    /// response. The user takes the address once the server acknowledges it.
+    pub fn receive_dhcp(
+        &mut self,
+        packet: &Ipv4Packet,
+        now: Instant,
+    ) -> anyhow::Result<Option<Ipv4Packet>> {
+        let previous = self.dhcp.address();
+        let response = self.dhcp.receive(packet, now)?;
+        self.apply_dhcp_lease(previous);
+        Ok(response)
+    }
+
+    #[must_use]
+    pub const fn resolver(&self) -> &DnsResolver {
+        &self.resolver
+    }
+
+    pub fn resolver_mut(&mut self) -> &mut DnsResolver {
+        &mut self.resolver
+    }
+
+    /// Resolves a name from the cache, or returns the query to send to the
+    /// nameserver.
+    pub fn resolve(
+        &mut self,
+        name: &str,
+        record_type: RecordType,
+        now: Instant,
+    ) -> anyhow::Result<Resolution> {
+        let source = self
+            .interface
+            .arp()
+            .ip_address()
+            .context("User has no address")?;
+        let question = Question::new(name, record_type)?;
+
+        self.resolver.resolve(question, source, now)
+    }
+
+    /// Makes the user an authoritative server for the zones of the server.
+    pub fn set_dns_server(&mut self, server: DnsServer) {
+        self.dns_server = Some(server);
+    }
+
+    #[must_use]
+    pub const fn dns_server(&self) -> Option<&DnsServer> {
+        self.dns_server.as_ref()
+    }
+
+    /// Answers a query sent to the user. Without a server the query is
+    /// ignored.
+    pub fn respond_dns(&self, packet: &Ipv4Packet) -> anyhow::Result<Option<Ipv4Packet>> {
+        match &self.dns_server {
+            Some(server) => server.receive_ipv4(packet),
+            None => Ok(None),
+        }
+    }
+
+    /// Takes the leased address, or gives it up once the lease is lost.
+    fn apply_dhcp_lease(&mut self, previous: Option<Ipv4Address>) {
+        match self.dhcp.address() {
+            current if current == previous => {}
+            // The server checked the address is free, so it isn't announced
+            Some(ip) => {
+                let _ = self.interface.arp_mut().set_ip_address(ip);
+            }
+            None => {
+                if self.interface.arp().ip_address() == previous {
+                    self.interface.arp_mut().clear_ip_address();
+                }
+            }
         }
     }
 }
@@ -147,10 +313,10 @@ impl Node for User {
     }
 
     fn get_mac(&self) -> &MacAddress {
-        &self.mac
+        self.interface.mac()
     }
 
     fn get_transmitter(&self) -> Arc<Sender<CableContext>> {
-        self.transmitter.clone()
+        self.interface.transmitter()
     }
 }
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use crate::{
+    data_link_layer::frame::{ethernet::EthernetFrame, vlan::VlanTag},
+    utils::mac_address::MacAddress,
+};
+
+pub const DEFAULT_VLAN: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortMode {
+    /// Carries a single VLAN, frames are sent and received untagged.
+    Access(u16),
+    /// Carries the allowed VLANs tagged. Untagged frames belong to the native
+    /// VLAN, if there is one.
+    Trunk {
+        native: Option<u16>,
+        allowed: Vec<u16>,
+    },
+}
+
+impl PortMode {
+    #[must_use]
+    pub fn carries(&self, vid: u16) -> bool {
+        match self {
+            Self::Access(access) => *access == vid,
+            Self::Trunk { native, allowed } => *native == Some(vid) || allowed.contains(&vid),
+        }
+    }
+
+    /// The VLAN a frame arriving on this port belongs to, based on its
+    /// outermost tag.
+    fn ingress_vlan(&self, frame: &EthernetFrame) -> Option<u16> {
+        let vid = match (self, frame.vlan_tags().first()) {
+            (Self::Access(vid), None) => Some(*vid),
+            (Self::Trunk { native, .. }, None) => *native,
+            (Self::Trunk { .. }, Some(tag)) => Some(tag.vid()),
+            // Tagged frames are not expected on access ports
+            (Self::Access(_), Some(_)) => None,
+        };
+
+        vid.filter(|vid| self.carries(*vid))
+    }
+
+    /// Whether frames in the given VLAN are tagged when leaving this port.
+    fn egress_tagged(&self, vid: u16) -> bool {
+        match self {
+            Self::Access(_) => false,
+            Self::Trunk { native, .. } => *native != Some(vid),
+        }
+    }
+}
+
+/// A VLAN-aware learning switch. Forwarding only ever happens between ports
+/// carrying the VLAN of a frame, so broadcasts and flooding stay inside it.
+///
+/// The switch only decides where frames go, ports are numbered by the caller
+/// and moving the frames between them is up to the caller as well.
+#[derive(Debug, Clone)]
+pub struct Switch {
+    ports: Vec<PortMode>,
+    mac_table: HashMap<(u16, MacAddress), usize>,
+}
+
+impl Switch {
+    /// Creates a switch with the given amount of access ports in the default
+    /// VLAN.
+    #[must_use]
+    pub fn new(port_count: usize) -> Self {
+        Self {
+            ports: vec![PortMode::Access(DEFAULT_VLAN); port_count],
+            mac_table: HashMap::new(),
+        }
+    }
+
+    pub fn set_port_mode(&mut self, port: usize, mode: PortMode) {
+        assert!(port < self.ports.len(), "Switch has no port {port}");
+
+        // Learned addresses may no longer be reachable through this port
+        self.mac_table.retain(|_, learned| *learned != port);
+        self.ports[port] = mode;
+    }
+
+    #[must_use]
+    pub fn port_mode(&self, port: usize) -> &PortMode {
+        &self.ports[port]
+    }
+
+    /// The port the address was last seen on in the given VLAN.
+    #[must_use]
+    pub fn lookup(&self, vid: u16, mac: MacAddress) -> Option<usize> {
+        self.mac_table.get(&(vid, mac)).copied()
+    }
+
+    /// Handles a frame arriving on the given port, returning the frames to send
+    /// out and the ports to send them on. The source address is learned, and
+    /// the frame is retagged for every egress port.
+    pub fn forward(
+        &mut self,
+        ingress: usize,
+        frame: &EthernetFrame,
+    ) -> Vec<(usize, EthernetFrame)> {
+        assert!(ingress < self.ports.len(), "Switch has no port {ingress}");
+
+        let Some(vid) = self.ports[ingress].ingress_vlan(frame) else {
+            return Vec::new();
+        };
+
+        if !frame.source().is_multicast() {
+            self.mac_table.insert((vid, frame.source()), ingress);
+        }
+
+        let egress_ports: Vec<usize> = match self.lookup(vid, frame.destination()) {
+            Some(port) if port == ingress => Vec::new(),
+            Some(port) => vec![port],
+            None => (0..self.ports.len())
+                .filter(|&port| port != ingress && self.ports[port].carries(vid))
+                .collect(),
+        };
+
+        egress_ports
+            .into_iter()
+            .map(|port| (port, self.retag(port, vid, frame)))
+            .collect()
+    }
+
+    fn retag(&self, egress: usize, vid: u16, frame: &EthernetFrame) -> EthernetFrame {
+        // Access ports drop tagged frames, so any tag came in over a trunk
+        let mut vlan_tags = frame.vlan_tags().to_vec();
+        let ingress_tagged = !vlan_tags.is_empty();
+
+        match (ingress_tagged, self.ports[egress].egress_tagged(vid)) {
+            (true, false) => {
+                vlan_tags.remove(0);
+            }
+            (false, true) => vlan_tags.insert(0, VlanTag::new(vid)),
+            (true, true) | (false, false) => return frame.clone(),
+        }
+
+        frame
+            .builder()
+            .set_vlan_tags(vlan_tags)
+            .build(frame.payload().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        data_link_layer::frame::{
+            ethernet::{EthernetFrame, EthernetFrameBuilder, ETHERTYPE_IPV4},
+            vlan::VlanTag,
+            Frame,
+        },
+        mac_address::MacAddress,
+    };
+
+    use super::{PortMode, Switch};
+
+    const HOST_A: MacAddress = MacAddress::new([0x02, 0, 0, 0, 0, 0x0A]);
+    const HOST_B: MacAddress = MacAddress::new([0x02, 0, 0, 0, 0, 0x0B]);
+    const HOST_C: MacAddress = MacAddress::new([0x02, 0, 0, 0, 0, 0x0C]);
+
+    fn frame(source: MacAddress, destination: MacAddress, tags: Vec<VlanTag>) -> EthernetFrame {
+        EthernetFrameBuilder::new()
+            .set_destination(destination)
+            .set_source(source)
+            .set_ether_type(ETHERTYPE_IPV4)
+            .set_vlan_tags(tags)
+            .build(BitString::from(b"payload".as_slice()))
+    }
+
+    /// Ports 0 and 1 are in VLAN 10, port 2 is in VLAN 20 and port 3 is a
+    /// trunk carrying both.
+    fn switch() -> Switch {
+        let mut switch = Switch::new(4);
+        switch.set_port_mode(0, PortMode::Access(10));
+        switch.set_port_mode(1, PortMode::Access(10));
+        switch.set_port_mode(2, PortMode::Access(20));
+        switch.set_port_mode(
+            3,
+            PortMode::Trunk {
+                native: None,
+                allowed: vec![10, 20],
+            },
+        );
+        switch
+    }
+
+    fn ports(forwarded: &[(usize, EthernetFrame)]) -> Vec<usize> {
+        forwarded.iter().map(|(port, _)| *port).collect()
+    }
+
+    #[test]
+    fn broadcast_stays_in_vlan() {
+        let mut switch = switch();
+
+        let forwarded = switch.forward(0, &frame(HOST_A, MacAddress::BROADCAST, Vec::new()));
+        assert_eq!(ports(&forwarded), [1, 3]);
+
+        let forwarded = switch.forward(2, &frame(HOST_C, MacAddress::BROADCAST, Vec::new()));
+        assert_eq!(ports(&forwarded), [3]);
+    }
+
+    #[test]
+    fn learns_addresses() {
+        let mut switch = switch();
+
+        // Unknown destinations are flooded in the VLAN
+        let forwarded = switch.forward(1, &frame(HOST_B, HOST_A, Vec::new()));
+        assert_eq!(ports(&forwarded), [0, 3]);
+        assert_eq!(switch.lookup(10, HOST_B), Some(1));
+        assert_eq!(switch.lookup(20, HOST_B), None);
+
+        let forwarded = switch.forward(0, &frame(HOST_A, HOST_B, Vec::new()));
+        assert_eq!(ports(&forwarded), [1]);
+        assert_eq!(forwarded[0].1, frame(HOST_A, HOST_B, Vec::new()));
+    }
+
+    #[test]
+    fn vlans_are_isolated() {
+        let mut switch = switch();
+
+        switch.forward(2, &frame(HOST_C, HOST_A, Vec::new()));
+
+        // HOST_C is only known in VLAN 20, so this is flooded in VLAN 10
+        let forwarded = switch.forward(0, &frame(HOST_A, HOST_C, Vec::new()));
+        assert_eq!(ports(&forwarded), [1, 3]);
+    }
+
+    #[test]
+    fn trunk_tags() {
+        let mut switch = switch();
+
+        let forwarded = switch.forward(2, &frame(HOST_C, MacAddress::BROADCAST, Vec::new()));
+        let (_, tagged) = &forwarded[0];
+        assert_eq!(tagged.vlan_tags(), [VlanTag::new(20)]);
+
+        let decoded = EthernetFrame::decode(tagged.as_bit_string()).expect("Frame is valid");
+        assert_eq!(&decoded, tagged);
+
+        // And untags again on the way back
+        let reply = frame(HOST_A, HOST_C, vec![VlanTag::new(20)]);
+        let forwarded = switch.forward(3, &reply);
+        assert_eq!(ports(&forwarded), [2]);
+        assert_eq!(forwarded[0].1, frame(HOST_A, HOST_C, Vec::new()));
+    }
+
+    #[test]
+    fn trunk_native_vlan() {
+        let mut switch = switch();
+        switch.set_port_mode(
+            3,
+            PortMode::Trunk {
+                native: Some(10),
+                allowed: vec![20],
+            },
+        );
+
+        let forwarded = switch.forward(3, &frame(HOST_A, MacAddress::BROADCAST, Vec::new()));
+        assert_eq!(ports(&forwarded), [0, 1]);
+
+        let forwarded = switch.forward(0, &frame(HOST_B, MacAddress::BROADCAST, Vec::new()));
+        assert_eq!(
+            forwarded[1],
+            (3, frame(HOST_B, MacAddress::BROADCAST, Vec::new()))
+        );
+    }
+
+    #[test]
+    fn drops_disallowed() {
+        let mut switch = switch();
+
+        // VLAN 30 isn't allowed on the trunk
+        let forwarded = switch.forward(
+            3,
+            &frame(HOST_A, MacAddress::BROADCAST, vec![VlanTag::new(30)]),
+        );
+        assert!(forwarded.is_empty());
+
+        // Untagged frames on a trunk without a native VLAN
+        let forwarded = switch.forward(3, &frame(HOST_A, MacAddress::BROADCAST, Vec::new()));
+        assert!(forwarded.is_empty());
+
+        // Tagged frames on an access port
+        let forwarded = switch.forward(
+            0,
+            &frame(HOST_A, MacAddress::BROADCAST, vec![VlanTag::new(10)]),
+        );
+        assert!(forwarded.is_empty());
+    }
+
+    #[test]
+    fn qinq_keeps_inner_tag() {
+        let mut switch = switch();
+        switch.set_port_mode(0, PortMode::Access(20));
+
+        let inner = VlanTag::new(100);
+        let tags = vec![VlanTag::service(20), inner];
+        let forwarded = switch.forward(3, &frame(HOST_A, HOST_B, tags));
+
+        assert_eq!(ports(&forwarded), [0, 2]);
+        assert_eq!(forwarded[0].1.vlan_tags(), [inner]);
+    }
+}
@@ -2,8 +2,10 @@
 #![allow(clippy::missing_errors_doc)]
 #![feature(int_roundings)]
 
+pub mod application_layer;
 pub mod data_link_layer;
 pub mod hardware;
+pub mod network_layer;
 pub mod physical_layer;
 pub mod utils;
 
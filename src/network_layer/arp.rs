@@ -0,0 +1,549 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::ethernet::{
+        append_mac, get_mac, EthernetFrame, EthernetFrameBuilder, ETHERTYPE_ARP, ETHERTYPE_IPV4,
+    },
+    ip_address::Ipv4Address,
+    mac_address::MacAddress,
+};
+
+use super::neighbor_cache::NeighborCache;
+
+const HARDWARE_TYPE_ETHERNET: u16 = 1;
+const ARP_PACKET_LEN: usize = 28;
+
+pub const DEFAULT_CACHE_TIMEOUT: Duration = Duration::from_secs(60);
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+/// The amount of requests sent for an address before giving up on it.
+pub const MAX_REQUESTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpOperation {
+    Request = 1,
+    Reply = 2,
+}
+
+/// An ARP packet for resolving IPv4 addresses to ethernet addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpPacket {
+    operation: ArpOperation,
+    sender_mac: MacAddress,
+    sender_ip: Ipv4Address,
+    target_mac: MacAddress,
+    target_ip: Ipv4Address,
+}
+
+impl ArpPacket {
+    #[must_use]
+    pub const fn request(
+        sender_mac: MacAddress,
+        sender_ip: Ipv4Address,
+        target_ip: Ipv4Address,
+    ) -> Self {
+        Self {
+            operation: ArpOperation::Request,
+            sender_mac,
+            sender_ip,
+            target_mac: MacAddress::new([0; 6]),
+            target_ip,
+        }
+    }
+
+    #[must_use]
+    pub const fn reply(
+        sender_mac: MacAddress,
+        sender_ip: Ipv4Address,
+        target_mac: MacAddress,
+        target_ip: Ipv4Address,
+    ) -> Self {
+        Self {
+            operation: ArpOperation::Reply,
+            sender_mac,
+            sender_ip,
+            target_mac,
+            target_ip,
+        }
+    }
+
+    /// A request for the own address, announcing it to the network.
+    #[must_use]
+    pub const fn gratuitous(mac: MacAddress, ip: Ipv4Address) -> Self {
+        Self::request(mac, ip, ip)
+    }
+
+    #[must_use]
+    pub const fn operation(&self) -> ArpOperation {
+        self.operation
+    }
+
+    #[must_use]
+    pub const fn sender_mac(&self) -> MacAddress {
+        self.sender_mac
+    }
+
+    #[must_use]
+    pub const fn sender_ip(&self) -> Ipv4Address {
+        self.sender_ip
+    }
+
+    #[must_use]
+    pub const fn target_mac(&self) -> MacAddress {
+        self.target_mac
+    }
+
+    #[must_use]
+    pub const fn target_ip(&self) -> Ipv4Address {
+        self.target_ip
+    }
+
+    #[must_use]
+    pub fn is_gratuitous(&self) -> bool {
+        self.sender_ip == self.target_ip
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> BitString {
+        let mut output = BitString::with_capacity(ARP_PACKET_LEN * 8);
+
+        output.append_u16(HARDWARE_TYPE_ETHERNET);
+        output.append_u16(ETHERTYPE_IPV4);
+        output.append_u8(6); // Hardware address length
+        output.append_u8(4); // Protocol address length
+        output.append_u16(self.operation as u16);
+        append_mac(&mut output, self.sender_mac);
+        output.append_u32(self.sender_ip.to_u32());
+        append_mac(&mut output, self.target_mac);
+        output.append_u32(self.target_ip.to_u32());
+
+        output
+    }
+
+    /// Parses a packet, ignoring anything after it such as ethernet padding.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= ARP_PACKET_LEN * 8,
+            "Packet of {} bits is too short to be an ARP packet",
+            data.len()
+        );
+        ensure!(
+            data.get_u16(0) == HARDWARE_TYPE_ETHERNET && data.get_u16(16) == ETHERTYPE_IPV4,
+            "Only ethernet to IPv4 resolution is supported"
+        );
+        ensure!(
+            data.get_u8(32) == 6 && data.get_u8(40) == 4,
+            "Address lengths do not match ethernet and IPv4"
+        );
+
+        let operation = match data.get_u16(48) {
+            1 => ArpOperation::Request,
+            2 => ArpOperation::Reply,
+            operation => bail!("Unknown ARP operation {operation}"),
+        };
+
+        Ok(Self {
+            operation,
+            sender_mac: get_mac(data, 64),
+            sender_ip: Ipv4Address::from_u32(data.get_u32(112)),
+            target_mac: get_mac(data, 144),
+            target_ip: Ipv4Address::from_u32(data.get_u32(192)),
+        })
+    }
+}
+
+/// Resolved IPv4 addresses.
+pub type ArpCache = NeighborCache<Ipv4Address>;
+
+#[derive(Debug)]
+struct PendingResolution {
+    // Payloads with their EtherType
+    packets: Vec<(u16, BitString)>,
+    last_request: Instant,
+    requests: usize,
+}
+
+/// Address resolution for a single interface. Packets for addresses that
+/// aren't resolved yet are queued until a reply arrives.
+#[derive(Debug)]
+pub struct Arp {
+    mac: MacAddress,
+    ip: Option<Ipv4Address>,
+    cache: ArpCache,
+    pending: HashMap<Ipv4Address, PendingResolution>,
+    request_timeout: Duration,
+    dropped_packets: usize,
+}
+
+impl Arp {
+    #[must_use]
+    pub fn new(mac: MacAddress) -> Self {
+        Self {
+            mac,
+            ip: None,
+            cache: ArpCache::new(DEFAULT_CACHE_TIMEOUT),
+            pending: HashMap::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            dropped_packets: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn set_cache_timeout(self, timeout: Duration) -> Self {
+        Self {
+            cache: ArpCache::new(timeout),
+            ..self
+        }
+    }
+
+    /// Sets how long to wait for a reply before asking again.
+    #[must_use]
+    pub fn set_request_timeout(self, request_timeout: Duration) -> Self {
+        Self {
+            request_timeout,
+            ..self
+        }
+    }
+
+    /// Sets the address this interface answers requests for, returning a
+    /// gratuitous ARP frame announcing it.
+    pub fn set_ip_address(&mut self, ip: Ipv4Address) -> EthernetFrame {
+        self.ip = Some(ip);
+        self.frame(MacAddress::BROADCAST, ArpPacket::gratuitous(self.mac, ip))
+    }
+
+    /// Stops answering requests, for example when a lease ran out.
+    pub fn clear_ip_address(&mut self) {
+        self.ip = None;
+    }
+
+    #[must_use]
+    pub const fn ip_address(&self) -> Option<Ipv4Address> {
+        self.ip
+    }
+
+    #[must_use]
+    pub const fn mac_address(&self) -> MacAddress {
+        self.mac
+    }
+
+    #[must_use]
+    pub const fn cache(&self) -> &ArpCache {
+        &self.cache
+    }
+
+    pub fn cache_mut(&mut self) -> &mut ArpCache {
+        &mut self.cache
+    }
+
+    /// The amount of queued packets dropped because their address could not
+    /// be resolved.
+    #[must_use]
+    pub const fn dropped_packets(&self) -> usize {
+        self.dropped_packets
+    }
+
+    /// Sends a payload to the given address. If the address is not resolved
+    /// yet the payload is queued, and a request is sent if there isn't one
+    /// outstanding.
+    pub fn send(
+        &mut self,
+        target_ip: Ipv4Address,
+        ether_type: u16,
+        payload: BitString,
+        now: Instant,
+    ) -> Vec<EthernetFrame> {
+        let target_mac = if target_ip == Ipv4Address::BROADCAST {
+            Some(MacAddress::BROADCAST)
+        } else {
+            self.cache.lookup(target_ip, now)
+        };
+
+        if let Some(target_mac) = target_mac {
+            return vec![self.payload_frame(target_mac, ether_type, payload)];
+        }
+
+        if let Some(pending) = self.pending.get_mut(&target_ip) {
+            pending.packets.push((ether_type, payload));
+            return Vec::new();
+        }
+
+        self.pending.insert(
+            target_ip,
+            PendingResolution {
+                packets: vec![(ether_type, payload)],
+                last_request: now,
+                requests: 1,
+            },
+        );
+
+        vec![self.request(target_ip)]
+    }
+
+    /// Handles a received ARP frame, returning the frames to send in response.
+    /// These are a reply if the frame was a request for this interface, and
+    /// any packets that were waiting on the sender's address.
+    pub fn receive(
+        &mut self,
+        frame: &EthernetFrame,
+        now: Instant,
+    ) -> anyhow::Result<Vec<EthernetFrame>> {
+        ensure!(
+            frame.ether_type() == ETHERTYPE_ARP,
+            "Frame does not carry an ARP packet"
+        );
+
+        let packet = ArpPacket::decode(frame.payload())?;
+        let sender_ip = packet.sender_ip();
+        let sender_mac = packet.sender_mac();
+
+        if sender_mac == self.mac || sender_ip == Ipv4Address::UNSPECIFIED {
+            return Ok(Vec::new());
+        }
+
+        // Known entries are always refreshed, others are only added when the
+        // packet is meant for us or answers an outstanding request
+        let for_us = Some(packet.target_ip()) == self.ip && !packet.is_gratuitous();
+        let merged = self.cache.update(sender_ip, sender_mac, now);
+        if !merged && (for_us || self.pending.contains_key(&sender_ip)) {
+            self.cache.insert(sender_ip, sender_mac, now);
+        }
+
+        let mut frames = Vec::new();
+
+        if for_us && packet.operation() == ArpOperation::Request {
+            let ip = self
+                .ip
+                .expect("Packet is only for us if we have an address");
+            let reply = ArpPacket::reply(self.mac, ip, sender_mac, sender_ip);
+            frames.push(self.frame(sender_mac, reply));
+        }
+
+        if let Some(pending) = self.pending.remove(&sender_ip) {
+            frames.extend(
+                pending.packets.into_iter().map(|(ether_type, payload)| {
+                    self.payload_frame(sender_mac, ether_type, payload)
+                }),
+            );
+        }
+
+        Ok(frames)
+    }
+
+    /// Resends requests that timed out, and drops the queued packets of
+    /// addresses that didn't reply to any of the requests.
+    pub fn poll(&mut self, now: Instant) -> Vec<EthernetFrame> {
+        self.cache.remove_expired(now);
+
+        let request_timeout = self.request_timeout;
+        let mut dropped_packets = 0;
+        self.pending.retain(|_, pending| {
+            let timed_out = now.duration_since(pending.last_request) >= request_timeout;
+            if timed_out && pending.requests >= MAX_REQUESTS {
+                dropped_packets += pending.packets.len();
+                return false;
+            }
+            true
+        });
+        self.dropped_packets += dropped_packets;
+
+        let mut retries = Vec::new();
+        for (ip, pending) in &mut self.pending {
+            if now.duration_since(pending.last_request) >= request_timeout {
+                pending.last_request = now;
+                pending.requests += 1;
+                retries.push(*ip);
+            }
+        }
+
+        retries.into_iter().map(|ip| self.request(ip)).collect()
+    }
+
+    fn request(&self, target_ip: Ipv4Address) -> EthernetFrame {
+        let sender_ip = self.ip.unwrap_or(Ipv4Address::UNSPECIFIED);
+        let request = ArpPacket::request(self.mac, sender_ip, target_ip);
+        self.frame(MacAddress::BROADCAST, request)
+    }
+
+    fn frame(&self, destination: MacAddress, packet: ArpPacket) -> EthernetFrame {
+        self.payload_frame(destination, ETHERTYPE_ARP, packet.encode())
+    }
+
+    fn payload_frame(
+        &self,
+        destination: MacAddress,
+        ether_type: u16,
+        payload: BitString,
+    ) -> EthernetFrame {
+        EthernetFrameBuilder::new()
+            .set_destination(destination)
+            .set_source(self.mac)
+            .set_ether_type(ether_type)
+            .build(payload)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        bit_string::BitString,
+        data_link_layer::frame::ethernet::{EthernetFrame, ETHERTYPE_ARP, ETHERTYPE_IPV4},
+        ip_address::Ipv4Address,
+        mac_address::{MacAddress, MacAddressGenerator},
+    };
+
+    use super::{Arp, ArpCache, ArpOperation, ArpPacket, DEFAULT_REQUEST_TIMEOUT, MAX_REQUESTS};
+
+    const IP_A: Ipv4Address = Ipv4Address::new([192, 168, 1, 1]);
+    const IP_B: Ipv4Address = Ipv4Address::new([192, 168, 1, 2]);
+    const IP_C: Ipv4Address = Ipv4Address::new([192, 168, 1, 3]);
+    const PAYLOAD: &[u8] = b"Hello world!";
+
+    fn nodes() -> (Arp, Arp) {
+        let mut mac_gen = MacAddressGenerator::new(42);
+
+        let mut a = Arp::new(mac_gen.gen_addr());
+        let mut b = Arp::new(mac_gen.gen_addr());
+        let _ = a.set_ip_address(IP_A);
+        let _ = b.set_ip_address(IP_B);
+
+        (a, b)
+    }
+
+    fn packet(frame: &EthernetFrame) -> ArpPacket {
+        assert_eq!(frame.ether_type(), ETHERTYPE_ARP);
+        ArpPacket::decode(frame.payload()).expect("Frame carries an ARP packet")
+    }
+
+    #[test]
+    fn packet_layout() {
+        let mac = MacAddress::new([0x02, 0, 0, 0, 0, 0x01]);
+        let bs = ArpPacket::request(mac, IP_A, IP_B).encode();
+
+        assert_eq!(
+            bs.as_vec_exact_u8(),
+            [
+                0, 1, 0x08, 0, 6, 4, 0, 1, // Header
+                0x02, 0, 0, 0, 0, 0x01, 192, 168, 1, 1, // Sender
+                0, 0, 0, 0, 0, 0, 192, 168, 1, 2, // Target
+            ]
+        );
+    }
+
+    #[test]
+    fn packet_round_trip() {
+        let mac = MacAddress::new([0x02, 0, 0, 0, 0, 0x01]);
+        let packet = ArpPacket::reply(mac, IP_A, MacAddress::BROADCAST, IP_B);
+
+        let mut bs = packet.encode();
+        // Ethernet padding is ignored
+        bs.append_zeroes(18 * 8);
+
+        assert_eq!(ArpPacket::decode(&bs).expect("Packet is valid"), packet);
+        assert!(ArpPacket::decode(&BitString::with_zeroes(28 * 8)).is_err());
+    }
+
+    #[test]
+    fn cache_expires() {
+        let now = Instant::now();
+        let mac = MacAddress::new([0x02, 0, 0, 0, 0, 0x01]);
+        let mut cache = ArpCache::new(Duration::from_secs(10));
+
+        assert!(!cache.update(IP_A, mac, now));
+        cache.insert(IP_A, mac, now);
+
+        assert_eq!(cache.lookup(IP_A, now + Duration::from_secs(9)), Some(mac));
+        assert_eq!(cache.lookup(IP_A, now + Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn resolves_and_flushes_queue() {
+        let now = Instant::now();
+        let (mut a, mut b) = nodes();
+
+        // The first packet triggers a request, the second just waits
+        let sent = a.send(IP_B, ETHERTYPE_IPV4, PAYLOAD.into(), now);
+        assert!(a.send(IP_B, ETHERTYPE_IPV4, PAYLOAD.into(), now).is_empty());
+
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].destination(), MacAddress::BROADCAST);
+        assert_eq!(packet(&sent[0]).operation(), ArpOperation::Request);
+
+        let replies = b.receive(&sent[0], now).expect("Frame is ARP");
+        assert_eq!(replies.len(), 1);
+        assert_eq!(packet(&replies[0]).operation(), ArpOperation::Reply);
+        // The request taught B about A
+        assert_eq!(b.cache().lookup(IP_A, now), Some(a.mac_address()));
+
+        let flushed = a.receive(&replies[0], now).expect("Frame is ARP");
+        assert_eq!(flushed.len(), 2);
+        for frame in flushed {
+            assert_eq!(frame.destination(), b.mac_address());
+            assert_eq!(frame.ether_type(), ETHERTYPE_IPV4);
+        }
+
+        // And now it's cached
+        let sent = a.send(IP_B, ETHERTYPE_IPV4, PAYLOAD.into(), now);
+        assert_eq!(sent[0].destination(), b.mac_address());
+    }
+
+    #[test]
+    fn ignores_requests_for_others() {
+        let now = Instant::now();
+        let (mut a, mut b) = nodes();
+
+        let sent = a.send(IP_C, ETHERTYPE_IPV4, PAYLOAD.into(), now);
+
+        assert!(b.receive(&sent[0], now).expect("Frame is ARP").is_empty());
+        assert_eq!(b.cache().lookup(IP_A, now), None);
+    }
+
+    #[test]
+    fn gratuitous_updates_known() {
+        let now = Instant::now();
+        let (mut a, mut b) = nodes();
+        let new_mac = MacAddress::new([0x02, 0, 0, 0, 0, 0xFF]);
+
+        // Unknown addresses are not learned from announcements
+        let announcement = a.set_ip_address(IP_A);
+        assert!(packet(&announcement).is_gratuitous());
+        b.receive(&announcement, now).expect("Frame is ARP");
+        assert_eq!(b.cache().lookup(IP_A, now), None);
+
+        // But known ones are updated
+        b.cache_mut().insert(IP_C, a.mac_address(), now);
+        let mut moved = Arp::new(new_mac);
+        let announcement = moved.set_ip_address(IP_C);
+        b.receive(&announcement, now).expect("Frame is ARP");
+        assert_eq!(b.cache().lookup(IP_C, now), Some(new_mac));
+    }
+
+    #[test]
+    fn retries_then_drops() {
+        let now = Instant::now();
+        let (mut a, _) = nodes();
+
+        a.send(IP_C, ETHERTYPE_IPV4, PAYLOAD.into(), now);
+
+        assert!(a.poll(now).is_empty());
+
+        let mut time = now;
+        for _ in 1..MAX_REQUESTS {
+            time += DEFAULT_REQUEST_TIMEOUT;
+            let retries = a.poll(time);
+            assert_eq!(retries.len(), 1);
+            assert_eq!(packet(&retries[0]).target_ip(), IP_C);
+        }
+
+        time += DEFAULT_REQUEST_TIMEOUT;
+        assert!(a.poll(time).is_empty());
+        assert_eq!(a.dropped_packets(), 1);
+    }
+}
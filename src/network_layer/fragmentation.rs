@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bit_string::BitString, data_link_layer::frame::ipv4::Ipv4Packet, ip_address::Ipv4Address,
+    physical_layer::cable::Cable,
+};
+
+use super::icmp::IcmpMessage;
+
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The result of preparing a packet for a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Egress {
+    /// The packets to send over the link, the original packet if it fits.
+    Send(Vec<Ipv4Packet>),
+    /// The packet was dropped. Holds the ICMP error to send back to its
+    /// source, unless no error may be sent about it.
+    Dropped(Option<Ipv4Packet>),
+}
+
+impl Egress {
+    /// Drops the packet, reporting the error if that's allowed.
+    #[must_use]
+    pub fn drop_with(packet: &Ipv4Packet, error: &IcmpMessage, local: Ipv4Address) -> Self {
+        let report =
+            IcmpMessage::may_report(packet).then(|| error.to_packet(local, packet.source()));
+        Self::Dropped(report)
+    }
+}
+
+/// Fragments the packet to fit the MTU of the cable. Packets that don't fit
+/// but have DF set are dropped, with `local` the address the error is sent
+/// from.
+#[must_use]
+pub fn fragment_for(packet: &Ipv4Packet, cable: &Cable, local: Ipv4Address) -> Egress {
+    let mtu = cable.mtu();
+
+    packet.fragment(mtu).map_or_else(
+        || {
+            Egress::drop_with(
+                packet,
+                &IcmpMessage::fragmentation_needed(packet, mtu),
+                local,
+            )
+        },
+        Egress::Send,
+    )
+}
+
+/// Fragments belong to the same packet if these fields match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FragmentKey {
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    protocol: u8,
+    identification: u16,
+}
+
+impl FragmentKey {
+    const fn new(packet: &Ipv4Packet) -> Self {
+        Self {
+            source: packet.source(),
+            destination: packet.destination(),
+            protocol: packet.protocol(),
+            identification: packet.identification(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PartialPacket {
+    /// The fragment at offset zero, which provides the header.
+    first: Option<Ipv4Packet>,
+    bytes: Vec<Option<u8>>,
+    /// Set once the last fragment arrived.
+    total_len: Option<usize>,
+    expires: Instant,
+}
+
+impl PartialPacket {
+    /// Adds the fragment, returning false if it conflicts with the fragments
+    /// received so far.
+    fn insert(&mut self, fragment: &Ipv4Packet) -> bool {
+        let start = usize::from(fragment.fragment_offset()) * 8;
+        let data = fragment.payload().as_vec_exact_u8();
+        let end = start + data.len();
+
+        // The packet takes the header of the first fragment, and has to fit
+        // the total length field with it
+        let first = match &self.first {
+            Some(first) if start != 0 => first,
+            _ => fragment,
+        };
+        let header_len = usize::from(first.ihl()) * 4;
+        if header_len + end.max(self.bytes.len()) > usize::from(u16::MAX) {
+            return false;
+        }
+
+        if !fragment.more_fragments() {
+            if self.total_len.is_some_and(|len| len != end) {
+                return false;
+            }
+            self.total_len = Some(end);
+        }
+        if self.total_len.is_some_and(|len| end > len) {
+            return false;
+        }
+
+        if self.bytes.len() < end {
+            self.bytes.resize(end, None);
+        }
+
+        // Overlapping data is accepted as long as it's identical, which
+        // happens when fragments are duplicated
+        for (slot, byte) in self.bytes[start..end].iter_mut().zip(data) {
+            match slot {
+                Some(existing) if *existing != byte => return false,
+                _ => *slot = Some(byte),
+            }
+        }
+
+        if start == 0 {
+            self.first = Some(fragment.clone());
+        }
+
+        true
+    }
+
+    fn reassemble(&self) -> Option<Ipv4Packet> {
+        let first = self.first.as_ref()?;
+        let total_len = self.total_len?;
+
+        let mut payload = BitString::with_capacity(total_len * 8);
+        for byte in &self.bytes[..total_len] {
+            payload.append_u8((*byte)?);
+        }
+
+        let packet = first
+            .builder()
+            .set_more_fragments(false)
+            .set_fragment_offset(0)
+            .build(payload);
+
+        Some(packet)
+    }
+}
+
+/// Collects fragments until the packet they belong to is complete. Packets
+/// with conflicting overlapping fragments are discarded, as are packets that
+/// aren't complete before the timeout.
+#[derive(Debug)]
+pub struct Reassembler {
+    timeout: Duration,
+    partial: HashMap<FragmentKey, PartialPacket>,
+    discarded: usize,
+}
+
+impl Reassembler {
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            partial: HashMap::new(),
+            discarded: 0,
+        }
+    }
+
+    /// Handles a received packet, returning it once it's complete. Packets
+    /// that aren't fragmented are returned immediately.
+    pub fn receive(&mut self, packet: &Ipv4Packet, now: Instant) -> Option<Ipv4Packet> {
+        if !packet.more_fragments() && packet.fragment_offset() == 0 {
+            return Some(packet.clone());
+        }
+
+        let key = FragmentKey::new(packet);
+        let timeout = self.timeout;
+        let partial = self.partial.entry(key).or_insert_with(|| PartialPacket {
+            first: None,
+            bytes: Vec::new(),
+            total_len: None,
+            expires: now + timeout,
+        });
+
+        if !partial.insert(packet) {
+            self.partial.remove(&key);
+            self.discarded += 1;
+            return None;
+        }
+
+        let reassembled = partial.reassemble()?;
+        self.partial.remove(&key);
+
+        Some(reassembled)
+    }
+
+    /// Discards the packets whose timeout passed. Returns the first fragment
+    /// of each that had one, as the source is told about the timeout with it.
+    pub fn remove_expired(&mut self, now: Instant) -> Vec<Ipv4Packet> {
+        let mut expired = Vec::new();
+
+        self.partial.retain(|_, partial| {
+            if partial.expires > now {
+                return true;
+            }
+            expired.extend(partial.first.take());
+            false
+        });
+        self.discarded += expired.len();
+
+        expired
+    }
+
+    /// The amount of packets still waiting for fragments.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// The amount of packets discarded because of conflicting fragments or
+    /// the timeout.
+    #[must_use]
+    pub const fn discarded(&self) -> usize {
+        self.discarded
+    }
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_REASSEMBLY_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        bit_string::BitString,
+        data_link_layer::frame::ipv4::{Ipv4Packet, Ipv4PacketBuilder, PROTOCOL_UDP},
+        ip_address::Ipv4Address,
+    };
+
+    use super::{Reassembler, DEFAULT_REASSEMBLY_TIMEOUT};
+
+    const SOURCE: Ipv4Address = Ipv4Address::new([10, 0, 0, 1]);
+    const DESTINATION: Ipv4Address = Ipv4Address::new([10, 0, 1, 1]);
+
+    fn packet(identification: u16) -> Ipv4Packet {
+        let payload: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        Ipv4PacketBuilder::new()
+            .set_source(SOURCE)
+            .set_destination(DESTINATION)
+            .set_protocol(PROTOCOL_UDP)
+            .set_identification(identification)
+            .build(BitString::from(payload.as_slice()))
+    }
+
+    #[test]
+    fn reassembles_out_of_order() {
+        let original = packet(1);
+        let mut fragments = original.fragment(300).expect("Packet may be fragmented");
+        fragments.reverse();
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::default();
+
+        let (last, rest) = fragments.split_last().expect("Packet was fragmented");
+        for fragment in rest {
+            assert_eq!(reassembler.receive(fragment, now), None);
+        }
+        assert_eq!(reassembler.pending(), 1);
+
+        assert_eq!(reassembler.receive(last, now), Some(original));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn passes_unfragmented() {
+        let original = packet(1);
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(
+            reassembler.receive(&original, Instant::now()),
+            Some(original)
+        );
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn keeps_packets_apart() {
+        let first = packet(1);
+        let second = packet(2);
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::default();
+
+        let first_fragments = first.fragment(600).expect("Packet may be fragmented");
+        let second_fragments = second.fragment(600).expect("Packet may be fragmented");
+
+        assert_eq!(reassembler.receive(&first_fragments[0], now), None);
+        assert_eq!(reassembler.receive(&second_fragments[1], now), None);
+        assert_eq!(reassembler.receive(&second_fragments[0], now), Some(second));
+        assert_eq!(reassembler.receive(&first_fragments[1], now), Some(first));
+    }
+
+    #[test]
+    fn accepts_identical_overlap() {
+        let original = packet(1);
+
+        let large = original.fragment(600).expect("Packet may be fragmented");
+        let small = original.fragment(200).expect("Packet may be fragmented");
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(reassembler.receive(&small[1], now), None);
+        assert_eq!(reassembler.receive(&small[1], now), None);
+        assert_eq!(reassembler.receive(&large[0], now), None);
+        assert_eq!(reassembler.receive(&large[1], now), Some(original));
+    }
+
+    #[test]
+    fn discards_conflicting_overlap() {
+        let original = packet(1);
+        let fragments = original.fragment(600).expect("Packet may be fragmented");
+
+        // Same position, different data
+        let forged = fragments[1]
+            .builder()
+            .build(BitString::with_zeroes(fragments[1].payload().len()));
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::default();
+
+        assert_eq!(reassembler.receive(&fragments[1], now), None);
+        assert_eq!(reassembler.receive(&forged, now), None);
+        assert_eq!(reassembler.pending(), 0);
+        assert_eq!(reassembler.discarded(), 1);
+
+        // The remaining fragment starts over and can't complete the packet
+        assert_eq!(reassembler.receive(&fragments[0], now), None);
+    }
+
+    #[test]
+    fn discards_oversized_packets() {
+        // Fragments of 8184 bytes up to the largest offset
+        let fragment = |offset: u16, len: usize| {
+            Ipv4PacketBuilder::new()
+                .set_source(SOURCE)
+                .set_destination(DESTINATION)
+                .set_protocol(PROTOCOL_UDP)
+                .set_identification(1)
+                .set_more_fragments(offset < 8184)
+                .set_fragment_offset(offset)
+                .build(BitString::with_zeroes(len * 8))
+        };
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::default();
+
+        for offset in (0..8184).step_by(1023) {
+            assert_eq!(reassembler.receive(&fragment(offset, 8184), now), None);
+        }
+
+        // With its header the packet would exceed the total length field
+        assert_eq!(reassembler.receive(&fragment(8184, 64), now), None);
+        assert_eq!(reassembler.discarded(), 1);
+
+        for offset in (0..8184).step_by(1023) {
+            assert_eq!(reassembler.receive(&fragment(offset, 8184), now), None);
+        }
+
+        // The largest packet still fits
+        let packet = reassembler
+            .receive(&fragment(8184, 43), now)
+            .expect("Every fragment arrived");
+        assert_eq!(packet.total_length(), u16::MAX);
+    }
+
+    #[test]
+    fn times_out() {
+        let original = packet(1);
+        let fragments = original.fragment(400).expect("Packet may be fragmented");
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+
+        reassembler.receive(&fragments[0], now);
+        reassembler.receive(&fragments[1], now + Duration::from_secs(4));
+
+        assert!(reassembler
+            .remove_expired(now + Duration::from_secs(4))
+            .is_empty());
+
+        let expired = reassembler.remove_expired(now + Duration::from_secs(5));
+        assert_eq!(expired, [fragments[0].clone()]);
+        assert_eq!(reassembler.discarded(), 1);
+
+        // Late fragments start a new packet
+        let later = now + DEFAULT_REASSEMBLY_TIMEOUT;
+        assert_eq!(reassembler.receive(&fragments[2], later), None);
+        assert_eq!(reassembler.pending(), 1);
+    }
+}
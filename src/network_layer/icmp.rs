@@ -0,0 +1,380 @@
+use anyhow::{bail, ensure};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::ipv4::{
+        transport_checksum, Ipv4Packet, Ipv4PacketBuilder, PROTOCOL_ICMP,
+    },
+    ip_address::Ipv4Address,
+};
+
+// Message types
+pub const ICMP_ECHO_REPLY: u8 = 0;
+pub const ICMP_DESTINATION_UNREACHABLE: u8 = 3;
+pub const ICMP_ECHO_REQUEST: u8 = 8;
+pub const ICMP_TIME_EXCEEDED: u8 = 11;
+
+// Destination unreachable codes
+pub const CODE_NETWORK_UNREACHABLE: u8 = 0;
+pub const CODE_HOST_UNREACHABLE: u8 = 1;
+pub const CODE_PROTOCOL_UNREACHABLE: u8 = 2;
+pub const CODE_PORT_UNREACHABLE: u8 = 3;
+pub const CODE_FRAGMENTATION_NEEDED: u8 = 4;
+
+// Time exceeded codes
+pub const CODE_TTL_EXCEEDED: u8 = 0;
+pub const CODE_REASSEMBLY_TIME_EXCEEDED: u8 = 1;
+
+const ICMP_HEADER_LEN: usize = 8;
+/// The amount of payload bytes of the offending packet quoted in errors.
+const QUOTED_PAYLOAD_LEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IcmpMessage {
+    EchoReply {
+        identifier: u16,
+        sequence: u16,
+        data: BitString,
+    },
+    /// The packet could not be delivered. The MTU of the next hop is only set
+    /// when fragmentation was needed.
+    DestinationUnreachable {
+        code: u8,
+        next_hop_mtu: u16,
+        original: BitString,
+    },
+    EchoRequest {
+        identifier: u16,
+        sequence: u16,
+        data: BitString,
+    },
+    /// The TTL ran out on the way, or the fragments of the packet did not all
+    /// arrive in time.
+    TimeExceeded { code: u8, original: BitString },
+}
+
+impl IcmpMessage {
+    #[must_use]
+    pub fn destination_unreachable(original: &Ipv4Packet, code: u8) -> Self {
+        Self::DestinationUnreachable {
+            code,
+            next_hop_mtu: 0,
+            original: quote(original),
+        }
+    }
+
+    /// The error sent back when a packet with DF set does not fit the next
+    /// hop.
+    #[must_use]
+    pub fn fragmentation_needed(original: &Ipv4Packet, next_hop_mtu: u16) -> Self {
+        Self::DestinationUnreachable {
+            code: CODE_FRAGMENTATION_NEEDED,
+            next_hop_mtu,
+            original: quote(original),
+        }
+    }
+
+    #[must_use]
+    pub fn time_exceeded(original: &Ipv4Packet, code: u8) -> Self {
+        Self::TimeExceeded {
+            code,
+            original: quote(original),
+        }
+    }
+
+    /// The reply to an echo request, carrying the same data.
+    #[must_use]
+    pub fn echo_reply(&self) -> Option<Self> {
+        match self {
+            Self::EchoRequest {
+                identifier,
+                sequence,
+                data,
+            } => Some(Self::EchoReply {
+                identifier: *identifier,
+                sequence: *sequence,
+                data: data.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::DestinationUnreachable { .. } | Self::TimeExceeded { .. }
+        )
+    }
+
+    /// The start of the packet an error was caused by.
+    #[must_use]
+    pub const fn original(&self) -> Option<&BitString> {
+        match self {
+            Self::DestinationUnreachable { original, .. } | Self::TimeExceeded { original, .. } => {
+                Some(original)
+            }
+            Self::EchoReply { .. } | Self::EchoRequest { .. } => None,
+        }
+    }
+
+    /// Whether an error may be sent about the packet. Errors are never sent
+    /// about errors, fragments other than the first, or broadcasts, to keep
+    /// them from multiplying.
+    #[must_use]
+    pub fn may_report(packet: &Ipv4Packet) -> bool {
+        if packet.fragment_offset() != 0 || packet.destination() == Ipv4Address::BROADCAST {
+            return false;
+        }
+
+        // Only the type is needed, which is in the first fragment
+        let payload = packet.payload();
+        let is_error = packet.protocol() == PROTOCOL_ICMP
+            && payload.len() >= 8
+            && matches!(
+                payload.get_u8(0),
+                ICMP_DESTINATION_UNREACHABLE | ICMP_TIME_EXCEEDED
+            );
+
+        !is_error
+    }
+
+    #[must_use]
+    pub fn encode(&self) -> BitString {
+        let mut output = BitString::new();
+
+        let (kind, code) = match self {
+            Self::EchoReply { .. } => (ICMP_ECHO_REPLY, 0),
+            Self::DestinationUnreachable { code, .. } => (ICMP_DESTINATION_UNREACHABLE, *code),
+            Self::EchoRequest { .. } => (ICMP_ECHO_REQUEST, 0),
+            Self::TimeExceeded { code, .. } => (ICMP_TIME_EXCEEDED, *code),
+        };
+
+        output.append_u8(kind);
+        output.append_u8(code);
+        // Checksum defaults to zero
+        output.append_u16(0);
+
+        match self {
+            Self::EchoReply {
+                identifier,
+                sequence,
+                data,
+            }
+            | Self::EchoRequest {
+                identifier,
+                sequence,
+                data,
+            } => {
+                output.append_u16(*identifier);
+                output.append_u16(*sequence);
+                output.append_bits(data.clone());
+            }
+            Self::DestinationUnreachable {
+                next_hop_mtu,
+                original,
+                ..
+            } => {
+                output.append_u16(0);
+                output.append_u16(*next_hop_mtu);
+                output.append_bits(original.clone());
+            }
+            Self::TimeExceeded { original, .. } => {
+                output.append_u32(0);
+                output.append_bits(original.clone());
+            }
+        }
+
+        // ICMP has no pseudo header, but shares the checksum of TCP and UDP
+        output.set_u16(16, transport_checksum(&output, PROTOCOL_ICMP, None));
+
+        output
+    }
+
+    /// Parses a message, verifying the checksum.
+    pub fn decode(data: &BitString) -> anyhow::Result<Self> {
+        ensure!(
+            data.len() >= ICMP_HEADER_LEN * 8 && data.len().is_multiple_of(8),
+            "Message of {} bits is not a valid ICMP message",
+            data.len()
+        );
+        ensure!(
+            transport_checksum(data, PROTOCOL_ICMP, None) == 0,
+            "Message has an invalid checksum"
+        );
+
+        let code = data.get_u8(8);
+        let identifier = data.get_u16(32);
+        let sequence = data.get_u16(48);
+        let rest = data.copy_len(ICMP_HEADER_LEN * 8, data.len() - ICMP_HEADER_LEN * 8);
+
+        match data.get_u8(0) {
+            ICMP_ECHO_REPLY => Ok(Self::EchoReply {
+                identifier,
+                sequence,
+                data: rest,
+            }),
+            ICMP_DESTINATION_UNREACHABLE => Ok(Self::DestinationUnreachable {
+                code,
+                next_hop_mtu: data.get_u16(48),
+                original: rest,
+            }),
+            ICMP_ECHO_REQUEST => Ok(Self::EchoRequest {
+                identifier,
+                sequence,
+                data: rest,
+            }),
+            ICMP_TIME_EXCEEDED => Ok(Self::TimeExceeded {
+                code,
+                original: rest,
+            }),
+            kind => bail!("Unsupported ICMP message type {kind}"),
+        }
+    }
+
+    /// Wraps the message in a packet.
+    #[must_use]
+    pub fn to_packet(&self, source: Ipv4Address, destination: Ipv4Address) -> Ipv4Packet {
+        Ipv4PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_protocol(PROTOCOL_ICMP)
+            .build(self.encode())
+    }
+
+    pub fn decode_ipv4(packet: &Ipv4Packet) -> anyhow::Result<Self> {
+        ensure!(
+            packet.protocol() == PROTOCOL_ICMP,
+            "Packet does not carry an ICMP message"
+        );
+
+        Self::decode(packet.payload())
+    }
+}
+
+/// The header and the start of the payload of a packet, which errors include
+/// so the source can tell which packet caused them.
+fn quote(packet: &Ipv4Packet) -> BitString {
+    let bs = packet.as_bit_string();
+    let header_len = usize::from(packet.ihl()) * 32;
+
+    bs.copy_len(0, bs.len().min(header_len + QUOTED_PAYLOAD_LEN * 8))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bit_string::BitString,
+        data_link_layer::frame::ipv4::{Ipv4Packet, Ipv4PacketBuilder, PROTOCOL_UDP},
+        ip_address::Ipv4Address,
+    };
+
+    use super::{IcmpMessage, CODE_FRAGMENTATION_NEEDED, CODE_PORT_UNREACHABLE, CODE_TTL_EXCEEDED};
+
+    const SOURCE: Ipv4Address = Ipv4Address::new([10, 0, 0, 1]);
+    const DESTINATION: Ipv4Address = Ipv4Address::new([10, 0, 1, 1]);
+    const ROUTER: Ipv4Address = Ipv4Address::new([10, 0, 0, 254]);
+
+    fn packet() -> Ipv4Packet {
+        Ipv4PacketBuilder::new()
+            .set_source(SOURCE)
+            .set_destination(DESTINATION)
+            .set_protocol(PROTOCOL_UDP)
+            .set_dont_fragment(true)
+            .build(BitString::with_ones(800 * 8))
+    }
+
+    #[test]
+    fn fragmentation_needed() {
+        let original = packet();
+        let message = IcmpMessage::fragmentation_needed(&original, 576);
+
+        let IcmpMessage::DestinationUnreachable {
+            code,
+            next_hop_mtu,
+            original: quoted,
+        } = &message
+        else {
+            panic!("Fragmentation needed is a destination unreachable message");
+        };
+        assert_eq!(*code, CODE_FRAGMENTATION_NEEDED);
+        assert_eq!(*next_hop_mtu, 576);
+        assert_eq!(quoted, &original.as_bit_string().copy_len(0, 28 * 8));
+
+        let encoded = message.encode();
+        assert_eq!(encoded.get_u32(0) & 0xFFFF_0000, 0x0304_0000);
+        assert_eq!(encoded.get_u32(32), 576);
+    }
+
+    #[test]
+    fn round_trip() {
+        let echo = IcmpMessage::EchoRequest {
+            identifier: 0x1234,
+            sequence: 7,
+            // Odd lengths are fine, only the checksum pads
+            data: BitString::from(b"abc".as_slice()),
+        };
+        let messages = [
+            echo.clone(),
+            echo.echo_reply().expect("Echo request has a reply"),
+            IcmpMessage::fragmentation_needed(&packet(), 1280),
+            IcmpMessage::destination_unreachable(&packet(), CODE_PORT_UNREACHABLE),
+            IcmpMessage::time_exceeded(&packet(), CODE_TTL_EXCEEDED),
+        ];
+
+        for message in messages {
+            let packet = message.to_packet(ROUTER, SOURCE);
+
+            assert_eq!(packet.destination(), SOURCE);
+            assert_eq!(
+                IcmpMessage::decode_ipv4(&packet).expect("Message is valid"),
+                message
+            );
+
+            let mut corrupted = message.encode();
+            corrupted.flip_bit(50);
+            assert!(IcmpMessage::decode(&corrupted).is_err());
+        }
+    }
+
+    #[test]
+    fn echo_layout() {
+        let echo = IcmpMessage::EchoRequest {
+            identifier: 1,
+            sequence: 2,
+            data: BitString::new(),
+        };
+
+        assert_eq!(
+            echo.encode().as_vec_exact_u8(),
+            [8, 0, 0xF7, 0xFC, 0, 1, 0, 2]
+        );
+        assert_eq!(echo.original(), None);
+        assert!(!echo.is_error());
+    }
+
+    #[test]
+    fn no_errors_about_errors() {
+        let original = packet();
+        assert!(IcmpMessage::may_report(&original));
+
+        let error = IcmpMessage::time_exceeded(&original, CODE_TTL_EXCEEDED);
+        assert!(error.is_error());
+        assert!(!IcmpMessage::may_report(&error.to_packet(ROUTER, SOURCE)));
+
+        let echo = IcmpMessage::EchoRequest {
+            identifier: 1,
+            sequence: 1,
+            data: BitString::new(),
+        };
+        assert!(IcmpMessage::may_report(
+            &echo.to_packet(SOURCE, DESTINATION)
+        ));
+
+        let fragment = original
+            .builder()
+            .set_fragment_offset(10)
+            .build(BitString::new());
+        assert!(!IcmpMessage::may_report(&fragment));
+    }
+}
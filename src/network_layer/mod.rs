@@ -0,0 +1,6 @@
+pub mod arp;
+pub mod fragmentation;
+pub mod icmp;
+pub mod ndp;
+pub mod neighbor_cache;
+pub mod ping;
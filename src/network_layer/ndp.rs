@@ -0,0 +1,656 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::{
+        ethernet::{append_mac, get_mac, EthernetFrame, EthernetFrameBuilder, ETHERTYPE_IPV6},
+        ipv4::{transport_checksum, PseudoHeader},
+        ipv6::{Ipv6Packet, Ipv6PacketBuilder, NEXT_HEADER_ICMPV6},
+    },
+    ip_address::Ipv6Address,
+    mac_address::MacAddress,
+};
+
+use super::{
+    arp::{DEFAULT_CACHE_TIMEOUT, DEFAULT_REQUEST_TIMEOUT, MAX_REQUESTS},
+    neighbor_cache::NeighborCache,
+};
+
+// ICMPv6 message types
+pub const ICMPV6_NEIGHBOR_SOLICITATION: u8 = 135;
+pub const ICMPV6_NEIGHBOR_ADVERTISEMENT: u8 = 136;
+
+// Option types
+const OPTION_SOURCE_LINK_LAYER: u8 = 1;
+const OPTION_TARGET_LINK_LAYER: u8 = 2;
+
+/// Neighbor discovery messages are only accepted from the same link, which
+/// is guaranteed by them arriving with the hop limit they were sent with.
+pub const NDP_HOP_LIMIT: u8 = 255;
+
+const NDP_MESSAGE_LEN: usize = 24;
+
+/// Resolved IPv6 addresses.
+pub type NeighborDiscoveryCache = NeighborCache<Ipv6Address>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NdpMessage {
+    /// Asks the owner of the target address for its link-layer address.
+    NeighborSolicitation {
+        target: Ipv6Address,
+        source_mac: Option<MacAddress>,
+    },
+    /// Answers a solicitation, or announces a change when unsolicited.
+    NeighborAdvertisement {
+        target: Ipv6Address,
+        target_mac: Option<MacAddress>,
+        router: bool,
+        solicited: bool,
+        override_entry: bool,
+    },
+}
+
+impl NdpMessage {
+    #[must_use]
+    pub const fn target(&self) -> Ipv6Address {
+        match self {
+            Self::NeighborSolicitation { target, .. }
+            | Self::NeighborAdvertisement { target, .. } => *target,
+        }
+    }
+
+    /// Serializes the ICMPv6 message, with the checksum over the addresses of
+    /// the carrying packet.
+    #[must_use]
+    pub fn encode(&self, pseudo_header: PseudoHeader) -> BitString {
+        let mut output = BitString::with_capacity(NDP_MESSAGE_LEN * 8 + 64);
+
+        let (kind, flags, link_layer) = match self {
+            Self::NeighborSolicitation { source_mac, .. } => (
+                ICMPV6_NEIGHBOR_SOLICITATION,
+                0,
+                source_mac.map(|mac| (OPTION_SOURCE_LINK_LAYER, mac)),
+            ),
+            Self::NeighborAdvertisement {
+                target_mac,
+                router,
+                solicited,
+                override_entry,
+                ..
+            } => (
+                ICMPV6_NEIGHBOR_ADVERTISEMENT,
+                u32::from(*router) << 31
+                    | u32::from(*solicited) << 30
+                    | u32::from(*override_entry) << 29,
+                target_mac.map(|mac| (OPTION_TARGET_LINK_LAYER, mac)),
+            ),
+        };
+
+        output.append_u8(kind);
+        output.append_u8(0); // Code
+        output.append_u16(0); // Checksum defaults to zero
+        output.append_u32(flags);
+        for byte in self.target().octets() {
+            output.append_u8(byte);
+        }
+
+        if let Some((option, mac)) = link_layer {
+            output.append_u8(option);
+            output.append_u8(1); // Length in units of 8 bytes
+            append_mac(&mut output, mac);
+        }
+
+        let checksum = transport_checksum(&output, NEXT_HEADER_ICMPV6, Some(pseudo_header));
+        output.set_u16(16, checksum);
+
+        output
+    }
+
+    /// Parses the message carried by the packet, verifying the checksum and
+    /// that it did not cross a router.
+    pub fn decode_ipv6(packet: &Ipv6Packet) -> anyhow::Result<Self> {
+        ensure!(
+            packet.next_header() == NEXT_HEADER_ICMPV6,
+            "Packet does not carry an ICMPv6 message"
+        );
+        ensure!(
+            packet.hop_limit() == NDP_HOP_LIMIT,
+            "Neighbor discovery message was forwarded"
+        );
+
+        let data = packet.payload();
+        ensure!(
+            data.len() >= NDP_MESSAGE_LEN * 8,
+            "Message of {} bits is too short for neighbor discovery",
+            data.len()
+        );
+        ensure!(
+            transport_checksum(data, NEXT_HEADER_ICMPV6, Some(packet.pseudo_header())) == 0,
+            "Message has an invalid checksum"
+        );
+
+        let target_octets = data.copy_len(64, 128).as_vec_exact_u8();
+        let target = Ipv6Address::new(target_octets.try_into().expect("Address is 16 bytes"));
+
+        // Link-layer address options, other options are skipped
+        let mut source_mac = None;
+        let mut target_mac = None;
+        let mut index = NDP_MESSAGE_LEN * 8;
+        while index + 16 <= data.len() {
+            let option = data.get_u8(index);
+            let len = usize::from(data.get_u8(index + 8)) * 64;
+            ensure!(
+                len > 0 && index + len <= data.len(),
+                "Option {option} does not fit in the message"
+            );
+
+            match option {
+                OPTION_SOURCE_LINK_LAYER => source_mac = Some(get_mac(data, index + 16)),
+                OPTION_TARGET_LINK_LAYER => target_mac = Some(get_mac(data, index + 16)),
+                _ => {}
+            }
+            index += len;
+        }
+
+        let flags = data.get_u32(32);
+
+        match data.get_u8(0) {
+            ICMPV6_NEIGHBOR_SOLICITATION => Ok(Self::NeighborSolicitation { target, source_mac }),
+            ICMPV6_NEIGHBOR_ADVERTISEMENT => Ok(Self::NeighborAdvertisement {
+                target,
+                target_mac,
+                router: flags & (0b1 << 31) != 0,
+                solicited: flags & (0b1 << 30) != 0,
+                override_entry: flags & (0b1 << 29) != 0,
+            }),
+            kind => bail!("ICMPv6 message type {kind} is not neighbor discovery"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingResolution {
+    packets: Vec<BitString>,
+    last_solicitation: Instant,
+    solicitations: usize,
+}
+
+/// Neighbor discovery for a single interface, which takes the place of ARP
+/// for IPv6. Every interface has a link-local address derived from its MAC
+/// address, and answers solicitations for it and any address added later.
+#[derive(Debug)]
+pub struct Ndp {
+    mac: MacAddress,
+    // The link-local address comes first
+    addresses: Vec<Ipv6Address>,
+    cache: NeighborDiscoveryCache,
+    pending: HashMap<Ipv6Address, PendingResolution>,
+    request_timeout: Duration,
+    dropped_packets: usize,
+}
+
+impl Ndp {
+    #[must_use]
+    pub fn new(mac: MacAddress) -> Self {
+        Self {
+            mac,
+            addresses: vec![Ipv6Address::link_local(mac)],
+            cache: NeighborDiscoveryCache::new(DEFAULT_CACHE_TIMEOUT),
+            pending: HashMap::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            dropped_packets: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn set_cache_timeout(self, timeout: Duration) -> Self {
+        Self {
+            cache: NeighborDiscoveryCache::new(timeout),
+            ..self
+        }
+    }
+
+    /// Sets how long to wait for an advertisement before soliciting again.
+    #[must_use]
+    pub fn set_request_timeout(self, request_timeout: Duration) -> Self {
+        Self {
+            request_timeout,
+            ..self
+        }
+    }
+
+    /// Adds an address this interface answers solicitations for, returning an
+    /// unsolicited advertisement announcing it to all nodes.
+    pub fn add_address(&mut self, ip: Ipv6Address) -> EthernetFrame {
+        if !self.addresses.contains(&ip) {
+            self.addresses.push(ip);
+        }
+
+        let advertisement = NdpMessage::NeighborAdvertisement {
+            target: ip,
+            target_mac: Some(self.mac),
+            router: false,
+            solicited: false,
+            override_entry: true,
+        };
+        self.message_frame(Ipv6Address::ALL_NODES, advertisement)
+    }
+
+    #[must_use]
+    pub fn addresses(&self) -> &[Ipv6Address] {
+        &self.addresses
+    }
+
+    #[must_use]
+    pub fn link_local(&self) -> Ipv6Address {
+        self.addresses[0]
+    }
+
+    #[must_use]
+    pub const fn mac_address(&self) -> MacAddress {
+        self.mac
+    }
+
+    #[must_use]
+    pub const fn cache(&self) -> &NeighborDiscoveryCache {
+        &self.cache
+    }
+
+    pub fn cache_mut(&mut self) -> &mut NeighborDiscoveryCache {
+        &mut self.cache
+    }
+
+    /// The amount of queued packets dropped because their address could not
+    /// be resolved.
+    #[must_use]
+    pub const fn dropped_packets(&self) -> usize {
+        self.dropped_packets
+    }
+
+    /// Sends an IPv6 packet to the given neighbor. Multicast addresses map
+    /// directly to ethernet addresses, others are resolved first, queueing
+    /// the packet and soliciting if there isn't a solicitation outstanding.
+    pub fn send(
+        &mut self,
+        target_ip: Ipv6Address,
+        payload: BitString,
+        now: Instant,
+    ) -> Vec<EthernetFrame> {
+        let target_mac = if target_ip.is_multicast() {
+            Some(target_ip.multicast_mac())
+        } else {
+            self.cache.lookup(target_ip, now)
+        };
+
+        if let Some(target_mac) = target_mac {
+            return vec![self.payload_frame(target_mac, payload)];
+        }
+
+        if let Some(pending) = self.pending.get_mut(&target_ip) {
+            pending.packets.push(payload);
+            return Vec::new();
+        }
+
+        self.pending.insert(
+            target_ip,
+            PendingResolution {
+                packets: vec![payload],
+                last_solicitation: now,
+                solicitations: 1,
+            },
+        );
+
+        vec![self.solicitation(target_ip)]
+    }
+
+    /// Handles a received neighbor discovery frame, returning the frames to
+    /// send in response. These are an advertisement if the frame solicited
+    /// one of our addresses, and any packets that were waiting on the target
+    /// of an advertisement.
+    pub fn receive(
+        &mut self,
+        frame: &EthernetFrame,
+        now: Instant,
+    ) -> anyhow::Result<Vec<EthernetFrame>> {
+        ensure!(
+            frame.ether_type() == ETHERTYPE_IPV6,
+            "Frame does not carry an IPv6 packet"
+        );
+
+        let packet = Ipv6Packet::decode(frame.payload())?;
+        let message = NdpMessage::decode_ipv6(&packet)?;
+
+        if frame.source() == self.mac {
+            return Ok(Vec::new());
+        }
+
+        match message {
+            NdpMessage::NeighborSolicitation { target, source_mac } => {
+                if !self.addresses.contains(&target) {
+                    return Ok(Vec::new());
+                }
+
+                // Solicitations from the unspecified address check whether
+                // an address is in use, and are answered to all nodes
+                let source = packet.source();
+                let destination = if source.is_unspecified() {
+                    Ipv6Address::ALL_NODES
+                } else {
+                    if let Some(mac) = source_mac {
+                        self.cache.insert(source, mac, now);
+                    }
+                    source
+                };
+
+                let advertisement = NdpMessage::NeighborAdvertisement {
+                    target,
+                    target_mac: Some(self.mac),
+                    router: false,
+                    solicited: !source.is_unspecified(),
+                    override_entry: true,
+                };
+
+                let mut frames = self.send(
+                    destination,
+                    self.message(target, destination, advertisement),
+                    now,
+                );
+                frames.extend(self.flush(source, now));
+                Ok(frames)
+            }
+            NdpMessage::NeighborAdvertisement {
+                target,
+                target_mac,
+                override_entry,
+                ..
+            } => {
+                let mac = target_mac.unwrap_or(frame.source());
+
+                // Known entries are only replaced when the advertisement asks
+                // for it, others are only added when we asked for them
+                let known = self.cache.lookup(target, now).is_some();
+                if (known && override_entry) || self.pending.contains_key(&target) {
+                    self.cache.insert(target, mac, now);
+                }
+
+                Ok(self.flush(target, now))
+            }
+        }
+    }
+
+    /// Resends solicitations that timed out, and drops the queued packets of
+    /// addresses that didn't answer any of them.
+    pub fn poll(&mut self, now: Instant) -> Vec<EthernetFrame> {
+        self.cache.remove_expired(now);
+
+        let request_timeout = self.request_timeout;
+        let mut dropped_packets = 0;
+        self.pending.retain(|_, pending| {
+            let timed_out = now.duration_since(pending.last_solicitation) >= request_timeout;
+            if timed_out && pending.solicitations >= MAX_REQUESTS {
+                dropped_packets += pending.packets.len();
+                return false;
+            }
+            true
+        });
+        self.dropped_packets += dropped_packets;
+
+        let mut retries = Vec::new();
+        for (ip, pending) in &mut self.pending {
+            if now.duration_since(pending.last_solicitation) >= request_timeout {
+                pending.last_solicitation = now;
+                pending.solicitations += 1;
+                retries.push(*ip);
+            }
+        }
+
+        retries
+            .into_iter()
+            .map(|ip| self.solicitation(ip))
+            .collect()
+    }
+
+    /// Sends the packets queued for the address, if it's resolved.
+    fn flush(&mut self, ip: Ipv6Address, now: Instant) -> Vec<EthernetFrame> {
+        let Some(mac) = self.cache.lookup(ip, now) else {
+            return Vec::new();
+        };
+
+        self.pending
+            .remove(&ip)
+            .map(|pending| {
+                pending
+                    .packets
+                    .into_iter()
+                    .map(|payload| self.payload_frame(mac, payload))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Solicitations go to the solicited-node group of the target, so only
+    /// nodes sharing the low bits of the address see them.
+    fn solicitation(&self, target: Ipv6Address) -> EthernetFrame {
+        let solicitation = NdpMessage::NeighborSolicitation {
+            target,
+            source_mac: Some(self.mac),
+        };
+        self.message_frame(target.solicited_node(), solicitation)
+    }
+
+    fn message_frame(&self, destination: Ipv6Address, message: NdpMessage) -> EthernetFrame {
+        let payload = self.message(message.target(), destination, message);
+        self.payload_frame(destination.multicast_mac(), payload)
+    }
+
+    /// Wraps the message in a packet from the link-local address, or from
+    /// the target when advertising one of our own addresses.
+    fn message(
+        &self,
+        target: Ipv6Address,
+        destination: Ipv6Address,
+        message: NdpMessage,
+    ) -> BitString {
+        let source = match message {
+            NdpMessage::NeighborAdvertisement { .. } => target,
+            NdpMessage::NeighborSolicitation { .. } => self.link_local(),
+        };
+        let pseudo_header = PseudoHeader::Ipv6 {
+            source,
+            destination,
+        };
+
+        Ipv6PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_next_header(NEXT_HEADER_ICMPV6)
+            .set_hop_limit(NDP_HOP_LIMIT)
+            .build(message.encode(pseudo_header))
+            .as_bit_string()
+            .clone()
+    }
+
+    fn payload_frame(&self, destination: MacAddress, payload: BitString) -> EthernetFrame {
+        EthernetFrameBuilder::new()
+            .set_destination(destination)
+            .set_source(self.mac)
+            .set_ether_type(ETHERTYPE_IPV6)
+            .build(payload)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use crate::{
+        bit_string::BitString,
+        data_link_layer::frame::{
+            ethernet::{EthernetFrame, ETHERTYPE_IPV6},
+            ipv4::PseudoHeader,
+            ipv6::{Ipv6Packet, Ipv6PacketBuilder, NEXT_HEADER_ICMPV6},
+        },
+        ip_address::Ipv6Address,
+        mac_address::{MacAddress, MacAddressGenerator},
+    };
+
+    use super::{Ndp, NdpMessage, DEFAULT_REQUEST_TIMEOUT, MAX_REQUESTS, NDP_HOP_LIMIT};
+
+    const GLOBAL_B: Ipv6Address = Ipv6Address::from_u128(0x2001_0DB8 << 96 | 0xB);
+    const PAYLOAD: &[u8] = b"Hello world!";
+
+    fn nodes() -> (Ndp, Ndp) {
+        let mut mac_gen = MacAddressGenerator::new(42);
+        (Ndp::new(mac_gen.gen_addr()), Ndp::new(mac_gen.gen_addr()))
+    }
+
+    fn message(frame: &EthernetFrame) -> NdpMessage {
+        assert_eq!(frame.ether_type(), ETHERTYPE_IPV6);
+        let packet = Ipv6Packet::decode(frame.payload()).expect("Frame carries a packet");
+        NdpMessage::decode_ipv6(&packet).expect("Packet carries a message")
+    }
+
+    fn packet(message: NdpMessage, hop_limit: u8) -> Ipv6Packet {
+        let source = Ipv6Address::link_local(MacAddress::new([0x02, 0, 0, 0, 0, 0x01]));
+        let destination = message.target().solicited_node();
+        let pseudo_header = PseudoHeader::Ipv6 {
+            source,
+            destination,
+        };
+
+        Ipv6PacketBuilder::new()
+            .set_source(source)
+            .set_destination(destination)
+            .set_next_header(NEXT_HEADER_ICMPV6)
+            .set_hop_limit(hop_limit)
+            .build(message.encode(pseudo_header))
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let solicitation = NdpMessage::NeighborSolicitation {
+            target: GLOBAL_B,
+            source_mac: Some(MacAddress::new([0x02, 0, 0, 0, 0, 0x01])),
+        };
+        let advertisement = NdpMessage::NeighborAdvertisement {
+            target: GLOBAL_B,
+            target_mac: None,
+            router: true,
+            solicited: false,
+            override_entry: true,
+        };
+
+        for message in [solicitation, advertisement] {
+            let packet = packet(message, NDP_HOP_LIMIT);
+            assert_eq!(NdpMessage::decode_ipv6(&packet).expect("Valid"), message);
+        }
+
+        // Forwarded messages come from another link
+        assert!(NdpMessage::decode_ipv6(&packet(solicitation, NDP_HOP_LIMIT - 1)).is_err());
+
+        let mut corrupted = packet(solicitation, NDP_HOP_LIMIT).as_bit_string().clone();
+        corrupted.flip_bit(40 * 8 + 100);
+        let corrupted = Ipv6Packet::decode(&corrupted).expect("Header is intact");
+        assert!(NdpMessage::decode_ipv6(&corrupted).is_err());
+    }
+
+    #[test]
+    fn resolves_and_flushes_queue() {
+        let now = Instant::now();
+        let (mut a, mut b) = nodes();
+        let b_ip = b.link_local();
+
+        // The first packet triggers a solicitation, the second just waits
+        let sent = a.send(b_ip, PAYLOAD.into(), now);
+        assert!(a.send(b_ip, PAYLOAD.into(), now).is_empty());
+
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].destination(), b_ip.solicited_node().multicast_mac());
+        assert!(matches!(
+            message(&sent[0]),
+            NdpMessage::NeighborSolicitation { target, .. } if target == b_ip
+        ));
+
+        let replies = b.receive(&sent[0], now).expect("Frame is NDP");
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].destination(), a.mac_address());
+        assert!(matches!(
+            message(&replies[0]),
+            NdpMessage::NeighborAdvertisement {
+                solicited: true,
+                ..
+            }
+        ));
+        // The solicitation taught B about A
+        assert_eq!(b.cache().lookup(a.link_local(), now), Some(a.mac_address()));
+
+        let flushed = a.receive(&replies[0], now).expect("Frame is NDP");
+        assert_eq!(flushed.len(), 2);
+        for frame in &flushed {
+            assert_eq!(frame.destination(), b.mac_address());
+            // Ethernet pads the payload
+            let payload = frame.payload().copy_len(0, PAYLOAD.len() * 8);
+            assert_eq!(payload, BitString::from(PAYLOAD));
+        }
+    }
+
+    #[test]
+    fn added_addresses() {
+        let now = Instant::now();
+        let (mut a, mut b) = nodes();
+
+        let announcement = b.add_address(GLOBAL_B);
+        assert_eq!(
+            announcement.destination(),
+            Ipv6Address::ALL_NODES.multicast_mac()
+        );
+
+        // Unsolicited advertisements don't add new entries
+        assert!(a
+            .receive(&announcement, now)
+            .expect("Frame is NDP")
+            .is_empty());
+        assert_eq!(a.cache().lookup(GLOBAL_B, now), None);
+
+        let sent = a.send(GLOBAL_B, PAYLOAD.into(), now);
+        let replies = b.receive(&sent[0], now).expect("Frame is NDP");
+        let flushed = a.receive(&replies[0], now).expect("Frame is NDP");
+
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(a.cache().lookup(GLOBAL_B, now), Some(b.mac_address()));
+    }
+
+    #[test]
+    fn multicast_needs_no_resolution() {
+        let (mut a, _) = nodes();
+
+        let sent = a.send(Ipv6Address::ALL_ROUTERS, PAYLOAD.into(), Instant::now());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            sent[0].destination(),
+            MacAddress::new([0x33, 0x33, 0, 0, 0, 2])
+        );
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let mut now = Instant::now();
+        let (mut a, b) = nodes();
+
+        a.send(b.link_local(), PAYLOAD.into(), now);
+
+        for _ in 1..MAX_REQUESTS {
+            now += DEFAULT_REQUEST_TIMEOUT;
+            assert_eq!(a.poll(now).len(), 1);
+        }
+
+        now += DEFAULT_REQUEST_TIMEOUT;
+        assert!(a.poll(now).is_empty());
+        assert_eq!(a.dropped_packets(), 1);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use crate::mac_address::MacAddress;
+
+/// Resolved addresses, each forgotten after the timeout. Shared by ARP and
+/// neighbor discovery, which only differ in the IP version they resolve.
+#[derive(Debug, Clone)]
+pub struct NeighborCache<A> {
+    entries: HashMap<A, (MacAddress, Instant)>,
+    timeout: Duration,
+}
+
+impl<A: Copy + Eq + Hash> NeighborCache<A> {
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            timeout,
+        }
+    }
+
+    pub fn insert(&mut self, ip: A, mac: MacAddress, now: Instant) {
+        self.entries.insert(ip, (mac, now + self.timeout));
+    }
+
+    /// Refreshes the entry for the address if there is one, returning whether
+    /// there was.
+    pub fn update(&mut self, ip: A, mac: MacAddress, now: Instant) -> bool {
+        if self.lookup(ip, now).is_none() {
+            return false;
+        }
+
+        self.insert(ip, mac, now);
+        true
+    }
+
+    #[must_use]
+    pub fn lookup(&self, ip: A, now: Instant) -> Option<MacAddress> {
+        self.entries
+            .get(&ip)
+            .filter(|(_, expires)| now < *expires)
+            .map(|(mac, _)| *mac)
+    }
+
+    pub fn remove(&mut self, ip: A) -> Option<MacAddress> {
+        self.entries.remove(&ip).map(|(mac, _)| mac)
+    }
+
+    pub fn remove_expired(&mut self, now: Instant) {
+        self.entries.retain(|_, (_, expires)| now < *expires);
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bit_string::BitString,
+    data_link_layer::frame::ipv4::{Ipv4Packet, PROTOCOL_ICMP},
+    ip_address::Ipv4Address,
+};
+
+use super::icmp::{IcmpMessage, ICMP_ECHO_REQUEST};
+
+/// The amount of data bytes in an echo request, as sent by most ping tools.
+pub const DEFAULT_PING_DATA_LEN: usize = 56;
+pub const DEFAULT_MAX_HOPS: u8 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingReply {
+    pub sequence: u16,
+    pub from: Ipv4Address,
+    pub ttl: u8,
+    pub rtt: Duration,
+}
+
+/// Sends echo requests and matches the replies to them. Time is passed in,
+/// so round trip times follow the simulation rather than the wall clock.
+#[derive(Debug)]
+pub struct Ping {
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    identifier: u16,
+    next_sequence: u16,
+    sent: HashMap<u16, Instant>,
+}
+
+impl Ping {
+    #[must_use]
+    pub fn new(source: Ipv4Address, destination: Ipv4Address, identifier: u16) -> Self {
+        Self {
+            source,
+            destination,
+            identifier,
+            next_sequence: 0,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// The next echo request to send.
+    pub fn request(&mut self, now: Instant) -> Ipv4Packet {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.sent.insert(sequence, now);
+
+        echo_request(self.identifier, sequence).to_packet(self.source, self.destination)
+    }
+
+    /// Matches a received packet to an outstanding request. Anything that
+    /// isn't a reply to one is ignored.
+    pub fn receive(&mut self, packet: &Ipv4Packet, now: Instant) -> Option<PingReply> {
+        let Ok(IcmpMessage::EchoReply {
+            identifier,
+            sequence,
+            ..
+        }) = IcmpMessage::decode_ipv4(packet)
+        else {
+            return None;
+        };
+
+        if identifier != self.identifier || packet.source() != self.destination {
+            return None;
+        }
+
+        let sent = self.sent.remove(&sequence)?;
+
+        Some(PingReply {
+            sequence,
+            from: packet.source(),
+            ttl: packet.ttl(),
+            rtt: now.duration_since(sent),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    pub ttl: u8,
+    pub address: Ipv4Address,
+    pub rtt: Duration,
+    /// Whether the hop is the destination, or reported it can't be reached.
+    pub reached: bool,
+}
+
+/// Finds the routers on the path to a destination by sending echo requests
+/// with increasing TTLs, each router on the way answering with a time
+/// exceeded error.
+#[derive(Debug)]
+pub struct Traceroute {
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    identifier: u16,
+    next_sequence: u16,
+    // TTL and send time of every probe
+    probes: HashMap<u16, (u8, Instant)>,
+}
+
+impl Traceroute {
+    #[must_use]
+    pub fn new(source: Ipv4Address, destination: Ipv4Address, identifier: u16) -> Self {
+        Self {
+            source,
+            destination,
+            identifier,
+            next_sequence: 0,
+            probes: HashMap::new(),
+        }
+    }
+
+    pub fn probe(&mut self, ttl: u8, now: Instant) -> Ipv4Packet {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.probes.insert(sequence, (ttl, now));
+
+        let echo = echo_request(self.identifier, sequence);
+        echo.to_packet(self.source, self.destination)
+            .builder()
+            .set_ttl(ttl)
+            .build(echo.encode())
+    }
+
+    /// Matches a reply or error to an outstanding probe.
+    pub fn receive(&mut self, packet: &Ipv4Packet, now: Instant) -> Option<Hop> {
+        let message = IcmpMessage::decode_ipv4(packet).ok()?;
+
+        let (identifier, sequence, reached) = match &message {
+            IcmpMessage::EchoReply {
+                identifier,
+                sequence,
+                ..
+            } => (*identifier, *sequence, true),
+            IcmpMessage::TimeExceeded { original, .. } => {
+                let (identifier, sequence) = quoted_echo(original)?;
+                (identifier, sequence, false)
+            }
+            IcmpMessage::DestinationUnreachable { original, .. } => {
+                let (identifier, sequence) = quoted_echo(original)?;
+                (identifier, sequence, true)
+            }
+            IcmpMessage::EchoRequest { .. } => return None,
+        };
+
+        if identifier != self.identifier {
+            return None;
+        }
+
+        let (ttl, sent) = self.probes.remove(&sequence)?;
+
+        Some(Hop {
+            ttl,
+            address: packet.source(),
+            rtt: now.duration_since(sent),
+            reached,
+        })
+    }
+}
+
+fn echo_request(identifier: u16, sequence: u16) -> IcmpMessage {
+    IcmpMessage::EchoRequest {
+        identifier,
+        sequence,
+        data: BitString::with_zeroes(DEFAULT_PING_DATA_LEN * 8),
+    }
+}
+
+/// The identifier and sequence number of the echo request quoted in an
+/// error.
+fn quoted_echo(original: &BitString) -> Option<(u16, u16)> {
+    let header_len = usize::from(original.get_u8(0) & 0b1111) * 32;
+
+    let is_echo = original.len() >= header_len + 64
+        && original.get_u8(72) == PROTOCOL_ICMP
+        && original.get_u8(header_len) == ICMP_ECHO_REQUEST;
+
+    is_echo.then(|| {
+        (
+            original.get_u16(header_len + 32),
+            original.get_u16(header_len + 48),
+        )
+    })
+}
+
+/// Pings the destination `count` times, one request every `interval`.
+/// `network` delivers a packet sent at the given time, and returns the
+/// response that makes it back to the source with its arrival time, if any.
+pub fn ping<N>(
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    count: u16,
+    interval: Duration,
+    start: Instant,
+    mut network: N,
+) -> Vec<Option<PingReply>>
+where
+    N: FnMut(Ipv4Packet, Instant) -> Option<(Ipv4Packet, Instant)>,
+{
+    let mut ping = Ping::new(source, destination, 0);
+
+    (0..count)
+        .map(|idx| {
+            let now = start + interval * u32::from(idx);
+            let (response, arrival) = network(ping.request(now), now)?;
+            ping.receive(&response, arrival)
+        })
+        .collect()
+}
+
+/// Traces the path to the destination, with one probe per TTL. Stops at the
+/// destination or after `max_hops`. `network` works as with [`ping`], lost
+/// probes show up as [`None`].
+pub fn traceroute<N>(
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    max_hops: u8,
+    start: Instant,
+    mut network: N,
+) -> Vec<Option<Hop>>
+where
+    N: FnMut(Ipv4Packet, Instant) -> Option<(Ipv4Packet, Instant)>,
+{
+    let mut traceroute = Traceroute::new(source, destination, 0);
+    let mut now = start;
+    let mut hops = Vec::new();
+
+    for ttl in 1..=max_hops {
+        let hop = network(traceroute.probe(ttl, now), now).and_then(|(response, arrival)| {
+            now = arrival;
+            traceroute.receive(&response, arrival)
+        });
+        hops.push(hop);
+
+        if hop.is_some_and(|hop| hop.reached) {
+            break;
+        }
+    }
+
+    hops
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        data_link_layer::frame::ipv4::Ipv4Packet,
+        ip_address::Ipv4Address,
+        network_layer::icmp::{IcmpMessage, CODE_TTL_EXCEEDED},
+    };
+
+    use super::{ping, traceroute, Hop, Ping};
+
+    const SOURCE: Ipv4Address = Ipv4Address::new([10, 0, 0, 1]);
+    const DESTINATION: Ipv4Address = Ipv4Address::new([10, 0, 3, 1]);
+    const ROUTERS: [Ipv4Address; 2] = [
+        Ipv4Address::new([10, 0, 1, 254]),
+        Ipv4Address::new([10, 0, 2, 254]),
+    ];
+    const HOP_LATENCY: Duration = Duration::from_millis(5);
+
+    /// A path through both routers, where every hop takes the same time.
+    fn network(packet: Ipv4Packet, now: Instant) -> Option<(Ipv4Packet, Instant)> {
+        let request = IcmpMessage::decode_ipv4(&packet).expect("Probes are ICMP");
+
+        let mut packet = packet;
+        for (hops, router) in (1u32..).zip(ROUTERS) {
+            match packet.forwarded() {
+                Some(forwarded) => packet = forwarded,
+                None => {
+                    let error = IcmpMessage::time_exceeded(&packet, CODE_TTL_EXCEEDED);
+                    let arrival = now + HOP_LATENCY * hops * 2;
+                    return Some((error.to_packet(router, SOURCE), arrival));
+                }
+            }
+        }
+
+        let reply = request.echo_reply().expect("Probes are echo requests");
+        Some((reply.to_packet(DESTINATION, SOURCE), now + HOP_LATENCY * 6))
+    }
+
+    #[test]
+    fn ping_measures_rtt() {
+        let start = Instant::now();
+        let replies = ping(
+            SOURCE,
+            DESTINATION,
+            3,
+            Duration::from_secs(1),
+            start,
+            network,
+        );
+
+        assert_eq!(replies.len(), 3);
+        for (sequence, reply) in (0..).zip(replies) {
+            let reply = reply.expect("Nothing is lost");
+            assert_eq!(reply.sequence, sequence);
+            assert_eq!(reply.from, DESTINATION);
+            assert_eq!(reply.rtt, Duration::from_millis(30));
+        }
+    }
+
+    #[test]
+    fn ping_reports_loss() {
+        let mut lose = false;
+        let replies = ping(
+            SOURCE,
+            DESTINATION,
+            4,
+            Duration::from_secs(1),
+            Instant::now(),
+            |packet, now| {
+                lose = !lose;
+                network(packet, now).filter(|_| !lose)
+            },
+        );
+
+        let received: Vec<bool> = replies.iter().map(Option::is_some).collect();
+        assert_eq!(received, [false, true, false, true]);
+    }
+
+    #[test]
+    fn ignores_unrelated_replies() {
+        let now = Instant::now();
+        let mut ping = Ping::new(SOURCE, DESTINATION, 1);
+        let mut other = Ping::new(SOURCE, DESTINATION, 2);
+
+        let (reply, arrival) = network(other.request(now), now).expect("Nothing is lost");
+        assert_eq!(ping.receive(&reply, arrival), None);
+
+        let (reply, arrival) = network(ping.request(now), now).expect("Nothing is lost");
+        assert!(ping.receive(&reply, arrival).is_some());
+        // Duplicates don't count twice
+        assert_eq!(ping.receive(&reply, arrival), None);
+    }
+
+    #[test]
+    fn traceroute_finds_routers() {
+        let hops = traceroute(SOURCE, DESTINATION, 30, Instant::now(), network);
+
+        let expected = [
+            (ROUTERS[0], 10, false),
+            (ROUTERS[1], 20, false),
+            (DESTINATION, 30, true),
+        ];
+        assert_eq!(hops.len(), expected.len());
+
+        for ((ttl, hop), (address, rtt, reached)) in (1..).zip(hops).zip(expected) {
+            assert_eq!(
+                hop,
+                Some(Hop {
+                    ttl,
+                    address,
+                    rtt: Duration::from_millis(rtt),
+                    reached,
+                })
+            );
+        }
+    }
+}
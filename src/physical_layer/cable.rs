@@ -9,13 +9,19 @@ use anyhow::bail;
 use crate::{
     bit::Bit,
     bit_string::BitString,
+    data_link_layer::frame::ipv4::MIN_IPV4_MTU,
     hardware::Node,
     utils::{corruption_type::Corruption, mac_address::MacAddress},
 };
 
+/// The MTU of ethernet, in bytes.
+pub const DEFAULT_MTU: u16 = 1500;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct CableContext {
     pub bit: Bit,
+    // The node that put the bit on the cable
+    pub source_mac: MacAddress,
     pub source_port: u16,
     pub target_port: u16,
 }
@@ -29,6 +35,7 @@ pub struct Cable {
     latency: Duration,
     corruption_type: Corruption,
     time_between_bits: Duration,
+    mtu: u16,
 }
 
 impl Eq for Cable {}
@@ -39,6 +46,7 @@ impl PartialEq for Cable {
             && self.node2_mac == other.node2_mac
             && self.latency == other.latency
             && self.corruption_type == other.corruption_type
+            && self.mtu == other.mtu
     }
 }
 
@@ -71,9 +79,40 @@ impl Cable {
             latency,
             corruption_type,
             time_between_bits,
+            mtu: DEFAULT_MTU,
         }
     }
 
+    /// Sets the largest IP packet the cable carries in one piece, in bytes.
+    #[must_use]
+    pub fn set_mtu(self, mtu: u16) -> Self {
+        assert!(mtu >= MIN_IPV4_MTU, "MTU is below the IPv4 minimum");
+        Self { mtu, ..self }
+    }
+
+    #[must_use]
+    pub const fn mtu(&self) -> u16 {
+        self.mtu
+    }
+
+    /// The node at the other end of the cable from the given one, if the
+    /// cable connects it.
+    #[must_use]
+    pub fn peer(&self, mac: &MacAddress) -> Option<MacAddress> {
+        if *mac == self.node1_mac {
+            Some(self.node2_mac)
+        } else if *mac == self.node2_mac {
+            Some(self.node1_mac)
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub const fn latency(&self) -> Duration {
+        self.latency
+    }
+
     pub fn send_bits(
         &mut self,
         source_mac: MacAddress,
@@ -96,6 +135,7 @@ impl Cable {
         for bit in data {
             dest.send(CableContext {
                 bit,
+                source_mac,
                 source_port,
                 target_port,
             })?;
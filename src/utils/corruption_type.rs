@@ -13,6 +13,9 @@ pub enum Corruption {
     MultiBitFlipOdd(XorShift, u8),
     MultiBitFlipEven(XorShift, u8),
     BurstFlip(XorShift),
+    /// Flips a burst of bits in the given percentage of the transmissions,
+    /// leaving the others intact.
+    BurstChance(XorShift, u8),
     //ByteLoss,
 }
 
@@ -35,6 +38,7 @@ impl Corruption {
                 Self::multi_bit_flip_odd(rand, *chance, data)
             }
             Self::BurstFlip(ref mut rand) => Self::burst_flip(rand, data),
+            Self::BurstChance(ref mut rand, chance) => Self::burst_chance(rand, *chance, data),
             Self::Random(rand) => Self::random(rand, data),
             Self::RandomCorruption(rand) => Self::random_corruption(rand, data),
         }
@@ -110,6 +114,18 @@ impl Corruption {
         data
     }
 
+    fn burst_chance(rand: &mut XorShift, chance: u8, data: BitString) -> BitString {
+        assert!(chance <= 100);
+
+        let event = (rand.next_int() % 100) as u8;
+
+        if event < chance {
+            Self::burst_flip(rand, data)
+        } else {
+            data
+        }
+    }
+
     fn random(rand: &mut XorShift, data: BitString) -> BitString {
         let mut rand = rand.copy_reset();
 
@@ -253,6 +269,22 @@ mod test {
         assert!(bits_flipped(&data, &data_copy) <= 8);
     }
 
+    #[test]
+    fn test_burst_chance() {
+        let mut rand = XorShift::new(69);
+        let data = get_data_default();
+
+        let corrupted = (0..RANDOM_TEST_CYCLES)
+            .filter(|_| {
+                let flipped = Corruption::burst_chance(&mut rand, 30, data.clone());
+                bits_flipped(&flipped, &data) > 0
+            })
+            .count();
+
+        assert!((10..=50).contains(&corrupted));
+        assert_eq!(Corruption::burst_chance(&mut rand, 0, data.clone()), data);
+    }
+
     // --- Make sure the panics work as intended ---
     const fn get_data_empty() -> BitString {
         BitString::new()
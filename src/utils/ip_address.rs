@@ -0,0 +1,736 @@
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+use anyhow::{bail, ensure, Context};
+
+use super::mac_address::MacAddress;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv4Address {
+    addr: [u8; 4],
+}
+
+impl Ipv4Address {
+    pub const UNSPECIFIED: Self = Self::new([0; 4]);
+    pub const BROADCAST: Self = Self::new([0xFF; 4]);
+
+    #[must_use]
+    pub const fn new(addr: [u8; 4]) -> Self {
+        Self { addr }
+    }
+
+    #[must_use]
+    pub const fn octets(&self) -> [u8; 4] {
+        self.addr
+    }
+
+    #[must_use]
+    pub const fn to_u32(&self) -> u32 {
+        u32::from_be_bytes(self.addr)
+    }
+
+    #[must_use]
+    pub const fn from_u32(addr: u32) -> Self {
+        Self::new(addr.to_be_bytes())
+    }
+}
+
+impl Display for Ipv4Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d] = self.addr;
+        write!(f, "{a}.{b}.{c}.{d}")
+    }
+}
+
+impl FromStr for Ipv4Address {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let octets = s
+            .split('.')
+            .map(|octet| {
+                octet
+                    .parse::<u8>()
+                    .with_context(|| format!("Invalid octet {octet} in address {s}"))
+            })
+            .collect::<anyhow::Result<Vec<u8>>>()?;
+
+        let Ok(addr) = <[u8; 4]>::try_from(octets) else {
+            bail!("Address {s} does not have 4 octets");
+        };
+
+        Ok(Self::new(addr))
+    }
+}
+
+/// An IPv4 network in CIDR notation. Host bits of the address are always
+/// cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv4Cidr {
+    network: Ipv4Address,
+    prefix_len: u8,
+}
+
+impl Ipv4Cidr {
+    pub fn new(addr: Ipv4Address, prefix_len: u8) -> anyhow::Result<Self> {
+        ensure!(
+            prefix_len <= 32,
+            "Prefix length of {prefix_len} is longer than an address"
+        );
+
+        let network = Ipv4Address::from_u32(addr.to_u32() & mask(prefix_len));
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    #[must_use]
+    pub const fn network(&self) -> Ipv4Address {
+        self.network
+    }
+
+    #[must_use]
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    #[must_use]
+    pub const fn netmask(&self) -> Ipv4Address {
+        Ipv4Address::from_u32(mask(self.prefix_len))
+    }
+
+    #[must_use]
+    pub const fn broadcast(&self) -> Ipv4Address {
+        Ipv4Address::from_u32(self.network.to_u32() | !mask(self.prefix_len))
+    }
+
+    /// The amount of addresses in the network, including network and
+    /// broadcast address.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        1 << (32 - self.prefix_len)
+    }
+
+    #[must_use]
+    pub const fn contains(&self, addr: Ipv4Address) -> bool {
+        addr.to_u32() & mask(self.prefix_len) == self.network.to_u32()
+    }
+
+    /// Whether the other network lies entirely within this one.
+    #[must_use]
+    pub const fn contains_cidr(&self, other: &Self) -> bool {
+        other.prefix_len >= self.prefix_len && self.contains(other.network)
+    }
+
+    /// The lowest address that can be given to a host.
+    #[must_use]
+    pub const fn first_host(&self) -> Ipv4Address {
+        match self.prefix_len {
+            31.. => self.network,
+            _ => Ipv4Address::from_u32(self.network.to_u32() + 1),
+        }
+    }
+
+    /// The highest address that can be given to a host.
+    #[must_use]
+    pub const fn last_host(&self) -> Ipv4Address {
+        match self.prefix_len {
+            31.. => self.broadcast(),
+            _ => Ipv4Address::from_u32(self.broadcast().to_u32() - 1),
+        }
+    }
+
+    /// The addresses that can be given to hosts. These exclude the network and
+    /// broadcast address, except for /31 and /32 networks which have no room
+    /// for them.
+    pub fn hosts(&self) -> impl Iterator<Item = Ipv4Address> {
+        (self.first_host().to_u32()..=self.last_host().to_u32()).map(Ipv4Address::from_u32)
+    }
+
+    /// The network one bit shorter containing this one.
+    #[must_use]
+    pub fn supernet(&self) -> Option<Self> {
+        let prefix_len = self.prefix_len.checked_sub(1)?;
+        Some(Self::new(self.network, prefix_len).expect("Prefix only got shorter"))
+    }
+
+    /// Splits the network into all networks with the given prefix length.
+    pub fn subnets(&self, prefix_len: u8) -> anyhow::Result<impl Iterator<Item = Self>> {
+        ensure!(
+            (self.prefix_len..=32).contains(&prefix_len),
+            "Cannot split a /{} into /{prefix_len} networks",
+            self.prefix_len
+        );
+
+        let network = u64::from(self.network.to_u32());
+        let step = 1u64 << (32 - prefix_len);
+        let count = 1u64 << (prefix_len - self.prefix_len);
+
+        #[allow(clippy::cast_possible_truncation)]
+        Ok((0..count).map(move |idx| Self {
+            network: Ipv4Address::from_u32((network + idx * step) as u32),
+            prefix_len,
+        }))
+    }
+}
+
+impl Display for Ipv4Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl FromStr for Ipv4Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((addr, prefix_len)) = s.split_once('/') else {
+            bail!("Network {s} is missing a prefix length");
+        };
+
+        let prefix_len = prefix_len
+            .parse::<u8>()
+            .with_context(|| format!("Invalid prefix length in network {s}"))?;
+
+        Self::new(addr.parse()?, prefix_len)
+    }
+}
+
+const fn mask(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        prefix_len => u32::MAX << (32 - prefix_len),
+    }
+}
+
+/// Hands out host addresses from a network, lowest first. Released addresses
+/// are handed out again before new ones.
+#[derive(Debug, Clone)]
+pub struct Ipv4AddressAllocator {
+    cidr: Ipv4Cidr,
+    // The lowest address never handed out, which is past the last host once
+    // the pool runs dry
+    next: u64,
+    released: BTreeSet<Ipv4Address>,
+    reserved: BTreeSet<Ipv4Address>,
+}
+
+impl Ipv4AddressAllocator {
+    #[must_use]
+    pub const fn new(cidr: Ipv4Cidr) -> Self {
+        Self {
+            cidr,
+            next: cidr.first_host().to_u32() as u64,
+            released: BTreeSet::new(),
+            reserved: BTreeSet::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn cidr(&self) -> Ipv4Cidr {
+        self.cidr
+    }
+
+    /// Keeps the address from being handed out, for example for a gateway.
+    pub fn reserve(&mut self, addr: Ipv4Address) -> anyhow::Result<()> {
+        ensure!(
+            self.cidr.contains(addr),
+            "Address {addr} is not in {}",
+            self.cidr
+        );
+
+        self.released.remove(&addr);
+        self.reserved.insert(addr);
+        Ok(())
+    }
+
+    pub fn gen_addr(&mut self) -> Option<Ipv4Address> {
+        if let Some(addr) = self.released.pop_first() {
+            return Some(addr);
+        }
+
+        while self.next <= u64::from(self.cidr.last_host().to_u32()) {
+            #[allow(clippy::cast_possible_truncation)]
+            let addr = Ipv4Address::from_u32(self.next as u32);
+            self.next += 1;
+
+            if !self.reserved.contains(&addr) {
+                return Some(addr);
+            }
+        }
+
+        None
+    }
+
+    /// Returns a handed out address to the pool.
+    pub fn release(&mut self, addr: Ipv4Address) {
+        let handed_out = self.cidr.first_host() <= addr && u64::from(addr.to_u32()) < self.next;
+
+        if handed_out && !self.reserved.contains(&addr) {
+            self.released.insert(addr);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv6Address {
+    addr: [u8; 16],
+}
+
+impl Ipv6Address {
+    pub const UNSPECIFIED: Self = Self::new([0; 16]);
+    pub const LOOPBACK: Self = Self::from_u128(1);
+    /// The link-local all-nodes multicast address, ff02::1.
+    pub const ALL_NODES: Self = Self::from_u128(0xFF02 << 112 | 1);
+    /// The link-local all-routers multicast address, ff02::2.
+    pub const ALL_ROUTERS: Self = Self::from_u128(0xFF02 << 112 | 2);
+
+    #[must_use]
+    pub const fn new(addr: [u8; 16]) -> Self {
+        Self { addr }
+    }
+
+    #[must_use]
+    pub const fn from_segments(segments: [u16; 8]) -> Self {
+        let mut addr = 0;
+        let mut idx = 0;
+        while idx < segments.len() {
+            addr = addr << 16 | segments[idx] as u128;
+            idx += 1;
+        }
+
+        Self::from_u128(addr)
+    }
+
+    /// The link-local address of an interface, with the interface identifier
+    /// derived from its MAC address as modified EUI-64.
+    #[must_use]
+    pub const fn link_local(mac: MacAddress) -> Self {
+        let id = interface_id(mac);
+        Self::from_u128(0xFE80 << 112 | id as u128)
+    }
+
+    #[must_use]
+    pub const fn octets(&self) -> [u8; 16] {
+        self.addr
+    }
+
+    #[must_use]
+    pub const fn segments(&self) -> [u16; 8] {
+        let mut segments = [0; 8];
+        let mut idx = 0;
+        while idx < segments.len() {
+            segments[idx] = u16::from_be_bytes([self.addr[idx * 2], self.addr[idx * 2 + 1]]);
+            idx += 1;
+        }
+
+        segments
+    }
+
+    #[must_use]
+    pub const fn to_u128(&self) -> u128 {
+        u128::from_be_bytes(self.addr)
+    }
+
+    #[must_use]
+    pub const fn from_u128(addr: u128) -> Self {
+        Self::new(addr.to_be_bytes())
+    }
+
+    #[must_use]
+    pub const fn is_unspecified(&self) -> bool {
+        self.to_u128() == 0
+    }
+
+    #[must_use]
+    pub const fn is_multicast(&self) -> bool {
+        self.addr[0] == 0xFF
+    }
+
+    /// Whether the address is in fe80::/10.
+    #[must_use]
+    pub const fn is_link_local(&self) -> bool {
+        self.addr[0] == 0xFE && self.addr[1] & 0xC0 == 0x80
+    }
+
+    /// The multicast group neighbor solicitations for this address are sent
+    /// to, ff02::1:ffXX:XXXX with the low 24 bits of the address.
+    #[must_use]
+    pub const fn solicited_node(&self) -> Self {
+        Self::from_u128(0xFF02 << 112 | 0x1_FF00_0000 | self.to_u128() & 0xFF_FFFF)
+    }
+
+    /// The ethernet address a multicast address maps to, 33:33 followed by
+    /// the low 32 bits of the address.
+    #[must_use]
+    pub const fn multicast_mac(&self) -> MacAddress {
+        let [.., a, b, c, d] = self.addr;
+        MacAddress::new([0x33, 0x33, a, b, c, d])
+    }
+}
+
+/// The modified EUI-64 interface identifier of a MAC address, which has
+/// ff:fe inserted in the middle and the universal/local bit flipped.
+const fn interface_id(mac: MacAddress) -> u64 {
+    let [a, b, c, d, e, f] = mac.octets();
+    u64::from_be_bytes([a ^ 0b10, b, c, 0xFF, 0xFE, d, e, f])
+}
+
+impl Display for Ipv6Address {
+    /// Formats the address as recommended by RFC 5952, with the longest run
+    /// of zero segments shortened to `::`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let segments = self.segments();
+
+        // Start and length of the longest run, only runs of two or more count
+        let mut longest = (0, 0);
+        let mut start = 0;
+        for (idx, segment) in segments.iter().enumerate() {
+            if *segment != 0 {
+                start = idx + 1;
+            } else if idx + 1 - start > longest.1 {
+                longest = (start, idx + 1 - start);
+            }
+        }
+
+        let write_segments = |f: &mut std::fmt::Formatter<'_>, segments: &[u16]| {
+            let strings: Vec<String> = segments.iter().map(|s| format!("{s:x}")).collect();
+            write!(f, "{}", strings.join(":"))
+        };
+
+        match longest {
+            (start, len) if len >= 2 => {
+                write_segments(f, &segments[..start])?;
+                write!(f, "::")?;
+                write_segments(f, &segments[start + len..])
+            }
+            _ => write_segments(f, &segments),
+        }
+    }
+}
+
+impl FromStr for Ipv6Address {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |part: &str| -> anyhow::Result<Vec<u16>> {
+            if part.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            part.split(':')
+                .map(|segment| {
+                    ensure!(
+                        segment.len() <= 4,
+                        "Invalid segment {segment} in address {s}"
+                    );
+                    u16::from_str_radix(segment, 16)
+                        .with_context(|| format!("Invalid segment {segment} in address {s}"))
+                })
+                .collect()
+        };
+
+        let segments = match s.split_once("::") {
+            Some((head, tail)) => {
+                let head = parse(head)?;
+                let tail = parse(tail)?;
+                ensure!(
+                    head.len() + tail.len() < 8,
+                    "Address {s} has too many segments"
+                );
+
+                let mut segments = head;
+                segments.resize(8 - tail.len(), 0);
+                segments.extend(tail);
+                segments
+            }
+            None => parse(s)?,
+        };
+
+        let Ok(segments) = <[u16; 8]>::try_from(segments) else {
+            bail!("Address {s} does not have 8 segments");
+        };
+
+        Ok(Self::from_segments(segments))
+    }
+}
+
+/// An IPv6 prefix. Bits past the prefix are always cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv6Cidr {
+    network: Ipv6Address,
+    prefix_len: u8,
+}
+
+impl Ipv6Cidr {
+    pub fn new(addr: Ipv6Address, prefix_len: u8) -> anyhow::Result<Self> {
+        ensure!(
+            prefix_len <= 128,
+            "Prefix length of {prefix_len} is longer than an address"
+        );
+
+        let network = Ipv6Address::from_u128(addr.to_u128() & mask_v6(prefix_len));
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    #[must_use]
+    pub const fn network(&self) -> Ipv6Address {
+        self.network
+    }
+
+    #[must_use]
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    #[must_use]
+    pub const fn contains(&self, addr: Ipv6Address) -> bool {
+        addr.to_u128() & mask_v6(self.prefix_len) == self.network.to_u128()
+    }
+
+    /// Whether the other prefix lies entirely within this one.
+    #[must_use]
+    pub const fn contains_cidr(&self, other: &Self) -> bool {
+        other.prefix_len >= self.prefix_len && self.contains(other.network)
+    }
+
+    /// The address an interface configures for itself in this prefix, with
+    /// the interface identifier derived from its MAC address. Only /64
+    /// prefixes leave room for the identifier.
+    pub fn eui64_address(&self, mac: MacAddress) -> anyhow::Result<Ipv6Address> {
+        ensure!(
+            self.prefix_len == 64,
+            "Interface identifiers need a /64 prefix, not a /{}",
+            self.prefix_len
+        );
+
+        Ok(Ipv6Address::from_u128(
+            self.network.to_u128() | u128::from(interface_id(mac)),
+        ))
+    }
+}
+
+impl Display for Ipv6Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl FromStr for Ipv6Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((addr, prefix_len)) = s.split_once('/') else {
+            bail!("Prefix {s} is missing a prefix length");
+        };
+
+        let prefix_len = prefix_len
+            .parse::<u8>()
+            .with_context(|| format!("Invalid prefix length in prefix {s}"))?;
+
+        Self::new(addr.parse()?, prefix_len)
+    }
+}
+
+const fn mask_v6(prefix_len: u8) -> u128 {
+    match prefix_len {
+        0 => 0,
+        prefix_len => u128::MAX << (128 - prefix_len),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mac_address::MacAddress;
+
+    use super::{Ipv4Address, Ipv4AddressAllocator, Ipv4Cidr, Ipv6Address, Ipv6Cidr};
+
+    fn cidr(s: &str) -> Ipv4Cidr {
+        s.parse().expect("Valid network")
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let addr: Ipv4Address = "192.168.1.42".parse().expect("Valid address");
+
+        assert_eq!(addr, Ipv4Address::new([192, 168, 1, 42]));
+        assert_eq!(addr.to_string(), "192.168.1.42");
+        assert_eq!(addr.to_u32(), 0xC0A8_012A);
+
+        assert!("192.168.1".parse::<Ipv4Address>().is_err());
+        assert!("192.168.1.256".parse::<Ipv4Address>().is_err());
+        assert!("192.168.1.1.1".parse::<Ipv4Address>().is_err());
+
+        assert_eq!(cidr("10.1.2.3/8").to_string(), "10.0.0.0/8");
+        assert!("10.0.0.0".parse::<Ipv4Cidr>().is_err());
+        assert!("10.0.0.0/33".parse::<Ipv4Cidr>().is_err());
+    }
+
+    #[test]
+    fn network_and_broadcast() {
+        let net = cidr("192.168.1.77/26");
+
+        assert_eq!(net.network(), Ipv4Address::new([192, 168, 1, 64]));
+        assert_eq!(net.broadcast(), Ipv4Address::new([192, 168, 1, 127]));
+        assert_eq!(net.netmask(), Ipv4Address::new([255, 255, 255, 192]));
+        assert_eq!(net.size(), 64);
+
+        let all = cidr("0.0.0.0/0");
+        assert_eq!(all.broadcast(), Ipv4Address::BROADCAST);
+        assert_eq!(all.netmask(), Ipv4Address::UNSPECIFIED);
+    }
+
+    #[test]
+    fn contains() {
+        let net = cidr("10.0.0.0/8");
+
+        assert!(net.contains(Ipv4Address::new([10, 255, 0, 1])));
+        assert!(!net.contains(Ipv4Address::new([11, 0, 0, 0])));
+        assert!(net.contains_cidr(&cidr("10.20.0.0/16")));
+        assert!(!net.contains_cidr(&cidr("0.0.0.0/0")));
+        assert!(cidr("0.0.0.0/0").contains(Ipv4Address::BROADCAST));
+    }
+
+    #[test]
+    fn hosts() {
+        let hosts: Vec<_> = cidr("192.168.1.0/30").hosts().collect();
+        assert_eq!(
+            hosts,
+            [
+                Ipv4Address::new([192, 168, 1, 1]),
+                Ipv4Address::new([192, 168, 1, 2])
+            ]
+        );
+
+        assert_eq!(cidr("192.168.1.0/31").hosts().count(), 2);
+        assert_eq!(cidr("192.168.1.5/32").hosts().count(), 1);
+        assert_eq!(cidr("10.0.0.0/16").hosts().count(), 65534);
+    }
+
+    #[test]
+    fn supernet_and_subnets() {
+        let net = cidr("192.168.1.128/25");
+
+        assert_eq!(net.supernet(), Some(cidr("192.168.1.0/24")));
+        assert_eq!(cidr("0.0.0.0/0").supernet(), None);
+
+        let subnets: Vec<_> = cidr("192.168.1.0/24")
+            .subnets(26)
+            .expect("Prefix is longer")
+            .collect();
+        assert_eq!(
+            subnets,
+            [
+                cidr("192.168.1.0/26"),
+                cidr("192.168.1.64/26"),
+                cidr("192.168.1.128/26"),
+                cidr("192.168.1.192/26")
+            ]
+        );
+
+        assert!(net.subnets(24).is_err());
+        assert!(net.subnets(33).is_err());
+        assert_eq!(cidr("0.0.0.0/0").subnets(1).expect("Valid").count(), 2);
+    }
+
+    #[test]
+    fn allocator() {
+        let mut allocator = Ipv4AddressAllocator::new(cidr("10.0.0.0/29"));
+        allocator
+            .reserve(Ipv4Address::new([10, 0, 0, 1]))
+            .expect("Address is in network");
+        assert!(allocator.reserve(Ipv4Address::new([10, 0, 1, 1])).is_err());
+
+        let addrs: Vec<_> = std::iter::from_fn(|| allocator.gen_addr()).collect();
+        assert_eq!(addrs.len(), 5);
+        assert_eq!(addrs[0], Ipv4Address::new([10, 0, 0, 2]));
+        assert_eq!(addrs[4], Ipv4Address::new([10, 0, 0, 6]));
+
+        allocator.release(Ipv4Address::new([10, 0, 0, 4]));
+        // Never handed out, so not released
+        allocator.release(Ipv4Address::new([10, 0, 0, 1]));
+        allocator.release(Ipv4Address::new([10, 0, 0, 7]));
+
+        assert_eq!(allocator.gen_addr(), Some(Ipv4Address::new([10, 0, 0, 4])));
+        assert_eq!(allocator.gen_addr(), None);
+    }
+
+    fn addr_v6(s: &str) -> Ipv6Address {
+        s.parse().expect("Valid address")
+    }
+
+    #[test]
+    fn parse_and_display_v6() {
+        let addr = addr_v6("2001:DB8:0:0:1:0:0:1");
+
+        assert_eq!(addr.segments(), [0x2001, 0x0DB8, 0, 0, 1, 0, 0, 1]);
+        // The first of two equally long runs is shortened
+        assert_eq!(addr.to_string(), "2001:db8::1:0:0:1");
+
+        assert_eq!(addr_v6("::").to_string(), "::");
+        assert_eq!(addr_v6("::1"), Ipv6Address::LOOPBACK);
+        assert_eq!(addr_v6("ff02::1"), Ipv6Address::ALL_NODES);
+        assert_eq!(addr_v6("fe80::").to_string(), "fe80::");
+        // A single zero segment is not shortened
+        assert_eq!(addr_v6("1:0:2:3:4:5:6:7").to_string(), "1:0:2:3:4:5:6:7");
+
+        assert!("1:2:3:4:5:6:7".parse::<Ipv6Address>().is_err());
+        assert!("1:2:3:4:5:6:7:8:9".parse::<Ipv6Address>().is_err());
+        assert!("1::2::3".parse::<Ipv6Address>().is_err());
+        assert!("1:2:3:4::5:6:7:8".parse::<Ipv6Address>().is_err());
+        assert!("12345::".parse::<Ipv6Address>().is_err());
+        assert!("g::".parse::<Ipv6Address>().is_err());
+    }
+
+    #[test]
+    fn link_local_eui64() {
+        let mac = MacAddress::new([0x00, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E]);
+        let addr = Ipv6Address::link_local(mac);
+
+        assert_eq!(addr, addr_v6("fe80::21a:2bff:fe3c:4d5e"));
+        assert!(addr.is_link_local());
+        assert!(!addr.is_multicast());
+
+        let prefix: Ipv6Cidr = "2001:db8:1:2::/64".parse().expect("Valid prefix");
+        assert_eq!(
+            prefix.eui64_address(mac).expect("Prefix is a /64"),
+            addr_v6("2001:db8:1:2:21a:2bff:fe3c:4d5e")
+        );
+
+        let short: Ipv6Cidr = "2001:db8::/48".parse().expect("Valid prefix");
+        assert!(short.eui64_address(mac).is_err());
+    }
+
+    #[test]
+    fn multicast_v6() {
+        let addr = addr_v6("2001:db8::21a:2bff:fe3c:4d5e");
+        let group = addr.solicited_node();
+
+        assert_eq!(group, addr_v6("ff02::1:ff3c:4d5e"));
+        assert!(group.is_multicast());
+        assert_eq!(
+            group.multicast_mac(),
+            MacAddress::new([0x33, 0x33, 0xFF, 0x3C, 0x4D, 0x5E])
+        );
+    }
+
+    #[test]
+    fn prefixes_v6() {
+        let prefix: Ipv6Cidr = "2001:db8:abcd:12ff::1/56".parse().expect("Valid prefix");
+
+        assert_eq!(prefix.to_string(), "2001:db8:abcd:1200::/56");
+        assert!(prefix.contains(addr_v6("2001:db8:abcd:12aa::1")));
+        assert!(!prefix.contains(addr_v6("2001:db8:abcd:1300::")));
+
+        let sub: Ipv6Cidr = "2001:db8:abcd:1234::/64".parse().expect("Valid prefix");
+        assert!(prefix.contains_cidr(&sub));
+        assert!(!sub.contains_cidr(&prefix));
+
+        assert!("::/129".parse::<Ipv6Cidr>().is_err());
+    }
+}
@@ -1,15 +1,42 @@
+use std::fmt::Display;
+
 use super::rand::XorShift;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MacAddress {
     addr: [u8; 6],
 }
 
 impl MacAddress {
+    pub const BROADCAST: Self = Self::new([0xFF; 6]);
+
     #[must_use]
     pub const fn new(addr: [u8; 6]) -> Self {
         Self { addr }
     }
+
+    #[must_use]
+    pub const fn octets(&self) -> [u8; 6] {
+        self.addr
+    }
+
+    #[must_use]
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    /// Whether the address is a group address, which includes broadcast.
+    #[must_use]
+    pub const fn is_multicast(&self) -> bool {
+        self.addr[0] & 0b1 == 0b1
+    }
+}
+
+impl Display for MacAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d, e, g] = self.addr;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
 }
 
 pub struct MacAddressGenerator {
@@ -0,0 +1,272 @@
+#[path = "utils/mod.rs"]
+mod test_utils;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::test_utils::test_fns::create_cable;
+
+use network_sim::bit_string::BitString;
+use network_sim::data_link_layer::arq::Arq;
+use network_sim::data_link_layer::frame::ipv4::MIN_IPV4_MTU;
+use network_sim::data_link_layer::frame::tcp::TCPFrame;
+use network_sim::data_link_layer::DataLinkLayer;
+use network_sim::rand::XorShift;
+use network_sim::{corruption_type::Corruption, hardware::Node};
+
+const TIMEOUT: Duration = Duration::from_millis(250);
+
+fn data(len: usize) -> BitString {
+    (0..len)
+        .map(|byte| u8::try_from(byte % 251).expect("Bytes are below 251"))
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+/// Sends the data from one user to the other over a cable carrying small
+/// frames. Returns what arrived along with the sending link layer.
+fn transfer(
+    arq: Arq,
+    latency: Duration,
+    corruption: Corruption,
+    window_size: u16,
+    data: &BitString,
+) -> anyhow::Result<(BitString, DataLinkLayer<TCPFrame>)> {
+    let (mut received, sender) = transfers(
+        arq,
+        latency,
+        corruption,
+        window_size,
+        std::slice::from_ref(data),
+    )?;
+
+    Ok((received.remove(0), sender))
+}
+
+/// Sends every data in turn over the same link layers, starting the next
+/// transfer once the one before is acknowledged.
+fn transfers(
+    arq: Arq,
+    latency: Duration,
+    corruption: Corruption,
+    window_size: u16,
+    data: &[BitString],
+) -> anyhow::Result<(Vec<BitString>, DataLinkLayer<TCPFrame>)> {
+    let (cable, usr1, usr2) = create_cable(latency, corruption, 1000);
+    let cable = Arc::new(Mutex::new(cable.set_mtu(MIN_IPV4_MTU)));
+
+    let mut sender = DataLinkLayer::<TCPFrame>::new()
+        .set_arq(arq)
+        .set_retransmit_timeout(TIMEOUT);
+    let mut receiver = DataLinkLayer::<TCPFrame>::new().set_arq(arq);
+
+    let mut received = Vec::new();
+    for data in data {
+        sender.send_bits(window_size, *usr1.get_mac(), 30, 40, &cable, data.clone())?;
+
+        let transfers = received.len();
+        while sender.is_sending() || received.len() == transfers {
+            if let Some(data) = receiver.poll(*usr2.get_mac(), &cable, usr2.get_receiver())? {
+                received.push(data);
+            }
+            sender.poll(*usr1.get_mac(), &cable, usr1.get_receiver())?;
+        }
+    }
+
+    Ok((received, sender))
+}
+
+#[test]
+fn go_back_n_clean() -> anyhow::Result<()> {
+    let data = data(600);
+
+    let (received, sender) = transfer(Arq::GoBackN, Duration::ZERO, Corruption::None, 3, &data)?;
+    assert_eq!(received, data);
+
+    // The transfer takes more frames than there are sequence numbers
+    let sender = sender.sender().expect("A transfer was sent");
+    assert!(sender.acknowledged() > sender.sequence_space() as usize);
+
+    Ok(())
+}
+
+#[test]
+fn go_back_n_corrupted() -> anyhow::Result<()> {
+    let data = data(600);
+    let corruption = Corruption::BurstChance(XorShift::new(47), 25);
+
+    let (received, dll) = transfer(Arq::GoBackN, Duration::ZERO, corruption, 4, &data)?;
+    assert_eq!(received, data);
+
+    let sender = dll.sender().expect("A transfer was sent");
+    assert!(sender.retransmissions() > 0);
+
+    Ok(())
+}
+
+#[test]
+fn back_to_back_transfers() -> anyhow::Result<()> {
+    let data = [
+        data(300),
+        BitString::from(&b"Another transfer"[..]),
+        BitString::new(),
+    ];
+
+    for (arq, seed) in [
+        (Arq::StopAndWait, 61),
+        (Arq::GoBackN, 62),
+        (Arq::SelectiveRepeat, 63),
+    ] {
+        let corruption = Corruption::BurstChance(XorShift::new(seed), 25);
+
+        // Every transfer arrives once, none mixed up with the one before
+        let (received, _) = transfers(arq, Duration::ZERO, corruption, 3, &data)?;
+        assert_eq!(received, data, "{arq:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn selective_repeat_clean() -> anyhow::Result<()> {
+    let data = data(600);
+
+    let (received, dll) = transfer(
+        Arq::SelectiveRepeat,
+        Duration::ZERO,
+        Corruption::None,
+        3,
+        &data,
+    )?;
+    assert_eq!(received, data);
+
+    // The sequence space holds two windows, and is still wrapped around
+    let sender = dll.sender().expect("A transfer was sent");
+    assert_eq!(sender.sequence_space(), 8);
+    assert!(sender.acknowledged() > sender.sequence_space() as usize);
+    assert_eq!(sender.retransmissions(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn selective_repeat_corrupted() -> anyhow::Result<()> {
+    let data = data(600);
+    let corruption = Corruption::BurstChance(XorShift::new(48), 25);
+
+    let (received, dll) = transfer(Arq::SelectiveRepeat, Duration::ZERO, corruption, 4, &data)?;
+    assert_eq!(received, data);
+
+    let sender = dll.sender().expect("A transfer was sent");
+    assert!(sender.retransmissions() > 0);
+
+    Ok(())
+}
+
+#[test]
+fn goodput_comparison() -> anyhow::Result<()> {
+    let data = data(1200);
+    let corruption = || Corruption::BurstChance(XorShift::new(49), 20);
+
+    // The share of the transmitted frames that were needed
+    let efficiency = |arq| -> anyhow::Result<f64> {
+        let (received, dll) = transfer(arq, Duration::ZERO, corruption(), 7, &data)?;
+        assert_eq!(received, data);
+
+        let sender = dll.sender().expect("A transfer was sent");
+        Ok(sender.acknowledged() as f64 / sender.transmissions() as f64)
+    };
+
+    let go_back_n = efficiency(Arq::GoBackN)?;
+    let selective_repeat = efficiency(Arq::SelectiveRepeat)?;
+
+    // Go-Back-N resends frames that arrived intact along with the lost ones
+    assert!(selective_repeat > go_back_n);
+
+    Ok(())
+}
+
+#[test]
+fn stop_and_wait_corrupted() -> anyhow::Result<()> {
+    let data = data(300);
+    let corruption = Corruption::BurstChance(XorShift::new(50), 25);
+
+    let (received, dll) = transfer(Arq::StopAndWait, Duration::ZERO, corruption, 1, &data)?;
+    assert_eq!(received, data);
+
+    // Only ever a single frame in flight, numbered by an alternating bit
+    let sender = dll.sender().expect("A transfer was sent");
+    assert_eq!(sender.sequence_space(), 2);
+    assert!(sender.retransmissions() > 0);
+
+    Ok(())
+}
+
+#[test]
+fn stop_and_wait_latency() -> anyhow::Result<()> {
+    let data = data(300);
+    let latency = Duration::from_millis(20);
+
+    let start = Instant::now();
+    let (received, dll) = transfer(Arq::StopAndWait, latency, Corruption::None, 1, &data)?;
+    let elapsed = start.elapsed();
+    assert_eq!(received, data);
+
+    // Every frame waits a round trip for its acknowledgement before the next
+    // one is sent
+    let sender = dll.sender().expect("A transfer was sent");
+    assert!(sender.acknowledged() > 1);
+    assert!(elapsed >= latency * 2 * u32::try_from(sender.acknowledged())?);
+
+    Ok(())
+}
+
+#[test]
+fn gives_up_and_sends_again() -> anyhow::Result<()> {
+    let data = data(300);
+    let timeout = Duration::from_millis(5);
+
+    let mut sender = DataLinkLayer::<TCPFrame>::new().set_retransmit_timeout(timeout);
+    let mut receiver = DataLinkLayer::<TCPFrame>::new();
+
+    // Every frame arrives damaged, so the transfer makes no progress
+    let corruption = Corruption::BurstChance(XorShift::new(64), 100);
+    let (cable, usr1, usr2) = create_cable(Duration::ZERO, corruption, 1000);
+    let cable = Arc::new(Mutex::new(cable.set_mtu(MIN_IPV4_MTU)));
+
+    sender.send_bits(3, *usr1.get_mac(), 30, 40, &cable, data.clone())?;
+    let failure = loop {
+        assert_eq!(
+            receiver.poll(*usr2.get_mac(), &cable, usr2.get_receiver())?,
+            None
+        );
+        if let Err(failure) = sender.poll(*usr1.get_mac(), &cable, usr1.get_receiver()) {
+            break failure;
+        }
+    };
+    assert!(failure.to_string().starts_with("Gave up"));
+
+    // The transfer is dropped and the failure only reported once
+    assert!(!sender.is_sending());
+    assert!(sender.sender().is_none());
+    assert_eq!(
+        sender.poll(*usr1.get_mac(), &cable, usr1.get_receiver())?,
+        None
+    );
+
+    // The same link layers carry the next transfer once the cable is fine
+    let (cable, usr1, usr2) = create_cable(Duration::ZERO, Corruption::None, 1000);
+    let cable = Arc::new(Mutex::new(cable.set_mtu(MIN_IPV4_MTU)));
+
+    sender.send_bits(3, *usr1.get_mac(), 30, 40, &cable, data.clone())?;
+    let mut received = None;
+    while sender.is_sending() || received.is_none() {
+        if let Some(data) = receiver.poll(*usr2.get_mac(), &cable, usr2.get_receiver())? {
+            received = Some(data);
+        }
+        sender.poll(*usr1.get_mac(), &cable, usr1.get_receiver())?;
+    }
+    assert_eq!(received, Some(data));
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+#[path = "utils/mod.rs"]
+mod test_utils;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::test_utils::test_fns::create_cable;
+
+use network_sim::bit::Bit;
+use network_sim::bit_string::BitString;
+use network_sim::data_link_layer::fec::{
+    BlockInterleaver, Convolutional, ForwardErrorCorrection, Hamming, Interleaved,
+};
+use network_sim::data_link_layer::frame::udp::{UDPFrame, UDP_HEADER_LEN};
+use network_sim::data_link_layer::DataLinkLayer;
+use network_sim::rand::XorShift;
+use network_sim::{corruption_type::Corruption, hardware::Node};
+
+// Twice the greeting, so a UDP frame is about as long as a TCP frame with one
+// greeting
+const ASCII_TEST_MSG: &[u8] = b"Hello world! Hello world!";
+const FRAMES: usize = 10;
+
+/// Sends `FRAMES` frames over a cable with the given corruption, optionally
+/// coded, and returns how many arrived intact.
+fn delivered_frames<C>(corruption: Corruption, fec: Option<C>) -> anyhow::Result<usize>
+where
+    C: ForwardErrorCorrection + 'static,
+{
+    let (cable, usr1, usr2) = create_cable(Duration::ZERO, corruption, 1000);
+    let cable = Arc::new(Mutex::new(cable));
+
+    let mut dll = DataLinkLayer::<UDPFrame>::new();
+    if let Some(fec) = fec {
+        dll = dll.set_fec(fec);
+    }
+
+    let data = BitString::from(ASCII_TEST_MSG);
+    let mut delivered = 0;
+
+    for _ in 0..FRAMES {
+        dll.send_bits(*usr1.get_mac(), 30, 40, &cable, data.clone())?;
+
+        let received = usr2
+            .get_receiver()
+            .try_iter()
+            .map(|cc| cc.bit)
+            .collect::<Vec<Bit>>();
+
+        if let Some(frame) = dll.deframe_bits(received.into()) {
+            assert_eq!(frame.copy_len(UDP_HEADER_LEN * 8, data.len()), data);
+            delivered += 1;
+        }
+    }
+
+    assert_eq!(dll.dropped_frames(), FRAMES - delivered);
+
+    Ok(delivered)
+}
+
+/// Sends the coded data `FRAMES` times straight over a cable with the given
+/// corruption, without any framing, and returns how often it was repaired.
+fn repaired_transmissions<C>(
+    corruption: Corruption,
+    fec: &C,
+    data: &BitString,
+) -> anyhow::Result<usize>
+where
+    C: ForwardErrorCorrection,
+{
+    let (mut cable, usr1, usr2) = create_cable(Duration::ZERO, corruption, 1000);
+    let mut repaired = 0;
+
+    for _ in 0..FRAMES {
+        cable.send_bits(*usr1.get_mac(), 30, 40, fec.encode(data.clone()))?;
+
+        let received = usr2
+            .get_receiver()
+            .try_iter()
+            .map(|cc| cc.bit)
+            .collect::<Vec<Bit>>();
+
+        if fec.decode(received.into())?.data == *data {
+            repaired += 1;
+        }
+    }
+
+    Ok(repaired)
+}
+
+#[test]
+fn clean_link_delivers_everything() -> anyhow::Result<()> {
+    assert_eq!(delivered_frames::<Hamming>(Corruption::None, None)?, FRAMES);
+    assert_eq!(
+        delivered_frames(Corruption::None, Some(Convolutional::nasa_k7()))?,
+        FRAMES
+    );
+
+    Ok(())
+}
+
+#[test]
+fn coding_repairs_one_bit_flips() -> anyhow::Result<()> {
+    let uncoded = delivered_frames::<Hamming>(Corruption::OneBitFlip(XorShift::new(1)), None)?;
+    let hamming = delivered_frames(
+        Corruption::OneBitFlip(XorShift::new(1)),
+        Some(Hamming::hamming_7_4()),
+    )?;
+    let convolutional = delivered_frames(
+        Corruption::OneBitFlip(XorShift::new(1)),
+        Some(Convolutional::nasa_k7()),
+    )?;
+
+    assert!(uncoded < FRAMES);
+    assert_eq!(hamming, FRAMES);
+    assert_eq!(convolutional, FRAMES);
+
+    Ok(())
+}
+
+#[test]
+fn convolutional_code_beats_uncoded_on_multi_bit_flips() -> anyhow::Result<()> {
+    let uncoded =
+        delivered_frames::<Hamming>(Corruption::MultiBitFlipOdd(XorShift::new(5), 1), None)?;
+    let convolutional = delivered_frames(
+        Corruption::MultiBitFlipOdd(XorShift::new(5), 1),
+        Some(Convolutional::nasa_k7()),
+    )?;
+
+    assert!(convolutional > uncoded);
+
+    Ok(())
+}
+
+#[test]
+fn interleaving_repairs_burst_flips() -> anyhow::Result<()> {
+    let hamming = Hamming::hamming_7_4();
+    let interleaver = BlockInterleaver::new(16, 7);
+
+    // The codewords fill whole matrices, so a burst no longer than a column
+    // never hits a codeword twice
+    let data = BitString::from(&ASCII_TEST_MSG[..24]);
+    let matrix_len = interleaver.rows() * interleaver.columns();
+    assert_eq!(hamming.encoded_len(data.len()) % matrix_len, 0);
+
+    let plain = repaired_transmissions(Corruption::BurstFlip(XorShift::new(9)), &hamming, &data)?;
+    let interleaved = repaired_transmissions(
+        Corruption::BurstFlip(XorShift::new(9)),
+        &Interleaved::new(hamming, interleaver),
+        &data,
+    )?;
+
+    assert!(plain < FRAMES);
+    assert_eq!(interleaved, FRAMES);
+
+    Ok(())
+}
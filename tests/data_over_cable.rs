@@ -5,9 +5,18 @@ use crate::test_utils::test_fns::{
     bits_flipped_slice_bit_vec, create_cable, equals_bit_vec_and_byte_slice,
 };
 
+use std::sync::{Arc, Mutex};
 pub use std::time::Duration;
 use std::time::Instant;
 
+use network_sim::bit::Bit;
+use network_sim::bit_string::BitString;
+use network_sim::data_link_layer::frame::ipv4::{Ipv4Packet, Ipv4PacketBuilder, PROTOCOL_UDP};
+use network_sim::data_link_layer::frame::udp::UDPFrame;
+use network_sim::data_link_layer::DataLinkLayer;
+use network_sim::ip_address::Ipv4Address;
+use network_sim::network_layer::fragmentation::{fragment_for, Egress, Reassembler};
+use network_sim::network_layer::icmp::IcmpMessage;
 use network_sim::physical_layer::cable::CableContext;
 use network_sim::rand::XorShift;
 use network_sim::{corruption_type::Corruption, hardware::Node};
@@ -117,3 +126,94 @@ fn correct_throughput() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn send_datagram() -> anyhow::Result<()> {
+    let corruption = Corruption::None;
+    let (cable, usr1, usr2) = create_cable(Duration::ZERO, corruption, 100);
+    let cable = Arc::new(Mutex::new(cable));
+
+    let mut dll = DataLinkLayer::<UDPFrame>::new();
+
+    dll.send_bits(*usr1.get_mac(), 30, 40, &cable, ASCII_TEST_MSG.into())?;
+
+    let recv_data = usr2
+        .get_receiver()
+        .try_iter()
+        .map(|cc| cc.bit)
+        .collect::<Vec<Bit>>();
+
+    let datagram = dll.deframe_bits(recv_data.into()).expect("Clean cable");
+
+    // 8 byte header followed by the message
+    assert_eq!(datagram.get_u16(0), 30);
+    assert_eq!(datagram.get_u16(16), 40);
+    assert_eq!(datagram.get_u16(32), 8 + 12);
+    assert_eq!(datagram.copy_len(64, 96), ASCII_TEST_MSG.into());
+
+    Ok(())
+}
+
+#[test]
+fn send_fragmented() -> anyhow::Result<()> {
+    let corruption = Corruption::None;
+    let (cable, usr1, usr2) = create_cable(Duration::ZERO, corruption, 1000);
+    let mut cable = cable.set_mtu(576);
+
+    let source = Ipv4Address::new([10, 0, 0, 1]);
+    let destination = Ipv4Address::new([10, 0, 0, 2]);
+    let builder = Ipv4PacketBuilder::new()
+        .set_source(source)
+        .set_destination(destination)
+        .set_protocol(PROTOCOL_UDP);
+
+    let packet = builder.clone().build(BitString::with_ones(1200 * 8));
+
+    let Egress::Send(fragments) = fragment_for(&packet, &cable, source) else {
+        panic!("Packet may be fragmented");
+    };
+    assert_eq!(fragments.len(), 3);
+
+    for fragment in &fragments {
+        assert!(fragment.total_length() <= cable.mtu());
+        cable.send_bits(*usr1.get_mac(), 30, 40, fragment.as_bit_string().clone())?;
+    }
+
+    let mut received: BitString = usr2
+        .get_receiver()
+        .try_iter()
+        .map(|cc| cc.bit)
+        .collect::<Vec<Bit>>()
+        .into();
+
+    let now = Instant::now();
+    let mut reassembler = Reassembler::default();
+    let mut reassembled = None;
+
+    while !received.is_empty() {
+        let fragment = Ipv4Packet::decode(&received)?;
+        let len = usize::from(fragment.total_length()) * 8;
+        received = received.copy_len(len, received.len() - len);
+
+        reassembled = reassembler.receive(&fragment, now);
+    }
+    assert_eq!(reassembled, Some(packet));
+
+    // With DF set the packet is dropped, and the source is told the MTU
+    let packet = builder
+        .set_dont_fragment(true)
+        .build(BitString::with_ones(1200 * 8));
+    let Egress::Dropped(Some(error)) = fragment_for(&packet, &cable, destination) else {
+        panic!("Packet may not be fragmented");
+    };
+    assert_eq!(error.destination(), source);
+
+    let IcmpMessage::DestinationUnreachable { next_hop_mtu, .. } =
+        IcmpMessage::decode_ipv4(&error)?
+    else {
+        panic!("Error is a destination unreachable message");
+    };
+    assert_eq!(next_hop_mtu, 576);
+
+    Ok(())
+}
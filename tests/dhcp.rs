@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use easy_threadpool::ThreadPoolBuilder;
+use network_sim::application_layer::dhcp::{DhcpServer, DhcpState};
+use network_sim::data_link_layer::frame::ipv4::Ipv4Packet;
+use network_sim::hardware::{Router, User};
+use network_sim::ip_address::{Ipv4Address, Ipv4Cidr};
+use network_sim::mac_address::MacAddressGenerator;
+
+const LEASE_TIME: Duration = Duration::from_secs(600);
+
+/// An edge router handing out addresses to the users on its network.
+fn network(users: usize) -> anyhow::Result<(Router, Vec<User>)> {
+    let mut mac_gen = MacAddressGenerator::new(4321);
+    let pool = ThreadPoolBuilder::default().build()?;
+
+    let gateway = Ipv4Address::new([192, 168, 0, 1]);
+    let cidr: Ipv4Cidr = "192.168.0.0/24".parse()?;
+
+    let mut router = Router::new(true, &mut mac_gen, pool);
+    let _ = router.interface_mut().set_ip_address(gateway);
+    router.set_dhcp_server(DhcpServer::new(gateway, cidr)?.set_lease_time(LEASE_TIME));
+
+    let users = (0..users).map(|_| User::new(&mut mac_gen)).collect();
+
+    Ok((router, users))
+}
+
+/// Delivers the packets of the users to the router and its replies back,
+/// the way a shared segment would. Replies are sent to every user, who
+/// ignore the ones that aren't theirs.
+fn run(router: &mut Router, users: &mut [User], now: Instant) -> anyhow::Result<()> {
+    let mut outgoing: Vec<Ipv4Packet> = users
+        .iter_mut()
+        .filter_map(|user| user.poll_dhcp(now))
+        .collect();
+
+    while !outgoing.is_empty() {
+        let mut replies = Vec::new();
+        for packet in &outgoing {
+            replies.extend(router.receive_dhcp(packet, now)?);
+        }
+
+        outgoing.clear();
+        for reply in &replies {
+            for user in users.iter_mut() {
+                let for_user = reply.destination() == Ipv4Address::BROADCAST
+                    || user.interface().arp().ip_address() == Some(reply.destination());
+                if for_user {
+                    outgoing.extend(user.receive_dhcp(reply, now)?);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn configures_network() -> anyhow::Result<()> {
+    let (mut router, mut users) = network(50)?;
+    let now = Instant::now();
+
+    run(&mut router, &mut users, now)?;
+
+    let server = router.dhcp_server().expect("Router runs a server");
+    let mut addresses = HashSet::new();
+
+    for user in &users {
+        assert_eq!(user.dhcp().state(), DhcpState::Bound);
+
+        let ip = user
+            .interface()
+            .arp()
+            .ip_address()
+            .expect("User got an address");
+        assert!(server.cidr().contains(ip));
+        assert_ne!(ip, server.address());
+        assert!(addresses.insert(ip), "Address {ip} was handed out twice");
+
+        let lease = user.dhcp().lease().expect("User holds a lease");
+        assert_eq!(lease.router, Some(server.address()));
+        assert_eq!(
+            lease.subnet_mask,
+            Some(Ipv4Address::new([255, 255, 255, 0]))
+        );
+    }
+    assert_eq!(server.lease_count(), users.len());
+
+    Ok(())
+}
+
+#[test]
+fn keeps_addresses_while_renewing() -> anyhow::Result<()> {
+    let (mut router, mut users) = network(10)?;
+    let start = Instant::now();
+
+    run(&mut router, &mut users, start)?;
+    let addresses: Vec<_> = users
+        .iter()
+        .map(|user| user.interface().arp().ip_address())
+        .collect();
+
+    // Renewing every half lease keeps the addresses well past the lease time
+    for renewal in 1..=4 {
+        run(&mut router, &mut users, start + LEASE_TIME / 2 * renewal)?;
+    }
+
+    let now = start + LEASE_TIME * 2;
+    let renewed: Vec<_> = users
+        .iter()
+        .map(|user| user.interface().arp().ip_address())
+        .collect();
+    assert_eq!(renewed, addresses);
+
+    for user in &users {
+        let lease = user.dhcp().lease().expect("User holds a lease");
+        assert!(lease.expires() > now);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn loses_address_without_server() -> anyhow::Result<()> {
+    let (mut router, mut users) = network(1)?;
+    let start = Instant::now();
+
+    run(&mut router, &mut users, start)?;
+    let user = &mut users[0];
+    assert!(user.interface().arp().ip_address().is_some());
+
+    // The server is gone, so renewing and rebinding go unanswered
+    let _ = user.poll_dhcp(start + LEASE_TIME / 2);
+    let _ = user.poll_dhcp(start + LEASE_TIME * 7 / 8);
+    assert_eq!(user.dhcp().state(), DhcpState::Rebinding);
+    assert!(user.interface().arp().ip_address().is_some());
+
+    let discover = user.poll_dhcp(start + LEASE_TIME);
+    assert!(discover.is_some());
+    assert_eq!(user.interface().arp().ip_address(), None);
+
+    // Meanwhile the server took the address back
+    let server = router.dhcp_server_mut().expect("Router runs a server");
+    server.remove_expired(start + LEASE_TIME);
+    assert_eq!(server.lease_count(), 0);
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use network_sim::application_layer::dns::{
+    DnsAnswer, DnsServer, RecordType, Resolution, ResponseCode,
+};
+use network_sim::data_link_layer::frame::ipv4::Ipv4Packet;
+use network_sim::hardware::User;
+use network_sim::ip_address::Ipv4Address;
+use network_sim::mac_address::MacAddressGenerator;
+use network_sim::rand::XorShift;
+
+const ZONE: &str = "
+    $ORIGIN sim.test.
+    $TTL 120
+    ns      A      10.0.0.53
+    alice   A      10.0.0.2
+    bob     30 A   10.0.0.3
+    www     CNAME  bob
+";
+
+const NAMESERVER: Ipv4Address = Ipv4Address::new([10, 0, 0, 53]);
+
+/// A user resolving names through an authoritative server.
+fn hosts() -> anyhow::Result<(User, User)> {
+    let mut mac_gen = MacAddressGenerator::new(5353);
+
+    let mut server = User::new(&mut mac_gen);
+    let _ = server.interface_mut().set_ip_address(NAMESERVER);
+    server.set_dns_server(DnsServer::new(ZONE.parse()?));
+
+    let mut client = User::new(&mut mac_gen);
+    let _ = client
+        .interface_mut()
+        .set_ip_address(Ipv4Address::new([10, 0, 0, 2]));
+    let _ = client
+        .resolver_mut()
+        .set_nameserver(NAMESERVER, Instant::now());
+
+    Ok((server, client))
+}
+
+/// Sends a query to the server and hands the response to the client, unless
+/// either is lost.
+fn exchange(
+    server: &User,
+    client: &mut User,
+    query: &Ipv4Packet,
+    now: Instant,
+    mut lost: impl FnMut() -> bool,
+) -> anyhow::Result<Option<DnsAnswer>> {
+    if lost() {
+        return Ok(None);
+    }
+    let Some(response) = server.respond_dns(query)? else {
+        return Ok(None);
+    };
+    if lost() {
+        return Ok(None);
+    }
+
+    client.resolver_mut().receive(&response, now)
+}
+
+#[test]
+fn resolves_and_caches() -> anyhow::Result<()> {
+    let (server, mut client) = hosts()?;
+    let start = Instant::now();
+
+    let Resolution::Query(Some(query)) = client.resolve("www.sim.test", RecordType::A, start)?
+    else {
+        panic!("Nothing is cached yet");
+    };
+    let answer = exchange(&server, &mut client, &query, start, || false)?.expect("Nothing is lost");
+    assert_eq!(answer.response_code, ResponseCode::NoError);
+    assert_eq!(answer.ipv4_addresses(), [Ipv4Address::new([10, 0, 0, 3])]);
+
+    // Answered from the cache until the shortest TTL runs out
+    let later = start + Duration::from_secs(20);
+    let Resolution::Cached(records) = client.resolve("WWW.sim.test.", RecordType::A, later)? else {
+        panic!("Answer is cached");
+    };
+    assert!(records
+        .iter()
+        .all(|record| record.ttl <= Duration::from_secs(100)));
+
+    let expired = start + Duration::from_secs(30);
+    assert!(matches!(
+        client.resolve("www.sim.test", RecordType::A, expired)?,
+        Resolution::Query(Some(_))
+    ));
+
+    // Unknown names aren't cached
+    let Resolution::Query(Some(query)) = client.resolve("carol.sim.test", RecordType::A, start)?
+    else {
+        panic!("Nothing is cached yet");
+    };
+    let answer = exchange(&server, &mut client, &query, start, || false)?.expect("Nothing is lost");
+    assert_eq!(answer.response_code, ResponseCode::NameError);
+    assert!(client
+        .resolver_mut()
+        .lookup(&answer.question, start)
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+fn caching_under_loss() -> anyhow::Result<()> {
+    let (server, mut client) = hosts()?;
+    let start = Instant::now();
+    let mut rand = XorShift::new(99);
+    let mut lost = || rand.next_01() < 0.3;
+
+    let names = [
+        "ns.sim.test",
+        "alice.sim.test",
+        "bob.sim.test",
+        "www.sim.test",
+    ];
+    let mut answers = 0;
+    let mut queries = 0;
+
+    // Every name is looked up once a second for a few minutes
+    for second in 0..300 {
+        let now = start + Duration::from_secs(second);
+
+        let mut outgoing = client.resolver_mut().poll(now);
+        for name in names {
+            if let Resolution::Query(Some(query)) = client.resolve(name, RecordType::A, now)? {
+                queries += 1;
+                outgoing.push(query);
+            }
+        }
+
+        for query in &outgoing {
+            if exchange(&server, &mut client, query, now, &mut lost)?.is_some() {
+                answers += 1;
+            }
+        }
+    }
+
+    let resolver = client.resolver();
+    // Most lookups are served from the cache despite the loss
+    assert!(resolver.cache_hits() > resolver.cache_misses() * 4);
+    assert!(resolver.failed_queries() > 0);
+
+    // Queries that are still outstanding may be answered later
+    let outstanding = queries - answers - resolver.failed_queries();
+    assert!(outstanding <= names.len());
+
+    Ok(())
+}
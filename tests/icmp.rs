@@ -0,0 +1,235 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use easy_threadpool::ThreadPoolBuilder;
+use network_sim::bit_string::BitString;
+use network_sim::corruption_type::Corruption;
+use network_sim::data_link_layer::frame::ipv4::{Ipv4Packet, Ipv4PacketBuilder};
+use network_sim::hardware::{Router, User};
+use network_sim::ip_address::Ipv4Address;
+use network_sim::mac_address::MacAddressGenerator;
+use network_sim::network_layer::fragmentation::Egress;
+use network_sim::network_layer::icmp::{
+    IcmpMessage, CODE_NETWORK_UNREACHABLE, CODE_PROTOCOL_UNREACHABLE,
+};
+use network_sim::network_layer::ping::{ping, traceroute};
+use network_sim::physical_layer::cable::Cable;
+
+const LATENCIES: [Duration; 3] = [
+    Duration::from_millis(2),
+    Duration::from_millis(10),
+    Duration::from_millis(3),
+];
+
+/// A user connected to another through two routers.
+struct Path {
+    source: User,
+    routers: [Router; 2],
+    destination: User,
+    cables: [Cable; 3],
+}
+
+/// Takes a node back once the cables know it, as they only keep its address.
+fn into_inner<N>(node: Arc<N>) -> N {
+    Arc::into_inner(node).expect("The cable doesn't keep the node")
+}
+
+impl Path {
+    // The nodes are only shared with the cables while they are built, on
+    // this thread
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn new() -> Self {
+        let mut mac_gen = MacAddressGenerator::new(1234);
+
+        let mut source = User::new(&mut mac_gen);
+        source
+            .interface_mut()
+            .set_ip_address(Ipv4Address::new([10, 0, 0, 1]));
+
+        let routers = [0u8, 1].map(|net| {
+            let pool = ThreadPoolBuilder::default()
+                .build()
+                .expect("Thread pool can be created");
+            let mut router = Router::new(false, &mut mac_gen, pool);
+            router
+                .interface_mut()
+                .set_ip_address(Ipv4Address::new([10, 0, net, 254]));
+            router
+        });
+
+        let mut destination = User::new(&mut mac_gen);
+        destination
+            .interface_mut()
+            .set_ip_address(Ipv4Address::new([10, 0, 2, 1]));
+
+        let source = Arc::new(source);
+        let routers = routers.map(Arc::new);
+        let destination = Arc::new(destination);
+
+        let cables = [
+            Cable::new(&source, &routers[0], LATENCIES[0], Corruption::None, 1000),
+            Cable::new(
+                &routers[0],
+                &routers[1],
+                LATENCIES[1],
+                Corruption::None,
+                1000,
+            ),
+            Cable::new(
+                &routers[1],
+                &destination,
+                LATENCIES[2],
+                Corruption::None,
+                1000,
+            ),
+        ];
+
+        Self {
+            source: into_inner(source),
+            routers: routers.map(into_inner),
+            destination: into_inner(destination),
+            cables,
+        }
+    }
+
+    fn source_ip(&self) -> Ipv4Address {
+        self.source
+            .interface()
+            .arp()
+            .ip_address()
+            .expect("Source has an address")
+    }
+
+    fn destination_ip(&self) -> Ipv4Address {
+        self.destination
+            .interface()
+            .arp()
+            .ip_address()
+            .expect("Destination has an address")
+    }
+
+    /// Carries a packet from the source towards the destination, returning
+    /// the response that makes it back to the source and when it arrives.
+    fn deliver(&self, packet: Ipv4Packet, now: Instant) -> Option<(Ipv4Packet, Instant)> {
+        let mut now = now + self.cables[0].latency();
+        let mut packet = packet;
+
+        for (hop, router) in self.routers.iter().enumerate() {
+            let response = if packet.destination() == router.interface().arp().ip_address()? {
+                router.interface().respond_ipv4(&packet)
+            } else {
+                match router.forward_ipv4(&packet, Some(&self.cables[hop + 1])) {
+                    Egress::Send(mut fragments) => {
+                        packet = fragments.remove(0);
+                        now += self.cables[hop + 1].latency();
+                        continue;
+                    }
+                    Egress::Dropped(error) => error,
+                }
+            };
+
+            return response.map(|response| (response, now + self.back(hop)));
+        }
+
+        let response = self.destination.interface().respond_ipv4(&packet)?;
+        Some((response, now + self.back(self.cables.len() - 1)))
+    }
+
+    /// The time it takes to get back to the source from behind the cable.
+    fn back(&self, cable: usize) -> Duration {
+        self.cables[..=cable].iter().map(Cable::latency).sum()
+    }
+}
+
+#[test]
+fn ping_over_routers() -> anyhow::Result<()> {
+    let path = Path::new();
+    let start = Instant::now();
+
+    let replies = ping(
+        path.source_ip(),
+        path.destination_ip(),
+        4,
+        Duration::from_secs(1),
+        start,
+        |packet, now| path.deliver(packet, now),
+    );
+
+    assert_eq!(replies.len(), 4);
+    for reply in replies {
+        let reply = reply.expect("Nothing is lost");
+        assert_eq!(reply.from, path.destination_ip());
+        assert_eq!(reply.rtt, Duration::from_millis(30));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn traceroute_over_routers() -> anyhow::Result<()> {
+    let path = Path::new();
+
+    let hops = traceroute(
+        path.source_ip(),
+        path.destination_ip(),
+        30,
+        Instant::now(),
+        |packet, now| path.deliver(packet, now),
+    );
+
+    let expected = [
+        (path.routers[0].interface().arp().ip_address(), 4),
+        (path.routers[1].interface().arp().ip_address(), 24),
+        (Some(path.destination_ip()), 30),
+    ];
+    assert_eq!(hops.len(), expected.len());
+
+    for ((ttl, hop), (address, rtt)) in (1..).zip(&hops).zip(expected) {
+        let hop = hop.expect("Nothing is lost");
+        assert_eq!(hop.ttl, ttl);
+        assert_eq!(Some(hop.address), address);
+        assert_eq!(hop.rtt, Duration::from_millis(rtt));
+        // Only the destination ends the trace
+        assert_eq!(hop.reached, ttl == 3);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn router_errors() -> anyhow::Result<()> {
+    let path = Path::new();
+    let router = &path.routers[0];
+
+    let packet = Ipv4PacketBuilder::new()
+        .set_source(path.source_ip())
+        .set_destination(path.destination_ip())
+        .set_protocol(253)
+        .build(BitString::with_zeroes(64));
+
+    // Without a route the source is told the network can't be reached
+    let Egress::Dropped(Some(error)) = router.forward_ipv4(&packet, None) else {
+        panic!("Packet has no route");
+    };
+    assert_eq!(error.destination(), path.source_ip());
+    let IcmpMessage::DestinationUnreachable { code, .. } = IcmpMessage::decode_ipv4(&error)? else {
+        panic!("Error is a destination unreachable message");
+    };
+    assert_eq!(code, CODE_NETWORK_UNREACHABLE);
+
+    // Errors about errors are never sent
+    assert_eq!(router.forward_ipv4(&error, None), Egress::Dropped(None));
+
+    // Hosts report protocols they don't speak
+    let error = path
+        .destination
+        .interface()
+        .respond_ipv4(&packet)
+        .expect("Protocol is unknown");
+    let IcmpMessage::DestinationUnreachable { code, .. } = IcmpMessage::decode_ipv4(&error)? else {
+        panic!("Error is a destination unreachable message");
+    };
+    assert_eq!(code, CODE_PROTOCOL_UNREACHABLE);
+
+    Ok(())
+}
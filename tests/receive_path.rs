@@ -0,0 +1,199 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use easy_threadpool::ThreadPoolBuilder;
+use network_sim::bit_string::BitString;
+use network_sim::corruption_type::Corruption;
+use network_sim::data_link_layer::arq::Arq;
+use network_sim::data_link_layer::frame::ipv4::MIN_IPV4_MTU;
+use network_sim::data_link_layer::frame::tcp::TCPFrame;
+use network_sim::data_link_layer::DataLinkLayer;
+use network_sim::hardware::{interface::Interface, Node, Router, User};
+use network_sim::mac_address::{MacAddress, MacAddressGenerator};
+use network_sim::physical_layer::cable::Cable;
+use network_sim::rand::XorShift;
+
+const DATA: &[u8] = b"The quick brown fox jumps over the lazy dog, again and again and again \
+                      until every frame of the window has been filled up at least once.";
+
+/// Connects two nodes with a cable carrying small frames, handing the nodes
+/// back once the cable knows them.
+fn connect<A, B>(node1: A, node2: B, corruption: Corruption) -> (A, B, Arc<Mutex<Cable>>)
+where
+    A: Node,
+    B: Node,
+{
+    let (node1, node2) = (Arc::new(node1), Arc::new(node2));
+    let cable = Cable::new(&node1, &node2, Duration::ZERO, corruption, 1000).set_mtu(MIN_IPV4_MTU);
+
+    let node1 = Arc::into_inner(node1).expect("The cable doesn't keep the node");
+    let node2 = Arc::into_inner(node2).expect("The cable doesn't keep the node");
+
+    (node1, node2, Arc::new(Mutex::new(cable)))
+}
+
+/// Whether the user is still sending a transfer to the peer.
+fn is_sending(user: &User, peer: &MacAddress) -> bool {
+    user.interface()
+        .data_link_layer(peer)
+        .is_some_and(DataLinkLayer::is_sending)
+}
+
+/// The transfers the interface received, failing if any of its links did.
+fn receive(interface: &mut Interface) -> anyhow::Result<Vec<(MacAddress, BitString)>> {
+    interface
+        .receive_bits()
+        .into_iter()
+        .map(|(peer, transfer)| Ok((peer, transfer?)))
+        .collect()
+}
+
+fn link_layer(arq: Arq) -> DataLinkLayer<TCPFrame> {
+    DataLinkLayer::<TCPFrame>::new()
+        .set_arq(arq)
+        .set_retransmit_timeout(Duration::from_millis(250))
+}
+
+#[test]
+fn user_to_user() -> anyhow::Result<()> {
+    let mut mac_gen = MacAddressGenerator::new(50);
+    let corruption = Corruption::BurstChance(XorShift::new(50), 40);
+    let (mut sender, mut receiver, cable) =
+        connect(User::new(&mut mac_gen), User::new(&mut mac_gen), corruption);
+    let (sender_mac, receiver_mac) = (*sender.get_mac(), *receiver.get_mac());
+
+    sender
+        .interface_mut()
+        .add_link(cable.clone(), link_layer(Arq::SelectiveRepeat))?;
+    receiver
+        .interface_mut()
+        .add_link(cable, link_layer(Arq::SelectiveRepeat))?;
+
+    sender
+        .interface_mut()
+        .send_bits(4, 30, 40, &receiver_mac, BitString::from(DATA))?;
+
+    let mut received = Vec::new();
+    while is_sending(&sender, &receiver_mac) || received.is_empty() {
+        received.extend(receive(receiver.interface_mut())?);
+        // Nothing is sent the other way
+        assert_eq!(receive(sender.interface_mut())?, []);
+    }
+
+    assert_eq!(received, [(sender_mac, BitString::from(DATA))]);
+
+    // Acknowledgements made it back over the same cable
+    let transfer = sender
+        .interface()
+        .data_link_layer(&receiver_mac)
+        .and_then(DataLinkLayer::sender)
+        .expect("A transfer was sent");
+    assert!(transfer.is_finished());
+    assert!(transfer.retransmissions() > 0);
+
+    Ok(())
+}
+
+#[test]
+fn users_to_router() -> anyhow::Result<()> {
+    let mut mac_gen = MacAddressGenerator::new(51);
+    let pool = ThreadPoolBuilder::default().build()?;
+    let router = Router::new(true, &mut mac_gen, pool);
+    let router_mac = *router.get_mac();
+
+    let (mut user1, router, cable1) = connect(User::new(&mut mac_gen), router, Corruption::None);
+    let corruption = Corruption::BurstChance(XorShift::new(51), 40);
+    let (mut user2, mut router, cable2) = connect(User::new(&mut mac_gen), router, corruption);
+
+    // Every end runs Go-Back-N by default
+    let other = BitString::from(&b"A shorter transfer over the other cable"[..]);
+    for (user, cable, data) in [
+        (&mut user1, cable1, BitString::from(DATA)),
+        (&mut user2, cable2, other.clone()),
+    ] {
+        router
+            .interface_mut()
+            .add_link(cable.clone(), link_layer(Arq::GoBackN))?;
+        user.interface_mut()
+            .add_link(cable, link_layer(Arq::GoBackN))?;
+        user.interface_mut()
+            .send_bits(3, 30, 40, &router_mac, data)?;
+    }
+
+    // Both transfers arrive at the router at once, its acknowledgements go
+    // back over the cable each frame came from
+    let mut received = Vec::new();
+    while is_sending(&user1, &router_mac) || is_sending(&user2, &router_mac) || received.len() < 2 {
+        received.extend(receive(router.interface_mut())?);
+        receive(user1.interface_mut())?;
+        receive(user2.interface_mut())?;
+    }
+
+    received.sort_by_key(|(_, data)| data.len());
+    assert_eq!(
+        received,
+        [
+            (*user2.get_mac(), other),
+            (*user1.get_mac(), BitString::from(DATA))
+        ]
+    );
+
+    let link = |user: &User| router.interface().data_link_layer(user.get_mac());
+    assert_eq!(link(&user1).map(DataLinkLayer::dropped_frames), Some(0));
+    assert!(link(&user2).is_some_and(|link| link.dropped_frames() > 0));
+
+    Ok(())
+}
+
+#[test]
+fn failing_link_keeps_others() -> anyhow::Result<()> {
+    let mut mac_gen = MacAddressGenerator::new(52);
+    let pool = ThreadPoolBuilder::default().build()?;
+    let router = Router::new(true, &mut mac_gen, pool);
+    let router_mac = *router.get_mac();
+
+    let (mut user1, router, cable1) = connect(User::new(&mut mac_gen), router, Corruption::None);
+    // Every frame to the second user arrives damaged
+    let corruption = Corruption::BurstChance(XorShift::new(52), 100);
+    let (mut user2, mut router, cable2) = connect(User::new(&mut mac_gen), router, corruption);
+    let (user1_mac, user2_mac) = (*user1.get_mac(), *user2.get_mac());
+
+    let hopeless =
+        DataLinkLayer::<TCPFrame>::new().set_retransmit_timeout(Duration::from_millis(5));
+    router.interface_mut().add_link(cable2.clone(), hopeless)?;
+    user2
+        .interface_mut()
+        .add_link(cable2, link_layer(Arq::GoBackN))?;
+    router
+        .interface_mut()
+        .add_link(cable1.clone(), link_layer(Arq::GoBackN))?;
+    user1
+        .interface_mut()
+        .add_link(cable1, link_layer(Arq::GoBackN))?;
+
+    router
+        .interface_mut()
+        .send_bits(3, 40, 30, &user2_mac, BitString::from(DATA))?;
+    user1
+        .interface_mut()
+        .send_bits(3, 30, 40, &router_mac, BitString::from(DATA))?;
+
+    let mut received = Vec::new();
+    let mut failures = Vec::new();
+    while is_sending(&user1, &router_mac) || received.is_empty() || failures.is_empty() {
+        for (peer, transfer) in router.interface_mut().receive_bits() {
+            match transfer {
+                Ok(data) => received.push((peer, data)),
+                Err(_) => failures.push(peer),
+            }
+        }
+        receive(user1.interface_mut())?;
+        receive(user2.interface_mut())?;
+    }
+
+    // The link to the second user gave up, the first one was still read
+    assert_eq!(failures, [user2_mac]);
+    assert_eq!(received, [(user1_mac, BitString::from(DATA))]);
+
+    Ok(())
+}
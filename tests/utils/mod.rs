@@ -1,2 +1,5 @@
+// Every test crate includes these helpers but only uses some of them
+#![allow(dead_code)]
+
 pub mod test_fns;
 pub mod test_structs;